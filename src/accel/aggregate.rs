@@ -1,9 +1,38 @@
 use crate::common::*;
 
-pub trait Aggregate: Primitive {
+stat_distribution!(NODES_PER_RAY, "Accelerator/Nodes visited per ray");
+stat_distribution!(PRIMITIVES_PER_RAY, "Accelerator/Primitives tested per ray");
+stat_distribution!(NODES_PER_SHADOW_RAY, "Accelerator/Nodes visited per shadow ray");
+stat_distribution!(PRIMITIVES_PER_SHADOW_RAY, "Accelerator/Primitives tested per shadow ray");
+
+// Rays traced together by intersect_packet, as many as an AVX register holds
+pub const PACKET_SIZE: usize = 8;
+
+// Acceleration structure over the primitives of a scene, chosen with <accel type="..."> and
+// configured through its properties before it is built
+pub trait Aggregate: Primitive + LeadObjectTrait {
     fn primitives(&self) -> &Vec<Arc<dyn Primitive>>;
 
-    fn create(&mut self, primitives: Vec<Arc<dyn Primitive>>, max_primitives_in_node: u32, split_method: SplitMethod);
+    fn create(&mut self, primitives: Vec<Arc<dyn Primitive>>);
+
+    // Only the first n_rays lanes are real, a packet that is not full repeats one of them in the rest.
+    // Structures without a packet traversal trace the real rays one at a time
+    fn intersect_packet(&self, rays: &[Ray; PACKET_SIZE], its: &mut [SurfaceInteraction; PACKET_SIZE], n_rays: usize) -> [bool; PACKET_SIZE] {
+        std::array::from_fn(|i| i < n_rays && self.intersect(&rays[i], &mut its[i]))
+    }
+
+    fn intersect_p_packet(&self, rays: &[Ray; PACKET_SIZE], n_rays: usize) -> [bool; PACKET_SIZE] {
+        std::array::from_fn(|i| i < n_rays && self.intersect_p(&rays[i]))
+    }
+}
+
+// Work done by a traversal for a single ray, recorded in the per ray distributions
+pub fn record_ray(nodes_visited: u64, primitives_tested: u64) {
+    NODES_PER_RAY.with(|nodes| nodes.record(nodes_visited as f64));
+    PRIMITIVES_PER_RAY.with(|primitives| primitives.record(primitives_tested as f64));
+}
 
-    fn to_string(&self) -> String;
-}
\ No newline at end of file
+pub fn record_shadow_ray(nodes_visited: u64, primitives_tested: u64) {
+    NODES_PER_SHADOW_RAY.with(|nodes| nodes.record(nodes_visited as f64));
+    PRIMITIVES_PER_SHADOW_RAY.with(|primitives| primitives.record(primitives_tested as f64));
+}
@@ -14,11 +14,12 @@ impl SplitMethod {
             Self::SAH => String::from("Surface Area Heuristic"),
             Self::EqualCounts => String::from("Equal Counts"),
             Self::Middle => String::from("Middle"),
-            _ => String::from("Unsupported"),
+            Self::HLGBH => String::from("Hierarchical Linear BVH"),
         }
     }
 }
 
+#[derive(Clone, Copy)]
 struct BVHPrimitiveInfo {
     primitive_number: u32,
     bounds: Bounds3f,
@@ -35,351 +36,963 @@ impl BVHPrimitiveInfo{
     }
 }
 
-struct  BVHBuildNode {
+// Primitive with its centroid's position along the Morton curve
+#[derive(Debug, Clone, Copy, Default)]
+struct MortonPrimitive {
+    primitive_index: u32,
+    morton_code: u32,
+}
+
+// Run of sorted primitives sharing the top bits of their code, i.e. one cell of a coarse grid
+struct LBVHTreelet {
+    start_index: usize,
+    n_primitives: usize,
+}
+
+// Spreads the lower 10 bits of x out so that two zero bits follow each of them
+fn left_shift_3(mut x: u32) -> u32 {
+    if x == (1 << 10) {
+        x -= 1;
+    }
+    x = (x | (x << 16)) & 0b00000011000000000000000011111111;
+    x = (x | (x << 8)) & 0b00000011000000001111000000001111;
+    x = (x | (x << 4)) & 0b00000011000011000011000011000011;
+    x = (x | (x << 2)) & 0b00001001001001001001001001001001;
+    x
+}
+
+fn encode_morton_3(v: &Vector3f) -> u32 {
+    (left_shift_3(v.z() as u32) << 2) | (left_shift_3(v.y() as u32) << 1) | left_shift_3(v.x() as u32)
+}
+
+// Least significant digit first, 6 bits at a time over the 30 bits of the codes
+fn radix_sort(v: &mut Vec<MortonPrimitive>) {
+    const BITS_PER_PASS: u32 = 6;
+    const N_BITS: u32 = 30;
+    const N_PASSES: u32 = N_BITS / BITS_PER_PASS;
+    const N_BUCKETS: usize = 1 << BITS_PER_PASS;
+    const BIT_MASK: u32 = (1 << BITS_PER_PASS) - 1;
+
+    let mut temp = vec![MortonPrimitive::default(); v.len()];
+    for pass in 0..N_PASSES {
+        let low_bit = pass * BITS_PER_PASS;
+        // every pass goes back and forth between the two, so v holds the result after an even count
+        let (input, output): (&[MortonPrimitive], &mut [MortonPrimitive]) = if pass % 2 == 0 { (v, &mut temp) } else { (&temp, v) };
+
+        let mut bucket_count = [0usize; N_BUCKETS];
+        for mp in input.iter() {
+            bucket_count[((mp.morton_code >> low_bit) & BIT_MASK) as usize] += 1;
+        }
+
+        let mut out_index = [0usize; N_BUCKETS];
+        for i in 1..N_BUCKETS {
+            out_index[i] = out_index[i - 1] + bucket_count[i - 1];
+        }
+
+        for mp in input.iter() {
+            let bucket = ((mp.morton_code >> low_bit) & BIT_MASK) as usize;
+            output[out_index[bucket]] = *mp;
+            out_index[bucket] += 1;
+        }
+    }
+
+    if N_PASSES % 2 == 1 {
+        std::mem::swap(v, &mut temp);
+    }
+}
+
+// Splits the sorted primitives where the code bit at bit_index changes, going one bit down per level.
+// Leaves point into the primitives in Morton order, so the first one of morton_prims sits at first_offset
+fn emit_lbvh(primitive_info: &[BVHPrimitiveInfo], morton_prims: &[MortonPrimitive], first_offset: usize, bit_index: i32, max_primitives_in_node: u32, arena: &mut BuildArena) -> u32 {
+    let n_primitives = morton_prims.len();
+    // the codes have no bits left to tell these apart, halve them until they fit into leaves
+    if bit_index == -1 && n_primitives > max_primitives_in_node as usize {
+        let mid = n_primitives / 2;
+        let c_0 = emit_lbvh(primitive_info, &morton_prims[..mid], first_offset, bit_index, max_primitives_in_node, arena);
+        let c_1 = emit_lbvh(primitive_info, &morton_prims[mid..], first_offset + mid, bit_index, max_primitives_in_node, arena);
+        return arena.add_interior(0, c_0, c_1);
+    }
+    if bit_index == -1 || n_primitives < max_primitives_in_node as usize {
+        let bounds = morton_prims.iter()
+            .fold(Bounds3f::new(), |b, mp| Bounds3f::union(&b, &primitive_info[mp.primitive_index as usize].bounds));
+        return arena.add_leaf(n_primitives as u32, bounds, first_offset as u32);
+    }
+
+    let mask = 1u32 << bit_index;
+    // all on one side of this split, try the next bit
+    if (morton_prims[0].morton_code & mask) == (morton_prims[n_primitives - 1].morton_code & mask) {
+        return emit_lbvh(primitive_info, morton_prims, first_offset, bit_index - 1, max_primitives_in_node, arena);
+    }
+
+    // the codes are sorted, so the ones with the bit set form the tail
+    let split_offset = morton_prims.partition_point(|mp| (mp.morton_code & mask) == (morton_prims[0].morton_code & mask));
+
+    let c_0 = emit_lbvh(primitive_info, &morton_prims[..split_offset], first_offset, bit_index - 1, max_primitives_in_node, arena);
+    let c_1 = emit_lbvh(primitive_info, &morton_prims[split_offset..], first_offset + split_offset, bit_index - 1, max_primitives_in_node, arena);
+    arena.add_interior((bit_index % 3) as u32, c_0, c_1)
+}
+
+// SAH over the treelet roots, to join them into one tree
+fn build_upper_sah(arena: &mut BuildArena, treelet_roots: &mut [u32]) -> u32 {
+    let n_nodes = treelet_roots.len();
+    if n_nodes == 1 {
+        return treelet_roots[0];
+    }
+
+    let (dim, mid) = {
+        let node_bounds = |root: &u32| arena.nodes[*root as usize].bounds;
+        let centroid = |root: &u32| node_bounds(root).p_min * 0.5 + node_bounds(root).p_max * 0.5;
+
+        let bounds = treelet_roots.iter().fold(Bounds3f::new(), |b, root| Bounds3f::union(&b, &node_bounds(root)));
+        let centroid_bounds = treelet_roots.iter().fold(Bounds3f::new(), |b, root| Bounds3f::union_pt(&b, &centroid(root)));
+        let dim = centroid_bounds.max_extent() as usize;
+
+        if centroid_bounds.p_max[dim] == centroid_bounds.p_min[dim] {
+            (dim, n_nodes / 2)
+        } else {
+            const N_BUCKETS: usize = 12;
+            let bucket_of = |root: &u32| {
+                let b = (N_BUCKETS as f32 * centroid_bounds.offset(&centroid(root))[dim]) as usize;
+                b.min(N_BUCKETS - 1)
+            };
+
+            let mut counts = [0f32; N_BUCKETS];
+            let mut bucket_bounds = [Bounds3f::new(); N_BUCKETS];
+            for root in treelet_roots.iter() {
+                let b = bucket_of(root);
+                counts[b] += 1.0;
+                bucket_bounds[b] = Bounds3f::union(&bucket_bounds[b], &node_bounds(root));
+            }
+
+            let mut min_cost = INFINITY;
+            let mut min_cost_bucket = 0usize;
+            for i in 0..(N_BUCKETS - 1) {
+                let (mut b0, mut b1) = (Bounds3f::new(), Bounds3f::new());
+                let (mut count_0, mut count_1) = (0f32, 0f32);
+                for j in 0..=i {
+                    b0 = Bounds3f::union(&b0, &bucket_bounds[j]);
+                    count_0 += counts[j];
+                }
+                for j in (i + 1)..N_BUCKETS {
+                    b1 = Bounds3f::union(&b1, &bucket_bounds[j]);
+                    count_1 += counts[j];
+                }
+
+                // empty sides would give an infinite area of nothing
+                let area_0 = if count_0 > 0.0 { count_0 * b0.surface_area() } else { 0.0 };
+                let area_1 = if count_1 > 0.0 { count_1 * b1.surface_area() } else { 0.0 };
+                let cost = 0.125 + (area_0 + area_1) / bounds.surface_area();
+                if cost < min_cost {
+                    min_cost = cost;
+                    min_cost_bucket = i;
+                }
+            }
+
+            // put the roots at or below the cheapest bucket first
+            let mut mid = 0usize;
+            for i in 0..n_nodes {
+                if bucket_of(&treelet_roots[i]) <= min_cost_bucket {
+                    treelet_roots.swap(i, mid);
+                    mid += 1;
+                }
+            }
+            (dim, if mid == 0 || mid == n_nodes { n_nodes / 2 } else { mid })
+        }
+    };
+
+    let (left, right) = treelet_roots.split_at_mut(mid);
+    let c_0 = build_upper_sah(arena, left);
+    let c_1 = build_upper_sah(arena, right);
+    arena.add_interior(dim as u32, c_0, c_1)
+}
+
+// Linear BVH: primitives are sorted along a Morton curve, grid cells of them are built into treelets
+// on all threads, and the treelets are joined with the SAH. The infos are left in Morton order
+fn hlbvh_build(max_primitives_in_node: u32, primitive_info: &mut Vec<BVHPrimitiveInfo>, arena: &mut BuildArena, n_threads: usize) -> u32 {
+    let centroid_bounds = primitive_info.iter()
+        .fold(Bounds3f::new(), |b, info| Bounds3f::union_pt(&b, &info.centroid));
+
+    let chunk_size = primitive_info.len().div_ceil(n_threads).max(1);
+
+    // 10 bits per axis
+    const MORTON_SCALE: f32 = 1024.0;
+    let mut morton_prims = vec![MortonPrimitive::default(); primitive_info.len()];
+    std::thread::scope(|scope| {
+        for (i, chunk) in morton_prims.chunks_mut(chunk_size).enumerate() {
+            let (primitive_info, centroid_bounds) = (&*primitive_info, &centroid_bounds);
+            scope.spawn(move || {
+                for (j, mp) in chunk.iter_mut().enumerate() {
+                    let index = i * chunk_size + j;
+                    let offset = centroid_bounds.offset(&primitive_info[index].centroid);
+                    mp.primitive_index = index as u32;
+                    mp.morton_code = encode_morton_3(&(offset * MORTON_SCALE));
+                }
+            });
+        }
+    });
+
+    radix_sort(&mut morton_prims);
+
+    // treelets are the runs sharing the top 12 bits, a 16x16x16 grid
+    let mask = 0b00111111111111000000000000000000u32;
+    let mut treelets: Vec<LBVHTreelet> = Vec::new();
+    let mut start = 0usize;
+    for end in 1..=morton_prims.len() {
+        if end == morton_prims.len() || (morton_prims[start].morton_code & mask) != (morton_prims[end].morton_code & mask) {
+            treelets.push(LBVHTreelet { start_index: start, n_primitives: end - start });
+            start = end;
+        }
+    }
+
+    // threads take the next treelet until none are left, the big ones would not split evenly up front
+    let next_treelet = std::sync::atomic::AtomicUsize::new(0);
+    let first_bit_index = 29 - 12;
+    let mut treelet_roots: Vec<u32> = vec![0; treelets.len()];
+    std::thread::scope(|scope| {
+        let handles: Vec<_> = (0..n_threads.min(treelets.len())).map(|_| {
+            let (primitive_info, treelets, morton_prims, next_treelet) = (&*primitive_info, &treelets, &morton_prims, &next_treelet);
+            scope.spawn(move || {
+                let mut thread_arena = BuildArena::new();
+                let mut built = Vec::new();
+                loop {
+                    let i = next_treelet.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+                    if i >= treelets.len() {
+                        break;
+                    }
+                    let tr = &treelets[i];
+                    let prims = &morton_prims[tr.start_index..tr.start_index + tr.n_primitives];
+                    built.push((i, emit_lbvh(primitive_info, prims, tr.start_index, first_bit_index, max_primitives_in_node, &mut thread_arena)));
+                }
+                (built, thread_arena)
+            })
+        }).collect();
+
+        for handle in handles {
+            let (built, thread_arena) = handle.join().unwrap();
+            let base = arena.append(thread_arena);
+            for (i, root) in built {
+                treelet_roots[i] = base + root;
+            }
+        }
+    });
+
+    *primitive_info = morton_prims.iter().map(|mp| primitive_info[mp.primitive_index as usize]).collect();
+
+    build_upper_sah(arena, &mut treelet_roots)
+}
+
+// Subtrees with at least this many primitives are worth a thread of their own
+const PARALLEL_BUILD_MIN_PRIMITIVES: usize = 64 * 1024;
+
+// Builds the subtree over primitive_info, which sits at first_offset in the whole list. Partitioning only
+// moves infos within their node's range, so once the build is done they are in leaf order
+fn recursive_build(split_method: SplitMethod, max_primitives_in_node: u32, primitive_info: &mut [BVHPrimitiveInfo], first_offset: usize, arena: &mut BuildArena, n_threads: usize) -> u32 {
+    let n_primitives = primitive_info.len();
+    let bounds = primitive_info.iter().fold(Bounds3f::new(), |b, info| Bounds3f::union(&b, &info.bounds));
+
+    if n_primitives == 1 {
+        return arena.add_leaf(1, bounds, first_offset as u32);
+    }
+
+    let centroid_bounds = primitive_info.iter().fold(Bounds3f::new(), |b, info| Bounds3f::union_pt(&b, &info.centroid));
+    let dim = centroid_bounds.max_extent() as usize;
+    let mut mid = n_primitives / 2;
+
+    if centroid_bounds.p_max[dim] == centroid_bounds.p_min[dim] {
+        // nothing to split by, only leaves too big for their 16 bit count are cut in half
+        if n_primitives <= u16::MAX as usize {
+            return arena.add_leaf(n_primitives as u32, bounds, first_offset as u32);
+        }
+    } else {
+        let mut split_method = split_method;
+        loop {  // in a loop so that it can fall from middle to equal counts if needed
+        match split_method {
+        SplitMethod::Middle => {
+            let p_mid = (centroid_bounds.p_min[dim] + centroid_bounds.p_max[dim]) / 2.0;
+
+            mid = {
+                let mut left = 0usize;
+                let mut right = n_primitives;
+
+                while left < right {
+                    // Find the first element on the left that should be on the right
+                    while left < right && primitive_info[left].centroid[dim] < p_mid {
+                        left += 1;
+                    }
+                    // Find the first element on the right that should be on the left
+                    while left < right && primitive_info[right - 1].centroid[dim] >= p_mid {
+                        right -= 1;
+                    }
+                    // Swap the elements if necessary
+                    if left < right {
+                        primitive_info.swap(left, right - 1);
+                    }
+                }
+
+                // The `left` pointer now represents the partition point
+                left
+            };
+
+            if mid != 0 && mid != n_primitives {
+                break;
+            }
+            split_method = SplitMethod::EqualCounts;
+        }
+        SplitMethod::EqualCounts => {
+            mid = n_primitives / 2;
+            primitive_info.select_nth_unstable_by(mid, |a, b| a.centroid[dim].partial_cmp(&b.centroid[dim]).unwrap_or(std::cmp::Ordering::Less));
+            break;
+        }
+        SplitMethod::SAH => {
+            if n_primitives <= 4 {
+                mid = n_primitives / 2;
+                primitive_info.select_nth_unstable_by(mid, |a, b| {
+                    a.centroid[dim].partial_cmp(&b.centroid[dim]).unwrap_or(std::cmp::Ordering::Less)
+                });
+            } else {
+                let n_buckets = 12usize;
+                struct BucketInfo {
+                    pub count: f32,
+                    pub bounds: Bounds3f
+                }
+
+                impl BucketInfo {
+                    pub fn new() -> Self {
+                        Self {
+                            count: 0f32, bounds: Bounds3f::new()
+                        }
+                    }
+                }
+
+                let mut buckets: Vec<BucketInfo> = Vec::new();
+                for _ in 0..n_buckets as usize {
+                    buckets.push(BucketInfo::new());
+                }
+                for info in primitive_info.iter() {
+                    let mut b: usize = (n_buckets as f32 * centroid_bounds.offset(&info.centroid)[dim]) as usize;
+                    if b == n_buckets {
+                        b -= 1usize;
+                    }
+                    buckets[b].count += 1f32;
+                    buckets[b].bounds = Bounds3f::union(&buckets[b].bounds, &info.bounds);
+                }
+
+                let mut cost: Vec<f32> = Vec::new();
+                for _ in 0..(n_buckets - 1) { cost.push(0f32); }
+                for i in 0..(n_buckets - 1) {
+                    let mut b0 = Bounds3f::new();
+                    let mut b1 = Bounds3f::new();
+                    let mut count_0: f32 = 0f32; let mut count_1: f32 = 0f32;
+                    for j in 0..=i {
+                        b0 = Bounds3f::union(&b0, &buckets[j].bounds);
+                        count_0 += buckets[j].count;
+                    }
+                    for j in i+1..n_buckets {
+                        b1 = Bounds3f::union(&b1, &buckets[j].bounds);
+                        count_1 += buckets[j].count;
+                    }
+
+                    cost[i] = 0.125 + (count_0 * b0.surface_area() + count_1 * b1.surface_area()) / bounds.surface_area();
+                }
+
+                let mut min_cost = cost[0]; let mut min_cost_bucket: usize = 0usize;
+                for i in 0..(n_buckets - 1) {
+                    if cost[i] < min_cost {
+                        min_cost = cost[i];
+                        min_cost_bucket = i;
+                    }
+                }
+
+                let leaf_cost = n_primitives as f32;
+                if n_primitives > max_primitives_in_node as usize || min_cost < leaf_cost {
+                    let mut left = 0usize; let mut right = n_primitives;
+
+                    while left < right {
+                        while left < right {
+                            let mut b = (n_buckets as f32 * centroid_bounds.offset(&primitive_info[left].centroid)[dim]) as usize;
+
+                            if b == n_buckets {
+                                b -= 1;
+                            }
+                            if b > min_cost_bucket {
+                                break;
+                            }
+                            left += 1;
+                        }
+                        while left < right {
+                            let mut b = (n_buckets as f32 * centroid_bounds.offset(&primitive_info[right - 1].centroid)[dim]) as usize;
+                            if b == n_buckets {
+                                b = n_buckets - 1;
+                            }
+                            if b <= min_cost_bucket {
+                                break;
+                            }
+                            right -= 1;
+                        }
+                        if left < right {
+                            primitive_info.swap(left, right - 1);
+                        }
+                        mid = left;
+                    }
+                } else {
+                    return arena.add_leaf(n_primitives as u32, bounds, first_offset as u32);
+                }
+            }
+            break;
+        }
+        _ => { split_method = SplitMethod::SAH; }
+        }
+        }
+    }
+
+    let (left, right) = primitive_info.split_at_mut(mid);
+    let (c_0, c_1) = if n_threads > 1 && n_primitives >= PARALLEL_BUILD_MIN_PRIMITIVES {
+        // the right half is built into an arena of its own on another thread, then moved over
+        let mut right_arena = BuildArena::new();
+        let (c_0, c_1) = std::thread::scope(|scope| {
+            let right_build = scope.spawn(|| recursive_build(split_method, max_primitives_in_node, right, first_offset + mid, &mut right_arena, n_threads / 2));
+            let c_0 = recursive_build(split_method, max_primitives_in_node, left, first_offset, arena, n_threads - n_threads / 2);
+            (c_0, right_build.join().unwrap())
+        });
+        (c_0, arena.append(right_arena) + c_1)
+    } else {
+        let c_0 = recursive_build(split_method, max_primitives_in_node, left, first_offset, arena, n_threads);
+        let c_1 = recursive_build(split_method, max_primitives_in_node, right, first_offset + mid, arena, n_threads);
+        (c_0, c_1)
+    };
+
+    arena.add_interior(dim as u32, c_0, c_1)
+}
+
+#[derive(Clone, Copy)]
+struct BVHBuildNode {
     bounds: Bounds3f,
-    children: [Option<Arc<Self>>; 2],
+    children: [u32; 2],
     split_axis: u32,
     first_primitive_offset: u32,
     n_primitives: u32,
 }
 
-impl BVHBuildNode {
-    fn new() -> Self {
+// Build nodes all live in one vector and point to their children by index
+pub struct BuildArena {
+    nodes: Vec<BVHBuildNode>,
+}
+
+impl BuildArena {
+    pub fn new() -> Self {
         Self {
-            bounds: Bounds3f::new(),
-            children: [None, None],
-            split_axis: 0u32,
-            first_primitive_offset: 0u32,
-            n_primitives: 0u32
+            nodes: Vec::new()
         }
     }
 
-    fn init_leaf(&mut self, n: u32, bounds: Bounds3f, first: u32) {
-        self.bounds = bounds;
-        self.children[0] = None;
-        self.children[1] = None;
-        self.first_primitive_offset = first;
-        self.n_primitives = n;
+    pub fn add_leaf(&mut self, n: u32, bounds: Bounds3f, first: u32) -> u32 {
+        self.nodes.push(BVHBuildNode {
+            bounds,
+            children: [0, 0],
+            split_axis: 0u32,
+            first_primitive_offset: first,
+            n_primitives: n
+        });
+        (self.nodes.len() - 1) as u32
+    }
+
+    pub fn add_interior(&mut self, axis: u32, c_0: u32, c_1: u32) -> u32 {
+        self.nodes.push(BVHBuildNode {
+            bounds: Bounds3f::union(&self.nodes[c_0 as usize].bounds, &self.nodes[c_1 as usize].bounds),
+            children: [c_0, c_1],
+            split_axis: axis,
+            first_primitive_offset: 0u32,
+            n_primitives: 0u32
+        });
+        (self.nodes.len() - 1) as u32
     }
 
-    fn init_interior(&mut self, axis: u32, c_0: Arc<Self>, c_1: Arc<Self>) {
-        self.bounds = Bounds3f::union(&c_0.bounds, &c_1.bounds);
-        self.children[0] = Some(c_0);
-        self.children[1] = Some(c_1);
-        self.split_axis = axis;
-        self.n_primitives = 0u32;
+    // Moves the nodes of other to the end, returning where they start
+    fn append(&mut self, other: BuildArena) -> u32 {
+        let base = self.nodes.len() as u32;
+        self.nodes.extend(other.nodes.into_iter().map(|mut node| {
+            if node.n_primitives == 0 {
+                node.children = node.children.map(|child| child + base);
+            }
+            node
+        }));
+        base
     }
 }
 
-struct LinearBVHNode {
+// Kept to 32 bytes so a node never straddles a cache line
+#[derive(Clone, Copy)]
+#[repr(C, align(32))]
+pub struct LinearBVHNode {
     pub bounds: Bounds3f,
-    pub primitives_offset: Option<usize>,   // for leaf
-    pub second_child_offset: Option<usize>, // interior
-    pub n_primitives: u32,
-    pub axis: u32,
+    pub offset: u32,        // first primitive for a leaf, second child for an interior node
+    pub n_primitives: u16,  // 0 for interior nodes
+    pub axis: u8,
 }
 
+const _: () = assert!(std::mem::size_of::<LinearBVHNode>() == 32);
+
 impl LinearBVHNode {
     pub fn new() -> Self {
         Self {
             bounds: Bounds3f::new(),
-            primitives_offset: None,
-            second_child_offset: None,
-            n_primitives: 0u32,
-            axis: 0u32
+            offset: 0u32,
+            n_primitives: 0u16,
+            axis: 0u8
         }
     }
 }
 
+// The binary tree collapsed into wide nodes, which single rays are traced through
+enum WideNodes {
+    Binary,
+    Four(Vec<WideBVHNode<4>>),
+    Eight(Vec<WideBVHNode<8>>),
+}
+
+stat_ratio!(RAY_HITS, "BVH/Rays that hit something");
+stat_ratio!(SHADOW_RAYS_OCCLUDED, "BVH/Shadow rays occluded");
+stat_counter!(RAY_PACKETS, "BVH/Ray packets");
+
 pub struct BVHAccel {
     pub max_primitives_in_node: u32,
     pub split_method: SplitMethod,
+    pub build_threads: usize,
+    // children per node for single rays, 2 keeps the binary tree. Packets always use the binary tree
+    pub width: usize,
+    // where the build statistics are written as JSON, if anywhere
+    pub stats_file: String,
     pub primitives: Vec<Arc<dyn Primitive>>,
-    nodes: Vec<Arc<LinearBVHNode>>,
+    pub build_stats: BVHStats,
+    nodes: Vec<LinearBVHNode>,
+    wide_nodes: WideNodes,
+}
+
+// Constructor
+fn create_bvh(prop_list: PropertyList) -> Result<LeadObject, String> {
+    let mut bvh = BVHAccel::new();
+    bvh.configure(&prop_list)?;
+    Ok(LeadObject::Aggregate(Arc::new(bvh)))
+}
+
+// Capped at 255 like pbrt does, so scenes build the same trees there. The leaf count itself is a u16
+pub fn read_max_prims(prop_list: &PropertyList, default: u32) -> Result<u32, String> {
+    let max_prims = prop_list.get_int("max_prims", default as i32);
+    if max_prims < 1 {
+        return Err(format!("max_prims has to be at least 1, got {}", max_prims));
+    }
+    if max_prims > 255 {
+        println!("max_prims is capped at 255 as in pbrt, {} is lowered to 255", max_prims);
+    }
+
+    Ok(max_prims.min(255) as u32)
 }
 
 impl BVHAccel {
     pub fn new() -> Self {
+        Self::init_split(120, SplitMethod::SAH)
+    }
+
+    pub fn init_split(max_primitives_in_node: u32, split_method: SplitMethod) -> Self {
         Self {
             primitives: Vec::new(),
-            max_primitives_in_node: 0,
-            split_method: SplitMethod::SAH,
-            nodes: Vec::new()
+            max_primitives_in_node: max_primitives_in_node.min(255),
+            split_method,
+            build_threads: std::thread::available_parallelism().map(|n| n.get()).unwrap_or(1),
+            width: native_lanes(),
+            stats_file: String::new(),
+            build_stats: BVHStats::new(),
+            nodes: Vec::new(),
+            wide_nodes: WideNodes::Binary,
         }
     }
 
-    fn recursive_build(&self, primitive_info: &mut Vec<BVHPrimitiveInfo>, start: u32, end: u32, total_nodes: &mut u32, ordered_primitives: &mut Vec<Arc<dyn Primitive>>) -> Arc<BVHBuildNode> {
-        let mut node: BVHBuildNode = BVHBuildNode::new();
-        *total_nodes += 1;
-        let mut bounds: Bounds3f = Bounds3f::new();
+    // Reads the <accel type="bvh"> settings, so that bad ones are reported before anything is built
+    pub fn configure(&mut self, prop_list: &PropertyList) -> Result<(), String> {
+        self.max_primitives_in_node = read_max_prims(prop_list, self.max_primitives_in_node)?;
+        self.split_method = match prop_list.get_string("split", "sah").as_str() {
+            "middle" => SplitMethod::Middle,
+            "equal" => SplitMethod::EqualCounts,
+            "sah" => SplitMethod::SAH,
+            "hlbvh" => SplitMethod::HLGBH,
+            split => return Err(format!("Unknown BVH split method {}, expected middle, equal, sah or hlbvh", split))
+        };
+        self.width = match prop_list.get_int("width", self.width as i32) {
+            width @ (2 | 4 | 8) => width as usize,
+            width => return Err(format!("A BVH can have 2, 4 or 8 children per node, not {}", width))
+        };
+        self.stats_file = prop_list.get_string("stats_file", "");
+
+        Ok(())
+    }
 
-        for i in start..end{
-            bounds = Bounds3f::union(&bounds, &primitive_info[i as usize].bounds);
-        }
+    pub fn node_count(&self) -> usize {
+        self.nodes.len()
+    }
 
-        let n_primitives = end - start;
+    // Takes over a tree built elsewhere, e.g. by the SBVH. Its leaves point into primitives
+    pub fn create_from_arena(&mut self, primitives: Vec<Arc<dyn Primitive>>, arena: &BuildArena, root: u32) {
+        self.primitives = primitives;
+        self.nodes.clear();
+        self.nodes.reserve(arena.nodes.len());
+        self.flatten_bvh_tree(arena, root);
+
+        assert_eq!(arena.nodes.len(), self.nodes.len(), "Not everything added to BVH!");
+
+        self.wide_nodes = match self.width {
+            2 => WideNodes::Binary,
+            4 => WideNodes::Four(WideBVHNode::collapse(&self.nodes)),
+            8 => WideNodes::Eight(WideBVHNode::collapse(&self.nodes)),
+            width => panic!("A BVH can have 2, 4 or 8 children per node, not {}!", width)
+        };
+        self.build_stats = BVHStats::compute(&self.nodes, self.memory());
+    }
 
-        if n_primitives == 1u32 {
-            let first_offset = ordered_primitives.len();
-            for i in start..end {
-                let prim_num = primitive_info[i as usize].primitive_number;
-                ordered_primitives.push(self.primitives[prim_num as usize].clone());
-            }
-            node.init_leaf(n_primitives, bounds, first_offset as u32);
+    // Bytes taken by the nodes and the primitive references, not the primitives themselves
+    pub fn memory(&self) -> usize {
+        let wide_nodes = match &self.wide_nodes {
+            WideNodes::Binary => 0,
+            WideNodes::Four(nodes) => nodes.len() * std::mem::size_of::<WideBVHNode<4>>(),
+            WideNodes::Eight(nodes) => nodes.len() * std::mem::size_of::<WideBVHNode<8>>(),
+        };
+        self.nodes.len() * std::mem::size_of::<LinearBVHNode>() + wide_nodes
+            + self.primitives.len() * std::mem::size_of::<Arc<dyn Primitive>>()
+    }
+
+    fn flatten_bvh_tree(&mut self, arena: &BuildArena, node_index: u32) -> usize {
+        let node = &arena.nodes[node_index as usize];
+        let mut linear_node: LinearBVHNode = LinearBVHNode::new();
+
+        linear_node.bounds = node.bounds;
+
+        // nodes are laid out depth first, so reserve this node's slot before its children
+        let my_offset = self.nodes.len();
+        self.nodes.push(LinearBVHNode::new());
+
+        if node.n_primitives > 0 {
+            linear_node.offset = node.first_primitive_offset;
+            linear_node.n_primitives = node.n_primitives as u16;
         } else {
-            let mut centroid_bounds = Bounds3f::new();
-            for i in start..end {
-                centroid_bounds = Bounds3f::union_pt(&centroid_bounds, &primitive_info[i as usize].centroid);
-            }
-            let dim = centroid_bounds.max_extent() as usize;
-            let mut mid = (start + end) / 2;
-
-            if centroid_bounds.p_max[dim] == centroid_bounds.p_min[dim] {
-                let first_offset = ordered_primitives.len();
-                for i in start..end {
-                    let prim_num = primitive_info[i as usize].primitive_number;
-                    ordered_primitives.push(self.primitives[prim_num as usize].clone());
-                }
-                node.init_leaf(n_primitives, bounds, first_offset as u32);
-            } else {
-                let mut split_method = self.split_method;
-                loop {  // in a loop so that it can fall from middle to equal counts if needed
-                match split_method {
-                SplitMethod::Middle => {
-                    let p_mid = (centroid_bounds.p_min[dim] + centroid_bounds.p_max[dim]) / 2.0;
+            linear_node.axis = node.split_axis as u8;
+            self.flatten_bvh_tree(arena, node.children[0]);
+            linear_node.offset = self.flatten_bvh_tree(arena, node.children[1]) as u32;
+        }
 
-                    mid = {
-                        let mut left = start as usize;
-                        let mut right = end as usize;
+        self.nodes[my_offset] = linear_node;
 
-                        while left < right {
-                            // Find the first element on the left that should be on the right
-                            while left < right && primitive_info[left].centroid[dim] < p_mid {
-                                left += 1;
-                            }
-                            // Find the first element on the right that should be on the left
-                            while left < right && primitive_info[right - 1].centroid[dim] >= p_mid {
-                                right -= 1;
-                            }
-                            // Swap the elements if necessary
-                            if left < right {
-                                primitive_info.swap(left, right - 1);
-                            }
-                        }
+        my_offset
+    }
+
+    // Single rays through the binary tree, for width 2
+    fn intersect_binary(&self, ray: &Ray, its: &mut SurfaceInteraction) -> bool {
+        let mut hit = false;
+        if self.nodes.is_empty() {
+            return hit;
+        }
 
-                        // The `left` pointer now represents the partition point
-                        left as u32
-                    };
+        // shrink t_max with every hit so only the closest intersection survives
+        let mut ray = ray.clone();
 
-                    if mid != start && mid != end {
-                        break;
+        let inv_dir = Vector3f::init([1f32 / ray.d.x(), 1f32 / ray.d.y(), 1f32 / ray.d.z()]);
+        let dir_is_neg: [i32; 3] = [if inv_dir.x() < 0f32 { 1 } else { 0 }, if inv_dir.y() < 0f32 { 1 } else { 0 }, if inv_dir.z() < 0f32 { 1 } else { 0 }];
+
+        let mut current_idx = 0usize;
+        let mut nodes_to_visit: Vec<usize> = Vec::with_capacity(64);
+        let (mut nodes_visited, mut primitives_tested) = (0u64, 0u64);
+
+        loop {
+            let node = &self.nodes[current_idx];
+            nodes_visited += 1;
+
+            if node.bounds.intersect_inv_p(&ray, &inv_dir, dir_is_neg) {
+                if node.n_primitives > 0 {
+                    let first = node.offset as usize;
+                    primitives_tested += node.n_primitives as u64;
+                    if intersect_leaf(&self.primitives[first..first + node.n_primitives as usize], &mut ray, its) {
+                        hit = true;
                     }
-                    split_method = SplitMethod::EqualCounts;
+
+                    match nodes_to_visit.pop() {
+                        Some(idx) => current_idx = idx,
+                        None => break
+                    }
+                } else if dir_is_neg[node.axis as usize] == 1 {
+                    nodes_to_visit.push(current_idx + 1);
+                    current_idx = node.offset as usize;
+                } else {
+                    nodes_to_visit.push(node.offset as usize);
+                    current_idx += 1;
                 }
-                SplitMethod::EqualCounts => {
-                    mid = (start + end) / 2;
-                    primitive_info[start as usize..end as usize]
-                        .select_nth_unstable_by(mid as usize, |a, b| a.centroid[dim].partial_cmp(&b.centroid[dim]).unwrap_or(std::cmp::Ordering::Less));
-                    break;
+            } else {
+                match nodes_to_visit.pop() {
+                    Some(idx) => current_idx = idx,
+                    None => break
                 }
-                SplitMethod::SAH => {
-                    if n_primitives <= 4 {
-                        mid = (start + end) / 2;
-                        primitive_info[start as usize..end as usize].select_nth_unstable_by((mid - start) as usize, |a, b| {
-                            a.centroid[dim].partial_cmp(&b.centroid[dim]).unwrap_or(std::cmp::Ordering::Less)
-                        });
-                    } else {
-                        let n_buckets = 12usize;
-                        struct BucketInfo {
-                            pub count: f32,
-                            pub bounds: Bounds3f
-                        }
-
-                        impl BucketInfo {
-                            pub fn new() -> Self {
-                                Self {
-                                    count: 0f32, bounds: Bounds3f::new()
-                                }
-                            }
-                        }
+            }
+        }
 
-                        let mut buckets: Vec<BucketInfo> = Vec::new();
-                        for _ in 0..n_buckets as usize {
-                            buckets.push(BucketInfo::new());
-                        }
-                        for i in start as usize..end as usize {
-                            let mut b: usize = n_buckets * (centroid_bounds.offset(&primitive_info[i].centroid)[dim]) as usize;
-                            if b == n_buckets {
-                                b -= 1usize;
-                            }
-                            buckets[b].count += 1f32;
-                            buckets[b].bounds = Bounds3f::union(&buckets[b].bounds, &primitive_info[i].bounds);
-                        }
+        record_ray(nodes_visited, primitives_tested);
+        hit
+    }
 
-                        let mut cost: Vec<f32> = Vec::new();
-                        for _ in 0..(n_buckets - 1) { cost.push(0f32); }
-                        for i in 0..(n_buckets - 1) {
-                            let mut b0 = Bounds3f::new();
-                            let mut b1 = Bounds3f::new();
-                            let mut count_0: f32 = 0f32; let mut count_1: f32 = 0f32;
-                            for j in 0..=i {
-                                b0 = Bounds3f::union(&b0, &buckets[j].bounds);
-                                count_0 += buckets[j].count;
-                            }
-                            for j in i+1..n_buckets {
-                                b1 = Bounds3f::union(&b1, &buckets[j].bounds);
-                                count_1 += buckets[j].count;
-                            }
+    fn intersect_p_binary(&self, ray: &Ray) -> bool {
+        if self.nodes.is_empty() {
+            return false;
+        }
 
-                            cost[i] = 0.125 + (count_0 * b0.surface_area() + count_1 * b1.surface_area()) / bounds.surface_area();
-                        }
+        let inv_dir = Vector3f::init([1f32 / ray.d.x(), 1f32 / ray.d.y(), 1f32 / ray.d.z()]);
+        let dir_is_neg: [i32; 3] = [if inv_dir.x() < 0f32 { 1 } else { 0 }, if inv_dir.y() < 0f32 { 1 } else { 0 }, if inv_dir.z() < 0f32 { 1 } else { 0 }];
 
-                        let mut min_cost = cost[0]; let mut min_cost_bucket: usize = 0usize;
-                        for i in 0..(n_buckets - 1) {
-                            if cost[i] < min_cost {
-                                min_cost = cost[i];
-                                min_cost_bucket = i;
-                            }
-                        }
+        let mut current_idx = 0usize;
+        let mut nodes_to_visit: Vec<usize> = Vec::with_capacity(64);
+        let (mut nodes_visited, mut primitives_tested) = (0u64, 0u64);
 
-                        let leaf_cost = n_primitives as f32;
-                        if n_primitives > self.max_primitives_in_node || min_cost < leaf_cost {
-                            let mut left = start as usize; let mut right = end as usize;
-
-                            while left < right {
-                                while left < right {
-                                    let mut b = (n_buckets as f32 * centroid_bounds.offset(&primitive_info[left].centroid)[dim]) as usize;
-
-                                    if b == n_buckets {
-                                        b -= 1;
-                                    } 
-                                    if b > min_cost_bucket {
-                                        break;
-                                    }
-                                    left += 1;
-                                }
-                                while left < right {
-                                    let mut b = (n_buckets as f32 * centroid_bounds.offset(&primitive_info[right - 1].centroid)[dim]) as usize;
-                                    if b == n_buckets {
-                                        b = n_buckets - 1;
-                                    }
-                                    if b <= min_cost_bucket {
-                                        break;
-                                    }
-                                    right -= 1;
-                                }
-                                if left < right {
-                                    primitive_info.swap(left, right - 1);
-                                }
-                                mid = left as u32;
-                            }
-                        } else {
-                            let first_offset = ordered_primitives.len();
-                            for i in start as usize..end as usize {
-                                let prim_num = primitive_info[i].primitive_number as usize;
-                                ordered_primitives.push(self.primitives[prim_num].clone());
-                            }
+        loop {
+            let node = &self.nodes[current_idx];
+            nodes_visited += 1;
 
-                            node.init_leaf(n_primitives, bounds, first_offset as u32);
-                            return Arc::new(node);
+            if node.bounds.intersect_inv_p(ray, &inv_dir, dir_is_neg) {
+                if node.n_primitives > 0 {
+                    let first = node.offset as usize;
+                    for primitive in &self.primitives[first..first + node.n_primitives as usize] {
+                        primitives_tested += 1;
+                        if primitive.intersect_p(ray) {
+                            record_shadow_ray(nodes_visited, primitives_tested);
+                            return true;
                         }
                     }
-                    break;
+
+                    match nodes_to_visit.pop() {
+                        Some(idx) => current_idx = idx,
+                        None => break
+                    }
+                } else if dir_is_neg[node.axis as usize] == 1 {
+                    nodes_to_visit.push(current_idx + 1);
+                    current_idx = node.offset as usize;
+                } else {
+                    nodes_to_visit.push(node.offset as usize);
+                    current_idx += 1;
                 }
-                _ => { split_method = SplitMethod::SAH; }
+            } else {
+                match nodes_to_visit.pop() {
+                    Some(idx) => current_idx = idx,
+                    None => break
                 }
+            }
+        }
+
+        record_shadow_ray(nodes_visited, primitives_tested);
+        false
+    }
+
+    // Closest hits of N rays traced together through the binary tree, every node tested against all of
+    // them at once. Pays off for coherent rays, like the camera's, that mostly visit the same nodes.
+    // Lanes from n_rays on only fill up the packet and are left out of the statistics
+    pub fn intersect_packet_n<const N: usize>(&self, rays: &[Ray; N], its: &mut [SurfaceInteraction; N], n_rays: usize) -> [bool; N] {
+        let mut hits = [false; N];
+        if self.nodes.is_empty() {
+            return hits;
+        }
+
+        let mut rays = rays.clone();
+        let mut lanes = LaneRays::init(&rays);
+        let mut t_entry = [0f32; N];
+
+        let mut current_idx = 0usize;
+        let mut nodes_to_visit: Vec<usize> = Vec::with_capacity(64);
+        // every ray is tested against every node the packet visits
+        let (mut nodes_visited, mut primitives_tested) = (0u64, [0u64; N]);
+
+        loop {
+            let node = &self.nodes[current_idx];
+            nodes_visited += 1;
+            let mask = slab_test(&LaneBoxes::splat(&node.bounds), &lanes, &mut t_entry);
+
+            if mask != 0 && node.n_primitives == 0 {
+                // the first ray in the node decides which child is nearer for all of them
+                let lane = mask.trailing_zeros() as usize;
+                if lanes.inv_d[node.axis as usize][lane] < 0.0 {
+                    nodes_to_visit.push(current_idx + 1);
+                    current_idx = node.offset as usize;
+                } else {
+                    nodes_to_visit.push(node.offset as usize);
+                    current_idx += 1;
                 }
+                continue;
+            }
 
-                node.init_interior(
-                    dim as u32,
-                    self.recursive_build(primitive_info, start, mid, total_nodes, ordered_primitives),
-                    self.recursive_build(primitive_info, mid, end, total_nodes, ordered_primitives),
-                );
+            if mask != 0 {
+                let leaf = &self.primitives[node.offset as usize..node.offset as usize + node.n_primitives as usize];
+                for lane in (0..N).filter(|lane| mask & (1 << lane) != 0) {
+                    primitives_tested[lane] += leaf.len() as u64;
+                    if intersect_leaf(leaf, &mut rays[lane], &mut its[lane]) {
+                        hits[lane] = true;
+                        lanes.t_max[lane] = rays[lane].t_max;
+                    }
+                }
+            }
+
+            match nodes_to_visit.pop() {
+                Some(idx) => current_idx = idx,
+                None => break
             }
         }
 
-        Arc::<BVHBuildNode>::new(node)
+        for &primitives_tested in &primitives_tested[..n_rays] {
+            record_ray(nodes_visited, primitives_tested);
+        }
+        hits
     }
 
-    fn flatten_bvh_tree(&mut self, node: &Arc<BVHBuildNode>, offset: &mut usize) -> usize {    
-        let mut linear_node: LinearBVHNode = LinearBVHNode::new();
+    // Rays drop out of the packet as soon as something blocks them
+    pub fn intersect_p_packet_n<const N: usize>(&self, rays: &[Ray; N], n_rays: usize) -> [bool; N] {
+        let mut occluded = [false; N];
+        if self.nodes.is_empty() {
+            return occluded;
+        }
 
-        linear_node.bounds = node.bounds;
+        let mut lanes = LaneRays::init(rays);
+        let mut t_entry = [0f32; N];
 
-        (*offset) += 1;
-        let my_offset = *offset;
-        
-        if node.n_primitives > 0 {
-            linear_node.primitives_offset = Some(node.first_primitive_offset as usize);
-            linear_node.n_primitives = node.n_primitives;
-        } else {
-            linear_node.axis = node.split_axis;
-            linear_node.n_primitives = 0u32;
+        let mut nodes_to_visit: Vec<usize> = vec![0];
+        let (mut nodes_visited, mut primitives_tested) = ([0u64; N], [0u64; N]);
 
-            match &node.children[0] {
-                Some(s) => {self.flatten_bvh_tree(s, offset);}
-                None => {}
+        while let Some(current_idx) = nodes_to_visit.pop() {
+            let node = &self.nodes[current_idx];
+            for lane in (0..N).filter(|&lane| !occluded[lane]) {
+                nodes_visited[lane] += 1;
             }
-            match &node.children[1] {
-                Some(s) => {linear_node.second_child_offset = Some(self.flatten_bvh_tree(s, offset));}
-                None => {}
+            let mask = slab_test(&LaneBoxes::splat(&node.bounds), &lanes, &mut t_entry);
+            if mask == 0 {
+                continue;
+            }
+
+            if node.n_primitives == 0 {
+                nodes_to_visit.push(node.offset as usize);
+                nodes_to_visit.push(current_idx + 1);
+                continue;
             }
-        }
 
-        if self.nodes.len() <= *offset {
-            for _ in self.nodes.len()..(*offset) {
-                self.nodes.push(Arc::new(LinearBVHNode::new()));
+            let leaf = &self.primitives[node.offset as usize..node.offset as usize + node.n_primitives as usize];
+            for lane in (0..N).filter(|lane| mask & (1 << lane) != 0) {
+                for primitive in leaf {
+                    primitives_tested[lane] += 1;
+                    if primitive.intersect_p(&rays[lane]) {
+                        occluded[lane] = true;
+                        // no box is entered before -inf
+                        lanes.t_max[lane] = f32::NEG_INFINITY;
+                        break;
+                    }
+                }
+            }
+            if occluded.iter().all(|&occluded| occluded) {
+                break;
             }
         }
-        self.nodes[*offset - 1] = Arc::new(linear_node);
 
-        my_offset
+        for lane in 0..n_rays {
+            record_shadow_ray(nodes_visited[lane], primitives_tested[lane]);
+        }
+        occluded
     }
+}
 
+// Closest hit among a leaf's primitives, shortening the ray to it
+pub fn intersect_leaf(leaf: &[Arc<dyn Primitive>], ray: &mut Ray, its: &mut SurfaceInteraction) -> bool {
+    let mut hit = false;
+    for primitive in leaf {
+        // primitives wrapping others, like moving ones, name the one hit inside
+        let closest = its.primitive.take();
+        if primitive.intersect(ray, its) {
+            if its.primitive.is_none() {
+                its.set_primitive(primitive.clone());
+            }
+            hit = true;
+            ray.t_max = its.t;
+        } else {
+            its.primitive = closest;
+        }
+    }
+    hit
 }
 
 impl Aggregate for BVHAccel {
-    fn create(&mut self, primitives: Vec<Arc<dyn Primitive>>, max_primitives_in_node: u32, split_method: SplitMethod) {
+    fn create(&mut self, primitives: Vec<Arc<dyn Primitive>>) {
+        let start = std::time::Instant::now();
         self.primitives = primitives;
-        self.max_primitives_in_node = if max_primitives_in_node < 255 { max_primitives_in_node } else { 255u32 };
-        self.split_method = split_method;
+        self.nodes.clear();
+        self.wide_nodes = WideNodes::Binary;
+        self.build_stats = BVHStats::new();
+        if self.primitives.is_empty() {
+            return;
+        }
 
         let mut primitive_info: Vec<BVHPrimitiveInfo> = Vec::new();
         for i in 0..(self.primitives.len()) {
             primitive_info.push(BVHPrimitiveInfo::new(i as u32, self.primitives[i].world_bound()));
         }
 
-        let mut total_nodes = 0u32;
-        let mut ordered_primitives: Vec<Arc<dyn Primitive>> = Vec::new();
-
-        let root: Arc<BVHBuildNode> = self.recursive_build(&mut primitive_info, 0, self.primitives.len() as u32, &mut total_nodes, &mut ordered_primitives);
+        // the builders only see the infos, the primitives themselves are not shared between threads
+        let mut arena = BuildArena::new();
+        let n_threads = self.build_threads.max(1);
+        let root = match self.split_method {
+            SplitMethod::HLGBH => hlbvh_build(self.max_primitives_in_node, &mut primitive_info, &mut arena, n_threads),
+            _ => recursive_build(self.split_method, self.max_primitives_in_node, &mut primitive_info, 0, &mut arena, n_threads)
+        };
+
+        // the infos are in leaf order now
+        let ordered_primitives = primitive_info.iter()
+            .map(|info| self.primitives[info.primitive_number as usize].clone())
+            .collect();
+        self.create_from_arena(ordered_primitives, &arena, root);
+        self.build_stats.build_time = start.elapsed();
+
+        if !self.stats_file.is_empty() {
+            if let Err(e) = std::fs::write(&self.stats_file, self.build_stats.to_json()) {
+                println!("Could not write BVH statistics to {}: {}", self.stats_file, e);
+            }
+        }
+    }
 
-        self.primitives = ordered_primitives;
-        let mut offset: usize = 0usize;
+    fn primitives(&self) -> &Vec<Arc<dyn Primitive>> {
+        &self.primitives
+    }
 
-        let max_bvh_nodes = 2 * self.primitives.len() - 1;
-        self.nodes.reserve(max_bvh_nodes);
+    fn intersect_packet(&self, rays: &[Ray; PACKET_SIZE], its: &mut [SurfaceInteraction; PACKET_SIZE], n_rays: usize) -> [bool; PACKET_SIZE] {
+        RAY_PACKETS.with(|packets| packets.add(1));
+        let hits = self.intersect_packet_n(rays, its, n_rays);
+        RAY_HITS.with(|ray_hits| ray_hits.add(hits[..n_rays].iter().filter(|&&hit| hit).count() as u64, n_rays as u64));
+        hits
+    }
 
-        self.flatten_bvh_tree(&root, &mut offset);
-        
-        assert_eq!(total_nodes, offset as u32, "Not everything added to BVH!");
+    fn intersect_p_packet(&self, rays: &[Ray; PACKET_SIZE], n_rays: usize) -> [bool; PACKET_SIZE] {
+        RAY_PACKETS.with(|packets| packets.add(1));
+        let occluded = self.intersect_p_packet_n(rays, n_rays);
+        SHADOW_RAYS_OCCLUDED.with(|occluded_rays| occluded_rays.add(occluded[..n_rays].iter().filter(|&&hit| hit).count() as u64, n_rays as u64));
+        occluded
     }
+}
 
-    fn primitives(&self) -> &Vec<Arc<dyn Primitive>> {
-        &self.primitives
+impl LeadObjectTrait for BVHAccel {
+    fn init(&mut self, _prop_list: PropertyList) { }
+
+    fn add_child(&mut self, child: &mut LeadObject) {
+        println!("Struct BVHAccel does not take a child of class {}", child.to_string())
     }
 
+    fn activate(&mut self) { }
+
     fn to_string(&self) -> String {
         format!(
-            "BVH[\n  max primitives in node: {},\n  split method: {},\n  primitive count: {},\n  number of nodes: {}\n]",
-            self.max_primitives_in_node, self.split_method.to_string(),
-            self.primitives.len(), self.nodes.len()
+            "BVH[\n  max primitives in node: {},\n  split method: {},\n  width: {},\n  primitive count: {},\n  number of nodes: {},\n  build stats: \n{}\n]",
+            self.max_primitives_in_node, self.split_method.to_string(), self.width,
+            self.primitives.len(), self.nodes.len(), indent(&self.build_stats.to_string(), 4)
         )
     }
 }
 
 impl Primitive for BVHAccel {
-    fn compute_scattering_functions(&self, _its: &SurfaceInteraction, _mode: TransportMode, _allow_multiple_lobes: bool) {
+    fn compute_scattering_functions(&self, _its: &mut SurfaceInteraction, _mode: TransportMode, _allow_multiple_lobes: bool) {
         panic!("This should not be called for an aggregate!")
     }
 
@@ -392,110 +1005,35 @@ impl Primitive for BVHAccel {
     }
 
     fn intersect(&self, ray: &Ray, its: &mut SurfaceInteraction) -> bool {
-        let mut hit = false;
-
-        let inv_dir = Vector3f::init([1f32 / ray.d.x(), 1f32 / ray.d.y(), 1f32 / ray.d.z()]);
-        let dir_is_neg: [i32; 3] = [if inv_dir.x() < 0f32 { 1 } else { 0 }, if inv_dir.y() < 0f32 { 1 } else { 0 }, if inv_dir.z() < 0f32 { 1 } else { 0 }];
-
-        let mut to_visit_offset = 0usize; let mut current_idx = 0usize;
-        let mut nodes_to_visit: Vec<usize> = Vec::new();
-
-        loop {
-            let node = &self.nodes[current_idx];
-
-            if node.bounds.intersect_inv_p(ray, &inv_dir, dir_is_neg) {
-                if node.n_primitives > 0 {
-                    if let Some(primitives_offset) = node.primitives_offset{
-                        for i in 0..node.n_primitives as usize {
-                            if self.primitives[primitives_offset + i].intersect(ray, its) {
-                                hit = true;
-                            }
-                        }
-    
-                        if to_visit_offset == 0 {
-                            break;
-                        }
-                        to_visit_offset -= 1;
-                        current_idx = nodes_to_visit[to_visit_offset];
-                    }
-                } else if let Some(second_child_offset) = node.second_child_offset {
-                    if dir_is_neg[node.axis as usize] == 1 {
-                        nodes_to_visit.push(current_idx + 1);
-                        to_visit_offset += 1;
-                        current_idx = second_child_offset;
-                    } else {
-                        nodes_to_visit.push(second_child_offset);
-                        to_visit_offset += 1;
-                        current_idx = current_idx + 1;
-                    }
-                }
-            } else {
-                if to_visit_offset == 0 {
-                    break;
-                }
-
-                to_visit_offset -= 1;
-                current_idx = nodes_to_visit[to_visit_offset];
-            }
-        }
-
+        let hit = match &self.wide_nodes {
+            WideNodes::Four(nodes) => intersect_wide(nodes, &self.primitives, ray, its),
+            WideNodes::Eight(nodes) => intersect_wide(nodes, &self.primitives, ray, its),
+            WideNodes::Binary => self.intersect_binary(ray, its)
+        };
+        RAY_HITS.with(|hits| hits.add(hit as u64, 1));
         hit
     }
 
     fn intersect_p(&self, ray: &Ray) -> bool {
-        let inv_dir = Vector3f::init([1f32 / ray.d.x(), 1f32 / ray.d.y(), 1f32 / ray.d.z()]);
-        let dir_is_neg: [i32; 3] = [if inv_dir.x() < 0f32 { 1 } else { 0 }, if inv_dir.y() < 0f32 { 1 } else { 0 }, if inv_dir.z() < 0f32 { 1 } else { 0 }];
-
-        let mut to_visit_offset = 0usize; let mut current_idx = 0usize;
-        let mut nodes_to_visit: Vec<usize> = Vec::new();
-
-        loop {
-            let node = &self.nodes[current_idx];
-
-            if node.bounds.intersect_inv_p(ray, &inv_dir, dir_is_neg) {
-                if node.n_primitives > 0 {
-                    if let Some(primitives_offset) = node.primitives_offset{
-                        for i in 0..node.n_primitives as usize {
-                            if self.primitives[primitives_offset + i].intersect_p(ray) {
-                                return true;
-                            }
-                        }
-    
-                        if to_visit_offset == 0 {
-                            break;
-                        }
-                        to_visit_offset -= 1;
-                        current_idx = nodes_to_visit[to_visit_offset];
-                    }
-                } else if let Some(second_child_offset) = node.second_child_offset {
-                    if dir_is_neg[node.axis as usize] == 1 {
-                        nodes_to_visit.push(current_idx + 1);
-                        to_visit_offset += 1;
-                        current_idx = second_child_offset;
-                    } else {
-                        nodes_to_visit.push(second_child_offset);
-                        to_visit_offset += 1;
-                        current_idx = current_idx + 1;
-                    }
-                }
-            } else {
-                if to_visit_offset == 0 {
-                    break;
-                }
-
-                to_visit_offset -= 1;
-                current_idx = nodes_to_visit[to_visit_offset];
-            }
-        }
-
-        false
+        let occluded = match &self.wide_nodes {
+            WideNodes::Four(nodes) => intersect_p_wide(nodes, &self.primitives, ray),
+            WideNodes::Eight(nodes) => intersect_p_wide(nodes, &self.primitives, ray),
+            WideNodes::Binary => self.intersect_p_binary(ray)
+        };
+        SHADOW_RAYS_OCCLUDED.with(|occluded_rays| occluded_rays.add(occluded as u64, 1));
+        occluded
     }
 
     fn world_bound(&self) -> Bounds3f {
-        self.primitives[0 as usize].world_bound()
+        match self.nodes.first() {
+            Some(root) => root.bounds,
+            None => Bounds3f::new()
+        }
     }
 
     fn shape(&self) -> Option<Arc<dyn Shape>> {
         None
     }
-}
\ No newline at end of file
+}
+
+register_fallible_struct!("bvh", create_bvh);
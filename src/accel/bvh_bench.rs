@@ -0,0 +1,171 @@
+use crate::common::*;
+use std::time::{Duration, Instant};
+
+// Soup of random triangles filling [-1, 1]^3, about as many per volume everywhere. Thin ones are
+// stretched along a random direction, the case spatial splits are made for
+fn generate_triangles(n_triangles: usize, thin: bool, rng: &mut RNG) -> Vec<Arc<dyn Primitive>> {
+    let size = 2.0 / (n_triangles as f32).cbrt();
+    let mut p: Vec<Point3f> = Vec::with_capacity(3 * n_triangles);
+    for _ in 0..n_triangles {
+        let center = Point3f::init([0; 3].map(|_| 2.0 * rng.uniform_f32() - 1.0));
+        let mut corners = [0; 3].map(|_| Vector3f::init([0; 3].map(|_| (rng.uniform_f32() - 0.5) * size)));
+        if thin {
+            let stretch = Vector3f::init([0; 3].map(|_| (rng.uniform_f32() - 0.5) * 0.5));
+            corners[0] += stretch;
+            corners[1] -= stretch;
+        }
+        p.extend(corners.map(|corner| center + corner));
+    }
+
+    let mesh = Arc::new(TriangleMesh::new(Transform::new(), (0..3 * n_triangles).collect(), p, Vec::new(), Vec::new(), Vec::new(), false));
+    create_triangles(mesh).into_iter()
+        .map(|triangle| Arc::new(GeometricPrimitive::init_shape(triangle)) as Arc<dyn Primitive>)
+        .collect()
+}
+
+// Rays from a sphere around the scene towards random points inside it
+fn generate_rays(n_rays: usize, rng: &mut RNG) -> Vec<Ray> {
+    (0..n_rays).map(|_| {
+        let mut w = Warp::uniform_sample_hemisphere(Point2f::init([rng.uniform_f32(), rng.uniform_f32()]));
+        if rng.uniform_f32() < 0.5 {
+            w = -w;
+        }
+        let o = Point3f::new() + w * 3.0;
+        let target = Point3f::init([0; 3].map(|_| 2.0 * rng.uniform_f32() - 1.0));
+        Ray::init(&o, &(target - o), EPSILON, INFINITY)
+    }).collect()
+}
+
+// Pinhole camera rays in front of the scene, neighbours next to each other like an integrator makes them
+fn generate_camera_rays(n_rays: usize) -> Vec<Ray> {
+    let resolution = ((n_rays as f32).sqrt().ceil() as usize).max(1);
+    let o = Point3f::init([0.0, 0.0, -3.0]);
+    (0..n_rays).map(|i| {
+        let (x, y) = ((i % resolution) as f32 + 0.5, (i / resolution) as f32 + 0.5);
+        let target = Point3f::init([2.0 * x / resolution as f32 - 1.0, 1.0 - 2.0 * y / resolution as f32, 0.0]);
+        Ray::init(&o, &(target - o), EPSILON, INFINITY)
+    }).collect()
+}
+
+fn rays_per_second(n_rays: usize, elapsed: Duration) -> String {
+    format!("{:.2} Mrays/s", n_rays as f64 / elapsed.as_secs_f64() / 1e6)
+}
+
+// Traversal statistics recorded on this thread since the last call, the bench traces everything on one thread
+fn take_stats() -> String {
+    report_thread_stats();
+    let stats = category_stats_to_string("Accelerator");
+    clear_stats();
+    stats
+}
+
+// Closest hits and then shadow rays, each with the traversal statistics they produced
+fn trace(aggregate: &dyn Aggregate, rays: &[Ray]) -> String {
+    take_stats();
+    let start = Instant::now();
+    let mut hits = 0usize;
+    for ray in rays {
+        let mut its = SurfaceInteraction::new();
+        if aggregate.intersect(ray, &mut its) {
+            hits += 1;
+        }
+    }
+    let intersect_time = start.elapsed();
+    let intersect_stats = take_stats();
+
+    let start = Instant::now();
+    let occluded = rays.iter().filter(|ray| aggregate.intersect_p(ray)).count();
+    let intersect_p_time = start.elapsed();
+    let intersect_p_stats = take_stats();
+
+    format!(
+        "  intersect: {:?}, {} ({} hits)\n{}\n  intersect_p: {:?}, {} ({} hits)\n{}",
+        intersect_time, rays_per_second(rays.len(), intersect_time), hits, indent(&intersect_stats, 4),
+        intersect_p_time, rays_per_second(rays.len(), intersect_p_time), occluded, indent(&intersect_p_stats, 4)
+    )
+}
+
+// The same rays in packets, the last one filled up with copies of its first ray
+fn trace_packets(aggregate: &dyn Aggregate, rays: &[Ray]) -> String {
+    take_stats();
+    let packets: Vec<[Ray; PACKET_SIZE]> = rays.chunks(PACKET_SIZE)
+        .map(|chunk| std::array::from_fn(|i| chunk.get(i).unwrap_or(&chunk[0]).clone()))
+        .collect();
+
+    let start = Instant::now();
+    let mut hits = 0usize;
+    for (packet, chunk) in packets.iter().zip(rays.chunks(PACKET_SIZE)) {
+        let mut its: [SurfaceInteraction; PACKET_SIZE] = std::array::from_fn(|_| SurfaceInteraction::new());
+        hits += aggregate.intersect_packet(packet, &mut its, chunk.len())[..chunk.len()].iter().filter(|&&hit| hit).count();
+    }
+    let intersect_time = start.elapsed();
+    let intersect_stats = take_stats();
+
+    let start = Instant::now();
+    let mut occluded = 0usize;
+    for (packet, chunk) in packets.iter().zip(rays.chunks(PACKET_SIZE)) {
+        occluded += aggregate.intersect_p_packet(packet, chunk.len())[..chunk.len()].iter().filter(|&&hit| hit).count();
+    }
+    let intersect_p_time = start.elapsed();
+    let intersect_p_stats = take_stats();
+
+    format!(
+        "  intersect_packet: {:?}, {} ({} hits)\n{}\n  intersect_p_packet: {:?}, {} ({} hits)\n{}",
+        intersect_time, rays_per_second(rays.len(), intersect_time), hits, indent(&intersect_stats, 4),
+        intersect_p_time, rays_per_second(rays.len(), intersect_p_time), occluded, indent(&intersect_p_stats, 4)
+    )
+}
+
+// A few primitives per leaf, so the methods differ by where they split and not by when they stop
+const MAX_PRIMITIVES_IN_NODE: u32 = 4;
+
+// Times building every acceleration structure over a generated scene, and tracing rays through it
+pub fn bench_bvh(n_primitives: usize, n_rays: usize) {
+    let mut rng = RNG::new_seeded(0);
+    let rays = generate_rays(n_rays, &mut rng);
+    let camera_rays = generate_camera_rays(n_rays);
+
+    for thin in [false, true] {
+        println!("Generating {} {} triangles and {} rays", n_primitives, if thin { "long thin" } else { "small" }, n_rays);
+        let primitives = generate_triangles(n_primitives, thin, &mut rng);
+
+        for split_method in [SplitMethod::SAH, SplitMethod::HLGBH, SplitMethod::Middle, SplitMethod::EqualCounts] {
+            let mut bvh = BVHAccel::init_split(MAX_PRIMITIVES_IN_NODE, split_method);
+            let n_threads = bvh.build_threads;
+            bvh.build_threads = 1;
+            let start = Instant::now();
+            bvh.create(primitives.clone());
+            let serial_build_time = start.elapsed();
+
+            let mut bvh = BVHAccel::init_split(MAX_PRIMITIVES_IN_NODE, split_method);
+            let start = Instant::now();
+            bvh.create(primitives.clone());
+            let build_time = start.elapsed();
+
+            println!(
+                "BVH, {}:\n  build: {:?} on 1 thread, {:?} on {}\n{}",
+                split_method.to_string(), serial_build_time, build_time, n_threads, trace(&bvh, &rays)
+            );
+        }
+
+        // single rays through binary and wide nodes, then camera rays one by one and in packets
+        for width in [2, 4, 8] {
+            let mut bvh = BVHAccel::init_split(MAX_PRIMITIVES_IN_NODE, SplitMethod::SAH);
+            bvh.width = width;
+            bvh.create(primitives.clone());
+            println!("BVH{}:\n{}", width, trace(&bvh, &rays));
+        }
+        let mut bvh = BVHAccel::init_split(MAX_PRIMITIVES_IN_NODE, SplitMethod::SAH);
+        bvh.create(primitives.clone());
+        println!("BVH{}, camera rays:\n{}\n{}", bvh.width, trace(&bvh, &camera_rays), trace_packets(&bvh, &camera_rays));
+
+        let mut others: Vec<(&str, Box<dyn Aggregate>)> = vec![("SBVH", Box::new(SBVHAccel::new())), ("KdTree", Box::new(KdTreeAccel::new()))];
+        for (name, aggregate) in others.iter_mut() {
+            let start = Instant::now();
+            aggregate.create(primitives.clone());
+            let build_time = start.elapsed();
+
+            println!("{}:\n  build: {:?}\n{}", name, build_time, trace(aggregate.as_ref(), &rays));
+        }
+    }
+}
@@ -0,0 +1,100 @@
+use crate::common::*;
+use std::fmt;
+use std::time::Duration;
+
+// Shape and cost of a built BVH, to tune max_prims and the split method per scene
+#[derive(Clone, Default)]
+pub struct BVHStats {
+    pub interior_nodes: usize,
+    pub leaves: usize,
+    pub leaf_histogram: Vec<usize>,  // bucket k counts leaves of 2^k to 2^(k+1) - 1 primitives
+    pub max_depth: usize,
+    pub sah_cost: f32,               // relative to the root, with the builder's costs
+    pub build_time: Duration,
+    pub memory: usize,               // bytes of nodes and primitive references
+}
+
+impl BVHStats {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn compute(nodes: &[LinearBVHNode], memory: usize) -> Self {
+        let mut stats = Self::new();
+        stats.memory = memory;
+        if nodes.is_empty() {
+            return stats;
+        }
+
+        // a flat root would make every ratio infinite
+        let root_area = nodes[0].bounds.surface_area();
+        let root_area = if root_area > 0.0 { root_area } else { 1.0 };
+
+        // the first child of an interior node follows it, the second is at its offset
+        let mut nodes_to_visit = vec![(0usize, 1usize)];
+        while let Some((index, depth)) = nodes_to_visit.pop() {
+            let node = &nodes[index];
+            let area = node.bounds.surface_area() / root_area;
+            stats.max_depth = stats.max_depth.max(depth);
+
+            if node.n_primitives > 0 {
+                stats.leaves += 1;
+                stats.sah_cost += area * node.n_primitives as f32;
+
+                let bucket = node.n_primitives.ilog2() as usize;
+                if stats.leaf_histogram.len() <= bucket {
+                    stats.leaf_histogram.resize(bucket + 1, 0);
+                }
+                stats.leaf_histogram[bucket] += 1;
+            } else {
+                stats.interior_nodes += 1;
+                stats.sah_cost += area * 0.125;
+                nodes_to_visit.push((index + 1, depth + 1));
+                nodes_to_visit.push((node.offset as usize, depth + 1));
+            }
+        }
+
+        stats
+    }
+
+    // Histogram buckets as (label, count), leaving out the empty ones
+    fn leaf_buckets(&self) -> Vec<(String, usize)> {
+        self.leaf_histogram.iter().enumerate()
+            .filter(|(_, &count)| count > 0)
+            .map(|(k, &count)| {
+                let label = match k {
+                    0 => String::from("1"),
+                    _ => format!("{}-{}", 1 << k, (1 << (k + 1)) - 1)
+                };
+                (label, count)
+            })
+            .collect()
+    }
+
+    pub fn to_json(&self) -> String {
+        let buckets: Vec<String> = self.leaf_buckets().into_iter()
+            .map(|(label, count)| format!("\"{}\": {}", label, count))
+            .collect();
+
+        format!(
+            "{{\n  \"nodes\": {},\n  \"interior_nodes\": {},\n  \"leaves\": {},\n  \"leaf_histogram\": {{ {} }},\n  \"max_depth\": {},\n  \"sah_cost\": {},\n  \"build_time_seconds\": {},\n  \"memory_bytes\": {}\n}}\n",
+            self.interior_nodes + self.leaves, self.interior_nodes, self.leaves, buckets.join(", "),
+            self.max_depth, self.sah_cost, self.build_time.as_secs_f64(), self.memory
+        )
+    }
+}
+
+impl fmt::Display for BVHStats {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let buckets: Vec<String> = self.leaf_buckets().into_iter()
+            .map(|(label, count)| format!("{}: {}", label, count))
+            .collect();
+
+        write!(
+            f,
+            "BVHStats[\n  nodes: {},\n  interior nodes: {},\n  leaves: {},\n  leaf sizes: {{ {} }},\n  max depth: {},\n  SAH cost: {:.3},\n  build time: {:.3}s,\n  memory: {:.2} MB\n]",
+            self.interior_nodes + self.leaves, self.interior_nodes, self.leaves, buckets.join(", "),
+            self.max_depth, self.sah_cost, self.build_time.as_secs_f64(), self.memory as f64 / (1024.0 * 1024.0)
+        )
+    }
+}
@@ -0,0 +1,418 @@
+use crate::common::*;
+
+// kd-tree with split planes picked by the SAH along the primitives' bound edges (pbrt's KdTreeAccel).
+// Primitives crossing a plane go to both sides, and splits cutting off empty space get a bonus
+pub struct KdTreeAccel {
+    intersect_cost: i32,
+    traversal_cost: i32,
+    max_primitives: i32,
+    empty_bonus: f32,
+    // below 1 it follows the primitive count
+    max_depth: i32,
+    primitives: Vec<Arc<dyn Primitive>>,
+    // for leaves with more than one primitive
+    primitive_indices: Vec<u32>,
+    nodes: Vec<KdAccelNode>,
+    bounds: Bounds3f,
+}
+
+// Constructor
+fn create_kdtree(prop_list: PropertyList) -> Result<LeadObject, String> {
+    let mut kdtree = KdTreeAccel::new();
+    kdtree.configure(&prop_list)?;
+    Ok(LeadObject::Aggregate(Arc::new(kdtree)))
+}
+
+const LEAF_FLAG: u32 = 3;
+
+// The low two bits of flags are the split axis, or LEAF_FLAG. The rest is the child above the split,
+// the one below comes right after its parent, or for a leaf the number of primitives. offset is the
+// primitive itself for a leaf of one, else the first of its entries in primitive_indices
+#[derive(Clone, Copy)]
+struct KdAccelNode {
+    split: f32,
+    flags: u32,
+    offset: u32,
+}
+
+impl KdAccelNode {
+    fn init_leaf(primitive_numbers: &[u32], primitive_indices: &mut Vec<u32>) -> Self {
+        let n_primitives = primitive_numbers.len();
+        let offset = match n_primitives {
+            0 => 0,
+            1 => primitive_numbers[0],
+            _ => {
+                primitive_indices.extend_from_slice(primitive_numbers);
+                (primitive_indices.len() - n_primitives) as u32
+            }
+        };
+
+        Self {
+            split: 0.0,
+            flags: LEAF_FLAG | ((n_primitives as u32) << 2),
+            offset,
+        }
+    }
+
+    fn init_interior(axis: u32, above_child: u32, split: f32) -> Self {
+        Self {
+            split,
+            flags: axis | (above_child << 2),
+            offset: 0,
+        }
+    }
+
+    fn is_leaf(&self) -> bool { (self.flags & 3) == LEAF_FLAG }
+    fn split_axis(&self) -> usize { (self.flags & 3) as usize }
+    fn n_primitives(&self) -> usize { (self.flags >> 2) as usize }
+    fn above_child(&self) -> usize { (self.flags >> 2) as usize }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum EdgeType {
+    Start,
+    End,
+}
+
+#[derive(Clone, Copy)]
+struct BoundEdge {
+    t: f32,
+    primitive_number: u32,
+    edge_type: EdgeType,
+}
+
+// Node still to be visited, with the part of the ray inside it
+struct KdToDo {
+    node: usize,
+    t_min: f32,
+    t_max: f32,
+}
+
+impl KdTreeAccel {
+    pub fn new() -> Self {
+        Self {
+            intersect_cost: 80,
+            traversal_cost: 1,
+            max_primitives: 1,
+            empty_bonus: 0.5,
+            max_depth: -1,
+            primitives: Vec::new(),
+            primitive_indices: Vec::new(),
+            nodes: Vec::new(),
+            bounds: Bounds3f::new(),
+        }
+    }
+
+    // Costs that would make splits look free, or empty nodes better than free, give degenerate trees
+    pub fn configure(&mut self, prop_list: &PropertyList) -> Result<(), String> {
+        self.intersect_cost = prop_list.get_int("intersect_cost", self.intersect_cost);
+        if self.intersect_cost < 1 {
+            return Err(format!("intersect_cost has to be at least 1, got {}", self.intersect_cost));
+        }
+        self.traversal_cost = prop_list.get_int("traversal_cost", self.traversal_cost);
+        if self.traversal_cost < 0 {
+            return Err(format!("traversal_cost can not be negative, got {}", self.traversal_cost));
+        }
+        self.empty_bonus = prop_list.get_float("empty_bonus", self.empty_bonus);
+        if !(0.0..=1.0).contains(&self.empty_bonus) {
+            return Err(format!("empty_bonus has to be between 0 and 1, got {}", self.empty_bonus));
+        }
+        self.max_primitives = read_max_prims(prop_list, self.max_primitives as u32)? as i32;
+        self.max_depth = prop_list.get_int("max_depth", self.max_depth);
+
+        Ok(())
+    }
+
+    fn build_tree(&mut self, node_bounds: &Bounds3f, all_primitive_bounds: &[Bounds3f], primitive_numbers: &[u32], depth: i32, edges: &mut [Vec<BoundEdge>; 3], mut bad_refines: i32) {
+        let node_num = self.nodes.len();
+        let n_primitives = primitive_numbers.len();
+
+        if n_primitives <= self.max_primitives as usize || depth == 0 {
+            self.nodes.push(KdAccelNode::init_leaf(primitive_numbers, &mut self.primitive_indices));
+            return;
+        }
+
+        // SAH cost of a split at every edge, trying the other axes if the longest one has none inside the node
+        let mut best_axis: Option<usize> = None;
+        let mut best_offset = 0usize;
+        let mut best_cost = INFINITY;
+        let old_cost = (self.intersect_cost * n_primitives as i32) as f32;
+        let total_sa = node_bounds.surface_area();
+        let inv_total_sa = 1.0 / total_sa;
+        let d = node_bounds.diagonal();
+
+        let mut axis = node_bounds.max_extent() as usize;
+        for _ in 0..3 {
+            let axis_edges = &mut edges[axis];
+            axis_edges.clear();
+            for &pn in primitive_numbers {
+                let bounds = &all_primitive_bounds[pn as usize];
+                axis_edges.push(BoundEdge { t: bounds.p_min[axis], primitive_number: pn, edge_type: EdgeType::Start });
+                axis_edges.push(BoundEdge { t: bounds.p_max[axis], primitive_number: pn, edge_type: EdgeType::End });
+            }
+            // at the same spot primitives start before others end
+            axis_edges.sort_by(|e0, e1| e0.t.partial_cmp(&e1.t).unwrap_or(std::cmp::Ordering::Equal).then(e0.edge_type.cmp(&e1.edge_type)));
+
+            let mut n_below = 0usize;
+            let mut n_above = n_primitives;
+            for (i, edge) in axis_edges.iter().enumerate() {
+                if edge.edge_type == EdgeType::End {
+                    n_above -= 1;
+                }
+
+                let edge_t = edge.t;
+                if edge_t > node_bounds.p_min[axis] && edge_t < node_bounds.p_max[axis] {
+                    let other_axis_0 = (axis + 1) % 3;
+                    let other_axis_1 = (axis + 2) % 3;
+                    let below_sa = 2.0 * (d[other_axis_0] * d[other_axis_1] + (edge_t - node_bounds.p_min[axis]) * (d[other_axis_0] + d[other_axis_1]));
+                    let above_sa = 2.0 * (d[other_axis_0] * d[other_axis_1] + (node_bounds.p_max[axis] - edge_t) * (d[other_axis_0] + d[other_axis_1]));
+                    let p_below = below_sa * inv_total_sa;
+                    let p_above = above_sa * inv_total_sa;
+                    let eb = if n_above == 0 || n_below == 0 { self.empty_bonus } else { 0.0 };
+                    let cost = self.traversal_cost as f32 + self.intersect_cost as f32 * (1.0 - eb) * (p_below * n_below as f32 + p_above * n_above as f32);
+
+                    if cost < best_cost {
+                        best_cost = cost;
+                        best_axis = Some(axis);
+                        best_offset = i;
+                    }
+                }
+
+                if edge.edge_type == EdgeType::Start {
+                    n_below += 1;
+                }
+            }
+
+            if best_axis.is_some() {
+                break;
+            }
+            axis = (axis + 1) % 3;
+        }
+
+        // a few splits that do not pay off are allowed, the ones below may still do
+        if best_cost > old_cost {
+            bad_refines += 1;
+        }
+        let best_axis = match best_axis {
+            Some(best_axis) if !((best_cost > 4.0 * old_cost && n_primitives < 16) || bad_refines == 3) => best_axis,
+            _ => {
+                self.nodes.push(KdAccelNode::init_leaf(primitive_numbers, &mut self.primitive_indices));
+                return;
+            }
+        };
+
+        let split_edges = &edges[best_axis];
+        let below: Vec<u32> = split_edges[..best_offset].iter()
+            .filter(|e| e.edge_type == EdgeType::Start)
+            .map(|e| e.primitive_number)
+            .collect();
+        let above: Vec<u32> = split_edges[best_offset + 1..].iter()
+            .filter(|e| e.edge_type == EdgeType::End)
+            .map(|e| e.primitive_number)
+            .collect();
+        let t_split = split_edges[best_offset].t;
+
+        let mut bounds_below = *node_bounds;
+        bounds_below.p_max[best_axis] = t_split;
+        let mut bounds_above = *node_bounds;
+        bounds_above.p_min[best_axis] = t_split;
+
+        // reserve this node's slot, the child below follows directly
+        self.nodes.push(KdAccelNode::init_leaf(&[], &mut self.primitive_indices));
+        self.build_tree(&bounds_below, all_primitive_bounds, &below, depth - 1, edges, bad_refines);
+        let above_child = self.nodes.len() as u32;
+        self.build_tree(&bounds_above, all_primitive_bounds, &above, depth - 1, edges, bad_refines);
+        self.nodes[node_num] = KdAccelNode::init_interior(best_axis as u32, above_child, t_split);
+    }
+
+    fn leaf_primitives(&self, node: &KdAccelNode) -> Vec<&Arc<dyn Primitive>> {
+        match node.n_primitives() {
+            0 => Vec::new(),
+            1 => vec![&self.primitives[node.offset as usize]],
+            n => self.primitive_indices[node.offset as usize..node.offset as usize + n].iter()
+                .map(|&i| &self.primitives[i as usize])
+                .collect()
+        }
+    }
+
+    // Walks the nodes along the ray front to back, calling visit_leaf until it asks to stop. The ray's
+    // t_max is read again after every leaf, so hits found so far cut the walk short
+    fn traverse<F>(&self, ray: &Ray, t_max: &dyn Fn() -> f32, mut visit_leaf: F) -> u64
+        where F: FnMut(&KdAccelNode) -> bool
+    {
+        let (mut t_min, mut t_max_node) = (0f32, 0f32);
+        if self.nodes.is_empty() || !self.bounds.intersect_p(ray, &mut t_min, &mut t_max_node) {
+            return 0;
+        }
+
+        let inv_dir = Vector3f::init([1.0 / ray.d.x(), 1.0 / ray.d.y(), 1.0 / ray.d.z()]);
+        let mut todo: Vec<KdToDo> = Vec::with_capacity(64);
+        let mut nodes_visited = 0u64;
+        let mut current = 0usize;
+
+        loop {
+            if t_max() < t_min {
+                break;
+            }
+            let node = &self.nodes[current];
+            nodes_visited += 1;
+
+            if !node.is_leaf() {
+                let axis = node.split_axis();
+                let t_plane = (node.split - ray.o[axis]) * inv_dir[axis];
+
+                // the child holding the origin comes first
+                let below_first = ray.o[axis] < node.split || (ray.o[axis] == node.split && ray.d[axis] <= 0.0);
+                let (first, second) = if below_first { (current + 1, node.above_child()) } else { (node.above_child(), current + 1) };
+
+                if t_plane > t_max_node || t_plane <= 0.0 {
+                    current = first;
+                } else if t_plane < t_min {
+                    current = second;
+                } else {
+                    todo.push(KdToDo { node: second, t_min: t_plane, t_max: t_max_node });
+                    current = first;
+                    t_max_node = t_plane;
+                }
+                continue;
+            }
+
+            if !visit_leaf(node) {
+                break;
+            }
+
+            match todo.pop() {
+                Some(next) => {
+                    current = next.node;
+                    t_min = next.t_min;
+                    t_max_node = next.t_max;
+                }
+                None => break
+            }
+        }
+
+        nodes_visited
+    }
+}
+
+impl Aggregate for KdTreeAccel {
+    fn create(&mut self, primitives: Vec<Arc<dyn Primitive>>) {
+        self.primitives = primitives;
+        self.primitive_indices.clear();
+        self.nodes.clear();
+        if self.primitives.is_empty() {
+            self.bounds = Bounds3f::new();
+            return;
+        }
+
+        let max_depth = if self.max_depth > 0 {
+            self.max_depth
+        } else {
+            (8.0 + 1.3 * (self.primitives.len() as f32).log2()).round() as i32
+        };
+
+        let primitive_bounds: Vec<Bounds3f> = self.primitives.iter().map(|p| p.world_bound()).collect();
+        self.bounds = primitive_bounds.iter().fold(Bounds3f::new(), |b, p| Bounds3f::union(&b, p));
+
+        let primitive_numbers: Vec<u32> = (0..self.primitives.len() as u32).collect();
+        let mut edges: [Vec<BoundEdge>; 3] = [Vec::new(), Vec::new(), Vec::new()];
+        let bounds = self.bounds;
+        self.build_tree(&bounds, &primitive_bounds, &primitive_numbers, max_depth, &mut edges, 0);
+    }
+
+    fn primitives(&self) -> &Vec<Arc<dyn Primitive>> {
+        &self.primitives
+    }
+}
+
+impl Primitive for KdTreeAccel {
+    fn compute_scattering_functions(&self, _its: &mut SurfaceInteraction, _mode: TransportMode, _allow_multiple_lobes: bool) {
+        panic!("This should not be called for an aggregate!")
+    }
+
+    fn get_area_light(&self) -> Option<Arc<dyn AreaLight>> {
+        panic!("This should not be called for an aggregate!")
+    }
+
+    fn get_material(&self) -> Option<Arc<dyn Material>> {
+        panic!("This should not be called for an aggregate!")
+    }
+
+    fn intersect(&self, ray: &Ray, its: &mut SurfaceInteraction) -> bool {
+        // shrink t_max with every hit so only the closest intersection survives
+        let ray_t_max = std::cell::Cell::new(ray.t_max);
+        let mut hit = false;
+        let mut primitives_tested = 0u64;
+
+        let nodes_visited = self.traverse(ray, &|| ray_t_max.get(), |node| {
+            let mut ray = ray.clone();
+            ray.t_max = ray_t_max.get();
+            for primitive in self.leaf_primitives(node) {
+                primitives_tested += 1;
+                // primitives wrapping others, like moving ones, name the one hit inside
+                let closest = its.primitive.take();
+                if primitive.intersect(&ray, its) {
+                    if its.primitive.is_none() {
+                        its.set_primitive(primitive.clone());
+                    }
+                    hit = true;
+                    ray.t_max = its.t;
+                } else {
+                    its.primitive = closest;
+                }
+            }
+            ray_t_max.set(ray.t_max);
+            true
+        });
+
+        record_ray(nodes_visited, primitives_tested);
+        hit
+    }
+
+    fn intersect_p(&self, ray: &Ray) -> bool {
+        let mut hit = false;
+        let mut primitives_tested = 0u64;
+
+        let nodes_visited = self.traverse(ray, &|| ray.t_max, |node| {
+            for primitive in self.leaf_primitives(node) {
+                primitives_tested += 1;
+                if primitive.intersect_p(ray) {
+                    hit = true;
+                    return false;
+                }
+            }
+            true
+        });
+
+        record_shadow_ray(nodes_visited, primitives_tested);
+        hit
+    }
+
+    fn world_bound(&self) -> Bounds3f {
+        self.bounds
+    }
+
+    fn shape(&self) -> Option<Arc<dyn Shape>> {
+        None
+    }
+}
+
+impl LeadObjectTrait for KdTreeAccel {
+    fn init(&mut self, _prop_list: PropertyList) { }
+
+    fn add_child(&mut self, child: &mut LeadObject) {
+        println!("Struct KdTreeAccel does not take a child of class {}", child.to_string())
+    }
+
+    fn activate(&mut self) { }
+
+    fn to_string(&self) -> String {
+        format!(
+            "KdTree[\n  intersect cost: {},\n  traversal cost: {},\n  empty bonus: {},\n  max primitives in node: {},\n  max depth: {},\n  primitive count: {},\n  number of nodes: {}\n]",
+            self.intersect_cost, self.traversal_cost, self.empty_bonus, self.max_primitives,
+            self.max_depth, self.primitives.len(), self.nodes.len()
+        )
+    }
+}
+
+register_fallible_struct!("kdtree", create_kdtree);
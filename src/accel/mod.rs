@@ -1,7 +1,19 @@
 pub mod aggregate;
 pub mod bvh;
+pub mod bvh_bench;
+pub mod bvh_stats;
+pub mod kdtree;
 pub mod primitive;
+pub mod sbvh;
+pub mod simd;
+pub mod wide_bvh;
 
-pub use aggregate::Aggregate;
+pub use aggregate::{Aggregate, record_ray, record_shadow_ray, PACKET_SIZE};
 pub use bvh::*;
-pub use primitive::{Primitive, GeometricPrimitive};
\ No newline at end of file
+pub use bvh_bench::bench_bvh;
+pub use bvh_stats::BVHStats;
+pub use kdtree::KdTreeAccel;
+pub use primitive::{Primitive, GeometricPrimitive, TransformedPrimitive, AnimatedPrimitive};
+pub use sbvh::SBVHAccel;
+pub use simd::{LaneBoxes, LaneRays, slab_test, native_lanes};
+pub use wide_bvh::{WideBVHNode, intersect_wide, intersect_p_wide};
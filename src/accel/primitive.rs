@@ -6,10 +6,13 @@ pub trait Primitive{
     fn intersect_p(&self, ray: &Ray) -> bool;
     fn get_area_light(&self) -> Option<Arc<dyn AreaLight>>;
     fn get_material(&self) -> Option<Arc<dyn Material>>;
-    fn compute_scattering_functions(&self, its: &SurfaceInteraction, mode: TransportMode, allow_multiple_lobes: bool);
+    fn compute_scattering_functions(&self, its: &mut SurfaceInteraction, mode: TransportMode, allow_multiple_lobes: bool);
     fn shape(&self) -> Option<Arc<dyn Shape>>;
 }
 
+stat_ratio!(SHAPE_HITS, "Shapes/Intersection tests that hit");
+stat_ratio!(SHAPE_OCCLUSIONS, "Shapes/Occlusion tests that hit");
+
 pub struct GeometricPrimitive {
     pub shape: Arc<dyn Shape>,
     pub material: Option<Arc<dyn Material>>,
@@ -25,35 +28,35 @@ impl Primitive for GeometricPrimitive{
     // set ray.tmax after this is called (if a hit)
     fn intersect(&self, ray: &Ray, its: &mut SurfaceInteraction) -> bool {
         let mut t_hit = 0f32;
-        if !self.shape.intersect(ray, &mut t_hit, its){
+        let hit = self.shape.intersect(ray, &mut t_hit, its);
+        SHAPE_HITS.with(|hits| hits.add(hit as u64, 1));
+        if !hit {
             return false;
         }
 
         its.set_shape(self.shape.clone());
+        its.time = ray.time;
         true
     }
 
     fn intersect_p(&self, ray: &Ray) -> bool {
-        self.shape.intersect_p(ray)
+        let hit = self.shape.intersect_p(ray);
+        SHAPE_OCCLUSIONS.with(|occlusions| occlusions.add(hit as u64, 1));
+        hit
     }
 
     fn get_area_light(&self) -> Option<Arc<dyn AreaLight>> {
-        match &self.arealight {
-            Some(al) => { return Some(al.clone()); }
-            None => {return None;}
-        }
+        self.arealight.clone()
     }
 
     fn get_material(&self) -> Option<Arc<dyn Material>> {
-        match &self.material {
-            Some(mat) => { return Some(mat.clone()); }
-            None => { return None; }
-        }
+        self.material.clone()
     }
 
-    // TODO - this....................
-    fn compute_scattering_functions(&self, _its: &SurfaceInteraction, _mode: TransportMode, _allow_multiple_lobes: bool) {
-        
+    fn compute_scattering_functions(&self, its: &mut SurfaceInteraction, mode: TransportMode, allow_multiple_lobes: bool) {
+        if let Some(material) = &self.material {
+            material.compute_scattering_functions(its, mode, allow_multiple_lobes);
+        }
     }
 
     fn shape(&self) -> Option<Arc<dyn Shape>> {
@@ -64,7 +67,7 @@ impl Primitive for GeometricPrimitive{
 impl GeometricPrimitive {
     pub fn init(shape: Arc<dyn Shape>, material: Arc<dyn Material>, arealight: Arc<dyn AreaLight>, medium_interface: &MediumInterface) -> Self {
         Self {
-            shape: shape,
+            shape,
             material: Some(material),
             arealight: Some(arealight),
             medium_interface: Some(MediumInterface::init(medium_interface.inside.clone(), medium_interface.outside.clone()))
@@ -73,10 +76,125 @@ impl GeometricPrimitive {
 
     pub fn init_shape(shape: Arc<dyn Shape>) -> Self {
         Self {
-            shape: shape,
+            shape,
             material: None,
             arealight: None,
             medium_interface: None
         }
     }
-}
\ No newline at end of file
+}
+
+// Primitive placed somewhere else, e.g. one of many instances sharing a hierarchy
+pub struct TransformedPrimitive {
+    primitive: Arc<dyn Primitive>,
+    primitive_to_world: Transform,
+    world_to_primitive: Transform
+}
+
+impl Primitive for TransformedPrimitive {
+    fn world_bound(&self) -> Bounds3f {
+        &self.primitive_to_world * self.primitive.world_bound()
+    }
+
+    fn intersect(&self, ray: &Ray, its: &mut SurfaceInteraction) -> bool {
+        let ray = &self.world_to_primitive * ray;
+        if !self.primitive.intersect(&ray, its) {
+            return false;
+        }
+
+        *its = &self.primitive_to_world * &*its;
+        true
+    }
+
+    fn intersect_p(&self, ray: &Ray) -> bool {
+        self.primitive.intersect_p(&(&self.world_to_primitive * ray))
+    }
+
+    // The interaction names the primitive inside that was hit, which has the emitter and material
+    fn get_area_light(&self) -> Option<Arc<dyn AreaLight>> {
+        None
+    }
+
+    fn get_material(&self) -> Option<Arc<dyn Material>> {
+        None
+    }
+
+    fn compute_scattering_functions(&self, _its: &mut SurfaceInteraction, _mode: TransportMode, _allow_multiple_lobes: bool) {
+        panic!("TransformedPrimitive::compute_scattering_functions should not be called!");
+    }
+
+    fn shape(&self) -> Option<Arc<dyn Shape>> {
+        None
+    }
+}
+
+impl TransformedPrimitive {
+    pub fn new(primitive: Arc<dyn Primitive>, primitive_to_world: Transform) -> Self {
+        Self {
+            primitive,
+            world_to_primitive: primitive_to_world.inverse(),
+            primitive_to_world
+        }
+    }
+}
+
+// Primitive moving along an animated transform. It is given where it is at the start, and rays
+// are brought back there from wherever it has moved to at their time
+pub struct AnimatedPrimitive {
+    primitive: Arc<dyn Primitive>,
+    motion: AnimatedTransform,
+    world_to_start: Transform
+}
+
+impl Primitive for AnimatedPrimitive {
+    fn world_bound(&self) -> Bounds3f {
+        self.motion.motion_bounds(&(&self.world_to_start * self.primitive.world_bound()))
+    }
+
+    fn intersect(&self, ray: &Ray, its: &mut SurfaceInteraction) -> bool {
+        let start_to_time = self.start_to_time(ray.time);
+        let ray = &start_to_time.inverse() * ray;
+        if !self.primitive.intersect(&ray, its) {
+            return false;
+        }
+
+        *its = &start_to_time * &*its;
+        true
+    }
+
+    fn intersect_p(&self, ray: &Ray) -> bool {
+        let ray = &self.start_to_time(ray.time).inverse() * ray;
+        self.primitive.intersect_p(&ray)
+    }
+
+    // The interaction names the primitive inside that was hit, which has the emitter and material
+    fn get_area_light(&self) -> Option<Arc<dyn AreaLight>> {
+        None
+    }
+
+    fn get_material(&self) -> Option<Arc<dyn Material>> {
+        None
+    }
+
+    fn compute_scattering_functions(&self, _its: &mut SurfaceInteraction, _mode: TransportMode, _allow_multiple_lobes: bool) {
+        panic!("AnimatedPrimitive::compute_scattering_functions should not be called!");
+    }
+
+    fn shape(&self) -> Option<Arc<dyn Shape>> {
+        None
+    }
+}
+
+impl AnimatedPrimitive {
+    pub fn new(primitive: Arc<dyn Primitive>, motion: AnimatedTransform) -> Self {
+        Self {
+            primitive,
+            world_to_start: motion.start_transform().inverse(),
+            motion
+        }
+    }
+
+    fn start_to_time(&self, time: f32) -> Transform {
+        self.motion.interpolate(time) * self.world_to_start.clone()
+    }
+}
@@ -0,0 +1,409 @@
+use crate::common::*;
+
+// Spatial split BVH (Stich et al. 2009). Next to the usual object splits it may cut a node in space,
+// and a primitive crossing the cut is split between both children. That keeps long thin primitives
+// from blowing up the bounds of every node above them. The tree is traversed like any other BVH
+pub struct SBVHAccel {
+    max_primitives_in_node: u32,
+    // spatial splits are only tried where the children of the best object split overlap by more than
+    // this part of the scene's surface area
+    alpha: f32,
+    primitives: Vec<Arc<dyn Primitive>>,
+    bvh: BVHAccel,
+}
+
+// Constructor
+fn create_sbvh(prop_list: PropertyList) -> Result<LeadObject, String> {
+    let mut sbvh = SBVHAccel::new();
+    sbvh.max_primitives_in_node = read_max_prims(&prop_list, sbvh.max_primitives_in_node)?;
+    sbvh.init(prop_list);
+    Ok(LeadObject::Aggregate(Arc::new(sbvh)))
+}
+
+const N_OBJECT_BUCKETS: usize = 12;
+const N_SPATIAL_BINS: usize = 32;
+// every spatial split duplicates references, below this depth only object splits are made
+const MAX_SPATIAL_SPLIT_DEPTH: u32 = 48;
+
+// The part of a primitive that lies in a node
+#[derive(Clone, Copy)]
+struct SBVHReference {
+    primitive_index: u32,
+    bounds: Bounds3f,
+}
+
+impl SBVHReference {
+    fn centroid(&self) -> Point3f {
+        self.bounds.p_min * 0.5 + self.bounds.p_max * 0.5
+    }
+}
+
+#[derive(Clone, Copy)]
+struct ObjectSplit {
+    dim: usize,
+    bucket: usize,
+    cost: f32,
+    overlap: f32,
+}
+
+// Splits after bin, with the bounds and counts of both sides once the straddling references are split
+#[derive(Clone, Copy)]
+struct SpatialSplit {
+    dim: usize,
+    bin: usize,
+    origin: f32,
+    bin_width: f32,
+    cost: f32,
+    left_bounds: Bounds3f,
+    right_bounds: Bounds3f,
+    n_left: usize,
+    n_right: usize,
+}
+
+impl SpatialSplit {
+    fn bin_of(&self, x: f32) -> usize {
+        bin_of(x, self.origin, self.bin_width)
+    }
+
+    fn position(&self) -> f32 {
+        self.origin + (self.bin + 1) as f32 * self.bin_width
+    }
+}
+
+fn bin_of(x: f32, origin: f32, bin_width: f32) -> usize {
+    (((x - origin) / bin_width).max(0.0) as usize).min(N_SPATIAL_BINS - 1)
+}
+
+fn bucket_of(centroid_bounds: &Bounds3f, dim: usize, p: &Point3f) -> usize {
+    ((N_OBJECT_BUCKETS as f32 * centroid_bounds.offset(p)[dim]) as usize).min(N_OBJECT_BUCKETS - 1)
+}
+
+// The bounds and counts of every bin together with all bins after it
+fn sweep_from_right<const N: usize>(bounds: &[Bounds3f; N], counts: &[usize; N]) -> ([Bounds3f; N], [usize; N]) {
+    let (mut swept_bounds, mut swept_counts) = (*bounds, *counts);
+    for i in (0..(N - 1)).rev() {
+        swept_bounds[i] = Bounds3f::union(&swept_bounds[i], &swept_bounds[i + 1]);
+        swept_counts[i] += swept_counts[i + 1];
+    }
+    (swept_bounds, swept_counts)
+}
+
+fn is_empty(b: &Bounds3f) -> bool {
+    (0..3).any(|i| b.p_min[i] > b.p_max[i])
+}
+
+struct SBVHBuilder<'a> {
+    primitives: &'a [Arc<dyn Primitive>],
+    shapes: Vec<Option<Arc<dyn Shape>>>,
+    max_primitives_in_node: u32,
+    min_overlap: f32,
+    arena: BuildArena,
+    ordered_primitives: Vec<Arc<dyn Primitive>>,
+}
+
+impl SBVHBuilder<'_> {
+    // The reference cut in two at position along dim. Primitives without a shape, e.g. instances, can only be cut as a box
+    fn split(&self, reference: &SBVHReference, dim: usize, position: f32) -> (Bounds3f, Bounds3f) {
+        match &self.shapes[reference.primitive_index as usize] {
+            Some(shape) => shape.get_split_world_bounds(&reference.bounds, dim, position),
+            None => Bounds3f::split(&reference.bounds, dim, position)
+        }
+    }
+
+    fn build(&mut self, references: Vec<SBVHReference>, depth: u32) -> u32 {
+        let n_references = references.len();
+        let bounds = references.iter().fold(Bounds3f::new(), |b, r| Bounds3f::union(&b, &r.bounds));
+        if n_references == 1 {
+            return self.leaf(&references, bounds);
+        }
+
+        let centroid_bounds = references.iter().fold(Bounds3f::new(), |b, r| Bounds3f::union_pt(&b, &r.centroid()));
+        let object_split = self.find_object_split(&references, &bounds, &centroid_bounds);
+        let mut best_cost = object_split.map_or(INFINITY, |split| split.cost);
+
+        let mut spatial_split = None;
+        if depth < MAX_SPATIAL_SPLIT_DEPTH && object_split.is_none_or(|split| split.overlap > self.min_overlap) {
+            if let Some(split) = self.find_spatial_split(&references, &bounds) {
+                if split.cost < best_cost {
+                    best_cost = split.cost;
+                    spatial_split = Some(split);
+                }
+            }
+        }
+
+        // the same rule as the SAH builder of the BVH
+        if n_references <= self.max_primitives_in_node as usize && best_cost >= n_references as f32 {
+            return self.leaf(&references, bounds);
+        }
+
+        let children = spatial_split
+            .and_then(|split| self.partition_spatial(&references, &split))
+            .or_else(|| object_split.map(|split| {
+                let (left, right) = references.iter().partition(|r| bucket_of(&centroid_bounds, split.dim, &r.centroid()) <= split.bucket);
+                (left, right, split.dim)
+            }));
+
+        let (left, right, dim) = match children {
+            Some(children) => children,
+            // nothing to split by, only leaves too big for their 16 bit count are cut in half
+            None if n_references <= u16::MAX as usize => return self.leaf(&references, bounds),
+            None => {
+                let (left, right) = references.split_at(n_references / 2);
+                (left.to_vec(), right.to_vec(), 0)
+            }
+        };
+
+        let c_0 = self.build(left, depth + 1);
+        let c_1 = self.build(right, depth + 1);
+        self.arena.add_interior(dim as u32, c_0, c_1)
+    }
+
+    fn leaf(&mut self, references: &[SBVHReference], bounds: Bounds3f) -> u32 {
+        let first = self.ordered_primitives.len();
+        for r in references {
+            self.ordered_primitives.push(self.primitives[r.primitive_index as usize].clone());
+        }
+        self.arena.add_leaf(references.len() as u32, bounds, first as u32)
+    }
+
+    // Binned SAH over the centroids along every axis. Splits leaving a side empty are not taken
+    fn find_object_split(&self, references: &[SBVHReference], bounds: &Bounds3f, centroid_bounds: &Bounds3f) -> Option<ObjectSplit> {
+        let mut best: Option<ObjectSplit> = None;
+        for dim in 0..3 {
+            if centroid_bounds.p_max[dim] == centroid_bounds.p_min[dim] {
+                continue;
+            }
+
+            let mut counts = [0usize; N_OBJECT_BUCKETS];
+            let mut bucket_bounds = [Bounds3f::new(); N_OBJECT_BUCKETS];
+            for r in references {
+                let b = bucket_of(centroid_bounds, dim, &r.centroid());
+                counts[b] += 1;
+                bucket_bounds[b] = Bounds3f::union(&bucket_bounds[b], &r.bounds);
+            }
+
+            // what lies above each split is swept in from the right first, what lies below while going through them
+            let (above_bounds, above_counts) = sweep_from_right(&bucket_bounds, &counts);
+            let (mut b0, mut count_0) = (Bounds3f::new(), 0usize);
+            for i in 0..(N_OBJECT_BUCKETS - 1) {
+                b0 = Bounds3f::union(&b0, &bucket_bounds[i]);
+                count_0 += counts[i];
+                let (b1, count_1) = (above_bounds[i + 1], above_counts[i + 1]);
+                if count_0 == 0 || count_1 == 0 {
+                    continue;
+                }
+
+                let cost = 0.125 + (count_0 as f32 * b0.surface_area() + count_1 as f32 * b1.surface_area()) / bounds.surface_area();
+                if best.is_none_or(|best| cost < best.cost) {
+                    let overlap = Bounds3f::intersect(&b0, &b1);
+                    let overlap = if is_empty(&overlap) { 0.0 } else { overlap.surface_area() };
+                    best = Some(ObjectSplit { dim, bucket: i, cost, overlap });
+                }
+            }
+        }
+        best
+    }
+
+    // Bins the node in space along every axis. A reference counts as entering the first bin it
+    // touches and leaving the last, and is chopped at every plane in between with each piece going to its bin
+    fn find_spatial_split(&self, references: &[SBVHReference], bounds: &Bounds3f) -> Option<SpatialSplit> {
+        let mut best: Option<SpatialSplit> = None;
+        for dim in 0..3 {
+            let origin = bounds.p_min[dim];
+            let bin_width = (bounds.p_max[dim] - origin) / N_SPATIAL_BINS as f32;
+            if bin_width <= 0.0 {
+                continue;
+            }
+
+            let mut bin_bounds = [Bounds3f::new(); N_SPATIAL_BINS];
+            let mut entries = [0usize; N_SPATIAL_BINS];
+            let mut exits = [0usize; N_SPATIAL_BINS];
+            for r in references {
+                let first = bin_of(r.bounds.p_min[dim], origin, bin_width);
+                let last = bin_of(r.bounds.p_max[dim], origin, bin_width);
+                let mut rest = *r;
+                for (bin, bin_b) in bin_bounds.iter_mut().enumerate().take(last).skip(first) {
+                    let (piece, remainder) = self.split(&rest, dim, origin + (bin + 1) as f32 * bin_width);
+                    if !is_empty(&piece) {
+                        *bin_b = Bounds3f::union(bin_b, &piece);
+                    }
+                    rest.bounds = remainder;
+                }
+                if !is_empty(&rest.bounds) {
+                    bin_bounds[last] = Bounds3f::union(&bin_bounds[last], &rest.bounds);
+                }
+                entries[first] += 1;
+                exits[last] += 1;
+            }
+
+            let (above_bounds, above_exits) = sweep_from_right(&bin_bounds, &exits);
+            let (mut left_bounds, mut n_left) = (Bounds3f::new(), 0usize);
+            for i in 0..(N_SPATIAL_BINS - 1) {
+                left_bounds = Bounds3f::union(&left_bounds, &bin_bounds[i]);
+                n_left += entries[i];
+                let (right_bounds, n_right) = (above_bounds[i + 1], above_exits[i + 1]);
+                if n_left == 0 || n_right == 0 {
+                    continue;
+                }
+
+                let cost = 0.125 + (n_left as f32 * left_bounds.surface_area() + n_right as f32 * right_bounds.surface_area()) / bounds.surface_area();
+                if best.is_none_or(|best| cost < best.cost) {
+                    best = Some(SpatialSplit { dim, bin: i, origin, bin_width, cost, left_bounds, right_bounds, n_left, n_right });
+                }
+            }
+        }
+        best
+    }
+
+    // References crossing the plane are split between both sides, unless moving them whole to
+    // one side is cheaper. None when a side would end up empty
+    fn partition_spatial(&self, references: &[SBVHReference], split: &SpatialSplit) -> Option<(Vec<SBVHReference>, Vec<SBVHReference>, usize)> {
+        let (mut left, mut right) = (Vec::new(), Vec::new());
+        let (mut left_bounds, mut right_bounds) = (split.left_bounds, split.right_bounds);
+        let (mut n_left, mut n_right) = (split.n_left as f32, split.n_right as f32);
+        let position = split.position();
+
+        for r in references {
+            if split.bin_of(r.bounds.p_max[split.dim]) <= split.bin {
+                left.push(*r);
+                continue;
+            }
+            if split.bin_of(r.bounds.p_min[split.dim]) > split.bin {
+                right.push(*r);
+                continue;
+            }
+
+            let cost_split = left_bounds.surface_area() * n_left + right_bounds.surface_area() * n_right;
+            let whole_left = Bounds3f::union(&left_bounds, &r.bounds);
+            let cost_left = whole_left.surface_area() * n_left + right_bounds.surface_area() * (n_right - 1.0);
+            let whole_right = Bounds3f::union(&right_bounds, &r.bounds);
+            let cost_right = left_bounds.surface_area() * (n_left - 1.0) + whole_right.surface_area() * n_right;
+
+            if cost_left < cost_split && cost_left <= cost_right {
+                left.push(*r);
+                left_bounds = whole_left;
+                n_right -= 1.0;
+            } else if cost_right < cost_split {
+                right.push(*r);
+                right_bounds = whole_right;
+                n_left -= 1.0;
+            } else {
+                let (lower, upper) = self.split(r, split.dim, position);
+                if !is_empty(&lower) {
+                    left.push(SBVHReference { primitive_index: r.primitive_index, bounds: lower });
+                }
+                if !is_empty(&upper) {
+                    right.push(SBVHReference { primitive_index: r.primitive_index, bounds: upper });
+                }
+            }
+        }
+
+        if left.is_empty() || right.is_empty() {
+            return None;
+        }
+        Some((left, right, split.dim))
+    }
+}
+
+impl Aggregate for SBVHAccel {
+    fn create(&mut self, primitives: Vec<Arc<dyn Primitive>>) {
+        self.primitives = primitives;
+        if self.primitives.is_empty() {
+            self.bvh.create(Vec::new());
+            return;
+        }
+
+        let references: Vec<SBVHReference> = self.primitives.iter().enumerate()
+            .map(|(i, primitive)| SBVHReference { primitive_index: i as u32, bounds: primitive.world_bound() })
+            .collect();
+        let scene_bounds = references.iter().fold(Bounds3f::new(), |b, r| Bounds3f::union(&b, &r.bounds));
+
+        let mut builder = SBVHBuilder {
+            primitives: &self.primitives,
+            shapes: self.primitives.iter().map(|primitive| primitive.shape()).collect(),
+            max_primitives_in_node: self.max_primitives_in_node,
+            min_overlap: self.alpha * scene_bounds.surface_area(),
+            arena: BuildArena::new(),
+            ordered_primitives: Vec::new(),
+        };
+        let root = builder.build(references, 0);
+
+        let (arena, ordered_primitives) = (builder.arena, builder.ordered_primitives);
+        self.bvh.create_from_arena(ordered_primitives, &arena, root);
+    }
+
+    fn primitives(&self) -> &Vec<Arc<dyn Primitive>> {
+        &self.primitives
+    }
+
+    fn intersect_packet(&self, rays: &[Ray; PACKET_SIZE], its: &mut [SurfaceInteraction; PACKET_SIZE], n_rays: usize) -> [bool; PACKET_SIZE] {
+        self.bvh.intersect_packet(rays, its, n_rays)
+    }
+
+    fn intersect_p_packet(&self, rays: &[Ray; PACKET_SIZE], n_rays: usize) -> [bool; PACKET_SIZE] {
+        self.bvh.intersect_p_packet(rays, n_rays)
+    }
+}
+
+impl Primitive for SBVHAccel {
+    fn compute_scattering_functions(&self, _its: &mut SurfaceInteraction, _mode: TransportMode, _allow_multiple_lobes: bool) {
+        panic!("This should not be called for an aggregate!")
+    }
+
+    fn get_area_light(&self) -> Option<Arc<dyn AreaLight>> {
+        panic!("This should not be called for an aggregate!")
+    }
+
+    fn get_material(&self) -> Option<Arc<dyn Material>> {
+        panic!("This should not be called for an aggregate!")
+    }
+
+    fn intersect(&self, ray: &Ray, its: &mut SurfaceInteraction) -> bool {
+        self.bvh.intersect(ray, its)
+    }
+
+    fn intersect_p(&self, ray: &Ray) -> bool {
+        self.bvh.intersect_p(ray)
+    }
+
+    fn world_bound(&self) -> Bounds3f {
+        self.bvh.world_bound()
+    }
+
+    fn shape(&self) -> Option<Arc<dyn Shape>> {
+        None
+    }
+}
+
+impl LeadObjectTrait for SBVHAccel {
+    fn init(&mut self, prop_list: PropertyList) {
+        self.alpha = prop_list.get_float("alpha", 1e-5);
+    }
+
+    fn add_child(&mut self, child: &mut LeadObject) {
+        println!("Struct SBVHAccel does not take a child of class {}", child.to_string())
+    }
+
+    fn activate(&mut self) { }
+
+    fn to_string(&self) -> String {
+        format!(
+            "SBVH[\n  max primitives in node: {},\n  alpha: {},\n  primitive count: {},\n  references: {},\n  number of nodes: {}\n]",
+            self.max_primitives_in_node, self.alpha, self.primitives.len(),
+            self.bvh.primitives().len(), self.bvh.node_count()
+        )
+    }
+}
+
+impl SBVHAccel {
+    pub fn new() -> Self {
+        Self {
+            max_primitives_in_node: 4,
+            alpha: 1e-5,
+            primitives: Vec::new(),
+            bvh: BVHAccel::new(),
+        }
+    }
+}
+
+register_fallible_struct!("sbvh", create_sbvh);
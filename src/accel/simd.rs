@@ -0,0 +1,191 @@
+use crate::common::*;
+
+// Ray/box slab tests for L boxes and L rays side by side, lane i testing ray i against box i. A wide
+// node tests one ray against all its children, a packet tests all its rays against one node.
+// x86_64 uses AVX when the CPU has it and SSE otherwise, other targets test the lanes one by one
+
+// Boxes in lanes. Empty lanes are inverted, p_min above p_max, and never hit
+#[derive(Clone, Copy)]
+pub struct LaneBoxes<const L: usize> {
+    pub p_min: [[f32; L]; 3],
+    pub p_max: [[f32; L]; 3],
+}
+
+// Rays in lanes, with the reciprocal of their direction precomputed
+#[derive(Clone, Copy)]
+pub struct LaneRays<const L: usize> {
+    pub o: [[f32; L]; 3],
+    pub inv_d: [[f32; L]; 3],
+    pub t_max: [f32; L],
+}
+
+impl<const L: usize> LaneBoxes<L> {
+    pub fn new() -> Self {
+        Self {
+            p_min: [[f32::INFINITY; L]; 3],
+            p_max: [[f32::NEG_INFINITY; L]; 3],
+        }
+    }
+
+    pub fn set(&mut self, lane: usize, b: &Bounds3f) {
+        for axis in 0..3 {
+            self.p_min[axis][lane] = b.p_min[axis];
+            self.p_max[axis][lane] = b.p_max[axis];
+        }
+    }
+
+    // The same box in every lane
+    pub fn splat(b: &Bounds3f) -> Self {
+        Self {
+            p_min: [0, 1, 2].map(|axis| [b.p_min[axis]; L]),
+            p_max: [0, 1, 2].map(|axis| [b.p_max[axis]; L]),
+        }
+    }
+
+    pub fn get(&self, lane: usize) -> Bounds3f {
+        Bounds3f::init(
+            &Point3f::init([0, 1, 2].map(|axis| self.p_min[axis][lane])),
+            &Point3f::init([0, 1, 2].map(|axis| self.p_max[axis][lane]))
+        )
+    }
+}
+
+impl<const L: usize> LaneRays<L> {
+    // The same ray in every lane
+    pub fn splat(ray: &Ray) -> Self {
+        Self {
+            o: [0, 1, 2].map(|axis| [ray.o[axis]; L]),
+            inv_d: [0, 1, 2].map(|axis| [1.0 / ray.d[axis]; L]),
+            t_max: [ray.t_max; L],
+        }
+    }
+
+    pub fn init(rays: &[Ray; L]) -> Self {
+        Self {
+            o: [0, 1, 2].map(|axis| rays.each_ref().map(|ray| ray.o[axis])),
+            inv_d: [0, 1, 2].map(|axis| rays.each_ref().map(|ray| 1.0 / ray.d[axis])),
+            t_max: rays.each_ref().map(|ray| ray.t_max),
+        }
+    }
+}
+
+// The widest lanes the CPU tests at once, 8 with AVX and 4 otherwise
+pub fn native_lanes() -> usize {
+    #[cfg(target_arch = "x86_64")]
+    {
+        if std::is_x86_feature_detected!("avx") {
+            return 8;
+        }
+    }
+    4
+}
+
+// Bit i is set when ray i enters box i before its t_max and leaves it after 0. t_entry gets the
+// distance at which each ray enters its box
+pub fn slab_test<const L: usize>(boxes: &LaneBoxes<L>, rays: &LaneRays<L>, t_entry: &mut [f32; L]) -> u32 {
+    #[cfg(target_arch = "x86_64")]
+    {
+        if L.is_multiple_of(8) && std::is_x86_feature_detected!("avx") {
+            // SAFETY: the CPU was just checked for AVX
+            return unsafe { x86::slab_test_avx(boxes, rays, t_entry) };
+        }
+        if L.is_multiple_of(4) {
+            // SAFETY: SSE2 is part of x86_64
+            return unsafe { x86::slab_test_sse(boxes, rays, t_entry) };
+        }
+    }
+    slab_test_scalar(boxes, rays, t_entry)
+}
+
+// The near plane is picked by the sign of the direction rather than with a min, so inverted boxes
+// stay empty. A NaN from a ray lying in a slab's plane leaves that axis out
+fn slab_test_scalar<const L: usize>(boxes: &LaneBoxes<L>, rays: &LaneRays<L>, t_entry: &mut [f32; L]) -> u32 {
+    let mut mask = 0u32;
+    for (lane, t_entry) in t_entry.iter_mut().enumerate() {
+        let (mut entry, mut exit) = (f32::NEG_INFINITY, f32::INFINITY);
+        for axis in 0..3 {
+            let inv_d = rays.inv_d[axis][lane];
+            let (near, far) = if inv_d < 0.0 {
+                (boxes.p_max[axis][lane], boxes.p_min[axis][lane])
+            } else {
+                (boxes.p_min[axis][lane], boxes.p_max[axis][lane])
+            };
+            entry = entry.max((near - rays.o[axis][lane]) * inv_d);
+            exit = exit.min((far - rays.o[axis][lane]) * inv_d);
+        }
+
+        *t_entry = entry;
+        if entry <= exit && entry < rays.t_max[lane] && exit > 0.0 {
+            mask |= 1 << lane;
+        }
+    }
+    mask
+}
+
+#[cfg(target_arch = "x86_64")]
+mod x86 {
+    use super::{LaneBoxes, LaneRays};
+    use std::arch::x86_64::*;
+
+    // The same steps as the scalar test. max and min return their second operand when either is NaN,
+    // which keeps the running entry and exit
+    #[target_feature(enable = "sse2")]
+    pub unsafe fn slab_test_sse<const L: usize>(boxes: &LaneBoxes<L>, rays: &LaneRays<L>, t_entry: &mut [f32; L]) -> u32 {
+        let mut mask = 0u32;
+        for lane in (0..L).step_by(4) {
+            let mut entry = _mm_set1_ps(f32::NEG_INFINITY);
+            let mut exit = _mm_set1_ps(f32::INFINITY);
+            for axis in 0..3 {
+                let p_min = _mm_loadu_ps(boxes.p_min[axis].as_ptr().add(lane));
+                let p_max = _mm_loadu_ps(boxes.p_max[axis].as_ptr().add(lane));
+                let o = _mm_loadu_ps(rays.o[axis].as_ptr().add(lane));
+                let inv_d = _mm_loadu_ps(rays.inv_d[axis].as_ptr().add(lane));
+
+                let negative = _mm_cmplt_ps(inv_d, _mm_setzero_ps());
+                let near = _mm_or_ps(_mm_and_ps(negative, p_max), _mm_andnot_ps(negative, p_min));
+                let far = _mm_or_ps(_mm_and_ps(negative, p_min), _mm_andnot_ps(negative, p_max));
+                entry = _mm_max_ps(_mm_mul_ps(_mm_sub_ps(near, o), inv_d), entry);
+                exit = _mm_min_ps(_mm_mul_ps(_mm_sub_ps(far, o), inv_d), exit);
+            }
+
+            _mm_storeu_ps(t_entry.as_mut_ptr().add(lane), entry);
+            let t_max = _mm_loadu_ps(rays.t_max.as_ptr().add(lane));
+            let hit = _mm_and_ps(
+                _mm_and_ps(_mm_cmple_ps(entry, exit), _mm_cmplt_ps(entry, t_max)),
+                _mm_cmpgt_ps(exit, _mm_setzero_ps())
+            );
+            mask |= (_mm_movemask_ps(hit) as u32) << lane;
+        }
+        mask
+    }
+
+    #[target_feature(enable = "avx")]
+    pub unsafe fn slab_test_avx<const L: usize>(boxes: &LaneBoxes<L>, rays: &LaneRays<L>, t_entry: &mut [f32; L]) -> u32 {
+        let mut mask = 0u32;
+        for lane in (0..L).step_by(8) {
+            let mut entry = _mm256_set1_ps(f32::NEG_INFINITY);
+            let mut exit = _mm256_set1_ps(f32::INFINITY);
+            for axis in 0..3 {
+                let p_min = _mm256_loadu_ps(boxes.p_min[axis].as_ptr().add(lane));
+                let p_max = _mm256_loadu_ps(boxes.p_max[axis].as_ptr().add(lane));
+                let o = _mm256_loadu_ps(rays.o[axis].as_ptr().add(lane));
+                let inv_d = _mm256_loadu_ps(rays.inv_d[axis].as_ptr().add(lane));
+
+                let negative = _mm256_cmp_ps::<_CMP_LT_OQ>(inv_d, _mm256_setzero_ps());
+                let near = _mm256_blendv_ps(p_min, p_max, negative);
+                let far = _mm256_blendv_ps(p_max, p_min, negative);
+                entry = _mm256_max_ps(_mm256_mul_ps(_mm256_sub_ps(near, o), inv_d), entry);
+                exit = _mm256_min_ps(_mm256_mul_ps(_mm256_sub_ps(far, o), inv_d), exit);
+            }
+
+            _mm256_storeu_ps(t_entry.as_mut_ptr().add(lane), entry);
+            let t_max = _mm256_loadu_ps(rays.t_max.as_ptr().add(lane));
+            let hit = _mm256_and_ps(
+                _mm256_and_ps(_mm256_cmp_ps::<_CMP_LE_OQ>(entry, exit), _mm256_cmp_ps::<_CMP_LT_OQ>(entry, t_max)),
+                _mm256_cmp_ps::<_CMP_GT_OQ>(exit, _mm256_setzero_ps())
+            );
+            mask |= (_mm256_movemask_ps(hit) as u32) << lane;
+        }
+        mask
+    }
+}
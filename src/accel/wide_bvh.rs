@@ -0,0 +1,163 @@
+use crate::common::*;
+
+// A BVH node with up to W children whose boxes sit side by side, so a ray is tested against all of
+// them at once. Wide nodes are made by collapsing the binary tree after it is built
+#[derive(Clone, Copy)]
+#[repr(C, align(32))]
+pub struct WideBVHNode<const W: usize> {
+    pub bounds: LaneBoxes<W>,
+    pub offset: [u32; W],        // first primitive for a leaf child, the child's node otherwise
+    pub n_primitives: [u16; W],  // 0 for interior children and empty lanes
+}
+
+impl<const W: usize> WideBVHNode<W> {
+    pub fn new() -> Self {
+        Self {
+            bounds: LaneBoxes::new(),
+            offset: [0; W],
+            n_primitives: [0; W]
+        }
+    }
+
+    pub fn collapse(binary: &[LinearBVHNode]) -> Vec<Self> {
+        let mut nodes = Vec::new();
+        if !binary.is_empty() {
+            Self::collapse_node(binary, 0, &mut nodes);
+        }
+        nodes
+    }
+
+    // The interior child with the largest surface area is opened up, its two children taking its place,
+    // until W are gathered or only leaves are left
+    fn collapse_node(binary: &[LinearBVHNode], index: usize, nodes: &mut Vec<Self>) -> u32 {
+        let mut children = vec![index];
+        while children.len() < W {
+            let largest = children.iter().enumerate()
+                .filter(|(_, &child)| binary[child].n_primitives == 0)
+                .max_by(|(_, &a), (_, &b)| binary[a].bounds.surface_area().total_cmp(&binary[b].bounds.surface_area()))
+                .map(|(i, _)| i);
+            match largest {
+                Some(i) => {
+                    let opened = children[i];
+                    children[i] = opened + 1;
+                    children.push(binary[opened].offset as usize);
+                },
+                None => break
+            }
+        }
+
+        // reserve this node's slot before its children, like the binary layout
+        let my_offset = nodes.len();
+        nodes.push(Self::new());
+
+        for (lane, child) in children.into_iter().enumerate() {
+            let node = &binary[child];
+            let offset = match node.n_primitives {
+                0 => Self::collapse_node(binary, child, nodes),
+                _ => node.offset
+            };
+            let wide_node = &mut nodes[my_offset];
+            wide_node.bounds.set(lane, &node.bounds);
+            wide_node.offset[lane] = offset;
+            wide_node.n_primitives[lane] = node.n_primitives;
+        }
+
+        my_offset as u32
+    }
+}
+
+// A child waiting to be visited, with where the ray enters it
+struct WideStackEntry {
+    offset: u32,
+    n_primitives: u16,
+    t_entry: f32,
+}
+
+// Children are visited nearest first and skipped once a closer hit is known
+pub fn intersect_wide<const W: usize>(nodes: &[WideBVHNode<W>], primitives: &[Arc<dyn Primitive>], ray: &Ray, its: &mut SurfaceInteraction) -> bool {
+    let mut hit = false;
+    if nodes.is_empty() {
+        return hit;
+    }
+
+    // shrink t_max with every hit so only the closest intersection survives
+    let mut ray = ray.clone();
+    let mut lanes = LaneRays::<W>::splat(&ray);
+    let mut t_entry = [0f32; W];
+
+    let mut nodes_to_visit: Vec<WideStackEntry> = Vec::with_capacity(64);
+    nodes_to_visit.push(WideStackEntry { offset: 0, n_primitives: 0, t_entry: f32::NEG_INFINITY });
+    let (mut nodes_visited, mut primitives_tested) = (0u64, 0u64);
+
+    while let Some(entry) = nodes_to_visit.pop() {
+        if entry.t_entry >= ray.t_max {
+            continue;
+        }
+
+        if entry.n_primitives > 0 {
+            let first = entry.offset as usize;
+            primitives_tested += entry.n_primitives as u64;
+            if intersect_leaf(&primitives[first..first + entry.n_primitives as usize], &mut ray, its) {
+                hit = true;
+                lanes.t_max = [ray.t_max; W];
+            }
+            continue;
+        }
+
+        let node = &nodes[entry.offset as usize];
+        nodes_visited += 1;
+        let mut mask = slab_test(&node.bounds, &lanes, &mut t_entry);
+
+        // the farthest child goes on the stack first, so the nearest comes off next
+        let first_pushed = nodes_to_visit.len();
+        while mask != 0 {
+            let lane = mask.trailing_zeros() as usize;
+            mask &= mask - 1;
+            nodes_to_visit.push(WideStackEntry { offset: node.offset[lane], n_primitives: node.n_primitives[lane], t_entry: t_entry[lane] });
+        }
+        nodes_to_visit[first_pushed..].sort_unstable_by(|a, b| b.t_entry.total_cmp(&a.t_entry));
+    }
+
+    record_ray(nodes_visited, primitives_tested);
+    hit
+}
+
+// Any hit ends the traversal, so children are visited in whatever order they come
+pub fn intersect_p_wide<const W: usize>(nodes: &[WideBVHNode<W>], primitives: &[Arc<dyn Primitive>], ray: &Ray) -> bool {
+    if nodes.is_empty() {
+        return false;
+    }
+
+    let lanes = LaneRays::<W>::splat(ray);
+    let mut t_entry = [0f32; W];
+
+    let mut nodes_to_visit: Vec<(u32, u16)> = Vec::with_capacity(64);
+    nodes_to_visit.push((0, 0));
+    let (mut nodes_visited, mut primitives_tested) = (0u64, 0u64);
+
+    while let Some((offset, n_primitives)) = nodes_to_visit.pop() {
+        if n_primitives > 0 {
+            let first = offset as usize;
+            for primitive in &primitives[first..first + n_primitives as usize] {
+                primitives_tested += 1;
+                if primitive.intersect_p(ray) {
+                    record_shadow_ray(nodes_visited, primitives_tested);
+                    return true;
+                }
+            }
+            continue;
+        }
+
+        let node = &nodes[offset as usize];
+        nodes_visited += 1;
+        let mut mask = slab_test(&node.bounds, &lanes, &mut t_entry);
+        while mask != 0 {
+            let lane = mask.trailing_zeros() as usize;
+            mask &= mask - 1;
+            nodes_to_visit.push((node.offset[lane], node.n_primitives[lane]));
+        }
+    }
+
+    record_shadow_ray(nodes_visited, primitives_tested);
+    false
+}
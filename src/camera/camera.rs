@@ -4,17 +4,26 @@ use crate::common::*;
 pub struct CameraSample{
     pub p_film: Point2f,
     pub p_lens: Point2f,
+    // in [0, 1), mapped onto the time the shutter is open
+    pub time: f32,
 }
 
 pub trait Camera: LeadObjectTrait {
-    fn camera_to_world(&self) -> &Transform;
+    fn camera_to_world(&self) -> &AnimatedTransform;
     fn film(&self) -> Option<Arc<Film>>;
     fn medium(&self) -> Option<Arc<Medium>>;
+    fn shutter_open(&self) -> f32;
+    fn shutter_close(&self) -> f32;
 
-    fn set_camera_to_world(&mut self, t: &Transform);
+    fn set_camera_to_world(&mut self, t: &AnimatedTransform);
     fn set_film(&mut self, film: Arc<Film>);
     fn set_medium(&mut self, medium: Arc<Medium>);
 
+    // When the ray for the sample leaves the camera
+    fn ray_time(&self, sample: &CameraSample) -> f32 {
+        self.shutter_open() + sample.time * (self.shutter_close() - self.shutter_open())
+    }
+
     fn generate_ray(&self, ray: &mut Ray, sample: &CameraSample) -> f32;
     fn generate_ray_differential(&self, rd: &mut RayDifferential, sample: &CameraSample) -> f32 {
         let mut r: Ray = Ray::new();
@@ -22,6 +31,7 @@ pub trait Camera: LeadObjectTrait {
         rd.o = r.o;
         rd.d = r.d;
         rd.t_min = r.t_min; rd.t_max = r.t_max;
+        rd.time = r.time;
 
         let mut s_shift = sample.clone();
         s_shift.p_film[0] += 1.0;
@@ -67,7 +77,7 @@ pub trait ProjectiveCamera: Camera {
     fn set_lens_radius(&mut self, lr: f32);
     fn set_focal_distance(&mut self, fd: f32);
 
-    fn init_projective_camera(&mut self, camera_to_world: Transform, camera_to_screen: Transform, screen_window: Bounds2f, lens_r: f32, focal_d: f32, film: Arc<Film>, medium: Arc<Medium>) {
+    fn init_projective_camera(&mut self, camera_to_world: AnimatedTransform, camera_to_screen: Transform, screen_window: Bounds2f, lens_r: f32, focal_d: f32, film: Arc<Film>, medium: Arc<Medium>) {
         self.set_camera_to_world(&camera_to_world);
         self.set_film(film.clone());
         self.set_medium(medium);
@@ -80,9 +90,38 @@ pub trait ProjectiveCamera: Camera {
         
         let screen_window_vec = Vector3f::init([1.0 / (screen_window.p_max.x() - screen_window.p_min.x()), 1.0 / (screen_window.p_max.y() - screen_window.p_min.y()), 1.0]);
         screen_to_raster = screen_to_raster * Transform::scale(&screen_window_vec);
+        // raster rows go down while the screen's y goes up. Transform::scale drops signs, so the flip is spelled out
+        let flip_y = Matrix4x4::init(
+            1.0, 0.0, 0.0, 0.0,
+            0.0, -1.0, 0.0, 0.0,
+            0.0, 0.0, 1.0, 0.0,
+            0.0, 0.0, 0.0, 1.0);
+        screen_to_raster = screen_to_raster * Transform::init_mat(&flip_y);
         screen_to_raster = screen_to_raster * Transform::translate(&Vector3f::init([-screen_window.p_min.x(), -screen_window.p_max.y(), 0.0]));
         self.set_screen_to_raster(&screen_to_raster);
         self.set_raster_to_screen(&screen_to_raster.inverse());
         self.set_raster_to_camera(&(self.camera_to_screen().inverse() * screen_to_raster.inverse()));
     }
+}
+
+// The screen window spans [-1, 1] along the shorter side of the film and as much more along the
+// longer one as the aspect ratio asks for
+pub fn screen_window_from_aspect(resolution: &Point2f) -> Bounds2f {
+    let frame = resolution.x() / resolution.y();
+    let (x, y) = if frame > 1.0 { (frame, 1.0) } else { (1.0, 1.0 / frame) };
+    Bounds2f::init(&Point2f::init([-x, -y]), &Point2f::init([x, y]))
+}
+
+// Camera placed by its eye, lookat and up, which its <transform time="..."> keyframes then move
+pub fn animated_look_at(prop_list: &PropertyList) -> AnimatedTransform {
+    let lookat = prop_list.get_point3("lookat", Point3f::new());
+    let origin = prop_list.get_point3("eye", Point3f::init([0.0, 0.0, -1.0]));
+    let up = prop_list.get_vector3("up", Vector3f::init([0.0, 1.0, 0.0]));
+    let look_at = Transform::look_at(&origin, &lookat, &up);
+
+    let motion = prop_list.get_animated_transform();
+    AnimatedTransform::new(
+        motion.start_transform().clone() * look_at.clone(), motion.start_time(),
+        motion.end_transform().clone() * look_at, motion.end_time()
+    )
 }
\ No newline at end of file
@@ -2,9 +2,11 @@ use crate::common::*;
 
 #[derive(Debug, Clone)]
 pub struct EnvironmentCamera {
-    camera_to_world: Transform,
+    camera_to_world: AnimatedTransform,
     film: Option<Arc<Film>>,
     medium: Option<Arc<Medium>>,
+    shutter_open: f32,
+    shutter_close: f32,
 }
 
 // Constructor
@@ -15,24 +17,28 @@ fn create_environment_camera(prop_list: PropertyList) -> LeadObject {
 }
 
 impl Camera for EnvironmentCamera {
-    fn camera_to_world(&self) -> &Transform { &self.camera_to_world }
+    fn camera_to_world(&self) -> &AnimatedTransform { &self.camera_to_world }
     fn film(&self) -> Option<Arc<Film>> { self.film.clone() }
     fn medium(&self) -> Option<Arc<Medium>> { self.medium.clone() }
+    fn shutter_open(&self) -> f32 { self.shutter_open }
+    fn shutter_close(&self) -> f32 { self.shutter_close }
 
-    fn set_camera_to_world(&mut self, t: &Transform) { self.camera_to_world = t.clone(); }
+    fn set_camera_to_world(&mut self, t: &AnimatedTransform) { self.camera_to_world = t.clone(); }
     fn set_film(&mut self, film: Arc<Film>) { self.film = Some(film); }
     fn set_medium(&mut self, medium: Arc<Medium>) { self.medium = Some(medium); }
 
     fn generate_ray(&self, ray: &mut Ray, sample: &CameraSample) -> f32 {
-        let theta = M_PI * sample.p_film.y() / self.film().unwrap().full_resolution.y();
-        let phi = 2.0 * M_PI * sample.p_film.x() / self.film().unwrap().full_resolution.x();
-        let dir = Vector3f::init([theta.sin() * phi.cos(), theta.cos(), theta.sin() * phi.sin()]);
+        let resolution = self.film().unwrap().full_resolution;
+        let uv = Point2f::init([sample.p_film.x() / resolution.x(), sample.p_film.y() / resolution.y()]);
+        let dir = Frame::lat_long_to_direction(&uv);
 
         (*ray).o = Point3f::new();
         (*ray).d = dir;
         (*ray).t_min = EPSILON;
         (*ray).t_max = INFINITY;
         (*ray).medium = self.medium.clone();
+        (*ray).time = self.ray_time(sample);
+        *ray = &self.camera_to_world * &(*ray);
 
         1.0
     }
@@ -52,27 +58,28 @@ impl Camera for EnvironmentCamera {
 }   
 
 impl LeadObjectTrait for EnvironmentCamera {
-    // TODO ACTUALLY GET SCREEN WINDOW, Film, and MEDIUM here
+    // TODO ACTUALLY GET MEDIUM here
     fn init(&mut self, prop_list: PropertyList) {
-        let lookat = prop_list.get_point3("lookat", Point3f::new());
-        let origin = prop_list.get_point3("eye", Point3f::init([0.0, 0.0, -1.0]));
-        let up = prop_list.get_vector3("up", Vector3f::init([0.0, 1.0, 0.0]));
-
-        let camera_to_world = Transform::look_at(&origin, &lookat, &up);
-        let film = Film::new();
+        let camera_to_world = animated_look_at(&prop_list);
         let medium = Medium {};
 
         self.set_medium(Arc::new(medium));
-        self.set_film(Arc::new(film));
         self.set_camera_to_world(&camera_to_world);
+        self.shutter_open = prop_list.get_float("shutter_open", 0.0);
+        self.shutter_close = prop_list.get_float("shutter_close", 1.0);
     }
 
     fn activate(&mut self) {
-        
+        if self.film.is_none() {
+            panic!("Environment camera needs a film before it can be activated!");
+        }
     }
 
-    fn add_child(&mut self, _child: &mut LeadObject) {
-        panic!("Cannot add child to perspective camera!");
+    fn add_child(&mut self, child: &mut LeadObject) {
+        match child {
+            LeadObject::Film(film) => self.set_film(film.clone()),
+            _ => panic!("Cannot add child of class {} to environment camera!", child.to_string())
+        }
     }
 
     fn to_string(&self) -> String {
@@ -83,9 +90,11 @@ impl LeadObjectTrait for EnvironmentCamera {
 impl EnvironmentCamera {
     pub fn new() -> Self {
         Self {
-            camera_to_world:  Transform::new(),
+            camera_to_world:  AnimatedTransform::init_static(Transform::new()),
             film: None,
             medium: None,
+            shutter_open: 0.0,
+            shutter_close: 1.0,
         }
     }
 }
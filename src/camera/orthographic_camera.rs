@@ -2,7 +2,7 @@ use crate::common::*;
 
 #[derive(Debug, Clone)]
 pub struct OrthographicCamera {
-    camera_to_world: Transform,
+    camera_to_world: AnimatedTransform,
     camera_to_screen: Transform,
     raster_to_camera: Transform,
     screen_to_raster: Transform,
@@ -10,6 +10,8 @@ pub struct OrthographicCamera {
 
     film: Option<Arc<Film>>,
     medium: Option<Arc<Medium>>,
+    shutter_open: f32,
+    shutter_close: f32,
 
     lens_radius: f32,
     focal_distance: f32,
@@ -26,11 +28,13 @@ fn create_orthographic_camera(prop_list: PropertyList) -> LeadObject {
 }
 
 impl Camera for OrthographicCamera {
-    fn camera_to_world(&self) -> &Transform { &self.camera_to_world }
+    fn camera_to_world(&self) -> &AnimatedTransform { &self.camera_to_world }
     fn film(&self) -> Option<Arc<Film>> { self.film.clone() }
     fn medium(&self) -> Option<Arc<Medium>> { self.medium.clone() }
+    fn shutter_open(&self) -> f32 { self.shutter_open }
+    fn shutter_close(&self) -> f32 { self.shutter_close }
 
-    fn set_camera_to_world(&mut self, t: &Transform) { self.camera_to_world = t.clone(); }
+    fn set_camera_to_world(&mut self, t: &AnimatedTransform) { self.camera_to_world = t.clone(); }
     fn set_film(&mut self, film: Arc<Film>) { self.film = Some(film); }
     fn set_medium(&mut self, medium: Arc<Medium>) { self.medium = Some(medium); }
 
@@ -51,6 +55,7 @@ impl Camera for OrthographicCamera {
         }
 
         (*ray).medium = self.medium.clone();
+        (*ray).time = self.ray_time(sample);
         *ray = &self.camera_to_world * &(*ray);
 
         1.0
@@ -86,6 +91,7 @@ impl Camera for OrthographicCamera {
         }
 
         (*rd).medium = self.medium.clone();
+        (*rd).time = self.ray_time(sample);
         (*rd).has_differential = true;
 
         *rd = &self.camera_to_world * &(*rd);
@@ -124,35 +130,37 @@ impl ProjectiveCamera for OrthographicCamera {
 }
 
 impl LeadObjectTrait for OrthographicCamera {
-    // TODO ACTUALLY GET SCREEN WINDOW, Film, and MEDIUM here
+    // TODO ACTUALLY GET MEDIUM here
     fn init(&mut self, prop_list: PropertyList) {
-        let lookat = prop_list.get_point3("lookat", Point3f::new());
-        let origin = prop_list.get_point3("eye", Point3f::init([0.0, 0.0, -1.0]));
-        let up = prop_list.get_vector3("up", Vector3f::init([0.0, 1.0, 0.0]));
-
-        let camera_to_world = Transform::look_at(&origin, &lookat, &up);
-        let lens_r = prop_list.get_float("lens_radius", 0.0);   // 0.0 means no depth of field
-        let focal_d = prop_list.get_float("focal_distance", 1.0);
+        self.camera_to_world = animated_look_at(&prop_list);
+        self.shutter_open = prop_list.get_float("shutter_open", 0.0);
+        self.shutter_close = prop_list.get_float("shutter_close", 1.0);
+        self.lens_radius = prop_list.get_float("lens_radius", 0.0);   // 0.0 means no depth of field
+        self.focal_distance = prop_list.get_float("focal_distance", 1.0);
+        self.medium = Some(Arc::new(Medium {}));
+    }
 
-        let mut screen_window = Bounds2f::new();
-        screen_window.p_min = Point2f::init([0.0, 0.0]);
-        screen_window.p_max = Point2f::init([800.0, 600.0]);
+    // The raster transforms depend on the film, so they are only built once it has been handed over
+    fn activate(&mut self) {
+        let film = match &self.film {
+            Some(film) => film.clone(),
+            None => panic!("Orthographic camera needs a film before it can be activated!")
+        };
+        let medium = self.medium.clone().unwrap_or(Arc::new(Medium {}));
 
-        let film = Film::new();
-        let medium = Medium {};
+        let screen_window = screen_window_from_aspect(&film.full_resolution);
 
-        self.init_projective_camera(camera_to_world, Self::ortho_projection_matrix(0.0, 1.0), screen_window, lens_r, focal_d, Arc::new(film), Arc::new(medium));
+        self.init_projective_camera(self.camera_to_world.clone(), Self::ortho_projection_matrix(0.0, 1.0), screen_window, self.lens_radius, self.focal_distance, film, medium);
 
         self.dx_camera = &self.raster_to_camera * Vector3f::init([1.0, 0.0, 0.0]);
         self.dy_camera = &self.raster_to_camera * Vector3f::init([0.0, 1.0, 0.0]);
     }
 
-    fn activate(&mut self) {
-        
-    }
-
-    fn add_child(&mut self, _child: &mut LeadObject) {
-        panic!("Cannot add child to orthographic camera!");
+    fn add_child(&mut self, child: &mut LeadObject) {
+        match child {
+            LeadObject::Film(film) => self.set_film(film.clone()),
+            _ => panic!("Cannot add child of class {} to orthographic camera!", child.to_string())
+        }
     }
 
     fn to_string(&self) -> String {
@@ -163,7 +171,7 @@ impl LeadObjectTrait for OrthographicCamera {
 impl OrthographicCamera {
     pub fn new() -> Self {
         Self {
-            camera_to_world:  Transform::new(),
+            camera_to_world:  AnimatedTransform::init_static(Transform::new()),
             camera_to_screen: Transform::new(),
             raster_to_camera: Transform::new(),
             screen_to_raster: Transform::new(),
@@ -171,6 +179,8 @@ impl OrthographicCamera {
 
             film: None,
             medium: None,
+            shutter_open: 0.0,
+            shutter_close: 1.0,
 
             lens_radius: 0.0, focal_distance: 1.0,
             dx_camera: Vector3f::new(), dy_camera: Vector3f::new()
@@ -2,7 +2,7 @@ use crate::common::*;
 
 #[derive(Debug, Clone)]
 pub struct PerspectiveCamera {
-    camera_to_world: Transform,
+    camera_to_world: AnimatedTransform,
     camera_to_screen: Transform,
     raster_to_camera: Transform,
     screen_to_raster: Transform,
@@ -10,6 +10,8 @@ pub struct PerspectiveCamera {
 
     film: Option<Arc<Film>>,
     medium: Option<Arc<Medium>>,
+    shutter_open: f32,
+    shutter_close: f32,
 
     lens_radius: f32,
     focal_distance: f32,
@@ -17,6 +19,7 @@ pub struct PerspectiveCamera {
     dx_camera: Vector3f,
     dy_camera: Vector3f,
     a: f32,
+    fov: f32,
 }
 
 // Constructor
@@ -27,11 +30,13 @@ fn create_perspective_camera(prop_list: PropertyList) -> LeadObject {
 }
 
 impl Camera for PerspectiveCamera {
-    fn camera_to_world(&self) -> &Transform { &self.camera_to_world }
+    fn camera_to_world(&self) -> &AnimatedTransform { &self.camera_to_world }
     fn film(&self) -> Option<Arc<Film>> { self.film.clone() }
     fn medium(&self) -> Option<Arc<Medium>> { self.medium.clone() }
+    fn shutter_open(&self) -> f32 { self.shutter_open }
+    fn shutter_close(&self) -> f32 { self.shutter_close }
 
-    fn set_camera_to_world(&mut self, t: &Transform) { self.camera_to_world = t.clone(); }
+    fn set_camera_to_world(&mut self, t: &AnimatedTransform) { self.camera_to_world = t.clone(); }
     fn set_film(&mut self, film: Arc<Film>) { self.film = Some(film); }
     fn set_medium(&mut self, medium: Arc<Medium>) { self.medium = Some(medium); }
 
@@ -39,8 +44,9 @@ impl Camera for PerspectiveCamera {
         let p_film = Point3f::init([sample.p_film.x(), sample.p_film.y(), 0.0]);
         let p_camera = &self.raster_to_camera * p_film;
 
-        *ray = Ray::init(&p_camera, &Vector3f::init([0.0, 0.0, 1.0]), EPSILON, INFINITY);
-        
+        // rays leave the pinhole at the origin through the point on the film
+        *ray = Ray::init(&Point3f::new(), &Vector3f::normalize(&(p_camera - Point3f::new())), EPSILON, INFINITY);
+
         if self.lens_radius > 0.0 {
             let p_lens = Warp::sample_concentric_disk(sample.p_lens) * self.lens_radius;
 
@@ -52,6 +58,7 @@ impl Camera for PerspectiveCamera {
         }
 
         (*ray).medium = self.medium.clone();
+        (*ray).time = self.ray_time(sample);
         *ray = &self.camera_to_world * &(*ray);
 
         1.0
@@ -60,33 +67,39 @@ impl Camera for PerspectiveCamera {
     fn generate_ray_differential(&self, rd: &mut RayDifferential, sample: &CameraSample) -> f32 {
         let p_film = Point3f::init([sample.p_film.x(), sample.p_film.y(), 0.0]);
         let p_camera = &self.raster_to_camera * p_film;
+        let d = p_camera - Point3f::new();
 
-        *rd = RayDifferential::init(&p_camera, &Vector3f::init([0.0, 0.0, 1.0]), EPSILON, INFINITY);
+        *rd = RayDifferential::init(&Point3f::new(), &Vector3f::normalize(&d), EPSILON, INFINITY);
 
         if self.lens_radius > 0.0 {
             let p_lens = Warp::sample_concentric_disk(sample.p_lens) * self.lens_radius;
+            let lens_point = Point3f::init([p_lens.x(), p_lens.y(), 0.0]);
 
             let ft = self.focal_distance / rd.d.z();
-            let mut p_focus = rd.at(ft);
+            let p_focus = rd.at(ft);
 
-            (*rd).o = Point3f::init([p_lens.x(), p_lens.y(), 0.0]);
+            (*rd).o = lens_point;
             (*rd).d = Vector3f::normalize(&(p_focus - rd.o));
 
-            p_focus = p_camera + self.dx_camera + Vector3f::init([0.0, 0.0, 1.0]) * ft;
-            (*rd).rx_o = Point3f::init([p_lens.x(), p_lens.y(), 0.0]);
+            // the neighbours' rays go through the same lens point to where they would be in focus
+            let dx = Vector3f::normalize(&(d + self.dx_camera));
+            let p_focus = Point3f::new() + dx * (self.focal_distance / dx.z());
+            (*rd).rx_o = lens_point;
             (*rd).rx_d = Vector3f::normalize(&(p_focus - rd.rx_o));
 
-            p_focus = p_camera + self.dy_camera + Vector3f::init([0.0, 0.0, 1.0]) * ft;
-            (*rd).ry_o = Point3f::init([p_lens.x(), p_lens.y(), 0.0]);
+            let dy = Vector3f::normalize(&(d + self.dy_camera));
+            let p_focus = Point3f::new() + dy * (self.focal_distance / dy.z());
+            (*rd).ry_o = lens_point;
             (*rd).ry_d = Vector3f::normalize(&(p_focus - rd.ry_o));
         } else {
-            (*rd).rx_o = rd.o + self.dx_camera;
-            (*rd).ry_o = rd.o + self.dy_camera;
-            (*rd).rx_d = rd.d; 
-            (*rd).ry_d = rd.d;
+            (*rd).rx_o = rd.o;
+            (*rd).ry_o = rd.o;
+            (*rd).rx_d = Vector3f::normalize(&(d + self.dx_camera));
+            (*rd).ry_d = Vector3f::normalize(&(d + self.dy_camera));
         }
 
         (*rd).medium = self.medium.clone();
+        (*rd).time = self.ray_time(sample);
         (*rd).has_differential = true;
 
         *rd = &self.camera_to_world * &(*rd);
@@ -125,33 +138,40 @@ impl ProjectiveCamera for PerspectiveCamera {
 }
 
 impl LeadObjectTrait for PerspectiveCamera {
-    // TODO ACTUALLY GET SCREEN WINDOW, Film, and MEDIUM here
+    // TODO ACTUALLY GET MEDIUM here
     fn init(&mut self, prop_list: PropertyList) {
-        let lookat = prop_list.get_point3("lookat", Point3f::new());
-        let origin = prop_list.get_point3("eye", Point3f::init([0.0, 0.0, -1.0]));
-        let up = prop_list.get_vector3("up", Vector3f::init([0.0, 1.0, 0.0]));
-
-        let camera_to_world = Transform::look_at(&origin, &lookat, &up);
-        let lens_r = prop_list.get_float("lens_radius", 0.0);   // 0.0 means no depth of field
-        let focal_d = prop_list.get_float("focal_distance", 1.0);
-
-        let fov = prop_list.get_float("fov", 30f32);    // in degrees
-
-        let mut screen_window = Bounds2f::new();
-        screen_window.p_min = Point2f::init([0.0, 0.0]);
-        screen_window.p_max = Point2f::init([700.0, 500.0]);
+        self.camera_to_world = animated_look_at(&prop_list);
+        self.shutter_open = prop_list.get_float("shutter_open", 0.0);
+        self.shutter_close = prop_list.get_float("shutter_close", 1.0);
+        self.lens_radius = prop_list.get_float("lens_radius", 0.0);   // 0.0 means no depth of field
+        self.focal_distance = prop_list.get_float("focal_distance", 1.0);
+
+        self.fov = prop_list.get_float("fov", 30f32);    // in degrees
+        self.medium = Some(Arc::new(Medium {}));
+    }
 
-        let film = Film::new();
-        let medium = Medium {};
+    // The raster transforms depend on the film, so they are only built once it has been handed over
+    fn activate(&mut self) {
+        let film = match &self.film {
+            Some(film) => film.clone(),
+            None => panic!("Perspective camera needs a film before it can be activated!")
+        };
+        let medium = self.medium.clone().unwrap_or(Arc::new(Medium {}));
+
+        // fov is the angle across the shorter side of the film
+        let screen_window = screen_window_from_aspect(&film.full_resolution);
         let res = film.full_resolution;
 
-        self.init_projective_camera(camera_to_world, Self::perspective_projection_matrix(fov, 0.01, 1000.0), screen_window, lens_r, focal_d, Arc::new(film), Arc::new(medium));
+        self.init_projective_camera(self.camera_to_world.clone(), Self::perspective_projection_matrix(self.fov, 0.01, 1000.0), screen_window, self.lens_radius, self.focal_distance, film, medium);
 
-        self.dx_camera = &self.raster_to_camera * Vector3f::init([1.0, 0.0, 0.0]) - &self.raster_to_camera * Vector3f::init([0.0, 0.0, 0.0]);
-        self.dy_camera = &self.raster_to_camera * Vector3f::init([0.0, 1.0, 0.0]) - &self.raster_to_camera * Vector3f::init([0.0, 0.0, 0.0]);
+        // points, since raster to camera is a projection and needs the divide vectors do not get
+        let origin = &self.raster_to_camera * Point3f::init([0.0, 0.0, 0.0]);
+        self.dx_camera = &self.raster_to_camera * Point3f::init([1.0, 0.0, 0.0]) - origin;
+        self.dy_camera = &self.raster_to_camera * Point3f::init([0.0, 1.0, 0.0]) - origin;
 
-        let mut p_min = &self.raster_to_camera * Vector3f::init([0.0, 0.0, 1.0]);
-        let mut p_max = &self.raster_to_camera * Vector3f::init([res.x(), res.y(), 1.0]);
+        // the film's corners on the plane at z = 1
+        let mut p_min = &self.raster_to_camera * Point3f::init([0.0, 0.0, 0.0]);
+        let mut p_max = &self.raster_to_camera * Point3f::init([res.x(), res.y(), 0.0]);
         p_min = p_min / p_min.z();
         p_max = p_max / p_max.z();
         
@@ -159,12 +179,11 @@ impl LeadObjectTrait for PerspectiveCamera {
         self.a = self.a.abs();
     }
 
-    fn activate(&mut self) {
-        
-    }
-
-    fn add_child(&mut self, _child: &mut LeadObject) {
-        panic!("Cannot add child to perspective camera!");
+    fn add_child(&mut self, child: &mut LeadObject) {
+        match child {
+            LeadObject::Film(film) => self.set_film(film.clone()),
+            _ => panic!("Cannot add child of class {} to perspective camera!", child.to_string())
+        }
     }
 
     fn to_string(&self) -> String {
@@ -175,7 +194,7 @@ impl LeadObjectTrait for PerspectiveCamera {
 impl PerspectiveCamera {
     pub fn new() -> Self {
         Self {
-            camera_to_world:  Transform::new(),
+            camera_to_world:  AnimatedTransform::init_static(Transform::new()),
             camera_to_screen: Transform::new(),
             raster_to_camera: Transform::new(),
             screen_to_raster: Transform::new(),
@@ -183,10 +202,13 @@ impl PerspectiveCamera {
 
             film: None,
             medium: None,
+            shutter_open: 0.0,
+            shutter_close: 1.0,
 
             lens_radius: 0.0, focal_distance: 1.0,
             dx_camera: Vector3f::new(), dy_camera: Vector3f::new(),
             a: 0.0,
+            fov: 30.0,
         }
     }
 
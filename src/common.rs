@@ -18,6 +18,7 @@ pub use crate::sampler::*;
 pub use crate::scene::*;
 pub use crate::shapes::*;
 pub use crate::spectrum::*;
+pub use crate::texture::*;
 
 pub use crate::utils::*;
 pub use crate::macros::*;
@@ -5,7 +5,8 @@ use lazy_static::lazy_static;
 use crate::common::PropertyList;
 use crate::common::LeadObject;
 
-type LeadObjCtor = fn(PropertyList) -> LeadObject;
+// Constructors that read files can fail, and report why instead of panicking
+type LeadObjCtor = fn(PropertyList) -> Result<LeadObject, String>;
 
 // start a factory
 lazy_static!{
@@ -21,10 +22,11 @@ pub fn register_lead_object(name: &'static str, ctor: LeadObjCtor) {
 }
 
 // Create a lead object
-pub fn create_lead_object(name: &str, prop_list: PropertyList) -> LeadObject {
-    let registery = REGISTERY.lock().unwrap();
-    match registery.get(name) {
-        Some(ctor) => ctor(prop_list),
-        None => panic!("No constructor found for struct {name}")
-    }
+pub fn create_lead_object(name: &str, prop_list: PropertyList) -> Result<LeadObject, String> {
+    let ctor = match REGISTERY.lock().unwrap().get(name) {
+        Some(ctor) => *ctor,
+        None => return Err(format!("No constructor found for struct {name}"))
+    };
+
+    ctor(prop_list).map_err(|e| format!("Could not create {name}: {e}"))
 }
\ No newline at end of file
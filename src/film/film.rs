@@ -2,6 +2,8 @@ use crate::common::*;
 use atomic_float::AtomicF64;
 use box_filter::BoxFilter;
 
+stat_timer!(TILE_MERGING, "Film/Merging tiles");
+
 #[derive(Debug)]
 struct Pixel {
     xyz: [f64; 3],
@@ -35,6 +37,67 @@ pub struct Film {
     // dont need mutex, can wrap the whole hting ina arc<mutex<film>>
 }
 
+// Constructor
+fn create_film(prop_list: PropertyList) -> Result<LeadObject, String> {
+    let width = prop_list.get_int("width", 1280);
+    let height = prop_list.get_int("height", 720);
+    let filename = prop_list.get_string("filename", "image");
+    let diagonal = prop_list.get_float("diagonal", 35.0);   // in mm
+    let scale = prop_list.get_float("scale", 1.0);
+
+    if width <= 0 || height <= 0 {
+        return Err(format!("Film resolution must be positive, not {}x{}", width, height));
+    }
+    let resolution = Point2f::init([width as f32, height as f32]);
+    let crop_window = parse_crop_window(&prop_list.get_string("crop_window", "0, 1, 0, 1"))?;
+    // a box over a single pixel unless the film declares an <rfilter>
+    let filter = Arc::new(BoxFilter::new(&Vector2f::init([0.5, 0.5])));
+
+    let film = Film::init(&resolution, &crop_window, filter, diagonal, filename, scale);
+    Ok(LeadObject::Film(Arc::new(film)))
+}
+
+// "x_min, x_max, y_min, y_max" as fractions of the image, like pbrt's cropwindow
+fn parse_crop_window(value: &str) -> Result<Bounds2f, String> {
+    let v: Vec<f32> = value.split(',')
+        .map(|part| part.trim().parse::<f32>().map_err(|_| format!("Unable to parse crop window {}", value)))
+        .collect::<Result<_, _>>()?;
+    if v.len() != 4 {
+        return Err(format!("Crop window takes x_min, x_max, y_min and y_max, not {}", value));
+    }
+
+    let p_min = Point2f::init([v[0].min(v[1]).clamp(0.0, 1.0), v[2].min(v[3]).clamp(0.0, 1.0)]);
+    let p_max = Point2f::init([v[0].max(v[1]).clamp(0.0, 1.0), v[2].max(v[3]).clamp(0.0, 1.0)]);
+    if p_min.x() == p_max.x() || p_min.y() == p_max.y() {
+        return Err(format!("Crop window {} leaves no pixels", value));
+    }
+    Ok(Bounds2f::init(&p_min, &p_max))
+}
+
+impl LeadObjectTrait for Film {
+    fn init(&mut self, _prop_list: PropertyList) { }
+
+    fn activate(&mut self) { }
+
+    fn add_child(&mut self, child: &mut LeadObject) {
+        match child {
+            LeadObject::Filter(filter) => {
+                self.filter_table = Self::filter_table(filter.as_ref());
+                self.filter = filter.clone();
+            },
+            _ => println!("Struct Film does not take a child of class {}", child.to_string())
+        }
+    }
+
+    fn to_string(&self) -> String {
+        format!(
+            "Film[\n  resolution: {},\n  cropped_pixel_bounds: \n{},\n  diagonal: {},\n  scale: {},\n  filter: {:?},\n  filename: {}\n]",
+            self.full_resolution.to_string(), indent(&self.cropped_pixel_bounds.to_string(), 4),
+            self.diagonal, self.scale, self.filter, self.filename
+        )
+    }
+}
+
 impl Film{
     pub fn new() -> Self {
         Self {
@@ -63,16 +126,8 @@ impl Film{
         }
         let pixels = Mutex::from(pixels_inner);
 
-        let mut filter_table: Vec<f32> = Vec::new();
         let filter_table_width = 16usize;
-        for y in 0..filter_table_width {
-            for x in 0..filter_table_width {
-                let mut p = Point2f::new();
-                p[0] = (x as f32 + 0.5) * filter.radius().x() / filter_table_width as f32;
-                p[1] = (y as f32 + 0.5) * filter.radius().y() / filter_table_width as f32;
-                filter_table.push(filter.evaluate(&p));
-            } 
-        }
+        let filter_table = Self::filter_table(filter.as_ref());
 
         Self {
             full_resolution: *resolution,
@@ -83,12 +138,27 @@ impl Film{
 
             cropped_pixel_bounds: cropped_pixel_bounds,
             pixels: pixels,
-            filter_table: filter_table.try_into().unwrap(), // always is 256 so we good
+            filter_table,
             filter_table_width: filter_table_width,
             // mutex: Mutex::from(1u8)
         }
     }
 
+    // The filter over the positive quadrant of its radius, 16 by 16 entries
+    fn filter_table(filter: &dyn Filter) -> [f32; 256] {
+        let mut filter_table = [0f32; 256];
+        let filter_table_width = 16usize;
+        for y in 0..filter_table_width {
+            for x in 0..filter_table_width {
+                let mut p = Point2f::new();
+                p[0] = (x as f32 + 0.5) * filter.radius().x() / filter_table_width as f32;
+                p[1] = (y as f32 + 0.5) * filter.radius().y() / filter_table_width as f32;
+                filter_table[y * filter_table_width + x] = filter.evaluate(&p);
+            }
+        }
+        filter_table
+    }
+
     pub fn get_sample_bounds(&self) -> Bounds2f {
         let p_min = Point2f::floor(
             &(Point2f::init_copy(&self.cropped_pixel_bounds.p_min)
@@ -120,12 +190,13 @@ impl Film{
 
         let tile_pixel_bounds = Bounds2f::intersect(&Bounds2f::init(&p0, &p1), &self.cropped_pixel_bounds);
 
-        let ret = FilmTile::new( &tile_pixel_bounds, &self.filter.radius(), self.filter_table.to_vec(), self.filter_table_width as f32);
+        let ret = FilmTile::new( &tile_pixel_bounds, &self.filter.radius(), self.filter_table.to_vec(), self.filter_table_width, INFINITY);
 
         Arc::new(ret)
     }
 
-    pub fn merge_film_title(&mut self, tile: Arc<FilmTile>) {
+    pub fn merge_film_title(&self, tile: Arc<FilmTile>) {
+        let start = std::time::Instant::now();
         let mut pixels = self.pixels.lock().unwrap();
 
         for pixel in tile.get_pixel_bounds().iter() {
@@ -141,6 +212,7 @@ impl Film{
             }
             merge_pixel.filter_weight_sum += tile_pixel.filter_weight_sum;
         }
+        TILE_MERGING.with(|timer| timer.add(start.elapsed()));
     }
 
     pub fn set_image(&self, img: &[Spectrum]) {
@@ -156,7 +228,7 @@ impl Film{
         }
     }
 
-    pub fn add_splat(&mut self, p: &Point2f, v: &Spectrum) {
+    pub fn add_splat(&self, p: &Point2f, v: &Spectrum) {
         if !Bounds2f::inside_exclusive(p, &self.cropped_pixel_bounds) {
             return;
         }
@@ -172,7 +244,7 @@ impl Film{
         }
     }
 
-    pub fn write_image(&mut self, splat_scale: f64) {
+    pub fn write_image(&self, splat_scale: f64) {
         let mut rgb: Vec<f32> = Vec::new();
         let pixels= self.pixels.lock().unwrap();
 
@@ -211,11 +283,16 @@ impl Film{
             rgb.push(z as f32);
         }
 
-        write_image_to_file(rgb, self.filename.clone(), &self.full_resolution);
+        // only the cropped part of the image was rendered, so that is all that gets written
+        let cropped_resolution = Point2f::init([
+            self.cropped_pixel_bounds.p_max.x() - self.cropped_pixel_bounds.p_min.x(),
+            self.cropped_pixel_bounds.p_max.y() - self.cropped_pixel_bounds.p_min.y()
+        ]);
+        write_image_to_file(rgb, self.filename.clone(), &cropped_resolution);
     }
 
-    pub fn clear(&mut self) {
-        let pixels = &mut self.pixels.lock().unwrap();
+    pub fn clear(&self) {
+        let mut pixels = self.pixels.lock().unwrap();
         for p in self.cropped_pixel_bounds.iter() {
             let pixel_offset = self.get_pixel_offset(&p);
             let pixel = &mut pixels[pixel_offset];
@@ -236,4 +313,6 @@ impl Film{
         
         offset
     }
-}
\ No newline at end of file
+}
+
+register_fallible_struct!("hdr", create_film);
\ No newline at end of file
@@ -21,12 +21,13 @@ pub struct FilmTile{
     pub filter_radius: Vector2f,
     pub inv_filter_radius: Vector2f,
     pub filter_table: Vec<f32>,
+    pub filter_table_width: usize,
     pub pixels: Vec<FilmTilePixel>,
     pub max_sample_luminance: f32
 }
 
 impl FilmTile {
-    pub fn new(pixel_bounds: &Bounds2f, filter_radius: &Vector2f, filter_table: Vec<f32>, max_sample_lum: f32) -> Self {
+    pub fn new(pixel_bounds: &Bounds2f, filter_radius: &Vector2f, filter_table: Vec<f32>, filter_table_width: usize, max_sample_lum: f32) -> Self {
         let inv_rad = Vector2f::init([1.0/filter_radius.x(), 1.0/filter_radius.y()]);
         let num_pixels = (pixel_bounds.area() as usize).max(0usize);
 
@@ -40,6 +41,7 @@ impl FilmTile {
             filter_radius: filter_radius.clone(),
             inv_filter_radius: inv_rad,
             filter_table: filter_table,
+            filter_table_width,
             max_sample_luminance: max_sample_lum,
             pixels: pixels
         }
@@ -62,9 +64,9 @@ impl FilmTile {
         let mut x= p0.x();
         loop {
             if x >= p1.x() { break; }
-            let fx = ((x - p_film_discrete.x()) * self.inv_filter_radius.x() * self.filter_table.len() as f32).abs();
+            let fx = ((x - p_film_discrete.x()) * self.inv_filter_radius.x() * self.filter_table_width as f32).abs();
 
-            ifx[(x- p0.x()) as usize] = (fx.floor() as usize).min(self.filter_table.len() - 1);
+            ifx[(x- p0.x()) as usize] = (fx.floor() as usize).min(self.filter_table_width - 1);
 
             x += 1.0;
         }
@@ -73,9 +75,9 @@ impl FilmTile {
         let mut y= p0.y();
         loop {
             if y >= p1.y() { break; }
-            let fy = ((y - p_film_discrete.y()) * self.inv_filter_radius.y() * self.filter_table.len() as f32).abs();
+            let fy = ((y - p_film_discrete.y()) * self.inv_filter_radius.y() * self.filter_table_width as f32).abs();
 
-            ify[(y- p0.y()) as usize] = (fy.floor() as usize).min(self.filter_table.len() - 1);
+            ify[(y- p0.y()) as usize] = (fy.floor() as usize).min(self.filter_table_width - 1);
 
             y += 1.0;
         }
@@ -87,14 +89,17 @@ impl FilmTile {
             'inner: loop {
                 if x >= p1.x() { break 'inner; }
 
-                let offset = ify[(y - p0.y()) as usize] * self.filter_table.len() + ifx[(x - p0.x()) as usize];
+                let offset = ify[(y - p0.y()) as usize] * self.filter_table_width + ifx[(x - p0.x()) as usize];
                 let filter_weight = self.filter_table[offset];
 
                 let pixel = self.get_pixel(&Point2f::init([x, y]));
 
                 pixel.contrib_sum = pixel.contrib_sum + l * sample_weight as f64 * filter_weight as f64;
                 pixel.filter_weight_sum += filter_weight;
+
+                x += 1.0;
             }
+            y += 1.0;
         }
     }
 
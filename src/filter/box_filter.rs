@@ -6,6 +6,12 @@ pub struct BoxFilter {
     inv_radius: Vector2f
 }
 
+// Constructor
+fn create_box_filter(prop_list: PropertyList) -> LeadObject {
+    let radius = prop_list.get_vector2("radius", Vector2f::init([0.5, 0.5]));
+    LeadObject::Filter(Arc::new(BoxFilter::new(&radius)))
+}
+
 impl Filter for BoxFilter {
     fn radius(&self) -> Vector2f { self.radius }
     fn inv_radius(&self) -> Vector2f { self.inv_radius }
@@ -22,4 +28,6 @@ impl BoxFilter {
             inv_radius: Vector2f::init([1.0/radius.x(), 1.0/radius.y()])
         }
     }
-}
\ No newline at end of file
+}
+
+register_struct!("box", create_box_filter);
\ No newline at end of file
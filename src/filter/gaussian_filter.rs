@@ -9,6 +9,13 @@ pub struct GaussianFilter {
     exp_x: f32, exp_y: f32
 }
 
+// Constructor
+fn create_gaussian_filter(prop_list: PropertyList) -> LeadObject {
+    let radius = prop_list.get_vector2("radius", Vector2f::init([2.0, 2.0]));
+    let alpha = prop_list.get_float("alpha", 2.0);
+    LeadObject::Filter(Arc::new(GaussianFilter::new(alpha, &radius)))
+}
+
 impl Filter for GaussianFilter {
     fn radius(&self) -> Vector2f { self.radius }
     fn inv_radius(&self) -> Vector2f { self.inv_radius }
@@ -34,4 +41,6 @@ impl GaussianFilter {
     fn gaussian_filter(&self, d: f32, exp_v: f32) -> f32 {
         0f32.max((-self.alpha * d * d).exp() - exp_v)
     }
-}
\ No newline at end of file
+}
+
+register_struct!("gaussian", create_gaussian_filter);
\ No newline at end of file
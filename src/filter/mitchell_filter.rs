@@ -8,6 +8,14 @@ pub struct MitchellFilter {
     b: f32, c: f32
 }
 
+// Constructor
+fn create_mitchell_filter(prop_list: PropertyList) -> LeadObject {
+    let radius = prop_list.get_vector2("radius", Vector2f::init([2.0, 2.0]));
+    let b = prop_list.get_float("b", 1.0 / 3.0);
+    let c = prop_list.get_float("c", 1.0 / 3.0);
+    LeadObject::Filter(Arc::new(MitchellFilter::new(&radius, b, c)))
+}
+
 impl Filter for MitchellFilter {
     fn radius(&self) -> Vector2f { self.radius }
     fn inv_radius(&self) -> Vector2f { self.inv_radius }
@@ -38,4 +46,6 @@ impl MitchellFilter {
             (6.0 - 2.0*self.b)) * (1.0/6.0)
         }
     }
-}
\ No newline at end of file
+}
+
+register_struct!("mitchell", create_mitchell_filter);
\ No newline at end of file
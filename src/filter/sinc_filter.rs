@@ -8,6 +8,13 @@ pub struct SincFilter {
     tau: f32
 }
 
+// Constructor
+fn create_sinc_filter(prop_list: PropertyList) -> LeadObject {
+    let radius = prop_list.get_vector2("radius", Vector2f::init([4.0, 4.0]));
+    let tau = prop_list.get_float("tau", 3.0);
+    LeadObject::Filter(Arc::new(SincFilter::new(&radius, tau)))
+}
+
 impl Filter for SincFilter {
     fn radius(&self) -> Vector2f { self.radius }
     fn inv_radius(&self) -> Vector2f { self.inv_radius }
@@ -29,8 +36,9 @@ impl SincFilter {
 
     fn sinc(&self, x: f32) -> f32 {
         let x = x.abs();
+        // sin(pi x) / (pi x) goes to 1 at the center
         if x < EPSILON {
-            return 0.0;
+            return 1.0;
         }
 
         (M_PI * x).sin() / x * M_INV_PI
@@ -46,4 +54,6 @@ impl SincFilter {
         let lanczos = self.sinc(x / self.tau);
         self.sinc(x) * lanczos
     }
-}
\ No newline at end of file
+}
+
+register_struct!("sinc", create_sinc_filter);
\ No newline at end of file
@@ -6,6 +6,12 @@ pub struct TriangleFilter {
     inv_radius: Vector2f
 }
 
+// Constructor
+fn create_triangle_filter(prop_list: PropertyList) -> LeadObject {
+    let radius = prop_list.get_vector2("radius", Vector2f::init([2.0, 2.0]));
+    LeadObject::Filter(Arc::new(TriangleFilter::new(&radius)))
+}
+
 impl Filter for TriangleFilter {
     fn radius(&self) -> Vector2f { self.radius }
     fn inv_radius(&self) -> Vector2f { self.inv_radius }
@@ -14,7 +20,7 @@ impl Filter for TriangleFilter {
     // Only called within the radius of the filter so we are good
     fn evaluate(&self, p: &Point2f) -> f32 {
         let x_part = 0f32.max(self.radius.x() - p.x().abs());
-        let y_part = 0f32.max(self.radius.x() - p.y().abs());
+        let y_part = 0f32.max(self.radius.y() - p.y().abs());
         x_part * y_part
     }
 }
@@ -26,4 +32,6 @@ impl TriangleFilter{
             inv_radius: Vector2f::init([1.0/radius.x(), 1.0/radius.y()])
         }
     }
-}
\ No newline at end of file
+}
+
+register_struct!("triangle", create_triangle_filter);
\ No newline at end of file
@@ -0,0 +1,161 @@
+use crate::common::*;
+use std::ops::Mul;
+
+// Transform moving between two keyframes. Both are split into a translation, a rotation and a
+// scale which are interpolated on their own, so a spinning object keeps its shape in between
+#[derive(Debug, Clone)]
+pub struct AnimatedTransform {
+    start_transform: Transform,
+    end_transform: Transform,
+    start_time: f32,
+    end_time: f32,
+    actually_animated: bool,
+
+    t: [Vector3f; 2],
+    r: [Quaternion; 2],
+    s: [Matrix4x4; 2],
+    has_rotation: bool,
+}
+
+impl AnimatedTransform {
+    pub fn new(start_transform: Transform, start_time: f32, end_transform: Transform, end_time: f32) -> Self {
+        let actually_animated = !Transform::equal(&start_transform, &end_transform);
+
+        let (t_0, r_0, s_0) = Self::decompose(&start_transform.m);
+        let (t_1, mut r_1, s_1) = Self::decompose(&end_transform.m);
+        // take the shorter way around
+        if Quaternion::dot(&r_0, &r_1) < 0.0 {
+            r_1 = -r_1;
+        }
+        let has_rotation = !Quaternion::equal(&r_0, &r_1);
+
+        Self {
+            start_transform,
+            end_transform,
+            start_time,
+            end_time,
+            actually_animated,
+
+            t: [t_0, t_1],
+            r: [r_0, r_1],
+            s: [s_0, s_1],
+            has_rotation,
+        }
+    }
+
+    // Transform that stays put over the whole shutter
+    pub fn init_static(transform: Transform) -> Self {
+        Self::new(transform.clone(), 0.0, transform, 1.0)
+    }
+
+    pub fn start_transform(&self) -> &Transform { &self.start_transform }
+    pub fn end_transform(&self) -> &Transform { &self.end_transform }
+    pub fn start_time(&self) -> f32 { self.start_time }
+    pub fn end_time(&self) -> f32 { self.end_time }
+    pub fn is_animated(&self) -> bool { self.actually_animated }
+
+    // Splits m into translation * rotation * scale, the rotation found by polar decomposition
+    fn decompose(m: &Matrix4x4) -> (Vector3f, Quaternion, Matrix4x4) {
+        let t = Vector3f::init([m[0][3], m[1][3], m[2][3]]);
+
+        let mut m = m.clone();
+        for i in 0..3 {
+            m[i][3] = 0.0;
+            m[3][i] = 0.0;
+        }
+        m[3][3] = 1.0;
+
+        // average the matrix with its inverse transpose until only the rotation is left
+        let mut r = m.clone();
+        for _ in 0..100 {
+            let r_it = r.inverse().transpose();
+            let mut r_next = Matrix4x4::new();
+            let mut norm = 0f32;
+            for i in 0..4 {
+                for j in 0..4 {
+                    r_next[i][j] = 0.5 * (r[i][j] + r_it[i][j]);
+                }
+            }
+            for i in 0..3 {
+                let n = (0..3).map(|j| (r[i][j] - r_next[i][j]).abs()).sum();
+                norm = f32::max(norm, n);
+            }
+
+            r = r_next;
+            if norm <= 0.0001 {
+                break;
+            }
+        }
+
+        let s = Matrix4x4::mul(&r.inverse(), &m);
+        (t, Quaternion::from_transform(&Transform::init_mat(&r)), s)
+    }
+
+    pub fn interpolate(&self, time: f32) -> Transform {
+        if !self.actually_animated || time <= self.start_time {
+            return self.start_transform.clone();
+        }
+        if time >= self.end_time {
+            return self.end_transform.clone();
+        }
+
+        let dt = (time - self.start_time) / (self.end_time - self.start_time);
+        let trans = self.t[0] * (1.0 - dt) + self.t[1] * dt;
+        let rotate = Quaternion::slerp(dt, &self.r[0], &self.r[1]);
+        let mut scale = Matrix4x4::new();
+        for i in 0..4 {
+            for j in 0..4 {
+                scale[i][j] = self.s[0][i][j] * (1.0 - dt) + self.s[1][i][j] * dt;
+            }
+        }
+
+        Transform::translate(&trans) * rotate.to_transform() * Transform::init_mat(&scale)
+    }
+
+    // Box holding b at every time in between the keyframes
+    pub fn motion_bounds(&self, b: &Bounds3f) -> Bounds3f {
+        let start = &self.start_transform * *b;
+        if !self.actually_animated {
+            return start;
+        }
+        let end = &self.end_transform * *b;
+        // without rotation every point moves along a straight line
+        if !self.has_rotation {
+            return Bounds3f::union(&start, &end);
+        }
+
+        // A rotating point stays within the length of its scaled position around the current
+        // translation. That length is largest at a keyframe, since the scale is interpolated linearly
+        let mut radius = 0f32;
+        for corner in 0..8 {
+            let p = b.corner(corner);
+            for s in &self.s {
+                let sp = Vector3f::init([0, 1, 2].map(|i| s[i][0] * p.x() + s[i][1] * p.y() + s[i][2] * p.z()));
+                radius = radius.max(sp.length());
+            }
+        }
+
+        let path = Bounds3f::union_pt(
+            &Bounds3f::init_one(&Point3f::init([self.t[0].x(), self.t[0].y(), self.t[0].z()])),
+            &Point3f::init([self.t[1].x(), self.t[1].y(), self.t[1].z()])
+        );
+        Bounds3f::expand(&path, radius)
+    }
+}
+
+// The ray is moved by the transform at its own time
+impl Mul<&Ray> for &AnimatedTransform {
+    type Output = Ray;
+
+    fn mul(self, r: &Ray) -> Self::Output {
+        &self.interpolate(r.time) * r
+    }
+}
+
+impl Mul<&RayDifferential> for &AnimatedTransform {
+    type Output = RayDifferential;
+
+    fn mul(self, r: &RayDifferential) -> Self::Output {
+        &self.interpolate(r.time) * r
+    }
+}
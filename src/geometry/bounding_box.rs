@@ -93,10 +93,10 @@ impl<T> Bounds2<T>
     pub fn offset(&self, p: &Point<T, 2usize>) -> Vector<T, 2usize> {
         let mut o = *p - self.p_min;
         if self.p_max.x() > self.p_min.x() {
-            o[0] = o.x() / self.p_max.x() - self.p_min.x();
+            o[0] = o.x() / (self.p_max.x() - self.p_min.x());
         }
         if self.p_max.y() > self.p_min.y() {
-            o[1] = o.y() / self.p_max.y() - self.p_min.y();
+            o[1] = o.y() / (self.p_max.y() - self.p_min.y());
         }
         o
     }
@@ -254,13 +254,13 @@ impl<T> Bounds3<T>
     pub fn offset(&self, p: &Point<T, 3usize>) -> Vector<T, 3usize> {
         let mut o = *p - self.p_min;
         if self.p_max.x() > self.p_min.x() {
-            o[0] = o.x() / self.p_max.x() - self.p_min.x();
+            o[0] = o.x() / (self.p_max.x() - self.p_min.x());
         }
         if self.p_max.y() > self.p_min.y() {
-            o[1] = o.y() / self.p_max.y() - self.p_min.y();
+            o[1] = o.y() / (self.p_max.y() - self.p_min.y());
         }
         if self.p_max.z() > self.p_min.z() {
-            o[2] = o.z() / self.p_max.z() - self.p_min.z();
+            o[2] = o.z() / (self.p_max.z() - self.p_min.z());
         }
         o
     }
@@ -324,26 +324,32 @@ impl<T> Bounds3<T>
         }
     }
 
+    // The parts of b below and above the plane at position along axis, empty when the plane misses that side
+    pub fn split(b: &Self, axis: usize, position: T) -> (Self, Self) {
+        let (mut below, mut above) = (*b, *b);
+        below.p_max[axis] = position.min(b.p_max[axis]);
+        above.p_min[axis] = position.max(b.p_min[axis]);
+        (below, above)
+    }
+
     pub fn intersect_p(&self, ray: &Ray, hit_0: &mut f32, hit_1: &mut f32) -> bool {
         let mut t_0: f32 = 0.0;
         let mut t_1: f32 = ray.t_max;
 
-        for i in 0..=3 {
+        for i in 0..3 {
             let inv_ray_dir = 1.0 / ray.d[i];
             let mut t_near = (self.p_min[i] - T::from(ray.o[i]).unwrap()) * T::from(inv_ray_dir).unwrap();
             let mut t_far = (self.p_max[i] - T::from(ray.o[i]).unwrap()) * T::from(inv_ray_dir).unwrap();
 
             if t_near > t_far {
-                let temp = t_near;
-                t_near = t_far;
-                t_far = temp;
+                std::mem::swap(&mut t_near, &mut t_far);
+            }
 
-                t_0 = if !t_near.is_nan() && t_near > T::from(t_0).unwrap() { t_near.to_f32().unwrap() } else { t_0 };
-                t_1 = if !t_far.is_nan() && t_far < T::from(t_1).unwrap() { t_far.to_f32().unwrap() } else { t_1 };
+            t_0 = if !t_near.is_nan() && t_near > T::from(t_0).unwrap() { t_near.to_f32().unwrap() } else { t_0 };
+            t_1 = if !t_far.is_nan() && t_far < T::from(t_1).unwrap() { t_far.to_f32().unwrap() } else { t_1 };
 
-                if t_0 > t_1 {
-                    return false;
-                }
+            if t_0 > t_1 {
+                return false;
             }
         }
 
@@ -68,4 +68,39 @@ impl Frame {
 
         (num/denom).clamp(-1.0, 1.0)
     }
+
+    pub fn same_hemisphere(w: &Vector3f, wp: &Vector3f) -> bool {
+        w.z() * wp.z() > 0.0
+    }
+
+    pub fn spherical_theta(v: &Vector3f) -> f32 {
+        v.z().clamp(-1.0, 1.0).acos()
+    }
+
+    pub fn spherical_phi(v: &Vector3f) -> f32 {
+        let p = v.y().atan2(v.x());
+        if p < 0.0 { p + 2.0 * M_PI } else { p }
+    }
+
+    pub fn spherical_direction(sin_theta: f32, cos_theta: f32, phi: f32) -> Vector3f {
+        Vector3f::init([sin_theta.clamp(-1.0, 1.0) * phi.cos(), sin_theta.clamp(-1.0, 1.0) * phi.sin(), cos_theta.clamp(-1.0, 1.0)])
+    }
+
+    // Lat-long mapping with y up, uv in [0, 1]^2 with v = 0 at the top
+    pub fn lat_long_to_direction(uv: &Point2f) -> Vector3f {
+        let theta = M_PI * uv.y();
+        let phi = 2.0 * M_PI * uv.x();
+
+        Vector3f::init([theta.sin() * phi.cos(), theta.cos(), theta.sin() * phi.sin()])
+    }
+
+    pub fn direction_to_lat_long(w: &Vector3f) -> Point2f {
+        let theta = w.y().clamp(-1.0, 1.0).acos();
+        let mut phi = w.z().atan2(w.x());
+        if phi < 0.0 {
+            phi += 2.0 * M_PI;
+        }
+
+        Point2f::init([phi * M_INV_PI * 0.5, theta * M_INV_PI])
+    }
 }
\ No newline at end of file
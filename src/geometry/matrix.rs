@@ -71,10 +71,10 @@ impl Matrix4x4 {
 
     pub fn transpose(&self) -> Self {
         Self::init(
-            self[0][0], self[0][1], self[0][2], self[0][3],
-            self[1][0], self[1][1], self[1][2], self[1][3],
-            self[2][0], self[2][1], self[2][2], self[2][3],
-            self[3][0], self[3][1], self[3][2], self[3][3]
+            self[0][0], self[1][0], self[2][0], self[3][0],
+            self[0][1], self[1][1], self[2][1], self[3][1],
+            self[0][2], self[1][2], self[2][2], self[3][2],
+            self[0][3], self[1][3], self[2][3], self[3][3]
         )
     }
 
@@ -123,13 +123,14 @@ impl Matrix4x4 {
 
     pub fn inverse(&self) -> Self{
         let det = self.determinant();
-        assert!(det > 0.0, "Non-invertible matrix!");
+        assert!(det != 0.0, "Non-invertible matrix!");
 
+        // inverse is the transposed cofactor matrix (adjugate) over the determinant
         let mut ret = Self::new();
         for r in 0..4 {
             for c in 0..4 {
                 let cof = Self::minor_determinant(&self.m, r, c) * if (r + c) % 2 == 0 {1f32} else {-1f32};
-                ret[r][c] = cof / det;
+                ret[c][r] = cof / det;
             }
         }
 
@@ -6,6 +6,8 @@ pub mod ray;
 pub mod transform;
 pub mod vector;
 pub mod frame;
+pub mod quaternion;
+pub mod animated_transform;
 
 pub use bounding_box::{Bounds2, Bounds2f, Bounds3, Bounds3f};
 pub use matrix::Matrix4x4;
@@ -13,5 +15,7 @@ pub use normal::{Normal, Normal3d, Normal3f};
 pub use point::{Point, Point2d, Point2f, Point3d, Point3f};
 pub use ray::{Ray, RayDifferential};
 pub use transform::Transform;
-pub use vector::{Vector, Vector2d, Vector2f, Vector3d, Vector3f};
-pub use frame::Frame;
\ No newline at end of file
+pub use vector::{Vector, Vector2d, Vector2f, Vector3d, Vector3f, coordinate_system};
+pub use frame::Frame;
+pub use quaternion::Quaternion;
+pub use animated_transform::AnimatedTransform;
\ No newline at end of file
@@ -0,0 +1,135 @@
+use crate::common::*;
+use std::ops::{Add, Mul, Neg, Sub};
+
+#[derive(Debug, Clone, Copy)]
+pub struct Quaternion {
+    pub v: Vector3f,
+    pub w: f32
+}
+
+impl Quaternion {
+    pub fn new() -> Self {
+        Self {
+            v: Vector3f::new(),
+            w: 1.0
+        }
+    }
+
+    // Only the rotation part of the transform is read, it has to be a pure rotation
+    pub fn from_transform(t: &Transform) -> Self {
+        let m = &t.m;
+        let trace = m[0][0] + m[1][1] + m[2][2];
+        if trace > 0.0 {
+            let s = (trace + 1.0).sqrt();
+            let w = s / 2.0;
+            let s = 0.5 / s;
+            return Self {
+                v: Vector3f::init([(m[2][1] - m[1][2]) * s, (m[0][2] - m[2][0]) * s, (m[1][0] - m[0][1]) * s]),
+                w
+            };
+        }
+
+        // work from the largest diagonal element so the square root stays well away from zero
+        let next = [1, 2, 0];
+        let mut i = 0;
+        if m[1][1] > m[0][0] {
+            i = 1;
+        }
+        if m[2][2] > m[i][i] {
+            i = 2;
+        }
+        let j = next[i];
+        let k = next[j];
+
+        let mut s = ((m[i][i] - (m[j][j] + m[k][k])) + 1.0).sqrt();
+        let mut q = [0f32; 3];
+        q[i] = s * 0.5;
+        if s != 0.0 {
+            s = 0.5 / s;
+        }
+        q[j] = (m[j][i] + m[i][j]) * s;
+        q[k] = (m[k][i] + m[i][k]) * s;
+
+        Self {
+            v: Vector3f::init(q),
+            w: (m[k][j] - m[j][k]) * s
+        }
+    }
+
+    pub fn to_transform(&self) -> Transform {
+        let (x, y, z) = (self.v.x(), self.v.y(), self.v.z());
+        let (xx, yy, zz) = (x * x, y * y, z * z);
+        let (xy, xz, yz) = (x * y, x * z, y * z);
+        let (wx, wy, wz) = (x * self.w, y * self.w, z * self.w);
+
+        let m = Matrix4x4::init(
+            1.0 - 2.0 * (yy + zz), 2.0 * (xy - wz), 2.0 * (xz + wy), 0.0,
+            2.0 * (xy + wz), 1.0 - 2.0 * (xx + zz), 2.0 * (yz - wx), 0.0,
+            2.0 * (xz - wy), 2.0 * (yz + wx), 1.0 - 2.0 * (xx + yy), 0.0,
+            0.0, 0.0, 0.0, 1.0
+        );
+
+        // a rotation is inverted by its transpose
+        Transform {
+            m_inv: m.transpose(),
+            m
+        }
+    }
+
+    pub fn dot(q1: &Self, q2: &Self) -> f32 {
+        Vector3f::dot(&q1.v, &q2.v) + q1.w * q2.w
+    }
+
+    pub fn normalize(q: &Self) -> Self {
+        *q * (1.0 / Self::dot(q, q).sqrt())
+    }
+
+    pub fn equal(q1: &Self, q2: &Self) -> bool {
+        Vector3f::equal(&q1.v, &q2.v) && q1.w == q2.w
+    }
+
+    // Spherical interpolation, falling back to a linear one when the two are nearly the same
+    pub fn slerp(t: f32, q1: &Self, q2: &Self) -> Self {
+        let cos_theta = Self::dot(q1, q2);
+        if cos_theta > 0.9995 {
+            return Self::normalize(&(*q1 * (1.0 - t) + *q2 * t));
+        }
+
+        let theta = cos_theta.clamp(-1.0, 1.0).acos();
+        let theta_p = theta * t;
+        let q_perp = Self::normalize(&(*q2 - *q1 * cos_theta));
+        *q1 * theta_p.cos() + q_perp * theta_p.sin()
+    }
+}
+
+impl Add for Quaternion {
+    type Output = Quaternion;
+
+    fn add(self, rhs: Self) -> Self::Output {
+        Quaternion { v: self.v + rhs.v, w: self.w + rhs.w }
+    }
+}
+
+impl Sub for Quaternion {
+    type Output = Quaternion;
+
+    fn sub(self, rhs: Self) -> Self::Output {
+        Quaternion { v: self.v - rhs.v, w: self.w - rhs.w }
+    }
+}
+
+impl Mul<f32> for Quaternion {
+    type Output = Quaternion;
+
+    fn mul(self, rhs: f32) -> Self::Output {
+        Quaternion { v: self.v * rhs, w: self.w * rhs }
+    }
+}
+
+impl Neg for Quaternion {
+    type Output = Quaternion;
+
+    fn neg(self) -> Self::Output {
+        Quaternion { v: -self.v, w: -self.w }
+    }
+}
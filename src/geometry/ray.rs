@@ -1,18 +1,22 @@
 use crate::common::*;
 
+#[derive(Clone)]
 pub struct Ray {
     pub o: Point3f,
     pub d: Vector3f,
     pub t_min: f32,
     pub t_max: f32,
+    pub time: f32,
     pub medium: Option<Arc<Medium>>
 }
 
+#[derive(Clone)]
 pub struct RayDifferential {
     pub o: Point3f,
     pub d: Vector3f,
     pub t_min: f32,
     pub t_max: f32,
+    pub time: f32,
     pub medium: Option<Arc<Medium>>,
 
     pub rx_o: Point3f,
@@ -29,6 +33,7 @@ impl Ray {
             d: Vector3f::new(),
             t_min: EPSILON,
             t_max: INFINITY,
+            time: 0.0,
             medium: None
         }
     }
@@ -39,6 +44,7 @@ impl Ray {
             d: Vector3f::normalize(dir),
             t_min: t_min,
             t_max: t_max,
+            time: 0.0,
             medium: None
         }
     }
@@ -63,6 +69,7 @@ impl RayDifferential {
             d: Vector3f::new(),
             t_min: EPSILON,
             t_max: INFINITY,
+            time: 0.0,
             medium: None,
 
             rx_o: Point3f::new(),
@@ -79,6 +86,7 @@ impl RayDifferential {
             d: dir.clone(),
             t_min: t_min,
             t_max: t_max,
+            time: 0.0,
             medium: None,
             
             rx_o: Point3f::new(),
@@ -89,6 +97,16 @@ impl RayDifferential {
         }
     }
 
+    // A ray spawned off a surface, which has no differentials to carry along
+    pub fn init_ray(ray: &Ray) -> Self {
+        let mut ret = Self::init(&ray.o, &ray.d, ray.t_min, ray.t_max);
+        ret.time = ray.time;
+        ret.medium = ray.medium.clone();
+        ret.has_differential = false;
+
+        ret
+    }
+
     pub fn at(&self, t: f32) -> Point3f {
         self.o + self.d * t
     }
@@ -107,6 +125,18 @@ impl RayDifferential {
         format!("Ray[o: {}, d: {}, t_min: {}, t_max: {}]", self.o.to_string(), self.d.to_string(), self.t_min, self.t_max)
     }
 
+    // Drops the differentials, for when only the main ray is needed
+    pub fn to_ray(&self) -> Ray {
+        Ray {
+            o: self.o,
+            d: self.d,
+            t_min: self.t_min,
+            t_max: self.t_max,
+            time: self.time,
+            medium: self.medium.clone()
+        }
+    }
+
     pub fn scale_differentials(&mut self, s: f32) {
         self.rx_o = self.o + (self.rx_o - self.o) * s;
         self.ry_o = self.o + (self.ry_o - self.o) * s;
@@ -297,6 +297,7 @@ impl Mul<&Ray> for &Transform {
         ret.d = self * b.d;
         ret.t_min = b.t_min;
         ret.t_max = b.t_max;
+        ret.time = b.time;
         ret.medium = b.medium.clone();
 
         ret
@@ -318,6 +319,7 @@ impl Mul<&RayDifferential> for &Transform {
 
         ret.t_min = b.t_min;
         ret.t_max = b.t_max;
+        ret.time = b.time;
         ret.medium = b.medium.clone();
 
         ret
@@ -330,7 +332,7 @@ impl Mul for Transform {
     fn mul(self, rhs: Self) -> Self::Output {
         Transform {
             m: Matrix4x4::mul(&self.m, &rhs.m),
-            m_inv: Matrix4x4::mul(&rhs.m, &self.m)
+            m_inv: Matrix4x4::mul(&rhs.m_inv, &self.m_inv)
         }
     }
 }
@@ -343,8 +345,9 @@ impl Mul<&SurfaceInteraction> for &Transform {
 
         ret.p = self * s.p;
         ret.n = Normal3f::normalize(&(self * s.n));
-        ret.wo = self * s.wo;
+        ret.wo = Vector3f::normalize(&(self * s.wo));
         ret.t = s.t;
+        ret.time = s.time;
         ret.medium_interface = s.medium_interface.clone();
         ret.uv = s.uv;
         ret.dpdu = self * s.dpdu;
@@ -364,7 +367,9 @@ impl Mul<&SurfaceInteraction> for &Transform {
         ret.dpdx = self * s.dpdx;
         ret.dpdy = self * s.dpdy;
 
-        // TODO - ADD BSDF, BSSRDF, PRIMITIVE copying as well
+        // TODO - ADD BSSRDF copying as well
+        ret.bsdf = s.bsdf.clone();
+        ret.primitive = s.primitive.clone();
 
         let temp = Vector3f::init([ret.n.x(), ret.n.y(), ret.n.z()]);
         ret.shading.n = Normal3f::faceforward(&ret.shading.n, &temp);
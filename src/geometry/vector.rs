@@ -130,7 +130,7 @@ impl<T, const N: usize> Vector<T, N>
         let mut cross = [T::zero(); N];
         cross[0] = v1.y()*v2.z() - v2.y()*v1.z();
         cross[1] = v1.z()*v2.x() - v2.z()*v1.x();
-        cross[2] = v1.x()*v2.y() - v2.y()*v1.x();
+        cross[2] = v1.x()*v2.y() - v1.y()*v2.x();
 
         Self::init(
             cross
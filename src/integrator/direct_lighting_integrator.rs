@@ -0,0 +1,119 @@
+use crate::common::*;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum LightStrategy {
+    UniformSampleAll,
+    UniformSampleOne
+}
+
+pub struct DirectLightingIntegrator {
+    camera: Option<Arc<dyn Camera>>,
+    sampler: Option<Arc<dyn Sampler>>,
+    strategy: LightStrategy,
+    max_depth: u32,
+}
+
+// Constructor
+fn create_direct_lighting_integrator(prop_list: PropertyList) -> LeadObject {
+    let mut integrator = DirectLightingIntegrator::new();
+    integrator.init(prop_list);
+    LeadObject::Integrator(Arc::new(integrator))
+}
+
+impl Integrator for DirectLightingIntegrator {
+    fn render(&mut self, scene: &Scene) {
+        SamplerIntegrator::render(self, scene)
+    }
+}
+
+impl SamplerIntegrator for DirectLightingIntegrator {
+    fn camera(&self) -> Option<Arc<dyn Camera>> { self.camera.clone() }
+    fn sampler(&mut self) -> &mut Option<Arc<dyn Sampler>> { &mut self.sampler }
+
+    fn set_camera(&mut self, camera: Arc<dyn Camera>) { self.camera = Some(camera); }
+    fn set_sampler(&mut self, sampler: Arc<dyn Sampler>) { self.sampler = Some(sampler); }
+
+    fn li_intersected(&self, ray: &RayDifferential, its: Option<SurfaceInteraction>, scene: &Scene, sampler: &mut dyn Sampler, depth: u32) -> Spectrum {
+        let mut l = Spectrum::init_one(0.0);
+
+        let mut its = match its {
+            Some(its) => its,
+            None => {
+                for light in scene.lights() {
+                    l = l + light.le(ray);
+                }
+                return l;
+            }
+        };
+
+        its.compute_scattering_functions(ray, TransportMode::Radiance, false);
+        if its.bsdf.is_none() {
+            return self.li(&RayDifferential::init_ray(&its.spawn_ray(&ray.d)), scene, sampler, depth);
+        }
+
+        let wo = its.wo;
+        l = l + its.le(&wo);
+
+        if !scene.lights().is_empty() {
+            l = l + match self.strategy {
+                LightStrategy::UniformSampleAll => uniform_sample_all_lights(&its, scene, sampler),
+                LightStrategy::UniformSampleOne => uniform_sample_one_light(&its, scene, sampler),
+            };
+        }
+
+        if depth + 1 < self.max_depth {
+            l = l + self.specular_reflect(ray, &its, scene, sampler, depth);
+            l = l + self.specular_transmit(ray, &its, scene, sampler, depth);
+        }
+
+        l
+    }
+}
+
+impl LeadObjectTrait for DirectLightingIntegrator {
+    fn init(&mut self, prop_list: PropertyList) {
+        self.max_depth = prop_list.get_int("max_depth", 5) as u32;
+        self.strategy = match prop_list.get_string("strategy", "all").as_str() {
+            "all" => LightStrategy::UniformSampleAll,
+            "one" => LightStrategy::UniformSampleOne,
+            other => panic!("Unknown light sampling strategy {other}, expected \"all\" or \"one\"!")
+        };
+    }
+
+    fn activate(&mut self) { }
+
+    fn add_child(&mut self, child: &mut LeadObject) {
+        match child {
+            LeadObject::Camera(camera) => self.set_camera(camera.clone()),
+            LeadObject::Sampler(sampler) => self.set_sampler(sampler.clone()),
+            _ => println!("Struct DirectLightingIntegrator does not take a child of class {}", child.to_string())
+        }
+    }
+
+    fn to_string(&self) -> String {
+        let strategy = match self.strategy {
+            LightStrategy::UniformSampleAll => "all",
+            LightStrategy::UniformSampleOne => "one",
+        };
+
+        let sampler_part = match &self.sampler {
+            Some(sampler) => sampler.to_string(),
+            None => String::from("None")
+        };
+
+        format!("DirectLightingIntegrator[\n  strategy: {},\n  max_depth: {},\n  sampler: {}\n]", strategy, self.max_depth, sampler_part)
+    }
+}
+
+impl DirectLightingIntegrator {
+    pub fn new() -> Self {
+        Self {
+            camera: None,
+            sampler: None,
+            strategy: LightStrategy::UniformSampleAll,
+            max_depth: 5
+        }
+    }
+}
+
+register_struct!("direct", create_direct_lighting_integrator);
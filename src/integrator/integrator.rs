@@ -1,4 +1,295 @@
+use crate::common::*;
+
+#[derive(Debug, Clone, Copy)]
 pub enum TransportMode {
     Radiance,
     Importance
-}
\ No newline at end of file
+}
+
+stat_counter!(CAMERA_RAYS, "Integrator/Camera rays traced");
+
+pub trait Integrator: LeadObjectTrait {
+    fn render(&mut self, scene: &Scene);
+}
+
+// Integrators that trace camera rays one sample at a time, driven by a sampler
+pub trait SamplerIntegrator: Integrator {
+    fn camera(&self) -> Option<Arc<dyn Camera>>;
+    // Handed out mutably so the sampler can be taken while rendering
+    fn sampler(&mut self) -> &mut Option<Arc<dyn Sampler>>;
+
+    fn set_camera(&mut self, camera: Arc<dyn Camera>);
+    fn set_sampler(&mut self, sampler: Arc<dyn Sampler>);
+
+    fn preprocess(&mut self, _scene: &Scene, _sampler: &mut dyn Sampler) { }
+    // Radiance along a ray whose closest hit is already known, None if it hit nothing
+    fn li_intersected(&self, ray: &RayDifferential, its: Option<SurfaceInteraction>, scene: &Scene, sampler: &mut dyn Sampler, depth: u32) -> Spectrum;
+
+    fn li(&self, ray: &RayDifferential, scene: &Scene, sampler: &mut dyn Sampler, depth: u32) -> Spectrum {
+        let mut its = SurfaceInteraction::new();
+        let found_intersection = scene.intersect(&ray.to_ray(), &mut its);
+        self.li_intersected(ray, found_intersection.then_some(its), scene, sampler, depth)
+    }
+
+    fn render(&mut self, scene: &Scene) {
+        let camera = match self.camera() {
+            Some(camera) => camera,
+            None => panic!("Integrator has no camera to render with!")
+        };
+        let film = match camera.film() {
+            Some(film) => film,
+            None => panic!("Integrator has no film to render to!")
+        };
+        let mut sampler = match self.sampler().take() {
+            Some(sampler) => sampler,
+            None => panic!("Integrator has no sampler to render with!")
+        };
+
+        {
+            let sampler = Arc::get_mut(&mut sampler).unwrap();
+            self.preprocess(scene, sampler);
+
+            let sample_bounds = film.get_sample_bounds();
+            let sample_extent = sample_bounds.diagonal();
+            let tile_size = 16f32;
+            let n_tiles_x = ((sample_extent.x() + tile_size - 1.0) / tile_size).floor() as usize;
+            let n_tiles_y = ((sample_extent.y() + tile_size - 1.0) / tile_size).floor() as usize;
+
+            for tile_y in 0..n_tiles_y {
+                for tile_x in 0..n_tiles_x {
+                    let x0 = sample_bounds.p_min.x() + tile_x as f32 * tile_size;
+                    let x1 = (x0 + tile_size).min(sample_bounds.p_max.x());
+                    let y0 = sample_bounds.p_min.y() + tile_y as f32 * tile_size;
+                    let y1 = (y0 + tile_size).min(sample_bounds.p_max.y());
+                    let tile_bounds = Bounds2f::init(&Point2f::init([x0, y0]), &Point2f::init([x1, y1]));
+
+                    let mut film_tile = film.get_film_tile(&tile_bounds);
+                    {
+                        let film_tile = Arc::get_mut(&mut film_tile).unwrap();
+
+                        // All camera rays of the tile are made first so they can be intersected in packets
+                        let mut camera_rays = Vec::new();
+                        for pixel in tile_bounds.iter() {
+                            sampler.start_pixel(&pixel);
+
+                            loop {
+                                let camera_sample = sampler.get_camera_sample(&pixel);
+
+                                let mut ray = RayDifferential::new();
+                                let ray_weight = camera.generate_ray_differential(&mut ray, &camera_sample);
+                                ray.scale_differentials(1.0 / (sampler.samples_per_pixel() as f32).sqrt());
+                                camera_rays.push((camera_sample, ray, ray_weight));
+                                CAMERA_RAYS.with(|rays| rays.add(1));
+
+                                if !sampler.start_next_sample() {
+                                    break;
+                                }
+                            }
+                        }
+                        let mut hits = intersect_camera_rays(scene, &camera_rays).into_iter();
+                        let mut camera_rays = camera_rays.into_iter();
+
+                        // Then the pixels are sampled again for the rest of each sample. Its camera
+                        // dimensions are drawn and dropped, the first pass already used them
+                        for pixel in tile_bounds.iter() {
+                            sampler.start_pixel(&pixel);
+
+                            loop {
+                                sampler.get_camera_sample(&pixel);
+                                let (camera_sample, ray, ray_weight) = camera_rays.next().unwrap();
+                                let its = hits.next().unwrap();
+
+                                let mut l = Spectrum::init_one(0.0);
+                                if ray_weight > 0.0 {
+                                    l = self.li_intersected(&ray, its, scene, sampler, 0);
+                                }
+                                if l.has_nan() {
+                                    l = Spectrum::init_one(0.0);
+                                }
+
+                                film_tile.add_sample(&camera_sample.p_film, l, ray_weight);
+
+                                if !sampler.start_next_sample() {
+                                    break;
+                                }
+                            }
+                        }
+                    }
+
+                    film.merge_film_title(film_tile);
+                }
+            }
+        }
+
+        film.write_image(1.0);
+        *self.sampler() = Some(sampler);
+    }
+
+    fn specular_reflect(&self, _ray: &RayDifferential, its: &SurfaceInteraction, scene: &Scene, sampler: &mut dyn Sampler, depth: u32) -> Spectrum {
+        let flags = BxDFType::BSDFReflection.to_u32() | BxDFType::BSDFSpecular.to_u32();
+        self.specular_bounce(its, scene, sampler, depth, flags)
+    }
+
+    fn specular_transmit(&self, _ray: &RayDifferential, its: &SurfaceInteraction, scene: &Scene, sampler: &mut dyn Sampler, depth: u32) -> Spectrum {
+        let flags = BxDFType::BSDFTtransmission.to_u32() | BxDFType::BSDFSpecular.to_u32();
+        self.specular_bounce(its, scene, sampler, depth, flags)
+    }
+
+    // Follows the single direction a specular lobe scatters into
+    fn specular_bounce(&self, its: &SurfaceInteraction, scene: &Scene, sampler: &mut dyn Sampler, depth: u32, flags: u32) -> Spectrum {
+        let bsdf = match &its.bsdf {
+            Some(bsdf) => bsdf.clone(),
+            None => return Spectrum::init_one(0.0)
+        };
+
+        let mut wi = Vector3f::new();
+        let mut pdf = 0f32;
+        let mut sampled_type = 0u32;
+        let f = bsdf.sample_f(&its.wo, &mut wi, &sampler.get_2d(), &mut pdf, flags, &mut sampled_type);
+
+        let cos_theta = Normal3f::abs_dot(&its.shading.n, &wi);
+        if pdf > 0.0 && !f.is_black() && cos_theta != 0.0 {
+            let ray = RayDifferential::init_ray(&its.spawn_ray(&wi));
+            return f * self.li(&ray, scene, sampler, depth + 1) * (cos_theta / pdf);
+        }
+
+        Spectrum::init_one(0.0)
+    }
+}
+
+// Closest hits of the camera rays, PACKET_SIZE at a time. The last packet is filled up with copies of its first ray
+fn intersect_camera_rays(scene: &Scene, camera_rays: &[(CameraSample, RayDifferential, f32)]) -> Vec<Option<SurfaceInteraction>> {
+    let mut hits = Vec::with_capacity(camera_rays.len());
+    for chunk in camera_rays.chunks(PACKET_SIZE) {
+        let rays: [Ray; PACKET_SIZE] = std::array::from_fn(|i| chunk.get(i).unwrap_or(&chunk[0]).1.to_ray());
+        let mut its: [SurfaceInteraction; PACKET_SIZE] = std::array::from_fn(|_| SurfaceInteraction::new());
+        let found = scene.intersect_packet(&rays, &mut its, chunk.len());
+
+        hits.extend(its.into_iter().zip(found).take(chunk.len()).map(|(its, found)| found.then_some(its)));
+    }
+    hits
+}
+
+pub fn power_heuristic(nf: u32, f_pdf: f32, ng: u32, g_pdf: f32) -> f32 {
+    let f = nf as f32 * f_pdf;
+    let g = ng as f32 * g_pdf;
+
+    (f * f) / (f * f + g * g)
+}
+
+pub fn uniform_sample_all_lights(its: &SurfaceInteraction, scene: &Scene, sampler: &mut dyn Sampler) -> Spectrum {
+    let mut l = Spectrum::init_one(0.0);
+
+    for light in scene.lights() {
+        let n_samples = light.n_samples();
+        let mut ld = Spectrum::init_one(0.0);
+
+        for _ in 0..n_samples {
+            let u_light = sampler.get_2d();
+            let u_scattering = sampler.get_2d();
+            ld = ld + estimate_direct(its, &u_scattering, light, &u_light, scene, false);
+        }
+
+        l = l + ld / n_samples as f32;
+    }
+
+    l
+}
+
+pub fn uniform_sample_one_light(its: &SurfaceInteraction, scene: &Scene, sampler: &mut dyn Sampler) -> Spectrum {
+    let n_lights = scene.lights().len();
+    if n_lights == 0 {
+        return Spectrum::init_one(0.0);
+    }
+
+    let light_num = ((sampler.get_1d() * n_lights as f32) as usize).min(n_lights - 1);
+    let light = &scene.lights()[light_num];
+
+    let u_light = sampler.get_2d();
+    let u_scattering = sampler.get_2d();
+
+    estimate_direct(its, &u_scattering, light, &u_light, scene, false) * n_lights as f32
+}
+
+// Direct lighting from one light, combining light and bsdf sampling with multiple importance sampling
+pub fn estimate_direct(its: &SurfaceInteraction, u_scattering: &Point2f, light: &Arc<dyn Light>, u_light: &Point2f, scene: &Scene, handle_specular: bool) -> Spectrum {
+    let bsdf = match &its.bsdf {
+        Some(bsdf) => bsdf.clone(),
+        None => return Spectrum::init_one(0.0)
+    };
+
+    let bsdf_flags = if handle_specular {
+        BxDFType::BSDFAll.to_u32()
+    } else {
+        BxDFType::BSDFAll.to_u32() & !BxDFType::BSDFSpecular.to_u32()
+    };
+
+    let mut ld = Spectrum::init_one(0.0);
+
+    // sample the light
+    let mut wi = Vector3f::new();
+    let mut light_pdf = 0f32;
+    let mut scattering_pdf = 0f32;
+    let mut vis = VisibilityTester::new();
+    let mut li = light.sample_li(its, u_light, &mut wi, &mut light_pdf, &mut vis);
+
+    if light_pdf > 0.0 && !li.is_black() {
+        let f = bsdf.f(&its.wo, &wi, bsdf_flags) * Normal3f::abs_dot(&its.shading.n, &wi);
+        scattering_pdf = bsdf.pdf(&its.wo, &wi, bsdf_flags);
+
+        if !f.is_black() {
+            if !vis.unoccluded(scene) {
+                li = Spectrum::init_one(0.0);
+            }
+
+            if !li.is_black() {
+                if light.is_delta_light() {
+                    ld = ld + f * li / light_pdf;
+                } else {
+                    let weight = power_heuristic(1, light_pdf, 1, scattering_pdf);
+                    ld = ld + f * li * (weight / light_pdf);
+                }
+            }
+        }
+    }
+
+    // sample the bsdf, delta lights cannot be hit this way
+    if !light.is_delta_light() {
+        let mut sampled_type = 0u32;
+        let f = bsdf.sample_f(&its.wo, &mut wi, u_scattering, &mut scattering_pdf, bsdf_flags, &mut sampled_type) * Normal3f::abs_dot(&its.shading.n, &wi);
+        let sampled_specular = sampled_type & BxDFType::BSDFSpecular.to_u32() != 0;
+
+        if !f.is_black() && scattering_pdf > 0.0 {
+            let mut weight = 1f32;
+            if !sampled_specular {
+                light_pdf = light.pdf_li(its, &wi);
+                if light_pdf == 0.0 {
+                    return ld;
+                }
+                weight = power_heuristic(1, scattering_pdf, 1, light_pdf);
+            }
+
+            let ray = its.spawn_ray(&wi);
+            let mut light_its = SurfaceInteraction::new();
+            let mut li = Spectrum::init_one(0.0);
+
+            if scene.intersect(&ray, &mut light_its) {
+                // only counts if the ray found this very light
+                let area_light = light_its.primitive.as_ref().and_then(|primitive| primitive.get_area_light());
+                if let Some(area_light) = area_light {
+                    if std::ptr::addr_eq(Arc::as_ptr(&area_light), Arc::as_ptr(light)) {
+                        li = light_its.le(&-wi);
+                    }
+                }
+            } else {
+                li = light.le(&RayDifferential::init_ray(&ray));
+            }
+
+            if !li.is_black() {
+                ld = ld + f * li * (weight / scattering_pdf);
+            }
+        }
+    }
+
+    ld
+}
@@ -1,3 +1,10 @@
 pub mod integrator;
+pub mod whitted_integrator;
+pub mod direct_lighting_integrator;
+pub mod path_integrator;
 
-pub use integrator::TransportMode;
\ No newline at end of file
+pub use integrator::{TransportMode, Integrator, SamplerIntegrator, power_heuristic, uniform_sample_all_lights, uniform_sample_one_light, estimate_direct};
+pub use whitted_integrator::WhittedIntegrator;
+pub use direct_lighting_integrator::{DirectLightingIntegrator, LightStrategy};
+
+pub use path_integrator::PathIntegrator;
\ No newline at end of file
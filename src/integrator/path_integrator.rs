@@ -0,0 +1,162 @@
+use crate::common::*;
+
+pub struct PathIntegrator {
+    camera: Option<Arc<dyn Camera>>,
+    sampler: Option<Arc<dyn Sampler>>,
+    max_depth: u32,
+    // paths whose throughput falls below the threshold may be terminated after rr_depth bounces
+    rr_threshold: f32,
+    rr_depth: u32,
+}
+
+// Constructor
+fn create_path_integrator(prop_list: PropertyList) -> LeadObject {
+    let mut integrator = PathIntegrator::new();
+    integrator.init(prop_list);
+    LeadObject::Integrator(Arc::new(integrator))
+}
+
+impl Integrator for PathIntegrator {
+    fn render(&mut self, scene: &Scene) {
+        SamplerIntegrator::render(self, scene)
+    }
+}
+
+impl SamplerIntegrator for PathIntegrator {
+    fn camera(&self) -> Option<Arc<dyn Camera>> { self.camera.clone() }
+    fn sampler(&mut self) -> &mut Option<Arc<dyn Sampler>> { &mut self.sampler }
+
+    fn set_camera(&mut self, camera: Arc<dyn Camera>) { self.camera = Some(camera); }
+    fn set_sampler(&mut self, sampler: Arc<dyn Sampler>) { self.sampler = Some(sampler); }
+
+    fn li_intersected(&self, r: &RayDifferential, its: Option<SurfaceInteraction>, scene: &Scene, sampler: &mut dyn Sampler, _depth: u32) -> Spectrum {
+        let mut l = Spectrum::init_one(0.0);
+        let mut beta = Spectrum::init_one(1.0);
+        let mut specular_bounce = false;
+        // undoes the radiance scaling from refraction, so it does not skew russian roulette
+        let mut eta_scale = 1f32;
+
+        let mut ray = r.clone();
+        let mut bounces = 0u32;
+        // the first hit was found before li was called
+        let mut first_hit = Some(its);
+
+        loop {
+            let hit = match first_hit.take() {
+                Some(hit) => hit,
+                None => {
+                    let mut its = SurfaceInteraction::new();
+                    scene.intersect(&ray.to_ray(), &mut its).then_some(its)
+                }
+            };
+            let found_intersection = hit.is_some();
+            let mut its = hit.unwrap_or_else(SurfaceInteraction::new);
+
+            // Emission is only added here when light sampling at the previous vertex could not have found it
+            if bounces == 0 || specular_bounce {
+                if found_intersection {
+                    l = l + beta * its.le(&-ray.d);
+                } else {
+                    for light in scene.lights() {
+                        l = l + beta * light.le(&ray);
+                    }
+                }
+            }
+
+            if !found_intersection || bounces >= self.max_depth {
+                break;
+            }
+
+            its.compute_scattering_functions(&ray, TransportMode::Radiance, true);
+            let bsdf = match &its.bsdf {
+                Some(bsdf) => bsdf.clone(),
+                // surfaces without a bsdf only mark a boundary, passing through them is not a bounce
+                None => {
+                    ray = RayDifferential::init_ray(&its.spawn_ray(&ray.d));
+                    continue;
+                }
+            };
+
+            // Next event estimation, specular lobes are handled by following the sampled direction instead
+            if bsdf.num_components(BxDFType::BSDFAll.to_u32() & !BxDFType::BSDFSpecular.to_u32()) > 0 {
+                l = l + beta * uniform_sample_one_light(&its, scene, sampler);
+            }
+
+            let wo = -ray.d;
+            let mut wi = Vector3f::new();
+            let mut pdf = 0f32;
+            let mut flags = 0u32;
+            let f = bsdf.sample_f(&wo, &mut wi, &sampler.get_2d(), &mut pdf, BxDFType::BSDFAll.to_u32(), &mut flags);
+            if f.is_black() || pdf == 0.0 {
+                break;
+            }
+
+            beta = beta * f * (Normal3f::abs_dot(&its.shading.n, &wi) / pdf);
+            specular_bounce = flags & BxDFType::BSDFSpecular.to_u32() != 0;
+
+            if specular_bounce && flags & BxDFType::BSDFTtransmission.to_u32() != 0 {
+                let eta = bsdf.eta;
+                eta_scale *= if Normal3f::dot(&its.n, &wo) > 0.0 { eta * eta } else { 1.0 / (eta * eta) };
+            }
+
+            ray = RayDifferential::init_ray(&its.spawn_ray(&wi));
+
+            let rr_beta = beta * eta_scale;
+            if rr_beta.max_component_value() < self.rr_threshold as f64 && bounces > self.rr_depth {
+                let q = 0.05f64.max(1.0 - rr_beta.max_component_value());
+                if (sampler.get_1d() as f64) < q {
+                    break;
+                }
+                beta = beta / (1.0 - q);
+            }
+
+            bounces += 1;
+        }
+
+        l
+    }
+}
+
+impl LeadObjectTrait for PathIntegrator {
+    fn init(&mut self, prop_list: PropertyList) {
+        self.max_depth = prop_list.get_int("max_depth", 5) as u32;
+        self.rr_threshold = prop_list.get_float("rr_threshold", 1.0);
+        self.rr_depth = prop_list.get_int("rr_depth", 3) as u32;
+    }
+
+    fn activate(&mut self) { }
+
+    fn add_child(&mut self, child: &mut LeadObject) {
+        match child {
+            LeadObject::Camera(camera) => self.set_camera(camera.clone()),
+            LeadObject::Sampler(sampler) => self.set_sampler(sampler.clone()),
+            _ => println!("Struct PathIntegrator does not take a child of class {}", child.to_string())
+        }
+    }
+
+    fn to_string(&self) -> String {
+        let sampler_part = match &self.sampler {
+            Some(sampler) => sampler.to_string(),
+            None => String::from("None")
+        };
+
+        format!(
+            "PathIntegrator[\n  max_depth: {},\n  rr_threshold: {},\n  rr_depth: {},\n  sampler: {}\n]",
+            self.max_depth, self.rr_threshold, self.rr_depth, sampler_part
+        )
+    }
+}
+
+impl PathIntegrator {
+    pub fn new() -> Self {
+        Self {
+            camera: None,
+            sampler: None,
+            max_depth: 5,
+            rr_threshold: 1.0,
+            rr_depth: 3
+        }
+    }
+}
+
+register_struct!("path", create_path_integrator);
@@ -0,0 +1,113 @@
+use crate::common::*;
+
+pub struct WhittedIntegrator {
+    camera: Option<Arc<dyn Camera>>,
+    sampler: Option<Arc<dyn Sampler>>,
+    max_depth: u32,
+}
+
+// Constructor
+fn create_whitted_integrator(prop_list: PropertyList) -> LeadObject {
+    let mut integrator = WhittedIntegrator::new();
+    integrator.init(prop_list);
+    LeadObject::Integrator(Arc::new(integrator))
+}
+
+impl Integrator for WhittedIntegrator {
+    fn render(&mut self, scene: &Scene) {
+        SamplerIntegrator::render(self, scene)
+    }
+}
+
+impl SamplerIntegrator for WhittedIntegrator {
+    fn camera(&self) -> Option<Arc<dyn Camera>> { self.camera.clone() }
+    fn sampler(&mut self) -> &mut Option<Arc<dyn Sampler>> { &mut self.sampler }
+
+    fn set_camera(&mut self, camera: Arc<dyn Camera>) { self.camera = Some(camera); }
+    fn set_sampler(&mut self, sampler: Arc<dyn Sampler>) { self.sampler = Some(sampler); }
+
+    fn li_intersected(&self, ray: &RayDifferential, its: Option<SurfaceInteraction>, scene: &Scene, sampler: &mut dyn Sampler, depth: u32) -> Spectrum {
+        let mut l = Spectrum::init_one(0.0);
+
+        let mut its = match its {
+            Some(its) => its,
+            None => {
+                for light in scene.lights() {
+                    l = l + light.le(ray);
+                }
+                return l;
+            }
+        };
+
+        let n = its.shading.n;
+        let wo = its.wo;
+
+        its.compute_scattering_functions(ray, TransportMode::Radiance, false);
+        let bsdf = match &its.bsdf {
+            Some(bsdf) => bsdf.clone(),
+            // surfaces without a bsdf only mark a boundary, so carry on through them
+            None => return self.li(&RayDifferential::init_ray(&its.spawn_ray(&ray.d)), scene, sampler, depth)
+        };
+
+        l = l + its.le(&wo);
+
+        for light in scene.lights() {
+            let mut wi = Vector3f::new();
+            let mut pdf = 0f32;
+            let mut vis = VisibilityTester::new();
+            let li = light.sample_li(&its, &sampler.get_2d(), &mut wi, &mut pdf, &mut vis);
+            if li.is_black() || pdf == 0.0 {
+                continue;
+            }
+
+            let f = bsdf.f(&wo, &wi, BxDFType::BSDFAll.to_u32());
+            if !f.is_black() && vis.unoccluded(scene) {
+                l = l + f * li * (Normal3f::abs_dot(&n, &wi) / pdf);
+            }
+        }
+
+        if depth + 1 < self.max_depth {
+            l = l + self.specular_reflect(ray, &its, scene, sampler, depth);
+            l = l + self.specular_transmit(ray, &its, scene, sampler, depth);
+        }
+
+        l
+    }
+}
+
+impl LeadObjectTrait for WhittedIntegrator {
+    fn init(&mut self, prop_list: PropertyList) {
+        self.max_depth = prop_list.get_int("max_depth", 5) as u32;
+    }
+
+    fn activate(&mut self) { }
+
+    fn add_child(&mut self, child: &mut LeadObject) {
+        match child {
+            LeadObject::Camera(camera) => self.set_camera(camera.clone()),
+            LeadObject::Sampler(sampler) => self.set_sampler(sampler.clone()),
+            _ => println!("Struct WhittedIntegrator does not take a child of class {}", child.to_string())
+        }
+    }
+
+    fn to_string(&self) -> String {
+        let sampler_part = match &self.sampler {
+            Some(sampler) => sampler.to_string(),
+            None => String::from("None")
+        };
+
+        format!("WhittedIntegrator[\n  max_depth: {},\n  sampler: {}\n]", self.max_depth, sampler_part)
+    }
+}
+
+impl WhittedIntegrator {
+    pub fn new() -> Self {
+        Self {
+            camera: None,
+            sampler: None,
+            max_depth: 5
+        }
+    }
+}
+
+register_struct!("whitted", create_whitted_integrator);
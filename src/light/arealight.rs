@@ -1,3 +1,11 @@
-pub trait AreaLight{
-    
-}
\ No newline at end of file
+use crate::common::*;
+
+pub trait AreaLight: Light {
+    fn shape(&self) -> Option<Arc<dyn Shape>>;
+    fn set_shape(&mut self, shape: Arc<dyn Shape>);
+    // Fresh copy without a shape, for shapes that are split into many
+    fn clone_area_light(&self) -> Arc<dyn AreaLight>;
+
+    // Radiance leaving a point on the light's surface in direction w
+    fn l(&self, its: &dyn Interaction, w: &Vector3f) -> Spectrum;
+}
@@ -0,0 +1,110 @@
+use crate::common::*;
+
+pub struct DiffuseAreaLight {
+    l_emit: Spectrum,
+    shape: Option<Arc<dyn Shape>>,
+    two_sided: bool,
+    area: f32,
+    n_samples: usize,
+}
+
+// Constructor
+fn create_diffuse_area_light(prop_list: PropertyList) -> LeadObject {
+    let mut light = DiffuseAreaLight::new();
+    light.init(prop_list);
+    LeadObject::AreaLight(Arc::new(light))
+}
+
+impl Light for DiffuseAreaLight {
+    fn flags(&self) -> u32 { LightFlags::Area.to_u32() }
+    fn n_samples(&self) -> usize { self.n_samples }
+
+    fn sample_li(&self, reference: &dyn Interaction, u: &Point2f, wi: &mut Vector3f, pdf: &mut f32, vis: &mut VisibilityTester) -> Spectrum {
+        let shape = self.shape.as_ref().expect("DiffuseAreaLight is not attached to a shape!");
+
+        let p_shape = shape.sample(reference, u, pdf);
+        if *pdf == 0.0 || (p_shape.p() - reference.p()).length_sqr() == 0.0 {
+            *pdf = 0.0;
+            return Spectrum::init_one(0.0);
+        }
+
+        *wi = Vector3f::normalize(&(p_shape.p() - reference.p()));
+        *vis = VisibilityTester::init(&reference.p(), &p_shape.p(), reference.time());
+
+        self.l(p_shape.as_ref(), &-*wi)
+    }
+
+    fn pdf_li(&self, reference: &dyn Interaction, wi: &Vector3f) -> f32 {
+        match &self.shape {
+            Some(shape) => shape.pdf_wi(reference, wi),
+            None => 0.0
+        }
+    }
+
+    fn power(&self) -> Spectrum {
+        let sides = if self.two_sided { 2.0 } else { 1.0 };
+        self.l_emit * (sides * self.area * M_PI)
+    }
+}
+
+impl AreaLight for DiffuseAreaLight {
+    fn shape(&self) -> Option<Arc<dyn Shape>> { self.shape.clone() }
+
+    fn set_shape(&mut self, shape: Arc<dyn Shape>) {
+        self.area = shape.area();
+        self.shape = Some(shape);
+    }
+
+    fn clone_area_light(&self) -> Arc<dyn AreaLight> {
+        Arc::new(Self {
+            l_emit: self.l_emit,
+            shape: None,
+            two_sided: self.two_sided,
+            area: 0.0,
+            n_samples: self.n_samples
+        })
+    }
+
+    fn l(&self, its: &dyn Interaction, w: &Vector3f) -> Spectrum {
+        if self.two_sided || Normal3f::dot(&its.n(), w) > 0.0 {
+            self.l_emit
+        } else {
+            Spectrum::init_one(0.0)
+        }
+    }
+}
+
+impl LeadObjectTrait for DiffuseAreaLight {
+    fn init(&mut self, prop_list: PropertyList) {
+        self.l_emit = prop_list.get_rgb("radiance", Spectrum::init_one(1.0));
+        self.two_sided = prop_list.get_bool("two_sided", false);
+        self.n_samples = prop_list.get_int("samples", 1).max(1) as usize;
+    }
+
+    fn activate(&mut self) { }
+
+    fn add_child(&mut self, child: &mut LeadObject) {
+        println!("Struct DiffuseAreaLight does not take a child of class {}", child.to_string());
+    }
+
+    fn to_string(&self) -> String {
+        format!(
+            "DiffuseAreaLight[radiance: {}, two_sided: {}, samples: {}]",
+            self.l_emit.to_string(), self.two_sided, self.n_samples
+        )
+    }
+}
+
+impl DiffuseAreaLight {
+    pub fn new() -> Self {
+        Self {
+            l_emit: Spectrum::init_one(1.0),
+            shape: None,
+            two_sided: false,
+            area: 0.0,
+            n_samples: 1
+        }
+    }
+}
+
+register_struct!("area", create_diffuse_area_light);
@@ -0,0 +1,82 @@
+use crate::common::*;
+use std::sync::OnceLock;
+
+pub struct DistantLight {
+    l: Spectrum,
+    // points towards the light
+    w_light: Vector3f,
+    // center and radius of the scene's bounding sphere, set by preprocess
+    world_sphere: OnceLock<(Point3f, f32)>,
+}
+
+// Constructor
+fn create_distant_light(prop_list: PropertyList) -> LeadObject {
+    let mut light = DistantLight::new();
+    light.init(prop_list);
+    LeadObject::Light(Arc::new(light))
+}
+
+impl Light for DistantLight {
+    fn flags(&self) -> u32 { LightFlags::DeltaDirection.to_u32() }
+
+    fn sample_li(&self, reference: &dyn Interaction, _u: &Point2f, wi: &mut Vector3f, pdf: &mut f32, vis: &mut VisibilityTester) -> Spectrum {
+        *wi = self.w_light;
+        *pdf = 1.0;
+
+        // anything past the scene's bounding sphere is as good as infinitely far
+        let p_outside = reference.p() + self.w_light * (2.0 * self.world_radius());
+        *vis = VisibilityTester::init(&reference.p(), &p_outside, reference.time());
+
+        self.l
+    }
+
+    fn pdf_li(&self, _reference: &dyn Interaction, _wi: &Vector3f) -> f32 { 0.0 }
+
+    fn power(&self) -> Spectrum {
+        let world_radius = self.world_radius();
+        self.l * (M_PI * world_radius * world_radius)
+    }
+
+    fn preprocess(&self, scene: &Scene) {
+        let (mut world_center, mut world_radius) = (Point3f::new(), 0f32);
+        scene.world_bound().bounding_sphere(&mut world_center, &mut world_radius);
+        // a light shared by several parents is preprocessed once for each, with the same scene
+        let _ = self.world_sphere.set((world_center, world_radius));
+    }
+}
+
+impl LeadObjectTrait for DistantLight {
+    fn init(&mut self, prop_list: PropertyList) {
+        let from = prop_list.get_point3("from", Point3f::new());
+        let to = prop_list.get_point3("to", Point3f::init([0.0, 0.0, 1.0]));
+
+        self.w_light = Vector3f::normalize(&(&prop_list.get_transform() * (from - to)));
+        self.l = prop_list.get_rgb("radiance", Spectrum::init_one(1.0));
+    }
+
+    fn activate(&mut self) { }
+
+    fn add_child(&mut self, child: &mut LeadObject) {
+        println!("Struct DistantLight does not take a child of class {}", child.to_string());
+    }
+
+    fn to_string(&self) -> String {
+        format!("DistantLight[direction: {}, radiance: {}]", (-self.w_light).to_string(), self.l.to_string())
+    }
+}
+
+impl DistantLight {
+    pub fn new() -> Self {
+        Self {
+            l: Spectrum::init_one(1.0),
+            w_light: Vector3f::init([0.0, 0.0, -1.0]),
+            world_sphere: OnceLock::new()
+        }
+    }
+
+    fn world_radius(&self) -> f32 {
+        self.world_sphere.get().expect("DistantLight is used before the scene preprocessed it!").1
+    }
+}
+
+register_struct!("distant", create_distant_light);
@@ -0,0 +1,93 @@
+use crate::common::*;
+
+pub struct GoniometricLight {
+    light_to_world: Transform,
+    world_to_light: Transform,
+    p_light: Point3f,
+    intensity: Spectrum,
+    filename: String,
+    // lat-long intensity scale, phi along x and theta along y
+    image: ImageMap,
+}
+
+// Constructor
+fn create_goniometric_light(prop_list: PropertyList) -> LeadObject {
+    let mut light = GoniometricLight::new();
+    light.init(prop_list);
+    LeadObject::Light(Arc::new(light))
+}
+
+impl Light for GoniometricLight {
+    fn flags(&self) -> u32 { LightFlags::DeltaPosition.to_u32() }
+
+    fn sample_li(&self, reference: &dyn Interaction, _u: &Point2f, wi: &mut Vector3f, pdf: &mut f32, vis: &mut VisibilityTester) -> Spectrum {
+        *wi = Vector3f::normalize(&(self.p_light - reference.p()));
+        *pdf = 1.0;
+        *vis = VisibilityTester::init(&reference.p(), &self.p_light, reference.time());
+
+        self.intensity * self.scale(&-*wi) / (self.p_light - reference.p()).length_sqr()
+    }
+
+    fn pdf_li(&self, _reference: &dyn Interaction, _wi: &Vector3f) -> f32 { 0.0 }
+
+    fn power(&self) -> Spectrum {
+        let average = if self.image.is_empty() { Spectrum::init_one(1.0) } else { self.image.average() };
+        self.intensity * average * (4.0 * M_PI)
+    }
+}
+
+impl LeadObjectTrait for GoniometricLight {
+    fn init(&mut self, prop_list: PropertyList) {
+        self.light_to_world = prop_list.get_transform();
+        self.world_to_light = self.light_to_world.inverse();
+        self.p_light = &self.light_to_world * prop_list.get_point3("position", Point3f::new());
+        self.intensity = prop_list.get_rgb("intensity", Spectrum::init_one(1.0));
+
+        self.filename = prop_list.get_string("filename", "");
+        if !self.filename.is_empty() {
+            self.image = ImageMap::read(&self.filename);
+        }
+    }
+
+    fn activate(&mut self) { }
+
+    fn add_child(&mut self, child: &mut LeadObject) {
+        println!("Struct GoniometricLight does not take a child of class {}", child.to_string());
+    }
+
+    fn to_string(&self) -> String {
+        format!(
+            "GoniometricLight[position: {}, intensity: {}, filename: {}, resolution: {}]",
+            self.p_light.to_string(), self.intensity.to_string(), self.filename, self.image.resolution.to_string()
+        )
+    }
+}
+
+impl GoniometricLight {
+    pub fn new() -> Self {
+        Self {
+            light_to_world: Transform::new(),
+            world_to_light: Transform::new(),
+            p_light: Point3f::new(),
+            intensity: Spectrum::init_one(1.0),
+            filename: String::new(),
+            image: ImageMap::new()
+        }
+    }
+
+    // Looks up the emission scale for a direction leaving the light
+    fn scale(&self, w: &Vector3f) -> Spectrum {
+        if self.image.is_empty() {
+            return Spectrum::init_one(1.0);
+        }
+
+        // goniometric diagrams have y as the up axis
+        let wl = Vector3f::normalize(&(&self.world_to_light * *w));
+        let wp = Vector3f::init([wl.x(), wl.z(), wl.y()]);
+
+        let st = Point2f::init([Frame::spherical_phi(&wp) * M_INV_PI * 0.5, Frame::spherical_theta(&wp) * M_INV_PI]);
+        self.image.bilerp(&st)
+    }
+}
+
+register_struct!("goniometric", create_goniometric_light);
@@ -0,0 +1,158 @@
+use crate::common::*;
+use std::sync::OnceLock;
+
+pub struct InfiniteAreaLight {
+    light_to_world: Transform,
+    world_to_light: Transform,
+    scale: Spectrum,
+    filename: String,
+    // lat-long radiance, same mapping as the environment camera
+    l_map: ImageMap,
+    distribution: Option<Distribution2D>,
+    // center and radius of the scene's bounding sphere, set by preprocess
+    world_sphere: OnceLock<(Point3f, f32)>,
+    n_samples: usize,
+}
+
+// Constructor
+fn create_infinite_area_light(prop_list: PropertyList) -> LeadObject {
+    let mut light = InfiniteAreaLight::new();
+    light.init(prop_list);
+    LeadObject::Light(Arc::new(light))
+}
+
+impl Light for InfiniteAreaLight {
+    fn flags(&self) -> u32 { LightFlags::Infinite.to_u32() }
+    fn n_samples(&self) -> usize { self.n_samples }
+
+    fn sample_li(&self, reference: &dyn Interaction, u: &Point2f, wi: &mut Vector3f, pdf: &mut f32, vis: &mut VisibilityTester) -> Spectrum {
+        let distribution = self.distribution.as_ref().expect("InfiniteAreaLight has no distribution!");
+
+        let mut map_pdf = 0f32;
+        let uv = distribution.sample_continuous(u, &mut map_pdf);
+        if map_pdf == 0.0 {
+            *pdf = 0.0;
+            return Spectrum::init_one(0.0);
+        }
+
+        // the image is a density over uv, the sin(theta) accounts for the squashed poles
+        let sin_theta = (M_PI * uv.y()).sin();
+        if sin_theta == 0.0 {
+            *pdf = 0.0;
+            return Spectrum::init_one(0.0);
+        }
+        *pdf = map_pdf / (2.0 * M_PI * M_PI * sin_theta);
+
+        // with no radius the point outside would be the reference point, and nothing could block the sky
+        let world_radius = self.world_radius();
+        assert!(world_radius > 0.0, "InfiniteAreaLight is sampled in a scene whose bounding sphere has no radius!");
+
+        *wi = Vector3f::normalize(&(&self.light_to_world * Frame::lat_long_to_direction(&uv)));
+        let p_outside = reference.p() + *wi * (2.0 * world_radius);
+        *vis = VisibilityTester::init(&reference.p(), &p_outside, reference.time());
+
+        self.l_map.bilerp(&uv) * self.scale
+    }
+
+    fn pdf_li(&self, _reference: &dyn Interaction, w: &Vector3f) -> f32 {
+        let distribution = match &self.distribution {
+            Some(distribution) => distribution,
+            None => return 0.0
+        };
+
+        let wi = Vector3f::normalize(&(&self.world_to_light * *w));
+        let uv = Frame::direction_to_lat_long(&wi);
+        let sin_theta = (M_PI * uv.y()).sin();
+        if sin_theta == 0.0 {
+            return 0.0;
+        }
+
+        distribution.pdf(&uv) / (2.0 * M_PI * M_PI * sin_theta)
+    }
+
+    fn power(&self) -> Spectrum {
+        let world_radius = self.world_radius();
+        self.l_map.average() * self.scale * (M_PI * world_radius * world_radius)
+    }
+
+    fn le(&self, ray: &RayDifferential) -> Spectrum {
+        let wi = Vector3f::normalize(&(&self.world_to_light * ray.d));
+        self.l_map.bilerp(&Frame::direction_to_lat_long(&wi)) * self.scale
+    }
+
+    fn preprocess(&self, scene: &Scene) {
+        let (mut world_center, mut world_radius) = (Point3f::new(), 0f32);
+        scene.world_bound().bounding_sphere(&mut world_center, &mut world_radius);
+        // a light shared by several parents is preprocessed once for each, with the same scene
+        let _ = self.world_sphere.set((world_center, world_radius));
+    }
+}
+
+impl LeadObjectTrait for InfiniteAreaLight {
+    fn init(&mut self, prop_list: PropertyList) {
+        self.light_to_world = prop_list.get_transform();
+        self.world_to_light = self.light_to_world.inverse();
+        self.scale = prop_list.get_rgb("scale", Spectrum::init_one(1.0));
+        self.n_samples = prop_list.get_int("samples", 1).max(1) as usize;
+
+        // without a map the sky is a constant radiance
+        self.filename = prop_list.get_string("filename", "");
+        self.l_map = if self.filename.is_empty() {
+            ImageMap::init(vec![prop_list.get_rgb("radiance", Spectrum::init_one(1.0))], Point2f::init([1.0, 1.0]))
+        } else {
+            ImageMap::read(&self.filename)
+        };
+
+        self.distribution = Some(Self::build_distribution(&self.l_map));
+    }
+
+    fn activate(&mut self) { }
+
+    fn add_child(&mut self, child: &mut LeadObject) {
+        println!("Struct InfiniteAreaLight does not take a child of class {}", child.to_string());
+    }
+
+    fn to_string(&self) -> String {
+        format!(
+            "InfiniteAreaLight[filename: {}, resolution: {}, scale: {}, samples: {}]",
+            self.filename, self.l_map.resolution.to_string(), self.scale.to_string(), self.n_samples
+        )
+    }
+}
+
+impl InfiniteAreaLight {
+    pub fn new() -> Self {
+        Self {
+            light_to_world: Transform::new(),
+            world_to_light: Transform::new(),
+            scale: Spectrum::init_one(1.0),
+            filename: String::new(),
+            l_map: ImageMap::new(),
+            distribution: None,
+            world_sphere: OnceLock::new(),
+            n_samples: 1
+        }
+    }
+
+    fn world_radius(&self) -> f32 {
+        self.world_sphere.get().expect("InfiniteAreaLight is used before the scene preprocessed it!").1
+    }
+
+    // Luminance over the map, weighted by the solid angle each row covers
+    fn build_distribution(l_map: &ImageMap) -> Distribution2D {
+        let width = l_map.width();
+        let height = l_map.height();
+
+        let mut func = vec![0f32; width * height];
+        for v in 0..height {
+            let sin_theta = (M_PI * (v as f32 + 0.5) / height as f32).sin();
+            for u in 0..width {
+                func[v * width + u] = l_map.texel(u as i64, v as i64).y() as f32 * sin_theta;
+            }
+        }
+
+        Distribution2D::new(&func, width, height)
+    }
+}
+
+register_struct!("envmap", create_infinite_area_light);
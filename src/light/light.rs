@@ -0,0 +1,37 @@
+use crate::common::*;
+
+pub enum LightFlags {
+    DeltaPosition = 1 << 0,
+    DeltaDirection = 1 << 1,
+    Area = 1 << 2,
+    Infinite = 1 << 3,
+}
+
+impl LightFlags {
+    pub fn to_u32(&self) -> u32 {
+        match self {
+            LightFlags::DeltaPosition => 1 << 0,
+            LightFlags::DeltaDirection => 1 << 1,
+            LightFlags::Area => 1 << 2,
+            LightFlags::Infinite => 1 << 3,
+        }
+    }
+}
+
+pub trait Light: LeadObjectTrait {
+    fn flags(&self) -> u32;
+    fn n_samples(&self) -> usize { 1 }
+
+    // Incident radiance at the reference point, wi points towards the light
+    fn sample_li(&self, reference: &dyn Interaction, u: &Point2f, wi: &mut Vector3f, pdf: &mut f32, vis: &mut VisibilityTester) -> Spectrum;
+    fn pdf_li(&self, reference: &dyn Interaction, wi: &Vector3f) -> f32;
+    fn power(&self) -> Spectrum;
+    // Radiance carried along rays that escape the scene
+    fn le(&self, _ray: &RayDifferential) -> Spectrum { Spectrum::init_one(0.0) }
+    // Lights may be shared, e.g. through <ref>, so what they keep of the scene has to go behind &self
+    fn preprocess(&self, _scene: &Scene) { }
+
+    fn is_delta_light(&self) -> bool {
+        self.flags() & (LightFlags::DeltaPosition.to_u32() | LightFlags::DeltaDirection.to_u32()) != 0
+    }
+}
@@ -1,3 +1,18 @@
+pub mod light;
 pub mod arealight;
+pub mod diffuse_area_light;
+pub mod point_light;
+pub mod spot_light;
+pub mod distant_light;
+pub mod goniometric_light;
+pub mod infinite_area_light;
 
-pub use arealight::AreaLight;
\ No newline at end of file
+pub use light::{Light, LightFlags};
+pub use arealight::AreaLight;
+pub use diffuse_area_light::DiffuseAreaLight;
+pub use point_light::PointLight;
+pub use spot_light::SpotLight;
+pub use distant_light::DistantLight;
+pub use goniometric_light::GoniometricLight;
+
+pub use infinite_area_light::InfiniteAreaLight;
@@ -0,0 +1,62 @@
+use crate::common::*;
+
+pub struct PointLight {
+    light_to_world: Transform,
+    p_light: Point3f,
+    intensity: Spectrum,
+}
+
+// Constructor
+fn create_point_light(prop_list: PropertyList) -> LeadObject {
+    let mut light = PointLight::new();
+    light.init(prop_list);
+    LeadObject::Light(Arc::new(light))
+}
+
+impl Light for PointLight {
+    fn flags(&self) -> u32 { LightFlags::DeltaPosition.to_u32() }
+
+    fn sample_li(&self, reference: &dyn Interaction, _u: &Point2f, wi: &mut Vector3f, pdf: &mut f32, vis: &mut VisibilityTester) -> Spectrum {
+        *wi = Vector3f::normalize(&(self.p_light - reference.p()));
+        *pdf = 1.0;
+        *vis = VisibilityTester::init(&reference.p(), &self.p_light, reference.time());
+
+        self.intensity / (self.p_light - reference.p()).length_sqr()
+    }
+
+    fn pdf_li(&self, _reference: &dyn Interaction, _wi: &Vector3f) -> f32 { 0.0 }
+
+    fn power(&self) -> Spectrum {
+        self.intensity * (4.0 * M_PI)
+    }
+}
+
+impl LeadObjectTrait for PointLight {
+    fn init(&mut self, prop_list: PropertyList) {
+        self.light_to_world = prop_list.get_transform();
+        self.p_light = &self.light_to_world * prop_list.get_point3("position", Point3f::new());
+        self.intensity = prop_list.get_rgb("intensity", Spectrum::init_one(1.0));
+    }
+
+    fn activate(&mut self) { }
+
+    fn add_child(&mut self, child: &mut LeadObject) {
+        println!("Struct PointLight does not take a child of class {}", child.to_string());
+    }
+
+    fn to_string(&self) -> String {
+        format!("PointLight[position: {}, intensity: {}]", self.p_light.to_string(), self.intensity.to_string())
+    }
+}
+
+impl PointLight {
+    pub fn new() -> Self {
+        Self {
+            light_to_world: Transform::new(),
+            p_light: Point3f::new(),
+            intensity: Spectrum::init_one(1.0)
+        }
+    }
+}
+
+register_struct!("point", create_point_light);
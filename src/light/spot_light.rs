@@ -0,0 +1,99 @@
+use crate::common::*;
+
+pub struct SpotLight {
+    light_to_world: Transform,
+    world_to_light: Transform,
+    p_light: Point3f,
+    intensity: Spectrum,
+    cos_total_width: f32,
+    cos_falloff_start: f32,
+}
+
+// Constructor
+fn create_spot_light(prop_list: PropertyList) -> LeadObject {
+    let mut light = SpotLight::new();
+    light.init(prop_list);
+    LeadObject::Light(Arc::new(light))
+}
+
+impl Light for SpotLight {
+    fn flags(&self) -> u32 { LightFlags::DeltaPosition.to_u32() }
+
+    fn sample_li(&self, reference: &dyn Interaction, _u: &Point2f, wi: &mut Vector3f, pdf: &mut f32, vis: &mut VisibilityTester) -> Spectrum {
+        *wi = Vector3f::normalize(&(self.p_light - reference.p()));
+        *pdf = 1.0;
+        *vis = VisibilityTester::init(&reference.p(), &self.p_light, reference.time());
+
+        self.intensity * self.falloff(&-*wi) / (self.p_light - reference.p()).length_sqr()
+    }
+
+    fn pdf_li(&self, _reference: &dyn Interaction, _wi: &Vector3f) -> f32 { 0.0 }
+
+    fn power(&self) -> Spectrum {
+        self.intensity * (2.0 * M_PI * (1.0 - 0.5 * (self.cos_falloff_start + self.cos_total_width)))
+    }
+}
+
+impl LeadObjectTrait for SpotLight {
+    fn init(&mut self, prop_list: PropertyList) {
+        let position = prop_list.get_point3("position", Point3f::new());
+        let lookat = prop_list.get_point3("lookat", Point3f::init([0.0, 0.0, 1.0]));
+        let up = prop_list.get_vector3("up", Vector3f::init([0.0, 1.0, 0.0]));
+
+        // light space has the spot pointing down +z
+        self.light_to_world = prop_list.get_transform() * Transform::look_at(&position, &lookat, &up);
+        self.world_to_light = self.light_to_world.inverse();
+        self.p_light = &self.light_to_world * Point3f::new();
+        self.intensity = prop_list.get_rgb("intensity", Spectrum::init_one(1.0));
+
+        let cone_angle = prop_list.get_float("cone_angle", 30.0);
+        let cone_delta_angle = prop_list.get_float("cone_delta_angle", 5.0);
+        self.cos_total_width = cone_angle.to_radians().cos();
+        self.cos_falloff_start = (cone_angle - cone_delta_angle).to_radians().cos();
+    }
+
+    fn activate(&mut self) { }
+
+    fn add_child(&mut self, child: &mut LeadObject) {
+        println!("Struct SpotLight does not take a child of class {}", child.to_string());
+    }
+
+    fn to_string(&self) -> String {
+        format!(
+            "SpotLight[position: {}, intensity: {}, cone_angle: {}, falloff_start: {}]",
+            self.p_light.to_string(), self.intensity.to_string(),
+            self.cos_total_width.acos().to_degrees(), self.cos_falloff_start.acos().to_degrees()
+        )
+    }
+}
+
+impl SpotLight {
+    pub fn new() -> Self {
+        Self {
+            light_to_world: Transform::new(),
+            world_to_light: Transform::new(),
+            p_light: Point3f::new(),
+            intensity: Spectrum::init_one(1.0),
+            cos_total_width: 30f32.to_radians().cos(),
+            cos_falloff_start: 25f32.to_radians().cos()
+        }
+    }
+
+    // Smoothly fades out between the falloff start and the edge of the cone
+    fn falloff(&self, w: &Vector3f) -> f32 {
+        let wl = Vector3f::normalize(&(&self.world_to_light * *w));
+        let cos_theta = wl.z();
+
+        if cos_theta < self.cos_total_width {
+            return 0.0;
+        }
+        if cos_theta >= self.cos_falloff_start {
+            return 1.0;
+        }
+
+        let delta = (cos_theta - self.cos_total_width) / (self.cos_falloff_start - self.cos_total_width);
+        (delta * delta) * (delta * delta)
+    }
+}
+
+register_struct!("spot", create_spot_light);
@@ -1,5 +1,16 @@
 #[macro_export]
 macro_rules! register_struct {
+    ($inp1:expr, $inp2:expr) => {
+        #[ctor::ctor]
+        fn register_sphere() {
+            register_lead_object($inp1, |prop_list| Ok($inp2(prop_list)));
+        }
+    };
+}
+
+// For constructors that return a Result, e.g. ones that load a file
+#[macro_export]
+macro_rules! register_fallible_struct {
     ($inp1:expr, $inp2:expr) => {
         #[ctor::ctor]
         fn register_sphere() {
@@ -8,6 +19,47 @@ macro_rules! register_struct {
     };
 }
 
+// Declares a thread local statistic and registers it with report_thread_stats, e.g.
+// stat_counter!(CAMERA_RAYS, "Integrator/Camera rays") and then CAMERA_RAYS.with(|rays| rays.add(1))
+#[macro_export]
+macro_rules! declare_stat {
+    ($name:ident, $kind:ident, $title:expr) => {
+        thread_local! {
+            static $name: $crate::utils::stats::$kind = const { $crate::utils::stats::$kind::new() };
+        }
+
+        // in a block of its own so a file can declare more than one
+        const _: () = {
+            #[ctor::ctor]
+            fn register_stat() {
+                $crate::utils::stats::register_stat(|accumulator| {
+                    $name.with(|stat| $crate::utils::stats::Stat::report(stat, $title, accumulator))
+                });
+            }
+        };
+    };
+}
+
+#[macro_export]
+macro_rules! stat_counter {
+    ($name:ident, $title:expr) => { $crate::declare_stat!($name, StatCounter, $title); };
+}
+
+#[macro_export]
+macro_rules! stat_ratio {
+    ($name:ident, $title:expr) => { $crate::declare_stat!($name, StatRatio, $title); };
+}
+
+#[macro_export]
+macro_rules! stat_distribution {
+    ($name:ident, $title:expr) => { $crate::declare_stat!($name, StatDistribution, $title); };
+}
+
+#[macro_export]
+macro_rules! stat_timer {
+    ($name:ident, $title:expr) => { $crate::declare_stat!($name, StatTimer, $title); };
+}
+
 #[macro_export]
 macro_rules! impl_operator {
     ($struct:ident<$generic:ident, const $size:ident: usize>, $trait:ident, $func:ident, $op:tt, $opt:ident<$generic_op:ident, const $size_op:ident: usize>) => {
@@ -69,6 +121,12 @@ macro_rules! impl_operator_inplace {
 
 
 pub use register_struct;
+pub use register_fallible_struct;
+pub use declare_stat;
+pub use stat_counter;
+pub use stat_ratio;
+pub use stat_distribution;
+pub use stat_timer;
 pub use impl_operator;
 pub use impl_operator_unary;
 pub use impl_operator_inplace;
\ No newline at end of file
@@ -11,6 +11,7 @@ pub mod sampler;
 pub mod scene;
 pub mod shapes;
 pub mod spectrum;
+pub mod texture;
 pub mod utils;
 
 pub mod macros;
@@ -18,22 +19,39 @@ pub mod common;
 pub mod factory;
 pub mod parser;
 
+use std::sync::Arc;
+
 use common::LeadObject;
 use parser::Parser;
 
 fn main() {
+    // `Lead --bench-bvh [primitives] [rays]` times the BVH on a generated scene instead of rendering
+    if std::env::args().nth(1).as_deref() == Some("--bench-bvh") {
+        let n_primitives = std::env::args().nth(2).and_then(|n| n.parse().ok()).unwrap_or(1_000_000);
+        let n_rays = std::env::args().nth(3).and_then(|n| n.parse().ok()).unwrap_or(100_000);
+        accel::bench_bvh(n_primitives, n_rays);
+        return;
+    }
+
+    let scene_file = std::env::args().nth(1).unwrap_or(String::from("./scenes/temp.xml"));
+
     let parser = Parser::new();
-    let mut main_scene_obj = match parser.parse_file("./scenes/temp.xml"){
+    let mut main_scene_obj = match parser.parse_file(&scene_file){
         Ok(root_node) => root_node,
-        Err(e) => panic!("Ran into error {:?}", e)
+        Err(e) => {
+            eprintln!("Could not load {}: {}", scene_file, e);
+            std::process::exit(1);
+        }
     };
 
     main_scene_obj.activate();
 
-    let main_scene  = match main_scene_obj {
+    let mut main_scene  = match main_scene_obj {
         LeadObject::Scene(scene) => scene,
         _ => panic!("Couldnt find a scene!"),
     };
 
     println!("{}", main_scene.to_string());
+
+    Arc::get_mut(&mut main_scene).unwrap().render();
 }
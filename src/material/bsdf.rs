@@ -0,0 +1,191 @@
+use crate::common::*;
+
+pub const MAX_BXDFS: usize = 8;
+
+stat_counter!(SAMPLES, "BSDF/Samples drawn");
+stat_ratio!(SPECULAR_SAMPLES, "BSDF/Samples of specular lobes");
+
+pub struct BSDF {
+    pub eta: f32,
+    ns: Normal3f,
+    ng: Normal3f,
+    ss: Vector3f,
+    ts: Vector3f,
+    bxdfs: Vec<Box<dyn BxDF>>
+}
+
+impl BSDF {
+    // Shading frame is built from the shading normal and dpdu at the hit point
+    pub fn new(its: &SurfaceInteraction, eta: f32) -> Self {
+        let ns = its.shading.n;
+        let ss = Vector3f::normalize(&its.shading.dpdu);
+        let ts = Vector3f::cross(&Vector3f::init([ns.x(), ns.y(), ns.z()]), &ss);
+
+        Self {
+            eta,
+            ns,
+            ng: its.n,
+            ss,
+            ts,
+            bxdfs: Vec::new()
+        }
+    }
+
+    pub fn add(&mut self, bxdf: Box<dyn BxDF>) {
+        assert!(self.bxdfs.len() < MAX_BXDFS, "A BSDF can hold at most {} BxDFs!", MAX_BXDFS);
+        self.bxdfs.push(bxdf);
+    }
+
+    pub fn num_components(&self, flags: u32) -> usize {
+        self.bxdfs.iter().filter(|bxdf| bxdf.matches_flags(flags)).count()
+    }
+
+    pub fn world_to_local(&self, v: &Vector3f) -> Vector3f {
+        let n = Vector3f::init([self.ns.x(), self.ns.y(), self.ns.z()]);
+
+        Vector3f::init([Vector3f::dot(v, &self.ss), Vector3f::dot(v, &self.ts), Vector3f::dot(v, &n)])
+    }
+
+    pub fn local_to_world(&self, v: &Vector3f) -> Vector3f {
+        let n = Vector3f::init([self.ns.x(), self.ns.y(), self.ns.z()]);
+
+        self.ss * v.x() + self.ts * v.y() + n * v.z()
+    }
+
+    pub fn f(&self, wo_w: &Vector3f, wi_w: &Vector3f, flags: u32) -> Spectrum {
+        let wi = self.world_to_local(wi_w);
+        let wo = self.world_to_local(wo_w);
+        if wo.z() == 0.0 {
+            return Spectrum::init_one(0.0);
+        }
+
+        // the geometric normal decides between reflection and transmission, not the shading one
+        let reflect = Normal3f::dot(&self.ng, wi_w) * Normal3f::dot(&self.ng, wo_w) > 0.0;
+
+        let mut ret = Spectrum::init_one(0.0);
+        for bxdf in &self.bxdfs {
+            let reflection = bxdf.bxdf_type() & BxDFType::BSDFReflection.to_u32() != 0;
+            let transmission = bxdf.bxdf_type() & BxDFType::BSDFTtransmission.to_u32() != 0;
+
+            if bxdf.matches_flags(flags) && ((reflect && reflection) || (!reflect && transmission)) {
+                ret = ret + bxdf.f(&wo, &wi);
+            }
+        }
+
+        ret
+    }
+
+    pub fn sample_f(&self, wo_w: &Vector3f, wi_w: &mut Vector3f, u: &Point2f, pdf: &mut f32, flags: u32, sampled_type: &mut u32) -> Spectrum {
+        SAMPLES.with(|samples| samples.add(1));
+        *pdf = 0.0;
+        *sampled_type = 0;
+
+        let matching_comps = self.num_components(flags);
+        if matching_comps == 0 {
+            return Spectrum::init_one(0.0);
+        }
+
+        // first dimension picks the component, and is then remapped to [0, 1) for the component's own sampling
+        let comp = ((u.x() * matching_comps as f32).floor() as usize).min(matching_comps - 1);
+        let bxdf = self.bxdfs.iter().filter(|bxdf| bxdf.matches_flags(flags)).nth(comp).unwrap();
+
+        let u_remapped = Point2f::init([(u.x() * matching_comps as f32 - comp as f32).min(ONE_MINUS_EPSILON), u.y()]);
+
+        let wo = self.world_to_local(wo_w);
+        if wo.z() == 0.0 {
+            return Spectrum::init_one(0.0);
+        }
+
+        let mut wi = Vector3f::new();
+        let mut f = bxdf.sample_f(&wo, &mut wi, &u_remapped, pdf, None);
+        if *pdf == 0.0 {
+            return Spectrum::init_one(0.0);
+        }
+
+        *sampled_type = bxdf.bxdf_type();
+        // lobes that both reflect and transmit report only the side wi ended up on
+        let both = BxDFType::BSDFReflection.to_u32() | BxDFType::BSDFTtransmission.to_u32();
+        if *sampled_type & both == both {
+            *sampled_type &= if Frame::same_hemisphere(&wo, &wi) { !BxDFType::BSDFTtransmission.to_u32() } else { !BxDFType::BSDFReflection.to_u32() };
+        }
+        *wi_w = self.local_to_world(&wi);
+
+        let specular = bxdf.bxdf_type() & BxDFType::BSDFSpecular.to_u32() != 0;
+        SPECULAR_SAMPLES.with(|samples| samples.add(specular as u64, 1));
+
+        // specular lobes are delta distributions, so the other components cannot contribute
+        if !specular && matching_comps > 1 {
+            for other in &self.bxdfs {
+                if !std::ptr::addr_eq(other.as_ref(), bxdf.as_ref()) && other.matches_flags(flags) {
+                    *pdf += other.pdf(&wo, &wi);
+                }
+            }
+        }
+        if matching_comps > 1 {
+            *pdf /= matching_comps as f32;
+        }
+
+        if !specular {
+            let reflect = Normal3f::dot(&self.ng, wi_w) * Normal3f::dot(&self.ng, wo_w) > 0.0;
+
+            f = Spectrum::init_one(0.0);
+            for other in &self.bxdfs {
+                let reflection = other.bxdf_type() & BxDFType::BSDFReflection.to_u32() != 0;
+                let transmission = other.bxdf_type() & BxDFType::BSDFTtransmission.to_u32() != 0;
+
+                if other.matches_flags(flags) && ((reflect && reflection) || (!reflect && transmission)) {
+                    f = f + other.f(&wo, &wi);
+                }
+            }
+        }
+
+        f
+    }
+
+    pub fn pdf(&self, wo_w: &Vector3f, wi_w: &Vector3f, flags: u32) -> f32 {
+        if self.bxdfs.is_empty() {
+            return 0.0;
+        }
+
+        let wo = self.world_to_local(wo_w);
+        let wi = self.world_to_local(wi_w);
+        if wo.z() == 0.0 {
+            return 0.0;
+        }
+
+        let mut pdf = 0.0;
+        let mut matching_comps = 0;
+        for bxdf in &self.bxdfs {
+            if bxdf.matches_flags(flags) {
+                matching_comps += 1;
+                pdf += bxdf.pdf(&wo, &wi);
+            }
+        }
+
+        if matching_comps > 0 { pdf / matching_comps as f32 } else { 0.0 }
+    }
+
+    pub fn rho(&self, wo_w: &Vector3f, samples: &[Point2f], flags: u32) -> Spectrum {
+        let wo = self.world_to_local(wo_w);
+
+        let mut ret = Spectrum::init_one(0.0);
+        for bxdf in self.bxdfs.iter().filter(|bxdf| bxdf.matches_flags(flags)) {
+            ret = ret + bxdf.rho(&wo, samples);
+        }
+
+        ret
+    }
+
+    pub fn rho_multi_sample(&self, samples_1: &[Point2f], samples_2: &[Point2f], flags: u32) -> Spectrum {
+        let mut ret = Spectrum::init_one(0.0);
+        for bxdf in self.bxdfs.iter().filter(|bxdf| bxdf.matches_flags(flags)) {
+            ret = ret + bxdf.rho_multi_sample(samples_1, samples_2);
+        }
+
+        ret
+    }
+
+    pub fn to_string(&self) -> String {
+        format!("BSDF[eta: {}, n_bxdfs: {}]", self.eta, self.bxdfs.len())
+    }
+}
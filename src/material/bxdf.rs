@@ -27,9 +27,48 @@ pub trait BxDF {
 
     // fn new(bxdf_type: BxDFType) -> Self;
     fn f(&self, _wo: &Vector3f, _wi: &Vector3f) -> Spectrum { Spectrum::init_one(0.0) }
-    fn sample_f(&self, _wo: &Vector3f, _wi: &mut Vector3f, _sample: &Point2f, _pdf: &mut f32, _sampled_type: Option<BxDFType>) -> Spectrum { Spectrum::init_one(0.0) }
-    fn rho(&self, _wo: &Vector3f, _samples: &Vec<Point2f>) -> Spectrum { Spectrum::init_one(0.0) }
-    fn rho_multi_sample(&self, _samples_1: &Vec<Point2f>, _samples_2: &Vec<Point2f>) -> Spectrum { Spectrum::init_one(0.0) }
-    fn pdf(&self, _wi: &Vector3f, _wo: &Vector3f) -> f32 { 0.0 }
-    fn matches_flags(&self, t: BxDFType) -> bool { t.to_u32() & self.bxdf_type() == self.bxdf_type() }
+    // Cosine-weighted hemisphere sampling, on the same side as wo
+    fn sample_f(&self, wo: &Vector3f, wi: &mut Vector3f, sample: &Point2f, pdf: &mut f32, _sampled_type: Option<BxDFType>) -> Spectrum {
+        *wi = Warp::cosine_sample_hemisphere(*sample);
+        if wo.z() < 0.0 {
+            wi[2] *= -1.0;
+        }
+
+        *pdf = self.pdf(wo, wi);
+        self.f(wo, wi)
+    }
+    // Hemispherical-directional reflectance, estimated with the bxdf's own sampling
+    fn rho(&self, wo: &Vector3f, samples: &[Point2f]) -> Spectrum {
+        let mut r = Spectrum::init_one(0.0);
+        for u in samples {
+            let mut wi = Vector3f::new();
+            let mut pdf = 0f32;
+            let f = self.sample_f(wo, &mut wi, u, &mut pdf, None);
+            if pdf > 0.0 {
+                r = r + f * (Frame::abs_cos_theta(&wi) / pdf);
+            }
+        }
+
+        r / samples.len() as f32
+    }
+    // Hemispherical-hemispherical reflectance, wo is sampled uniformly from samples_1
+    fn rho_multi_sample(&self, samples_1: &[Point2f], samples_2: &[Point2f]) -> Spectrum {
+        let mut r = Spectrum::init_one(0.0);
+        for (u1, u2) in samples_1.iter().zip(samples_2.iter()) {
+            let wo = Warp::uniform_sample_hemisphere(*u1);
+            let pdf_o = Warp::uniform_hemisphere_pdf();
+            let mut wi = Vector3f::new();
+            let mut pdf_i = 0f32;
+            let f = self.sample_f(&wo, &mut wi, u2, &mut pdf_i, None);
+            if pdf_i > 0.0 {
+                r = r + f * (Frame::abs_cos_theta(&wi) * Frame::abs_cos_theta(&wo) / (pdf_o * pdf_i));
+            }
+        }
+
+        r / (M_PI * samples_1.len() as f32)
+    }
+    fn pdf(&self, wo: &Vector3f, wi: &Vector3f) -> f32 {
+        if Frame::same_hemisphere(wo, wi) { Frame::abs_cos_theta(wi) * M_INV_PI } else { 0.0 }
+    }
+    fn matches_flags(&self, t: u32) -> bool { t & self.bxdf_type() == self.bxdf_type() }
 }
\ No newline at end of file
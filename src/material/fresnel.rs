@@ -24,9 +24,7 @@ pub struct FresnelConductor {
 
 impl FresnelConductor {
     pub fn evaluate(&self, cos_theta_i: f32) -> Spectrum {
-        let fr = fr_conductor(cos_theta_i, self.eta_i, self.eta_t, self.k);
-
-        Spectrum::init_one(fr as f64)
+        fr_conductor(cos_theta_i.abs(), self.eta_i, self.eta_t, self.k)
     }
 }
 
@@ -51,38 +49,45 @@ impl FresnelNoOp {
     }
 }
 
+// Dielectrics have a single real index of refraction, so only the first channel is used
 pub fn fr_dielectric(cos_theta_i: f32, eta_i: Spectrum, eta_t: Spectrum) -> f32 {
     let mut cos_theta_i = cos_theta_i.clamp(-1.0, 1.0);
     let entering = cos_theta_i > 0.0;
 
-    let mut eta_i = eta_i; let mut eta_t = eta_t;
+    let mut eta_i = eta_i[0] as f32; let mut eta_t = eta_t[0] as f32;
     if !entering {
-        let t = eta_i;
-        eta_i = eta_t;
-        eta_t = t;
+        std::mem::swap(&mut eta_i, &mut eta_t);
         cos_theta_i = cos_theta_i.abs();
     }
 
     let sin_theta_i = 0f32.max(1.0 - cos_theta_i*cos_theta_i).sqrt();
     let sin_theta_t = eta_i / eta_t * sin_theta_i;
 
-    if sin_theta_t.y() >= 1.0 {
+    if sin_theta_t >= 1.0 {
         return 1.0;
     }
 
-    let cos_theta_t = 0f32.max((Spectrum::init_one(1.0) - sin_theta_t*sin_theta_t).norm() as f32).sqrt();
+    let cos_theta_t = 0f32.max(1.0 - sin_theta_t*sin_theta_t).sqrt();
 
     let r_parallel = (eta_t*cos_theta_i - eta_i*cos_theta_t) / (eta_t*cos_theta_i + eta_i*cos_theta_t);
     let r_perp = (eta_i*cos_theta_i - eta_t*cos_theta_t) / (eta_i*cos_theta_i + eta_t*cos_theta_t);
 
-    ((r_parallel*r_parallel + r_perp*r_perp).norm() * 0.5) as f32
+    (r_parallel*r_parallel + r_perp*r_perp) * 0.5
 }
 
-pub fn fr_conductor(cos_theta_i: f32, eta_i: Spectrum, eta_t: Spectrum, k: Spectrum) -> f32 {
+// Evaluated per channel, so coloured metals keep their tint
+pub fn fr_conductor(cos_theta_i: f32, eta_i: Spectrum, eta_t: Spectrum, k: Spectrum) -> Spectrum {
     let cos_theta_i = cos_theta_i.clamp(-1.0, 1.0);
-    let eta_i = eta_i[0] as f32;
-    let eta_t = eta_t[1] as f32;
-    let k = k[0] as f32;
+
+    let mut ret = Spectrum::init_one(0.0);
+    for c in 0..3 {
+        ret[c] = fr_conductor_channel(cos_theta_i, eta_i[c] as f32, eta_t[c] as f32, k[c] as f32) as f64;
+    }
+
+    ret
+}
+
+fn fr_conductor_channel(cos_theta_i: f32, eta_i: f32, eta_t: f32, k: f32) -> f32 {
     let eta = eta_t / eta_i;
     let eta_k = k / eta_i;
 
@@ -103,4 +108,4 @@ pub fn fr_conductor(cos_theta_i: f32, eta_i: Spectrum, eta_t: Spectrum, k: Spect
     let r_p = r_s * (t3 - t4) / (t3 + t4);
 
     0.5 * (r_p + r_s)
-}
\ No newline at end of file
+}
@@ -0,0 +1,99 @@
+use crate::common::*;
+
+// Dielectric interface, frosted when given a roughness
+pub struct GlassMaterial {
+    kr: Arc<dyn Texture<Spectrum>>,
+    kt: Arc<dyn Texture<Spectrum>>,
+    eta: f32,
+    roughness: MicrofacetRoughness
+}
+
+// Constructor
+fn create_glass_material(prop_list: PropertyList) -> LeadObject {
+    let mut material = GlassMaterial::new();
+    material.init(prop_list);
+    LeadObject::Material(Arc::new(material))
+}
+
+impl Material for GlassMaterial {
+    fn compute_scattering_functions(&self, its: &mut SurfaceInteraction, mode: TransportMode, allow_multiple_lobes: bool) {
+        let mut bsdf = BSDF::new(its, self.eta);
+
+        let kr = self.kr.evaluate(its);
+        let kt = self.kt.evaluate(its);
+        if kr.is_black() && kt.is_black() {
+            its.bsdf = Some(Arc::new(bsdf));
+            return;
+        }
+
+        let distribution = self.roughness.distribution(its);
+
+        // a single lobe chooses between reflection and refraction by the fresnel term,
+        // otherwise both directions are followed separately
+        if distribution.is_none() && allow_multiple_lobes {
+            bsdf.add(Box::new(FresnelSpecular::new(&kr, &kt, 1.0, self.eta, mode)));
+        } else {
+            if !kr.is_black() {
+                let fresnel = FresnelDielectric {
+                    eta_i: Spectrum::init_one(1.0),
+                    eta_t: Spectrum::init_one(self.eta as f64)
+                };
+                match self.roughness.distribution(its) {
+                    Some(distribution) => bsdf.add(Box::new(MicrofacetReflection::new(&kr, distribution, Fresnel::Dielectric(fresnel)))),
+                    None => bsdf.add(Box::new(SpecularReflection::new(&kr, Fresnel::Dielectric(fresnel))))
+                }
+            }
+            if !kt.is_black() {
+                match distribution {
+                    Some(distribution) => bsdf.add(Box::new(MicrofacetTransmission::new(&kt, distribution, 1.0, self.eta, mode))),
+                    None => bsdf.add(Box::new(SpecularTransmission::new(&kt, 1.0, self.eta, mode)))
+                }
+            }
+        }
+
+        its.bsdf = Some(Arc::new(bsdf));
+    }
+}
+
+impl LeadObjectTrait for GlassMaterial {
+    fn init(&mut self, prop_list: PropertyList) {
+        self.kr = Arc::new(ConstantTexture::new(prop_list.get_rgb("reflectance", Spectrum::init_one(1.0))));
+        self.kt = Arc::new(ConstantTexture::new(prop_list.get_rgb("transmittance", Spectrum::init_one(1.0))));
+        self.eta = prop_list.get_float("eta", 1.5);
+        self.roughness.init(&prop_list, 0.0);
+    }
+
+    fn activate(&mut self) { }
+
+    fn add_child(&mut self, child: &mut LeadObject) {
+        if self.roughness.add_child(child) {
+            return;
+        }
+
+        match child {
+            LeadObject::SpectrumTexture(name, texture) if name == "reflectance" => self.kr = texture.clone(),
+            LeadObject::SpectrumTexture(name, texture) if name == "transmittance" => self.kt = texture.clone(),
+            _ => println!("Struct GlassMaterial does not take a child of class {}", child.to_string())
+        }
+    }
+
+    fn to_string(&self) -> String {
+        format!(
+            "GlassMaterial[reflectance: {}, transmittance: {}, eta: {}, roughness: {}]",
+            self.kr.to_string(), self.kt.to_string(), self.eta, self.roughness.to_string()
+        )
+    }
+}
+
+impl GlassMaterial {
+    pub fn new() -> Self {
+        Self {
+            kr: Arc::new(ConstantTexture::new(Spectrum::init_one(1.0))),
+            kt: Arc::new(ConstantTexture::new(Spectrum::init_one(1.0))),
+            eta: 1.5,
+            roughness: MicrofacetRoughness::new(0.0)
+        }
+    }
+}
+
+register_struct!("glass", create_glass_material);
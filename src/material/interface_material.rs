@@ -0,0 +1,40 @@
+use crate::common::*;
+
+// Marks a boundary without scattering anything, e.g. the outline of a medium. Its hits get no bsdf,
+// and the integrators pass straight through them
+pub struct InterfaceMaterial { }
+
+// Constructor
+fn create_interface_material(prop_list: PropertyList) -> LeadObject {
+    let mut material = InterfaceMaterial::new();
+    material.init(prop_list);
+    LeadObject::Material(Arc::new(material))
+}
+
+impl Material for InterfaceMaterial {
+    fn compute_scattering_functions(&self, its: &mut SurfaceInteraction, _mode: TransportMode, _allow_multiple_lobes: bool) {
+        its.bsdf = None;
+    }
+}
+
+impl LeadObjectTrait for InterfaceMaterial {
+    fn init(&mut self, _prop_list: PropertyList) { }
+
+    fn activate(&mut self) { }
+
+    fn add_child(&mut self, child: &mut LeadObject) {
+        println!("Struct InterfaceMaterial does not take a child of class {}", child.to_string())
+    }
+
+    fn to_string(&self) -> String {
+        String::from("InterfaceMaterial[]")
+    }
+}
+
+impl InterfaceMaterial {
+    pub fn new() -> Self {
+        Self { }
+    }
+}
+
+register_struct!("interface", create_interface_material);
@@ -13,11 +13,11 @@ impl BxDF for LambertianReflection {
         self.r * M_INV_PI
     }
 
-    fn rho(&self, _wo: &Vector3f, _samples: &Vec<Point2f>) -> Spectrum {
+    fn rho(&self, _wo: &Vector3f, _samples: &[Point2f]) -> Spectrum {
         self.r.clone()
     }
 
-    fn rho_multi_sample(&self, _samples_1: &Vec<Point2f>, _samples_2: &Vec<Point2f>) -> Spectrum {
+    fn rho_multi_sample(&self, _samples_1: &[Point2f], _samples_2: &[Point2f]) -> Spectrum {
         self.r.clone()
     }
 }
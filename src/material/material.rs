@@ -1,3 +1,6 @@
-pub trait Material {
-    
+use crate::common::*;
+
+pub trait Material: LeadObjectTrait {
+    // Fills in its.bsdf for the hit point
+    fn compute_scattering_functions(&self, its: &mut SurfaceInteraction, mode: TransportMode, allow_multiple_lobes: bool);
 }
\ No newline at end of file
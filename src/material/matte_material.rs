@@ -0,0 +1,63 @@
+use crate::common::*;
+
+pub struct MatteMaterial {
+    kd: Arc<dyn Texture<Spectrum>>,
+    sigma: Arc<dyn Texture<f32>>
+}
+
+// Constructor
+fn create_matte_material(prop_list: PropertyList) -> LeadObject {
+    let mut material = MatteMaterial::new();
+    material.init(prop_list);
+    LeadObject::Material(Arc::new(material))
+}
+
+impl Material for MatteMaterial {
+    fn compute_scattering_functions(&self, its: &mut SurfaceInteraction, _mode: TransportMode, _allow_multiple_lobes: bool) {
+        let mut bsdf = BSDF::new(its, 1.0);
+
+        let kd = self.kd.evaluate(its);
+        let sigma = self.sigma.evaluate(its).clamp(0.0, 90.0);
+        if !kd.is_black() {
+            if sigma == 0.0 {
+                bsdf.add(Box::new(LambertianReflection::new(&kd)));
+            } else {
+                bsdf.add(Box::new(OrenNayar::new(&kd, sigma)));
+            }
+        }
+
+        its.bsdf = Some(Arc::new(bsdf));
+    }
+}
+
+impl LeadObjectTrait for MatteMaterial {
+    fn init(&mut self, prop_list: PropertyList) {
+        self.kd = Arc::new(ConstantTexture::new(prop_list.get_rgb("reflectance", Spectrum::init_one(0.5))));
+        self.sigma = Arc::new(ConstantTexture::new(prop_list.get_float("sigma", 0.0)));
+    }
+
+    fn activate(&mut self) { }
+
+    fn add_child(&mut self, child: &mut LeadObject) {
+        match child {
+            LeadObject::SpectrumTexture(name, texture) if name == "reflectance" => self.kd = texture.clone(),
+            LeadObject::FloatTexture(name, texture) if name == "sigma" => self.sigma = texture.clone(),
+            _ => println!("Struct MatteMaterial does not take a child of class {}", child.to_string())
+        }
+    }
+
+    fn to_string(&self) -> String {
+        format!("MatteMaterial[reflectance: {}, sigma: {}]", self.kd.to_string(), self.sigma.to_string())
+    }
+}
+
+impl MatteMaterial {
+    pub fn new() -> Self {
+        Self {
+            kd: Arc::new(ConstantTexture::new(Spectrum::init_one(0.5))),
+            sigma: Arc::new(ConstantTexture::new(0.0))
+        }
+    }
+}
+
+register_struct!("matte", create_matte_material);
@@ -0,0 +1,74 @@
+use crate::common::*;
+
+// Conductor, smooth unless given a roughness. The defaults are the RGB fit of copper
+pub struct MetalMaterial {
+    eta: Arc<dyn Texture<Spectrum>>,
+    k: Arc<dyn Texture<Spectrum>>,
+    roughness: MicrofacetRoughness
+}
+
+// Constructor
+fn create_metal_material(prop_list: PropertyList) -> LeadObject {
+    let mut material = MetalMaterial::new();
+    material.init(prop_list);
+    LeadObject::Material(Arc::new(material))
+}
+
+impl Material for MetalMaterial {
+    fn compute_scattering_functions(&self, its: &mut SurfaceInteraction, _mode: TransportMode, _allow_multiple_lobes: bool) {
+        let mut bsdf = BSDF::new(its, 1.0);
+
+        let fresnel = FresnelConductor {
+            eta_i: Spectrum::init_one(1.0),
+            eta_t: self.eta.evaluate(its),
+            k: self.k.evaluate(its)
+        };
+        match self.roughness.distribution(its) {
+            Some(distribution) => bsdf.add(Box::new(MicrofacetReflection::new(&Spectrum::init_one(1.0), distribution, Fresnel::Conductor(fresnel)))),
+            None => bsdf.add(Box::new(SpecularReflection::new(&Spectrum::init_one(1.0), Fresnel::Conductor(fresnel))))
+        }
+
+        its.bsdf = Some(Arc::new(bsdf));
+    }
+}
+
+impl LeadObjectTrait for MetalMaterial {
+    fn init(&mut self, prop_list: PropertyList) {
+        self.eta = Arc::new(ConstantTexture::new(prop_list.get_rgb("eta", Spectrum::from_rgb([0.2004, 0.9240, 1.1022]))));
+        self.k = Arc::new(ConstantTexture::new(prop_list.get_rgb("k", Spectrum::from_rgb([3.9129, 2.4528, 2.1421]))));
+        self.roughness.init(&prop_list, 0.0);
+    }
+
+    fn activate(&mut self) { }
+
+    fn add_child(&mut self, child: &mut LeadObject) {
+        if self.roughness.add_child(child) {
+            return;
+        }
+
+        match child {
+            LeadObject::SpectrumTexture(name, texture) if name == "eta" => self.eta = texture.clone(),
+            LeadObject::SpectrumTexture(name, texture) if name == "k" => self.k = texture.clone(),
+            _ => println!("Struct MetalMaterial does not take a child of class {}", child.to_string())
+        }
+    }
+
+    fn to_string(&self) -> String {
+        format!(
+            "MetalMaterial[eta: {}, k: {}, roughness: {}]",
+            self.eta.to_string(), self.k.to_string(), self.roughness.to_string()
+        )
+    }
+}
+
+impl MetalMaterial {
+    pub fn new() -> Self {
+        Self {
+            eta: Arc::new(ConstantTexture::new(Spectrum::from_rgb([0.2004, 0.9240, 1.1022]))),
+            k: Arc::new(ConstantTexture::new(Spectrum::from_rgb([3.9129, 2.4528, 2.1421]))),
+            roughness: MicrofacetRoughness::new(0.0)
+        }
+    }
+}
+
+register_struct!("metal", create_metal_material);
@@ -0,0 +1,425 @@
+use crate::common::*;
+
+// Distribution of microfacet normals, all directions are in the local shading frame
+pub trait MicrofacetDistribution {
+    fn d(&self, wh: &Vector3f) -> f32;
+    // Ratio of invisible to visible microfacet area in direction w
+    fn lambda(&self, w: &Vector3f) -> f32;
+    fn sample_wh(&self, wo: &Vector3f, u: &Point2f) -> Vector3f;
+    fn sample_visible_area(&self) -> bool;
+    fn to_string(&self) -> String;
+
+    // Smith masking-shadowing
+    fn g1(&self, w: &Vector3f) -> f32 {
+        1.0 / (1.0 + self.lambda(w))
+    }
+
+    fn g(&self, wo: &Vector3f, wi: &Vector3f) -> f32 {
+        1.0 / (1.0 + self.lambda(wo) + self.lambda(wi))
+    }
+
+    fn pdf(&self, wo: &Vector3f, wh: &Vector3f) -> f32 {
+        if self.sample_visible_area() {
+            self.d(wh) * self.g1(wo) * Vector3f::abs_dot(wo, wh) / Frame::abs_cos_theta(wo)
+        } else {
+            self.d(wh) * Frame::abs_cos_theta(wh)
+        }
+    }
+}
+
+// Picks the distribution by name, as used by the material parameters
+pub fn create_microfacet_distribution(name: &str, alpha_x: f32, alpha_y: f32) -> Box<dyn MicrofacetDistribution> {
+    match name {
+        "beckmann" => Box::new(BeckmannDistribution::new(alpha_x, alpha_y, true)),
+        "ggx" | "trowbridge_reitz" => Box::new(TrowbridgeReitzDistribution::new(alpha_x, alpha_y, true)),
+        _ => panic!("Unknown microfacet distribution {}!", name)
+    }
+}
+
+// Roughness parameters of the glossy materials: "roughness", or the anisotropic "u_roughness" / "v_roughness" pair,
+// remapped to alphas unless "remap_roughness" is false
+pub struct MicrofacetRoughness {
+    u_roughness: Arc<dyn Texture<f32>>,
+    v_roughness: Arc<dyn Texture<f32>>,
+    remap_roughness: bool,
+    distribution: String
+}
+
+impl MicrofacetRoughness {
+    pub fn new(roughness: f32) -> Self {
+        Self {
+            u_roughness: Arc::new(ConstantTexture::new(roughness)),
+            v_roughness: Arc::new(ConstantTexture::new(roughness)),
+            remap_roughness: true,
+            distribution: String::from("ggx")
+        }
+    }
+
+    pub fn init(&mut self, prop_list: &PropertyList, default_roughness: f32) {
+        let roughness = prop_list.get_float("roughness", default_roughness);
+        self.u_roughness = Arc::new(ConstantTexture::new(prop_list.get_float("u_roughness", roughness)));
+        self.v_roughness = Arc::new(ConstantTexture::new(prop_list.get_float("v_roughness", roughness)));
+        self.remap_roughness = prop_list.get_bool("remap_roughness", true);
+        self.distribution = prop_list.get_string("distribution", "ggx");
+    }
+
+    // Takes roughness textures, returns false for any other child
+    pub fn add_child(&mut self, child: &LeadObject) -> bool {
+        match child {
+            LeadObject::FloatTexture(name, texture) if name == "roughness" => {
+                self.u_roughness = texture.clone();
+                self.v_roughness = texture.clone();
+            },
+            LeadObject::FloatTexture(name, texture) if name == "u_roughness" => self.u_roughness = texture.clone(),
+            LeadObject::FloatTexture(name, texture) if name == "v_roughness" => self.v_roughness = texture.clone(),
+            _ => return false
+        }
+
+        true
+    }
+
+    // None where the surface is perfectly smooth
+    pub fn distribution(&self, its: &SurfaceInteraction) -> Option<Box<dyn MicrofacetDistribution>> {
+        let mut alpha_x = self.u_roughness.evaluate(its);
+        let mut alpha_y = self.v_roughness.evaluate(its);
+        if self.remap_roughness {
+            alpha_x = roughness_to_alpha(alpha_x);
+            alpha_y = roughness_to_alpha(alpha_y);
+        }
+
+        if alpha_x <= 0.0 && alpha_y <= 0.0 {
+            return None;
+        }
+
+        Some(create_microfacet_distribution(&self.distribution, alpha_x, alpha_y))
+    }
+
+    pub fn to_string(&self) -> String {
+        format!(
+            "MicrofacetRoughness[u_roughness: {}, v_roughness: {}, remap_roughness: {}, distribution: {}]",
+            self.u_roughness.to_string(), self.v_roughness.to_string(), self.remap_roughness, self.distribution
+        )
+    }
+}
+
+// Maps a user facing roughness to the alpha of the distributions, zero stays zero so materials can fall back to specular lobes
+pub fn roughness_to_alpha(roughness: f32) -> f32 {
+    if roughness <= 0.0 {
+        return 0.0;
+    }
+
+    let x = roughness.max(1e-3).ln();
+    1.62142 + 0.819955 * x + 0.1734 * x * x + 0.0171201 * x * x * x + 0.000640711 * x * x * x * x
+}
+
+pub struct BeckmannDistribution {
+    alpha_x: f32,
+    alpha_y: f32,
+    sample_visible_area: bool
+}
+
+impl MicrofacetDistribution for BeckmannDistribution {
+    fn d(&self, wh: &Vector3f) -> f32 {
+        let tan2_theta = Frame::tan2_theta(wh);
+        if tan2_theta.is_infinite() {
+            return 0.0;
+        }
+
+        let cos4_theta = Frame::cos2_theta(wh) * Frame::cos2_theta(wh);
+        let e = tan2_theta * (Frame::cos2_phi(wh) / (self.alpha_x * self.alpha_x) + Frame::sin2_phi(wh) / (self.alpha_y * self.alpha_y));
+
+        (-e).exp() / (M_PI * self.alpha_x * self.alpha_y * cos4_theta)
+    }
+
+    // rational approximation of the exact erf based expression
+    fn lambda(&self, w: &Vector3f) -> f32 {
+        let abs_tan_theta = Frame::tan_theta(w).abs();
+        if abs_tan_theta.is_infinite() {
+            return 0.0;
+        }
+
+        let alpha = (Frame::cos2_phi(w) * self.alpha_x * self.alpha_x + Frame::sin2_phi(w) * self.alpha_y * self.alpha_y).sqrt();
+        let a = 1.0 / (alpha * abs_tan_theta);
+        if a >= 1.6 {
+            return 0.0;
+        }
+
+        (1.0 - 1.259 * a + 0.396 * a * a) / (3.535 * a + 2.181 * a * a)
+    }
+
+    fn sample_wh(&self, wo: &Vector3f, u: &Point2f) -> Vector3f {
+        if self.sample_visible_area {
+            let flip = wo.z() < 0.0;
+            let wh = beckmann_sample(&if flip { -*wo } else { *wo }, self.alpha_x, self.alpha_y, u.x(), u.y());
+
+            return if flip { -wh } else { wh };
+        }
+
+        // sample the full distribution of normals
+        let log_sample = (1.0 - u.x()).ln();
+        if log_sample.is_infinite() {
+            return Vector3f::init([0.0, 0.0, 1.0]);
+        }
+
+        let (tan2_theta, phi) = if self.alpha_x == self.alpha_y {
+            (-self.alpha_x * self.alpha_x * log_sample, u.y() * 2.0 * M_PI)
+        } else {
+            let phi = anisotropic_phi(self.alpha_x, self.alpha_y, u.y());
+            let (sin_phi, cos_phi) = phi.sin_cos();
+            let tan2_theta = -log_sample / (cos_phi * cos_phi / (self.alpha_x * self.alpha_x) + sin_phi * sin_phi / (self.alpha_y * self.alpha_y));
+
+            (tan2_theta, phi)
+        };
+
+        let cos_theta = 1.0 / (1.0 + tan2_theta).sqrt();
+        let sin_theta = 0f32.max(1.0 - cos_theta * cos_theta).sqrt();
+        let wh = Frame::spherical_direction(sin_theta, cos_theta, phi);
+
+        if Frame::same_hemisphere(wo, &wh) { wh } else { -wh }
+    }
+
+    fn sample_visible_area(&self) -> bool { self.sample_visible_area }
+
+    fn to_string(&self) -> String {
+        format!("BeckmannDistribution[alpha_x: {}, alpha_y: {}]", self.alpha_x, self.alpha_y)
+    }
+}
+
+impl BeckmannDistribution {
+    pub fn new(alpha_x: f32, alpha_y: f32, sample_visible_area: bool) -> Self {
+        Self {
+            alpha_x: alpha_x.max(1e-3),
+            alpha_y: alpha_y.max(1e-3),
+            sample_visible_area
+        }
+    }
+}
+
+pub struct TrowbridgeReitzDistribution {
+    alpha_x: f32,
+    alpha_y: f32,
+    sample_visible_area: bool
+}
+
+impl MicrofacetDistribution for TrowbridgeReitzDistribution {
+    fn d(&self, wh: &Vector3f) -> f32 {
+        let tan2_theta = Frame::tan2_theta(wh);
+        if tan2_theta.is_infinite() {
+            return 0.0;
+        }
+
+        let cos4_theta = Frame::cos2_theta(wh) * Frame::cos2_theta(wh);
+        let e = tan2_theta * (Frame::cos2_phi(wh) / (self.alpha_x * self.alpha_x) + Frame::sin2_phi(wh) / (self.alpha_y * self.alpha_y));
+
+        1.0 / (M_PI * self.alpha_x * self.alpha_y * cos4_theta * (1.0 + e) * (1.0 + e))
+    }
+
+    fn lambda(&self, w: &Vector3f) -> f32 {
+        let abs_tan_theta = Frame::tan_theta(w).abs();
+        if abs_tan_theta.is_infinite() {
+            return 0.0;
+        }
+
+        let alpha = (Frame::cos2_phi(w) * self.alpha_x * self.alpha_x + Frame::sin2_phi(w) * self.alpha_y * self.alpha_y).sqrt();
+        let alpha2_tan2_theta = (alpha * abs_tan_theta) * (alpha * abs_tan_theta);
+
+        (-1.0 + (1.0 + alpha2_tan2_theta).sqrt()) / 2.0
+    }
+
+    fn sample_wh(&self, wo: &Vector3f, u: &Point2f) -> Vector3f {
+        if self.sample_visible_area {
+            let flip = wo.z() < 0.0;
+            let wh = trowbridge_reitz_sample(&if flip { -*wo } else { *wo }, self.alpha_x, self.alpha_y, u.x(), u.y());
+
+            return if flip { -wh } else { wh };
+        }
+
+        let (tan2_theta, phi) = if self.alpha_x == self.alpha_y {
+            (self.alpha_x * self.alpha_x * u.x() / (1.0 - u.x()), u.y() * 2.0 * M_PI)
+        } else {
+            let phi = anisotropic_phi(self.alpha_x, self.alpha_y, u.y());
+            let (sin_phi, cos_phi) = phi.sin_cos();
+            let alpha2 = 1.0 / (cos_phi * cos_phi / (self.alpha_x * self.alpha_x) + sin_phi * sin_phi / (self.alpha_y * self.alpha_y));
+
+            (alpha2 * u.x() / (1.0 - u.x()), phi)
+        };
+
+        let cos_theta = 1.0 / (1.0 + tan2_theta).sqrt();
+        let sin_theta = 0f32.max(1.0 - cos_theta * cos_theta).sqrt();
+        let wh = Frame::spherical_direction(sin_theta, cos_theta, phi);
+
+        if Frame::same_hemisphere(wo, &wh) { wh } else { -wh }
+    }
+
+    fn sample_visible_area(&self) -> bool { self.sample_visible_area }
+
+    fn to_string(&self) -> String {
+        format!("TrowbridgeReitzDistribution[alpha_x: {}, alpha_y: {}]", self.alpha_x, self.alpha_y)
+    }
+}
+
+impl TrowbridgeReitzDistribution {
+    pub fn new(alpha_x: f32, alpha_y: f32, sample_visible_area: bool) -> Self {
+        Self {
+            alpha_x: alpha_x.max(1e-3),
+            alpha_y: alpha_y.max(1e-3),
+            sample_visible_area
+        }
+    }
+}
+
+fn anisotropic_phi(alpha_x: f32, alpha_y: f32, u: f32) -> f32 {
+    let phi = (alpha_y / alpha_x * (2.0 * M_PI * u + 0.5 * M_PI).tan()).atan();
+
+    if u > 0.5 { phi + M_PI } else { phi }
+}
+
+// Visible normal sampling: stretch wi to the unit roughness configuration, sample slopes there and transform back
+fn sample_stretched(wi: &Vector3f, alpha_x: f32, alpha_y: f32, u1: f32, u2: f32, sample11: fn(f32, f32, f32) -> (f32, f32)) -> Vector3f {
+    let wi_stretched = Vector3f::normalize(&Vector3f::init([alpha_x * wi.x(), alpha_y * wi.y(), wi.z()]));
+
+    let (mut slope_x, mut slope_y) = sample11(Frame::cos_theta(&wi_stretched), u1, u2);
+
+    let tmp = Frame::cos_phi(&wi_stretched) * slope_x - Frame::sin_phi(&wi_stretched) * slope_y;
+    slope_y = Frame::sin_phi(&wi_stretched) * slope_x + Frame::cos_phi(&wi_stretched) * slope_y;
+    slope_x = tmp;
+
+    slope_x *= alpha_x;
+    slope_y *= alpha_y;
+
+    Vector3f::normalize(&Vector3f::init([-slope_x, -slope_y, 1.0]))
+}
+
+fn beckmann_sample(wi: &Vector3f, alpha_x: f32, alpha_y: f32, u1: f32, u2: f32) -> Vector3f {
+    sample_stretched(wi, alpha_x, alpha_y, u1, u2, beckmann_sample11)
+}
+
+fn trowbridge_reitz_sample(wi: &Vector3f, alpha_x: f32, alpha_y: f32, u1: f32, u2: f32) -> Vector3f {
+    sample_stretched(wi, alpha_x, alpha_y, u1, u2, trowbridge_reitz_sample11)
+}
+
+fn beckmann_sample11(cos_theta_i: f32, u1: f32, u2: f32) -> (f32, f32) {
+    // normal incidence is sampled directly
+    if cos_theta_i > 0.9999 {
+        let r = (-(1.0 - u1).ln()).sqrt();
+        let (sin_phi, cos_phi) = (2.0 * M_PI * u2).sin_cos();
+
+        return (r * cos_phi, r * sin_phi);
+    }
+
+    let sin_theta_i = 0f32.max(1.0 - cos_theta_i * cos_theta_i).sqrt();
+    let tan_theta_i = sin_theta_i / cos_theta_i;
+    let cot_theta_i = 1.0 / tan_theta_i;
+    let sqrt_inv_pi = M_INV_PI.sqrt();
+
+    // invert the slope cdf with a bisection safeguarded newton search
+    let mut a = -1f32;
+    let mut c = erf(cot_theta_i);
+    let sample_x = u1.max(1e-6);
+
+    let theta_i = cos_theta_i.acos();
+    let fit = 1.0 + theta_i * (-0.876 + theta_i * (0.4265 - 0.0594 * theta_i));
+    let mut b = c - (1.0 + c) * (1.0 - sample_x).powf(fit);
+
+    let normalization = 1.0 / (1.0 + c + sqrt_inv_pi * tan_theta_i * (-cot_theta_i * cot_theta_i).exp());
+
+    for _ in 0..9 {
+        if !(b >= a && b <= c) {
+            b = 0.5 * (a + c);
+        }
+
+        let inv_erf = erf_inv(b);
+        let value = normalization * (1.0 + b + sqrt_inv_pi * tan_theta_i * (-inv_erf * inv_erf).exp()) - sample_x;
+        let derivative = normalization * (1.0 - inv_erf * tan_theta_i);
+
+        if value.abs() < 1e-5 {
+            break;
+        }
+
+        if value > 0.0 { c = b; } else { a = b; }
+        b -= value / derivative;
+    }
+
+    (erf_inv(b), erf_inv(2.0 * u2.max(1e-6) - 1.0))
+}
+
+fn trowbridge_reitz_sample11(cos_theta: f32, u1: f32, u2: f32) -> (f32, f32) {
+    if cos_theta > 0.9999 {
+        let r = (u1 / (1.0 - u1)).sqrt();
+        let (sin_phi, cos_phi) = (2.0 * M_PI * u2).sin_cos();
+
+        return (r * cos_phi, r * sin_phi);
+    }
+
+    let sin_theta = 0f32.max(1.0 - cos_theta * cos_theta).sqrt();
+    let tan_theta = sin_theta / cos_theta;
+    let a = 1.0 / tan_theta;
+    let g1 = 2.0 / (1.0 + (1.0 + 1.0 / (a * a)).sqrt());
+
+    // slope_x
+    let a = 2.0 * u1 / g1 - 1.0;
+    let tmp = (1.0 / (a * a - 1.0)).min(1e10);
+    let b = tan_theta;
+    let d = (b * b * tmp * tmp - (a * a - b * b) * tmp).max(0.0).sqrt();
+    let slope_x_1 = b * tmp - d;
+    let slope_x_2 = b * tmp + d;
+    let slope_x = if a < 0.0 || slope_x_2 > 1.0 / tan_theta { slope_x_1 } else { slope_x_2 };
+
+    // slope_y
+    let (s, u2) = if u2 > 0.5 { (1.0, 2.0 * (u2 - 0.5)) } else { (-1.0, 2.0 * (0.5 - u2)) };
+    let z = (u2 * (u2 * (u2 * 0.27385 - 0.73369) + 0.46341)) / (u2 * (u2 * (u2 * 0.093073 + 0.309420) - 1.000000) + 0.597999);
+    let slope_y = s * z * (1.0 + slope_x * slope_x).sqrt();
+
+    (slope_x, slope_y)
+}
+
+// The coefficients of erf and erf_inv are published fits, kept digit for digit as they were copied
+#[allow(clippy::excessive_precision)]
+fn erf(x: f32) -> f32 {
+    let a1 = 0.254829592f32;
+    let a2 = -0.284496736f32;
+    let a3 = 1.421413741f32;
+    let a4 = -1.453152027f32;
+    let a5 = 1.061405429f32;
+    let p = 0.3275911f32;
+
+    let sign = if x < 0.0 { -1.0 } else { 1.0 };
+    let x = x.abs();
+
+    let t = 1.0 / (1.0 + p * x);
+    let y = 1.0 - (((((a5 * t + a4) * t) + a3) * t + a2) * t + a1) * t * (-x * x).exp();
+
+    sign * y
+}
+
+#[allow(clippy::excessive_precision)]
+fn erf_inv(x: f32) -> f32 {
+    let x = x.clamp(-0.99999, 0.99999);
+    let mut w = -((1.0 - x) * (1.0 + x)).ln();
+
+    let p = if w < 5.0 {
+        w -= 2.5;
+        let mut p = 2.81022636e-08f32;
+        p = 3.43273939e-07 + p * w;
+        p = -3.5233877e-06 + p * w;
+        p = -4.39150654e-06 + p * w;
+        p = 0.00021858087 + p * w;
+        p = -0.00125372503 + p * w;
+        p = -0.00417768164 + p * w;
+        p = 0.246640727 + p * w;
+        1.50140941 + p * w
+    } else {
+        w = w.sqrt() - 3.0;
+        let mut p = -0.000200214257f32;
+        p = 0.000100950558 + p * w;
+        p = 0.00134934322 + p * w;
+        p = -0.00367342844 + p * w;
+        p = 0.00573950773 + p * w;
+        p = -0.0076224613 + p * w;
+        p = 0.00943887047 + p * w;
+        p = 1.00167406 + p * w;
+        2.83297682 + p * w
+    };
+
+    p * x
+}
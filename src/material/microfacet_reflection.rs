@@ -0,0 +1,74 @@
+use crate::common::*;
+
+// Torrance-Sparrow glossy reflection
+pub struct MicrofacetReflection {
+    r: Spectrum,
+    distribution: Box<dyn MicrofacetDistribution>,
+    fresnel: Fresnel
+}
+
+impl BxDF for MicrofacetReflection {
+    fn bxdf_type(&self) -> u32 {
+        BxDFType::BSDFReflection.to_u32() | BxDFType::BSDFGlossy.to_u32()
+    }
+
+    fn f(&self, wo: &Vector3f, wi: &Vector3f) -> Spectrum {
+        let cos_theta_o = Frame::abs_cos_theta(wo);
+        let cos_theta_i = Frame::abs_cos_theta(wi);
+        let wh = *wi + *wo;
+
+        // degenerate cases at grazing angles
+        if cos_theta_i == 0.0 || cos_theta_o == 0.0 || wh.length_sqr() == 0.0 {
+            return Spectrum::init_one(0.0);
+        }
+        let wh = Vector3f::normalize(&wh);
+
+        let n = Vector3f::init([0.0, 0.0, 1.0]);
+        let wh_forward = if Vector3f::dot(&wh, &n) < 0.0 { -wh } else { wh };
+        let f = self.fresnel.evaluate(Vector3f::dot(wi, &wh_forward));
+
+        self.r * f * (self.distribution.d(&wh) * self.distribution.g(wo, wi) / (4.0 * cos_theta_i * cos_theta_o))
+    }
+
+    fn sample_f(&self, wo: &Vector3f, wi: &mut Vector3f, sample: &Point2f, pdf: &mut f32, _sampled_type: Option<BxDFType>) -> Spectrum {
+        if wo.z() == 0.0 {
+            return Spectrum::init_one(0.0);
+        }
+
+        let wh = self.distribution.sample_wh(wo, sample);
+        if Vector3f::dot(wo, &wh) < 0.0 {
+            return Spectrum::init_one(0.0);
+        }
+
+        *wi = Self::reflect(wo, &wh);
+        if !Frame::same_hemisphere(wo, wi) {
+            return Spectrum::init_one(0.0);
+        }
+
+        *pdf = self.distribution.pdf(wo, &wh) / (4.0 * Vector3f::dot(wo, &wh));
+        self.f(wo, wi)
+    }
+
+    fn pdf(&self, wo: &Vector3f, wi: &Vector3f) -> f32 {
+        if !Frame::same_hemisphere(wo, wi) {
+            return 0.0;
+        }
+
+        let wh = Vector3f::normalize(&(*wo + *wi));
+        self.distribution.pdf(wo, &wh) / (4.0 * Vector3f::dot(wo, &wh))
+    }
+}
+
+impl MicrofacetReflection {
+    pub fn new(r: &Spectrum, distribution: Box<dyn MicrofacetDistribution>, fresnel: Fresnel) -> Self {
+        Self {
+            r: *r,
+            distribution,
+            fresnel
+        }
+    }
+
+    pub fn reflect(wo: &Vector3f, n: &Vector3f) -> Vector3f {
+        -*wo + *n * (2.0 * Vector3f::dot(wo, n))
+    }
+}
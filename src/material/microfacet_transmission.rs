@@ -0,0 +1,107 @@
+use crate::common::*;
+
+// Rough dielectric transmission, the refractive counterpart of MicrofacetReflection
+pub struct MicrofacetTransmission {
+    t: Spectrum,
+    distribution: Box<dyn MicrofacetDistribution>,
+    fresnel: FresnelDielectric,
+    eta_a: f32, eta_b: f32,
+    mode: TransportMode
+}
+
+impl BxDF for MicrofacetTransmission {
+    fn bxdf_type(&self) -> u32 {
+        BxDFType::BSDFTtransmission.to_u32() | BxDFType::BSDFGlossy.to_u32()
+    }
+
+    fn f(&self, wo: &Vector3f, wi: &Vector3f) -> Spectrum {
+        if Frame::same_hemisphere(wo, wi) {
+            return Spectrum::init_one(0.0);
+        }
+
+        let cos_theta_o = Frame::cos_theta(wo);
+        let cos_theta_i = Frame::cos_theta(wi);
+        if cos_theta_i == 0.0 || cos_theta_o == 0.0 {
+            return Spectrum::init_one(0.0);
+        }
+
+        // generalized half vector for refraction
+        let eta = if cos_theta_o > 0.0 { self.eta_b / self.eta_a } else { self.eta_a / self.eta_b };
+        let mut wh = Vector3f::normalize(&(*wo + *wi * eta));
+        if wh.z() < 0.0 {
+            wh = -wh;
+        }
+
+        // both directions on the same side of the microfacet means no refraction through it
+        if Vector3f::dot(wo, &wh) * Vector3f::dot(wi, &wh) > 0.0 {
+            return Spectrum::init_one(0.0);
+        }
+
+        let f = self.fresnel.evaluate(Vector3f::dot(wo, &wh));
+
+        let sqrt_denom = Vector3f::dot(wo, &wh) + eta * Vector3f::dot(wi, &wh);
+        let factor = match self.mode {
+            TransportMode::Radiance => 1.0 / eta,
+            TransportMode::Importance => 1.0
+        };
+
+        let value = self.distribution.d(&wh) * self.distribution.g(wo, wi) * eta * eta
+            * Vector3f::abs_dot(wi, &wh) * Vector3f::abs_dot(wo, &wh) * factor * factor
+            / (cos_theta_i * cos_theta_o * sqrt_denom * sqrt_denom);
+
+        (Spectrum::init_one(1.0) - f) * self.t * value.abs()
+    }
+
+    fn sample_f(&self, wo: &Vector3f, wi: &mut Vector3f, sample: &Point2f, pdf: &mut f32, _sampled_type: Option<BxDFType>) -> Spectrum {
+        if wo.z() == 0.0 {
+            return Spectrum::init_one(0.0);
+        }
+
+        let wh = self.distribution.sample_wh(wo, sample);
+        if Vector3f::dot(wo, &wh) < 0.0 {
+            return Spectrum::init_one(0.0);
+        }
+
+        let eta = if Frame::cos_theta(wo) > 0.0 { self.eta_a / self.eta_b } else { self.eta_b / self.eta_a };
+        if !SpecularTransmission::refract(wo, &Normal3f::init([wh.x(), wh.y(), wh.z()]), eta, wi) {
+            return Spectrum::init_one(0.0);
+        }
+
+        *pdf = self.pdf(wo, wi);
+        self.f(wo, wi)
+    }
+
+    fn pdf(&self, wo: &Vector3f, wi: &Vector3f) -> f32 {
+        if Frame::same_hemisphere(wo, wi) {
+            return 0.0;
+        }
+
+        let eta = if Frame::cos_theta(wo) > 0.0 { self.eta_b / self.eta_a } else { self.eta_a / self.eta_b };
+        let wh = Vector3f::normalize(&(*wo + *wi * eta));
+
+        if Vector3f::dot(wo, &wh) * Vector3f::dot(wi, &wh) > 0.0 {
+            return 0.0;
+        }
+
+        // change of variables from the half vector to wi
+        let sqrt_denom = Vector3f::dot(wo, &wh) + eta * Vector3f::dot(wi, &wh);
+        let dwh_dwi = (eta * eta * Vector3f::dot(wi, &wh) / (sqrt_denom * sqrt_denom)).abs();
+
+        self.distribution.pdf(wo, &wh) * dwh_dwi
+    }
+}
+
+impl MicrofacetTransmission {
+    pub fn new(t: &Spectrum, distribution: Box<dyn MicrofacetDistribution>, eta_a: f32, eta_b: f32, mode: TransportMode) -> Self {
+        Self {
+            t: *t,
+            distribution,
+            fresnel: FresnelDielectric {
+                eta_i: Spectrum::init_one(eta_a as f64),
+                eta_t: Spectrum::init_one(eta_b as f64)
+            },
+            eta_a, eta_b,
+            mode
+        }
+    }
+}
@@ -0,0 +1,54 @@
+use crate::common::*;
+
+pub struct MirrorMaterial {
+    kr: Arc<dyn Texture<Spectrum>>
+}
+
+// Constructor
+fn create_mirror_material(prop_list: PropertyList) -> LeadObject {
+    let mut material = MirrorMaterial::new();
+    material.init(prop_list);
+    LeadObject::Material(Arc::new(material))
+}
+
+impl Material for MirrorMaterial {
+    fn compute_scattering_functions(&self, its: &mut SurfaceInteraction, _mode: TransportMode, _allow_multiple_lobes: bool) {
+        let mut bsdf = BSDF::new(its, 1.0);
+
+        let kr = self.kr.evaluate(its);
+        if !kr.is_black() {
+            bsdf.add(Box::new(SpecularReflection::new(&kr, Fresnel::NoOp(FresnelNoOp {}))));
+        }
+
+        its.bsdf = Some(Arc::new(bsdf));
+    }
+}
+
+impl LeadObjectTrait for MirrorMaterial {
+    fn init(&mut self, prop_list: PropertyList) {
+        self.kr = Arc::new(ConstantTexture::new(prop_list.get_rgb("reflectance", Spectrum::init_one(0.9))));
+    }
+
+    fn activate(&mut self) { }
+
+    fn add_child(&mut self, child: &mut LeadObject) {
+        match child {
+            LeadObject::SpectrumTexture(name, texture) if name == "reflectance" => self.kr = texture.clone(),
+            _ => println!("Struct MirrorMaterial does not take a child of class {}", child.to_string())
+        }
+    }
+
+    fn to_string(&self) -> String {
+        format!("MirrorMaterial[reflectance: {}]", self.kr.to_string())
+    }
+}
+
+impl MirrorMaterial {
+    pub fn new() -> Self {
+        Self {
+            kr: Arc::new(ConstantTexture::new(Spectrum::init_one(0.9)))
+        }
+    }
+}
+
+register_struct!("mirror", create_mirror_material);
@@ -1,15 +1,37 @@
 pub mod material;
 pub mod bxdf;
+pub mod bsdf;
 pub mod fresnel;
 pub mod specular_reflection;
 pub mod specular_transmission;
 pub mod specular_fresnel;
 pub mod lambertian_reflection;
+pub mod oren_nayar;
+pub mod microfacet;
+pub mod microfacet_reflection;
+pub mod microfacet_transmission;
+pub mod matte_material;
+pub mod mirror_material;
+pub mod glass_material;
+pub mod metal_material;
+pub mod plastic_material;
+pub mod interface_material;
 
 pub use material::Material;
 pub use bxdf::{BxDF, BxDFType};
+pub use bsdf::{BSDF, MAX_BXDFS};
 pub use fresnel::{Fresnel, FresnelConductor, FresnelDielectric, FresnelNoOp, fr_conductor, fr_dielectric};
 pub use specular_reflection::SpecularReflection;
 pub use specular_transmission::SpecularTransmission;
 pub use specular_fresnel::FresnelSpecular;
-pub use lambertian_reflection::LambertianReflection;
\ No newline at end of file
+pub use lambertian_reflection::LambertianReflection;
+pub use oren_nayar::OrenNayar;
+pub use microfacet::{MicrofacetDistribution, BeckmannDistribution, TrowbridgeReitzDistribution, MicrofacetRoughness, create_microfacet_distribution, roughness_to_alpha};
+pub use microfacet_reflection::MicrofacetReflection;
+pub use microfacet_transmission::MicrofacetTransmission;
+pub use matte_material::MatteMaterial;
+pub use mirror_material::MirrorMaterial;
+pub use glass_material::GlassMaterial;
+pub use metal_material::MetalMaterial;
+pub use plastic_material::PlasticMaterial;
+pub use interface_material::InterfaceMaterial;
\ No newline at end of file
@@ -0,0 +1,47 @@
+use crate::common::*;
+
+// Rough diffuse reflection, sigma is the standard deviation of the microfacet angle in degrees
+pub struct OrenNayar {
+    r: Spectrum,
+    a: f32,
+    b: f32
+}
+
+impl BxDF for OrenNayar {
+    fn bxdf_type(&self) -> u32 {
+        BxDFType::BSDFReflection.to_u32() | BxDFType::BSDFDiffuse.to_u32()
+    }
+
+    fn f(&self, wo: &Vector3f, wi: &Vector3f) -> Spectrum {
+        let sin_theta_i = Frame::sin_theta(wi);
+        let sin_theta_o = Frame::sin_theta(wo);
+
+        // cosine of the azimuthal difference, only meaningful away from the pole
+        let mut max_cos = 0f32;
+        if sin_theta_i > 1e-4 && sin_theta_o > 1e-4 {
+            let d_cos = Frame::cos_phi(wi) * Frame::cos_phi(wo) + Frame::sin_phi(wi) * Frame::sin_phi(wo);
+            max_cos = d_cos.max(0.0);
+        }
+
+        let (sin_alpha, tan_beta) = if Frame::abs_cos_theta(wi) > Frame::abs_cos_theta(wo) {
+            (sin_theta_o, sin_theta_i / Frame::abs_cos_theta(wi))
+        } else {
+            (sin_theta_i, sin_theta_o / Frame::abs_cos_theta(wo))
+        };
+
+        self.r * (M_INV_PI * (self.a + self.b * max_cos * sin_alpha * tan_beta))
+    }
+}
+
+impl OrenNayar {
+    pub fn new(r: &Spectrum, sigma: f32) -> Self {
+        let sigma = sigma.to_radians();
+        let sigma2 = sigma * sigma;
+
+        Self {
+            r: *r,
+            a: 1.0 - sigma2 / (2.0 * (sigma2 + 0.33)),
+            b: 0.45 * sigma2 / (sigma2 + 0.09)
+        }
+    }
+}
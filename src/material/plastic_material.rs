@@ -0,0 +1,84 @@
+use crate::common::*;
+
+// Diffuse base under a dielectric coat, glossy unless the roughness is zero
+pub struct PlasticMaterial {
+    kd: Arc<dyn Texture<Spectrum>>,
+    ks: Arc<dyn Texture<Spectrum>>,
+    eta: f32,
+    roughness: MicrofacetRoughness
+}
+
+// Constructor
+fn create_plastic_material(prop_list: PropertyList) -> LeadObject {
+    let mut material = PlasticMaterial::new();
+    material.init(prop_list);
+    LeadObject::Material(Arc::new(material))
+}
+
+impl Material for PlasticMaterial {
+    fn compute_scattering_functions(&self, its: &mut SurfaceInteraction, _mode: TransportMode, _allow_multiple_lobes: bool) {
+        let mut bsdf = BSDF::new(its, 1.0);
+
+        let kd = self.kd.evaluate(its);
+        if !kd.is_black() {
+            bsdf.add(Box::new(LambertianReflection::new(&kd)));
+        }
+
+        let ks = self.ks.evaluate(its);
+        if !ks.is_black() {
+            let fresnel = FresnelDielectric {
+                eta_i: Spectrum::init_one(1.0),
+                eta_t: Spectrum::init_one(self.eta as f64)
+            };
+            match self.roughness.distribution(its) {
+                Some(distribution) => bsdf.add(Box::new(MicrofacetReflection::new(&ks, distribution, Fresnel::Dielectric(fresnel)))),
+                None => bsdf.add(Box::new(SpecularReflection::new(&ks, Fresnel::Dielectric(fresnel))))
+            }
+        }
+
+        its.bsdf = Some(Arc::new(bsdf));
+    }
+}
+
+impl LeadObjectTrait for PlasticMaterial {
+    fn init(&mut self, prop_list: PropertyList) {
+        self.kd = Arc::new(ConstantTexture::new(prop_list.get_rgb("diffuse_reflectance", Spectrum::init_one(0.25))));
+        self.ks = Arc::new(ConstantTexture::new(prop_list.get_rgb("specular_reflectance", Spectrum::init_one(0.25))));
+        self.eta = prop_list.get_float("eta", 1.5);
+        self.roughness.init(&prop_list, 0.1);
+    }
+
+    fn activate(&mut self) { }
+
+    fn add_child(&mut self, child: &mut LeadObject) {
+        if self.roughness.add_child(child) {
+            return;
+        }
+
+        match child {
+            LeadObject::SpectrumTexture(name, texture) if name == "diffuse_reflectance" => self.kd = texture.clone(),
+            LeadObject::SpectrumTexture(name, texture) if name == "specular_reflectance" => self.ks = texture.clone(),
+            _ => println!("Struct PlasticMaterial does not take a child of class {}", child.to_string())
+        }
+    }
+
+    fn to_string(&self) -> String {
+        format!(
+            "PlasticMaterial[diffuse_reflectance: {}, specular_reflectance: {}, eta: {}, roughness: {}]",
+            self.kd.to_string(), self.ks.to_string(), self.eta, self.roughness.to_string()
+        )
+    }
+}
+
+impl PlasticMaterial {
+    pub fn new() -> Self {
+        Self {
+            kd: Arc::new(ConstantTexture::new(Spectrum::init_one(0.25))),
+            ks: Arc::new(ConstantTexture::new(Spectrum::init_one(0.25))),
+            eta: 1.5,
+            roughness: MicrofacetRoughness::new(0.1)
+        }
+    }
+}
+
+register_struct!("plastic", create_plastic_material);
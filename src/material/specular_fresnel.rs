@@ -14,16 +14,39 @@ impl BxDF for FresnelSpecular {
         BxDFType::BSDFTtransmission.to_u32() | BxDFType::BSDFReflection.to_u32() | BxDFType::BSDFSpecular.to_u32()
     }
 
-    fn sample_f(&self, _wo: &Vector3f, _wi: &mut Vector3f, _sample: &Point2f, _pdf: &mut f32, _sampled_type: Option<BxDFType>) -> Spectrum {
-        // TODO this in chapter 14
-        Spectrum::init_one(0.0)
+    // Picks reflection or refraction proportionally to the fresnel term
+    fn sample_f(&self, wo: &Vector3f, wi: &mut Vector3f, sample: &Point2f, pdf: &mut f32, _sampled_type: Option<BxDFType>) -> Spectrum {
+        let f = self.fresnel.evaluate(Frame::cos_theta(wo))[0] as f32;
+
+        if sample.x() < f {
+            *wi = Vector3f::init([-wo.x(), -wo.y(), wo.z()]);
+            *pdf = f;
+
+            return self.r * (f / Frame::abs_cos_theta(wi));
+        }
+
+        let entering = Frame::cos_theta(wo) > 0.0;
+        let eta_i = if entering { self.eta_a } else { self.eta_b };
+        let eta_t = if entering { self.eta_b } else { self.eta_a };
+
+        if !SpecularTransmission::refract(wo, &Normal3f::faceforward(&Normal3f::init([0.0, 0.0, 1.0]), wo), eta_i / eta_t, wi) {
+            return Spectrum::init_one(0.0);
+        }
+
+        let mut ft = self.t * (1.0 - f);
+        if let TransportMode::Radiance = self.mode {
+            ft = ft * (eta_i * eta_i / (eta_t * eta_t));
+        }
+        *pdf = 1.0 - f;
+
+        ft / Frame::abs_cos_theta(wi)
     }
 
     fn f(&self, _wo: &Vector3f, _wi: &Vector3f) -> Spectrum {
         Spectrum::init_one(0.0)
     }
 
-    fn pdf(&self, _wi: &Vector3f, _wo: &Vector3f) -> f32 {
+    fn pdf(&self, _wo: &Vector3f, _wi: &Vector3f) -> f32 {
         0.0
     }
 }
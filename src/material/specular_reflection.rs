@@ -22,7 +22,7 @@ impl BxDF for SpecularReflection {
         Spectrum::init_one(0.0)
     }
 
-    fn pdf(&self, _wi: &Vector3f, _wo: &Vector3f) -> f32 {
+    fn pdf(&self, _wo: &Vector3f, _wi: &Vector3f) -> f32 {
         0.0
     }
 }
@@ -17,7 +17,7 @@ impl BxDF for SpecularTransmission {
         let eta_i = if entering { self.eta_a } else { self.eta_b };
         let eta_t = if entering { self.eta_b } else { self.eta_a };
 
-        if !self.refract(wo, &Normal3f::faceforward(&Normal3f::init([0.0, 0.0, 1.0]), wo), eta_i / eta_t, wi) {
+        if !Self::refract(wo, &Normal3f::faceforward(&Normal3f::init([0.0, 0.0, 1.0]), wo), eta_i / eta_t, wi) {
             return Spectrum::init_one(0.0);
         }
 
@@ -36,7 +36,7 @@ impl BxDF for SpecularTransmission {
         Spectrum::init_one(0.0)
     }
 
-    fn pdf(&self, _wi: &Vector3f, _wo: &Vector3f) -> f32 {
+    fn pdf(&self, _wo: &Vector3f, _wi: &Vector3f) -> f32 {
         0.0
     }
 }
@@ -54,11 +54,11 @@ impl SpecularTransmission {
         }
     }
 
-    fn refract(&self, wi: &Vector3f, n: &Normal3f, eta: f32, wt: &mut Vector3f) -> bool {
+    pub fn refract(wi: &Vector3f, n: &Normal3f, eta: f32, wt: &mut Vector3f) -> bool {
         let cos_theta_i = Normal3f::dot(&n, &wi);
         let sin2_theta_i = 0f32.max(1.0 - cos_theta_i*cos_theta_i);
         let sin2_theta_t = eta * eta * sin2_theta_i;
-        if sin2_theta_t >- 1.0 {
+        if sin2_theta_t >= 1.0 {
             return false;
         }
 
@@ -1,17 +1,29 @@
 use quick_xml::events::{BytesStart, Event};
 use quick_xml::Reader;
-use std::collections::HashMap;
+use std::cell::RefCell;
+use std::collections::{HashMap, HashSet};
 use std::fs::File;
 use std::io::BufReader;
 use std::path::Path;
 
 use crate::common::*;
 
-pub struct Parser;
+pub struct Parser {
+    // Objects declared with an id, to be used later through <ref id="..."/>
+    named_objects: RefCell<HashMap<String, LeadObject>>,
+    // Ids of shapes already handed to a parent, the scene takes a shape apart so it can not have two
+    referenced_shapes: RefCell<HashSet<String>>,
+    // Hierarchies declared with <object name="...">, to be placed through <instance ref="..."/>
+    objects: RefCell<HashMap<String, Arc<dyn Primitive>>>
+}
 
 impl Parser {
     pub fn new() -> Self {
-        Self
+        Self {
+            named_objects: RefCell::new(HashMap::new()),
+            referenced_shapes: RefCell::new(HashSet::new()),
+            objects: RefCell::new(HashMap::new())
+        }
     }
 
     pub fn parse_file<P: AsRef<Path>>(&self, filename: P) -> Result<LeadObject, String> {
@@ -33,6 +45,8 @@ impl Parser {
             }
             buf.clear();
         }
+        // the named objects are all in the tree by now, holding on to them would keep them shared
+        self.named_objects.borrow_mut().clear();
         
         match root_node {
             Some(root) => Ok(root),
@@ -47,6 +61,35 @@ impl Parser {
     ) -> Result<LeadObject, String> {
         let mut node_type = String::from_utf8_lossy(start.name().into_inner()).into_owned();
         let attributes = self.get_attributes(start).unwrap();
+        let (mut prop_list, mut children) = self.parse_children(reader, start)?;
+
+        if attributes.contains_key("type") {
+            node_type = attributes.get("type").unwrap().to_string();
+        }
+
+        self.add_attribute_properties(&attributes, &mut prop_list);
+        let motion = prop_list.get_animated_transform();
+        let mut obj = create_lead_object(&node_type, prop_list)?;
+        for child in children.iter_mut() {
+            obj.add_child(child);
+        }
+
+        // Shapes are built where they are at the start, moving ones are then wrapped to follow their keyframes
+        if motion.is_animated() {
+            obj = match obj {
+                LeadObject::Shape(shape) => LeadObject::Shape(Arc::new(AnimatedShape::new(shape, motion))),
+                obj => obj
+            };
+        }
+        if let Some(name) = attributes.get("name") {
+            obj.set_name(name);
+        }
+
+        return Ok(obj);
+    }
+
+    // Properties and child objects of a node, up to its end
+    fn parse_children(&self, reader: &mut Reader<BufReader<File>>, start: &BytesStart) -> Result<(PropertyList, Vec<LeadObject>), String> {
         let mut prop_list: PropertyList = PropertyList::new();
         let mut children: Vec<LeadObject> = Vec::new();
         let mut buf: Vec<u8>= Vec::new();
@@ -55,7 +98,22 @@ impl Parser {
             match reader.read_event_into(&mut buf) {
                 Ok(Event::Start(ref e)) => {
                     let child_name = String::from_utf8_lossy(e.name().into_inner()).into_owned();
-                    if PropertyList::is_property_type(&child_name) {
+                    if child_name == "ref" {
+                        children.push(self.get_reference(&self.get_attributes(e).unwrap())?);
+                        reader.read_to_end_into(e.name(), &mut Vec::new()).map_err(|e| e.to_string())?;
+                    } else if child_name == "object" {
+                        self.declare_object(reader, e)?;
+                    } else if child_name == "instance" {
+                        let (instance_props, instance_children) = self.parse_children(reader, e)?;
+                        if !instance_children.is_empty() {
+                            println!("Instances only take transforms, their other children are ignored");
+                        }
+                        children.push(self.create_instance(&self.get_attributes(e).unwrap(), &instance_props)?);
+                    } else if child_name == "transform" {
+                        let time = self.get_keyframe_time(&self.get_attributes(e).unwrap())?;
+                        let keyframe = self.parse_keyframe(reader, e)?;
+                        prop_list.add_keyframe(time, &keyframe);
+                    } else if PropertyList::is_property_type(&child_name) {
                         let child_attrs = self.get_attributes(e).unwrap();
                         if PropertyList::is_property_valid(&child_name, &child_attrs) {
                             prop_list.add_property(&child_name, &child_attrs);
@@ -63,20 +121,37 @@ impl Parser {
                             panic!("Non-valid property defined!");
                         }
                     } else {
+                        let child_attrs = self.get_attributes(e).unwrap();
                         let child = self.traverse_node(reader, e)?;
-                        children.push(child);
+                        self.add_or_declare(child, &child_attrs, &mut children)?;
                     }
                 }
-                // Since empty nodes can only be properties, we are only checking for that
+                // Empty nodes are either properties or objects that only use their defaults
                 Ok(Event::Empty(ref e)) => {
                     let child_name = String::from_utf8_lossy(e.name().into_inner()).into_owned();
-                    if PropertyList::is_property_type(&child_name) {
-                        let child_attrs = self.get_attributes(e).unwrap();
+                    let child_attrs = self.get_attributes(e).unwrap();
+                    if child_name == "ref" {
+                        children.push(self.get_reference(&child_attrs)?);
+                    } else if child_name == "instance" {
+                        children.push(self.create_instance(&child_attrs, &PropertyList::new())?);
+                    } else if child_name == "transform" {
+                        // an empty block leaves the object where it is
+                        prop_list.add_keyframe(self.get_keyframe_time(&child_attrs)?, &PropertyList::new());
+                    } else if PropertyList::is_property_type(&child_name) {
                         if PropertyList::is_property_valid(&child_name, &child_attrs) {
                             prop_list.add_property(&child_name, &child_attrs);
                         } else {
                             panic!("Non-valid property defined!");
                         }
+                    } else {
+                        let child_type = child_attrs.get("type").cloned().unwrap_or(child_name);
+                        let mut child_props = PropertyList::new();
+                        self.add_attribute_properties(&child_attrs, &mut child_props);
+                        let mut child = create_lead_object(&child_type, child_props)?;
+                        if let Some(name) = child_attrs.get("name") {
+                            child.set_name(name);
+                        }
+                        self.add_or_declare(child, &child_attrs, &mut children)?;
                     }
                 }
                 Ok(Event::End(ref e)) if e.name() == start.name() => break,
@@ -87,16 +162,150 @@ impl Parser {
             buf.clear();
         }
 
-        if attributes.contains_key("type") {
-            node_type = attributes.get("type").unwrap().to_string();
+        Ok((prop_list, children))
+    }
+
+    // The shapes of an object are built into a hierarchy of their own once, which all its instances share
+    fn declare_object(&self, reader: &mut Reader<BufReader<File>>, start: &BytesStart) -> Result<(), String> {
+        let attrs = self.get_attributes(start).unwrap();
+        let name = match attrs.get("name") {
+            Some(name) => name.clone(),
+            None => return Err("Object without a name!".to_string())
+        };
+        let (_, children) = self.parse_children(reader, start)?;
+
+        let mut lights = Vec::new();
+        let mut primitives: Vec<Arc<dyn Primitive>> = Vec::new();
+        for child in children {
+            match child {
+                LeadObject::Shape(mut shape) => {
+                    let shape_ref = Arc::get_mut(&mut shape)
+                        .ok_or(format!("A shape of object {} is shared and can not be built", name))?;
+                    if shape_ref.area_light().take().is_some() {
+                        println!("Instanced shapes can not be area lights, the emitter in object {} is ignored", name);
+                    }
+                    primitives.append(&mut create_primitives(shape, &mut lights)?);
+                },
+                LeadObject::Primitive(instance) => primitives.push(instance),
+                child => println!("Object {} does not take a child of class {}", name, child.to_string())
+            }
+        }
+        if primitives.is_empty() {
+            return Err(format!("Object {} has no shapes", name));
         }
 
-        let mut obj = create_lead_object(&node_type, prop_list);
-        for child in children.iter_mut() {
-            obj.add_child(child);
+        // always a BVH with the default settings, the scene's <accel> may come after its objects and
+        // is not applied to them
+        let mut bvh: BVHAccel = BVHAccel::new();
+        bvh.create(primitives);
+        if self.objects.borrow_mut().insert(name.clone(), Arc::new(bvh)).is_some() {
+            return Err(format!("Object {} is declared twice", name));
         }
 
-        return Ok(obj);
+        Ok(())
+    }
+
+    fn create_instance(&self, attrs: &HashMap<String, String>, prop_list: &PropertyList) -> Result<LeadObject, String> {
+        let name = match attrs.get("ref") {
+            Some(name) => name,
+            None => return Err("Instance without a ref!".to_string())
+        };
+        let object = match self.objects.borrow().get(name) {
+            Some(object) => object.clone(),
+            None => return Err(format!("Instance of undeclared object {}", name))
+        };
+
+        let motion = prop_list.get_animated_transform();
+        let instance: Arc<dyn Primitive> = if motion.is_animated() {
+            // put where it is at the start, which the animated primitive moves it from
+            let at_start = TransformedPrimitive::new(object, motion.start_transform().clone());
+            Arc::new(AnimatedPrimitive::new(Arc::new(at_start), motion))
+        } else {
+            Arc::new(TransformedPrimitive::new(object, prop_list.get_transform()))
+        };
+
+        Ok(LeadObject::Primitive(instance))
+    }
+
+    // Reads the transforms inside a <transform time="..."> block
+    fn parse_keyframe(&self, reader: &mut Reader<BufReader<File>>, start: &BytesStart) -> Result<PropertyList, String> {
+        let mut keyframe = PropertyList::new();
+        let mut buf: Vec<u8> = Vec::new();
+
+        loop {
+            match reader.read_event_into(&mut buf) {
+                Ok(Event::Start(ref e)) | Ok(Event::Empty(ref e)) => {
+                    let child_name = String::from_utf8_lossy(e.name().into_inner()).into_owned();
+                    let child_attrs = self.get_attributes(e).unwrap();
+                    if !matches!(child_name.as_str(), "scale" | "translate" | "rotate") {
+                        return Err(format!("Transform blocks can only hold scale, translate and rotate, got {}", child_name));
+                    }
+                    if !PropertyList::is_property_valid(&child_name, &child_attrs) {
+                        panic!("Non-valid property defined!");
+                    }
+                    keyframe.add_property(&child_name, &child_attrs);
+                }
+                Ok(Event::End(ref e)) if e.name() == start.name() => break,
+                Ok(Event::Eof) => panic!("Unexpected EOF while parsing file!"),
+                Err(e) => return Err(format!("Error reading XML: {:?}", e)),
+                _ => {}
+            }
+            buf.clear();
+        }
+
+        Ok(keyframe)
+    }
+
+    fn get_keyframe_time(&self, attrs: &HashMap<String, String>) -> Result<f32, String> {
+        match attrs.get("time") {
+            Some(time) => time.parse::<f32>().map_err(|_| format!("Invalid transform time {}", time)),
+            None => Err("Transform block without a time!".to_string())
+        }
+    }
+
+    // Objects with an id are kept aside for references instead of being given to their parent
+    fn add_or_declare(&self, child: LeadObject, attrs: &HashMap<String, String>, children: &mut Vec<LeadObject>) -> Result<(), String> {
+        match attrs.get("id") {
+            Some(id) => {
+                if self.named_objects.borrow_mut().insert(id.clone(), child).is_some() {
+                    return Err(format!("Object id {} is declared twice", id));
+                }
+            },
+            None => children.push(child)
+        }
+
+        Ok(())
+    }
+
+    // Files can be given directly on the node, e.g. <shape type="obj" filename="..."/>
+    fn add_attribute_properties(&self, attrs: &HashMap<String, String>, prop_list: &mut PropertyList) {
+        if let Some(filename) = attrs.get("filename") {
+            prop_list.set_string("filename".to_string(), filename.clone());
+        }
+    }
+
+    fn get_reference(&self, attrs: &HashMap<String, String>) -> Result<LeadObject, String> {
+        let id = match attrs.get("id") {
+            Some(id) => id,
+            None => return Err("Reference without an id!".to_string())
+        };
+
+        if self.referenced_shapes.borrow().contains(id) {
+            return Err(format!("Shape {} is referenced twice, use <object> and <instance> to place a shape more than once", id));
+        }
+        let mut obj = match self.named_objects.borrow().get(id) {
+            Some(obj) => obj.clone(),
+            None => return Err(format!("Reference to undeclared id {}", id))
+        };
+        if let LeadObject::Shape(_) = obj {
+            self.named_objects.borrow_mut().remove(id);
+            self.referenced_shapes.borrow_mut().insert(id.clone());
+        }
+        if let Some(name) = attrs.get("name") {
+            obj.set_name(name);
+        }
+
+        Ok(obj)
     }
 
     fn get_attributes(&self, start: &BytesStart) -> Result<HashMap<String, String>, String> {
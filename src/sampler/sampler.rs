@@ -32,6 +32,7 @@ pub trait Sampler: LeadObjectTrait {
     fn get_camera_sample(&mut self, p_raster: &Point2f) -> CameraSample {
         CameraSample {
             p_film: *p_raster + self.get_2d(),
+            time: self.get_1d(),
             p_lens: self.get_2d()
         }
     }
@@ -78,6 +78,31 @@ impl Sampler for StratifiedSampler {
         self.set_array_2d_offset(0usize);
     }
 
+    // PixelSampler::start_next_sample calls back into Sampler::start_next_sample, so it cannot be delegated to here
+    fn start_next_sample(&mut self) -> bool {
+        self.current_1d_dim = 0usize;
+        self.current_2d_dim = 0usize;
+
+        // what start_next_sample does
+        self.set_array_1d_offset(0usize);
+        self.set_array_2d_offset(0usize);
+        self.set_current_pixel_sample_index(self.current_pixel_sample_index + 1);
+
+        self.current_pixel_sample_index < self.samples_per_pixel
+    }
+
+    fn set_sample_number(&mut self, sample_num: usize) -> bool {
+        self.current_1d_dim = 0usize;
+        self.current_2d_dim = 0usize;
+
+        // what set_sample_number does
+        self.set_array_1d_offset(0usize);
+        self.set_array_2d_offset(0usize);
+        self.set_current_pixel_sample_index(sample_num);
+
+        self.current_pixel_sample_index < self.samples_per_pixel
+    }
+
     fn get_1d(&mut self) -> f32 {
         PixelSampler::get_1d(self)
     }
@@ -166,7 +191,7 @@ impl StratifiedSampler {
 
     fn shuffle<T: Copy>(samp: &mut [T], count: usize, n_dimensions: usize, rng: &mut RNG) {
         for i in 0..count {
-            let other = i + rng.uniform_u32_bounded((count - 1) as u32) as usize;
+            let other = i + rng.uniform_u32_bounded((count - i) as u32) as usize;
             for j in 0..n_dimensions {
                 samp.swap(n_dimensions * i + j, n_dimensions * other + j);
             }
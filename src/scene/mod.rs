@@ -1,4 +1,5 @@
 pub mod scene_trait;
 pub use scene_trait::SceneTrait;
 
-pub mod scene;
\ No newline at end of file
+pub mod scene;
+pub use scene::{Scene, create_primitives};
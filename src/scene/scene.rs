@@ -2,7 +2,17 @@ use crate::common::*;
 
 pub struct Scene{
     shapes: Vec<Arc<dyn Shape>>,
+    instances: Vec<Arc<dyn Primitive>>,
     accel: Arc<dyn Aggregate>,
+
+    camera: Option<Arc<dyn Camera>>,
+    sampler: Option<Arc<dyn Sampler>>,
+    film: Option<Arc<Film>>,
+    integrator: Option<Arc<dyn Integrator>>,
+    lights: Vec<Arc<dyn Light>>,
+
+    // where the render statistics are written as JSON, if anywhere
+    stats_file: String,
 }
 
 // Constructor
@@ -14,26 +24,71 @@ fn create_scene(prop_list: PropertyList) -> LeadObject {
 
 // imp lead object
 impl LeadObjectTrait for Scene {
-    fn init(&mut self, _prop_list: PropertyList) { }
+    fn init(&mut self, prop_list: PropertyList) {
+        self.stats_file = prop_list.get_string("stats_file", "");
+    }
 
     fn activate(&mut self) {
         let mut primitives: Vec<Arc<dyn Primitive>> = Vec::new();
 
         while let Some(cur_shape) = self.shapes.pop() {
-            let prim: GeometricPrimitive = GeometricPrimitive::init_shape(cur_shape);
-            primitives.push(Arc::new(prim));
+            // activate can not fail, a shape that can not be built is left out of the scene
+            match create_primitives(cur_shape, &mut self.lights) {
+                Ok(mut shape_primitives) => primitives.append(&mut shape_primitives),
+                Err(e) => println!("{}, the shape is ignored", e)
+            }
         }
+        // instances are built already, they only have to go into the hierarchy
+        primitives.append(&mut self.instances);
+
+        // a BVH unless the scene declared another <accel>
+        Arc::get_mut(&mut self.accel).expect("Acceleration structure is shared before the scene is built!").create(primitives);
 
-        if primitives.len() > 0 {
-            let mut bvh: BVHAccel = BVHAccel::new();
-            bvh.create(primitives, 120, SplitMethod::SAH);
-            self.accel = Arc::new(bvh);
+        // The camera needs the film before it can set up its raster space
+        if let Some(camera) = &mut self.camera {
+            let camera = Arc::get_mut(camera).unwrap();
+            if let Some(film) = &self.film {
+                camera.set_film(film.clone());
+            }
+            camera.activate();
         }
+
+        // Lights may need the finished scene, e.g. for its bounds
+        for light in &self.lights {
+            light.preprocess(self);
+        }
+
+        // The integrator owns the sampler from here on, it gets mutated while rendering
+        let mut integrator = match self.integrator.take() {
+            Some(integrator) => integrator,
+            None => match create_lead_object("path", PropertyList::new()) {
+                Ok(LeadObject::Integrator(integrator)) => integrator,
+                _ => panic!("Could not create the default integrator!")
+            }
+        };
+        {
+            let integrator = Arc::get_mut(&mut integrator).unwrap();
+            if let Some(camera) = &self.camera {
+                integrator.add_child(&mut LeadObject::Camera(camera.clone()));
+            }
+            if let Some(sampler) = self.sampler.take() {
+                integrator.add_child(&mut LeadObject::Sampler(sampler));
+            }
+            integrator.activate();
+        }
+        self.integrator = Some(integrator);
     }
 
     fn add_child(&mut self, child: &mut LeadObject) {
         match child {
             LeadObject::Shape(shape) => self.shapes.push(shape.clone()),
+            LeadObject::Primitive(instance) => self.instances.push(instance.clone()),
+            LeadObject::Camera(camera) => self.camera = Some(camera.clone()),
+            LeadObject::Sampler(sampler) => self.sampler = Some(sampler.clone()),
+            LeadObject::Film(film) => self.film = Some(film.clone()),
+            LeadObject::Integrator(integrator) => self.integrator = Some(integrator.clone()),
+            LeadObject::Light(light) => self.lights.push(light.clone()),
+            LeadObject::Aggregate(accel) => self.accel = accel.clone(),
             _ => println!("Struct Scene does not take a child of class {}", child.to_string())
         };
     }
@@ -41,19 +96,59 @@ impl LeadObjectTrait for Scene {
     fn to_string(&self) -> String {
         let mut shapes_part = String::new();
         for prim in self.accel.primitives() {
-            shapes_part += &prim.shape().unwrap().to_string();
-            shapes_part += "\n";
+            // moving shapes are hidden inside their AnimatedPrimitive
+            if let Some(shape) = prim.shape() {
+                shapes_part += &shape.to_string();
+                shapes_part += "\n";
+            }
+        };
+
+        let mut lights_part = String::new();
+        for light in &self.lights {
+            lights_part += &light.to_string();
+            lights_part += "\n";
+        };
+
+        let camera_part = match &self.camera {
+            Some(camera) => camera.to_string(),
+            None => String::from("None")
+        };
+        let integrator_part = match &self.integrator {
+            Some(integrator) => integrator.to_string(),
+            None => String::from("None")
+        };
+        let film_part = match self.get_film() {
+            Some(film) => film.to_string(),
+            None => String::from("None")
         };
         
         format!(
-            "Scene[\n  shapes: {{\n{}\n  }}\n]",
-            indent(&shapes_part, 4)
+            "Scene[\n  shapes: {{\n{}\n  }},\n  accel: {},\n  camera: {},\n  film: {},\n  integrator: {},\n  lights: {{\n{}\n  }}\n]",
+            indent(&shapes_part, 4), self.accel.to_string(), camera_part, film_part, integrator_part, indent(&lights_part, 4)
         )
     }
 }
 
 impl SceneTrait for Scene {
+    fn render(&mut self) {
+        // Taken out while rendering so the integrator can be mutated while the scene is borrowed
+        let mut integrator = match self.integrator.take() {
+            Some(integrator) => integrator,
+            None => panic!("Scene has no integrator to render with!")
+        };
 
+        Arc::get_mut(&mut integrator).unwrap().render(self);
+        self.integrator = Some(integrator);
+
+        // rendering happens on this thread, so its statistics are all there is
+        report_thread_stats();
+        println!("{}", stats_to_string());
+        if !self.stats_file.is_empty() {
+            if let Err(e) = std::fs::write(&self.stats_file, stats_to_json()) {
+                println!("Could not write statistics to {}: {}", self.stats_file, e);
+            }
+        }
+    }
 }
 
 impl Primitive for Scene {
@@ -65,7 +160,7 @@ impl Primitive for Scene {
         self.accel.intersect_p(ray)
     }
 
-    fn compute_scattering_functions(&self, _its: &SurfaceInteraction, _mode: TransportMode, _allow_multiple_lobes: bool) {
+    fn compute_scattering_functions(&self, _its: &mut SurfaceInteraction, _mode: TransportMode, _allow_multiple_lobes: bool) {
         panic!("Not implemented yet!");
     }
 
@@ -78,7 +173,7 @@ impl Primitive for Scene {
     }
 
     fn world_bound(&self) -> Bounds3f {
-        panic!("Not implemented yet!");
+        self.accel.world_bound()
     }
 
     fn shape(&self) -> Option<Arc<dyn Shape>> {
@@ -90,14 +185,97 @@ impl Scene{
     pub fn new() -> Self {
         Scene {
             shapes: Vec::new(),
-            accel: Arc::new(BVHAccel::new())
+            instances: Vec::new(),
+            accel: Arc::new(BVHAccel::new()),
+
+            camera: None,
+            sampler: None,
+            film: None,
+            integrator: None,
+            lights: Vec::new(),
+            stats_file: String::new(),
+        }
+    }
+
+    pub fn get_camera(&self) -> Option<Arc<dyn Camera>> {
+        self.camera.clone()
+    }
+
+    // The camera's film wins over the scene's, since a film can also be declared inside the camera
+    pub fn get_film(&self) -> Option<Arc<Film>> {
+        match &self.camera {
+            Some(camera) if camera.film().is_some() => camera.film(),
+            _ => self.film.clone()
+        }
+    }
+
+    pub fn lights(&self) -> &Vec<Arc<dyn Light>> {
+        &self.lights
+    }
+
+    pub fn intersect_packet(&self, rays: &[Ray; PACKET_SIZE], its: &mut [SurfaceInteraction; PACKET_SIZE], n_rays: usize) -> [bool; PACKET_SIZE] {
+        self.accel.intersect_packet(rays, its, n_rays)
+    }
+}
+
+// Primitives for a shape, with the emitters it declared added to the lights
+pub fn create_primitives(mut shape: Arc<dyn Shape>, lights: &mut Vec<Arc<dyn Light>>) -> Result<Vec<Arc<dyn Primitive>>, String> {
+    let shape_ref = Arc::get_mut(&mut shape)
+        .ok_or("A shape is shared and can not be built".to_string())?;
+    // The emitter is taken off the shape so the two do not keep each other alive
+    let area_light = shape_ref.area_light().take();
+    // shapes without a <bsdf> are diffuse, as in pbrt. Only <bsdf type="interface"> lets rays through
+    let material = shape_ref.material().take()
+        .or_else(|| Some(Arc::new(MatteMaterial::new()) as Arc<dyn Material>));
+
+    // moving shapes get a hierarchy of their own, which is moved as a whole
+    if let Some(motion) = shape.motion() {
+        if area_light.is_some() {
+            println!("Moving shapes can not be area lights, the emitter is ignored");
+        }
+
+        let parts: Vec<Arc<dyn Primitive>> = shape.refine().into_iter()
+            .map(|part| {
+                let mut prim = GeometricPrimitive::init_shape(part);
+                prim.material = material.clone();
+                Arc::new(prim) as Arc<dyn Primitive>
+            })
+            .collect();
+        let mut bvh: BVHAccel = BVHAccel::new();
+        bvh.create(parts);
+
+        return Ok(vec![Arc::new(AnimatedPrimitive::new(Arc::new(bvh), motion))]);
+    }
+
+    if !shape.can_intersect() {
+        // every part gets the material, and an emitter of its own
+        let mut primitives: Vec<Arc<dyn Primitive>> = Vec::new();
+        for part in shape.refine() {
+            let mut prim: GeometricPrimitive = GeometricPrimitive::init_shape(part.clone());
+            prim.material = material.clone();
+            if let Some(area_light) = &area_light {
+                let mut area_light = area_light.clone_area_light();
+                Arc::get_mut(&mut area_light).unwrap().set_shape(part);
+                lights.push(area_light.clone());
+                prim.arealight = Some(area_light);
+            }
+
+            primitives.push(Arc::new(prim));
         }
+        return Ok(primitives);
     }
 
-    pub fn get_camera(&self) -> String {
-        String::from("Scene method")
+    let mut prim: GeometricPrimitive = GeometricPrimitive::init_shape(shape.clone());
+    prim.material = material;
+    // the emitter may be a <ref> shared with other shapes, so each shape lights a copy of its own
+    if let Some(area_light) = area_light {
+        let mut area_light = area_light.clone_area_light();
+        Arc::get_mut(&mut area_light).unwrap().set_shape(shape);
+        lights.push(area_light.clone());
+        prim.arealight = Some(area_light);
     }
 
+    Ok(vec![Arc::new(prim)])
 }
 
 register_struct!("scene", create_scene);
\ No newline at end of file
@@ -1,5 +1,5 @@
 use crate::common::*;
 
 pub trait SceneTrait: Primitive + LeadObjectTrait {
-    
+    fn render(&mut self);
 }
\ No newline at end of file
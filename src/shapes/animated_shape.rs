@@ -0,0 +1,84 @@
+use crate::common::*;
+
+// Shape with <transform time="..."> keyframes. The shape itself is built where it is at the start,
+// the scene then puts its parts in an AnimatedPrimitive, so this one is never intersected
+pub struct AnimatedShape {
+    shape: Arc<dyn Shape>,
+    motion: AnimatedTransform,
+}
+
+impl Shape for AnimatedShape {
+    fn object_to_world(&self) -> Transform { self.shape.object_to_world() }
+
+    fn world_to_object(&self) -> Transform { self.shape.world_to_object() }
+
+    fn reverse_orientation(&self) -> bool { self.shape.reverse_orientation() }
+
+    fn transform_swaps_handedness(&self) -> bool { self.shape.transform_swaps_handedness() }
+
+    fn get_object_bounds(&self) -> Bounds3f { self.shape.get_object_bounds() }
+
+    // Everywhere the shape passes while the shutter is open
+    fn get_world_bounds(&self) -> Bounds3f {
+        let start_bounds = &self.motion.start_transform().inverse() * self.shape.get_world_bounds();
+        self.motion.motion_bounds(&start_bounds)
+    }
+
+    fn area(&self) -> f32 { self.shape.area() }
+
+    // The wrapped shape is only shared once the scene is built, until then it can be changed
+    fn area_light(&mut self) -> &mut Option<Arc<dyn AreaLight>> {
+        Arc::get_mut(&mut self.shape).expect("Moving shape is shared before the scene is built!").area_light()
+    }
+
+    fn material(&mut self) -> &mut Option<Arc<dyn Material>> {
+        Arc::get_mut(&mut self.shape).expect("Moving shape is shared before the scene is built!").material()
+    }
+
+    fn can_intersect(&self) -> bool { false }
+
+    fn refine(&self) -> Vec<Arc<dyn Shape>> {
+        if self.shape.can_intersect() {
+            vec![self.shape.clone()]
+        } else {
+            self.shape.refine()
+        }
+    }
+
+    fn motion(&self) -> Option<AnimatedTransform> { Some(self.motion.clone()) }
+
+    fn sample_u(&self, _u: &Point2f, _pdf: &mut f32) -> Box<dyn Interaction> {
+        panic!("Moving shapes can not be sampled, so they can not be area lights!");
+    }
+
+    fn intersect(&self, _ray: &Ray, _t_hit: &mut f32, _its: &mut SurfaceInteraction) -> bool {
+        panic!("Moving shapes have to be turned into an AnimatedPrimitive before they can be intersected!");
+    }
+}
+
+impl LeadObjectTrait for AnimatedShape {
+    fn init(&mut self, _prop_list: PropertyList) { }
+
+    fn add_child(&mut self, child: &mut LeadObject) {
+        match child {
+            LeadObject::AreaLight(area_light) => *self.area_light() = Some(area_light.clone()),
+            LeadObject::Material(material) => *self.material() = Some(material.clone()),
+            _ => println!("Struct AnimatedShape does not take a child of class {}", child.to_string())
+        }
+    }
+
+    fn activate(&mut self) { }
+
+    fn to_string(&self) -> String {
+        format!("animated: [\n  start_time: {}\n  end_time: {}\n  start: \n{}\n  end: \n{}\n  shape: \n{}\n]", self.motion.start_time(), self.motion.end_time(), indent(&self.motion.start_transform().to_string(), 4), indent(&self.motion.end_transform().to_string(), 4), indent(&self.shape.to_string(), 4))
+    }
+}
+
+impl AnimatedShape {
+    pub fn new(shape: Arc<dyn Shape>, motion: AnimatedTransform) -> Self {
+        Self {
+            shape,
+            motion,
+        }
+    }
+}
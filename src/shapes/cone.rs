@@ -0,0 +1,187 @@
+use crate::common::*;
+
+// Cone around the z axis with its base at z = 0 and its tip at z = height
+pub struct Cone {
+    height: f32,
+    radius: f32,
+    phi_max: f32,
+
+    object_to_world: Transform, world_to_object: Transform,
+    bounding_box: Bounds3f,
+    reverse_orientation: bool,
+    area_light: Option<Arc<dyn AreaLight>>,
+    material: Option<Arc<dyn Material>>,
+}
+
+fn create_cone(prop_list: PropertyList) -> LeadObject {
+    let cone = Cone::new(prop_list);
+    LeadObject::Shape(Arc::new(cone))
+}
+
+impl Shape for Cone {
+    fn object_to_world(&self) -> Transform { self.object_to_world.clone() }
+
+    fn world_to_object(&self) -> Transform { self.world_to_object.clone() }
+
+    fn reverse_orientation(&self) -> bool { self.reverse_orientation }
+
+    fn transform_swaps_handedness(&self) -> bool { self.object_to_world.swaps_handedness() }
+
+    fn get_object_bounds(&self) -> Bounds3f { self.bounding_box }
+
+    fn get_world_bounds(&self) -> Bounds3f { &self.object_to_world * self.bounding_box }
+
+    fn area(&self) -> f32 {
+        0.5 * self.phi_max * self.radius * (self.height * self.height + self.radius * self.radius).sqrt()
+    }
+
+    fn area_light(&mut self) -> &mut Option<Arc<dyn AreaLight>> { &mut self.area_light }
+    fn material(&mut self) -> &mut Option<Arc<dyn Material>> { &mut self.material }
+
+    fn sample_u(&self, u: &Point2f, pdf: &mut f32) -> Box<dyn Interaction> {
+        // the circumference grows linearly towards the base, so the distance from the tip goes with sqrt
+        let s = u.x().sqrt();
+        let r = self.radius * s;
+        let phi = u.y() * self.phi_max;
+        let p_obj = Point3f::init([r * phi.cos(), r * phi.sin(), self.height * (1.0 - s)]);
+
+        let n_obj = Normal3f::init([p_obj.x() * self.height, p_obj.y() * self.height, self.radius * r]);
+        let mut n = Normal3f::normalize(&(&self.object_to_world * n_obj));
+        if self.reverse_orientation {
+            n = n * -1.0;
+        }
+
+        let mut its = SurfaceInteraction::new();
+        its.p = &self.object_to_world * p_obj;
+        its.n = n;
+        its.shading.n = n;
+
+        *pdf = 1.0 / self.area();
+        Box::new(its)
+    }
+
+    fn intersect(&self, ray: &Ray, t_hit: &mut f32, its: &mut SurfaceInteraction) -> bool {
+        let (p, phi, t) = match self.hit(ray) {
+            Some(hit) => hit,
+            None => return false
+        };
+        *t_hit = t;
+
+        let u = phi / self.phi_max;
+        let v = p.z() / self.height;
+        let uv = Point2f::init([u, v]);
+
+        let dpdu = Vector3f::init([-self.phi_max * p.y(), self.phi_max * p.x(), 0.0]);
+        let dpdv = Vector3f::init([-p.x() / (1.0 - v), -p.y() / (1.0 - v), self.height]);
+
+        let d2_pduu = Vector3f::init([p.x(), p.y(), 0.0]) * -self.phi_max * self.phi_max;
+        let d2_pduv = Vector3f::init([p.y(), -p.x(), 0.0]) * (self.phi_max / (1.0 - v));
+        let d2_pdvv = Vector3f::new();
+        let (dndu, dndv) = SurfaceInteraction::normal_derivatives(&dpdu, &dpdv, &d2_pduu, &d2_pduv, &d2_pdvv);
+
+        let d_obj: Vector3f = &self.world_to_object * ray.d;
+        let obj_its = SurfaceInteraction::init(p, uv, -d_obj, dpdu, dpdv, dndu, dndv, t);
+
+        *its = &self.object_to_world * &obj_its;
+        if self.reverse_orientation {
+            its.n = -its.n;
+            its.shading.n = -its.shading.n;
+        }
+
+        true
+    }
+
+    fn intersect_p(&self, ray: &Ray) -> bool {
+        self.hit(ray).is_some()
+    }
+}
+
+impl LeadObjectTrait for Cone {
+    fn init(&mut self, _prop_list: PropertyList) { }
+
+    fn add_child(&mut self, child: &mut LeadObject) {
+        match child {
+            LeadObject::AreaLight(area_light) => self.area_light = Some(area_light.clone()),
+            LeadObject::Material(material) => self.material = Some(material.clone()),
+            _ => println!("Struct Cone does not take a child of class {}", child.to_string())
+        }
+    }
+
+    fn activate(&mut self) { }
+
+    fn to_string(&self) -> String {
+        format!("cone: [\n  height: {}\n  radius: {}\n  phi_max: {}\n  bounding_box: \n{}\n  object_to_world: \n{}\n]", self.height, self.radius, self.phi_max, indent(&(self.get_world_bounds()).to_string(), 4), indent(&self.object_to_world().to_string(), 4))
+    }
+}
+
+impl Cone {
+    pub fn new(prop_list: PropertyList) -> Self {
+        let height = prop_list.get_float("height", 1f32);
+        let radius = prop_list.get_float("radius", 1f32);
+        let phi_max = prop_list.get_float("phi_max", 360f32).clamp(0f32, 360f32).to_radians();
+
+        if height <= 0.0 {
+            panic!("A cone needs a positive height!");
+        }
+
+        let bounding_box = Bounds3f::init(
+            &Point3f::init([-radius, -radius, 0.0]),
+            &Point3f::init([radius, radius, height])
+        );
+
+        let object_to_world = prop_list.get_transform();
+
+        Cone {
+            height,
+            radius,
+            phi_max,
+            world_to_object: object_to_world.inverse(),
+            object_to_world,
+            bounding_box,
+            reverse_orientation: prop_list.get_bool("reverse_orientation", false),
+            area_light: None,
+            material: None,
+        }
+    }
+
+    // Closest hit inside the ray's range and the clipped part of the surface, in object space
+    fn hit(&self, ray: &Ray) -> Option<(Point3f, f32, f32)> {
+        let o_obj: Point3f = &self.world_to_object * ray.o;
+        let d_obj: Vector3f = &self.world_to_object * ray.d;
+
+        let k = (self.radius / self.height) * (self.radius / self.height);
+        let z = o_obj.z() - self.height;
+        let a = d_obj.x() * d_obj.x() + d_obj.y() * d_obj.y() - k * d_obj.z() * d_obj.z();
+        let b = 2.0 * (d_obj.x() * o_obj.x() + d_obj.y() * o_obj.y() - k * d_obj.z() * z);
+        let c = o_obj.x() * o_obj.x() + o_obj.y() * o_obj.y() - k * z * z;
+
+        let mut r_1: Option<f32> = None;
+        let mut r_2: Option<f32> = None;
+        if !Solver::quadratic(a, b, c, &mut r_1, &mut r_2) {
+            return None;
+        }
+
+        for t in [r_1?, r_2?] {
+            if t <= ray.t_min || t > ray.t_max {
+                continue;
+            }
+
+            let p = o_obj + d_obj * t;
+            let mut phi = p.y().atan2(p.x());
+            if phi < 0.0 {
+                phi += 2.0 * M_PI;
+            }
+
+            // the quadric is a double cone, only the part between base and tip counts
+            if p.z() < 0.0 || p.z() > self.height || phi > self.phi_max {
+                continue;
+            }
+
+            return Some((p, phi, t));
+        }
+
+        None
+    }
+}
+
+register_struct!("cone", create_cone);
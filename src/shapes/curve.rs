@@ -0,0 +1,397 @@
+use crate::common::*;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CurveType {
+    // always faces the incoming ray
+    Flat,
+    // faces the ray too, but its normal is bent to look like a tube
+    Cylinder,
+    // oriented by the normals given at both ends
+    Ribbon
+}
+
+// One cubic Bézier shared by all the segments it is split into
+pub struct CurveCommon {
+    pub curve_type: CurveType,
+    pub cp_obj: [Point3f; 4],
+    pub width: [f32; 2],
+    pub n: [Vector3f; 2],
+    pub normal_angle: f32,
+    pub inv_sin_normal_angle: f32,
+    // u range of this Bézier on the whole strand, so uvs run along the hair
+    pub strand_u: [f32; 2],
+
+    pub object_to_world: Transform,
+    pub world_to_object: Transform,
+    pub reverse_orientation: bool,
+}
+
+impl CurveCommon {
+    pub fn new(curve_type: CurveType, cp_obj: [Point3f; 4], width: [f32; 2], n: Option<[Vector3f; 2]>, strand_u: [f32; 2],
+               object_to_world: Transform, reverse_orientation: bool) -> Self {
+        let n = match n {
+            Some([n_0, n_1]) => [Vector3f::normalize(&n_0), Vector3f::normalize(&n_1)],
+            None if curve_type == CurveType::Ribbon => panic!("A ribbon curve needs a normal at both ends!"),
+            None => [Vector3f::new(), Vector3f::new()]
+        };
+        let normal_angle = Vector3f::dot(&n[0], &n[1]).clamp(0.0, 1.0).acos();
+
+        Self {
+            curve_type,
+            cp_obj,
+            width,
+            n,
+            normal_angle,
+            inv_sin_normal_angle: 1.0 / normal_angle.sin(),
+            strand_u,
+            world_to_object: object_to_world.inverse(),
+            object_to_world,
+            reverse_orientation,
+        }
+    }
+
+    fn width_at(&self, u: f32) -> f32 {
+        self.width[0] + (self.width[1] - self.width[0]) * u
+    }
+
+    // Slerp between the end normals, falling back to a lerp when they are (nearly) the same
+    fn normal_at(&self, u: f32) -> Vector3f {
+        if self.normal_angle < 1e-4 {
+            return Vector3f::normalize(&(self.n[0] * (1.0 - u) + self.n[1] * u));
+        }
+
+        let sin_0 = ((1.0 - u) * self.normal_angle).sin() * self.inv_sin_normal_angle;
+        let sin_1 = (u * self.normal_angle).sin() * self.inv_sin_normal_angle;
+        self.n[0] * sin_0 + self.n[1] * sin_1
+    }
+}
+
+// Splits a curve into 2^split_depth segments, each with its own bounds for the BVH
+pub fn create_curve_segments(common: Arc<CurveCommon>, split_depth: u32) -> Vec<Arc<dyn Shape>> {
+    let n_segments = 1 << split_depth;
+
+    (0..n_segments).map(|i| {
+        let segment: Arc<dyn Shape> = Arc::new(Curve {
+            common: common.clone(),
+            u_min: i as f32 / n_segments as f32,
+            u_max: (i + 1) as f32 / n_segments as f32,
+            area_light: None,
+            material: None,
+        });
+        segment
+    }).collect()
+}
+
+fn lerp_point(t: f32, p_0: &Point3f, p_1: &Point3f) -> Point3f {
+    *p_0 + (*p_1 - *p_0) * t
+}
+
+// Point at u and the tangent there
+fn eval_bezier(cp: &[Point3f; 4], u: f32) -> (Point3f, Vector3f) {
+    let cp_1 = [lerp_point(u, &cp[0], &cp[1]), lerp_point(u, &cp[1], &cp[2]), lerp_point(u, &cp[2], &cp[3])];
+    let cp_2 = [lerp_point(u, &cp_1[0], &cp_1[1]), lerp_point(u, &cp_1[1], &cp_1[2])];
+
+    // the tangent vanishes when control points coincide, the chord is a decent stand in
+    let deriv = if (cp_2[1] - cp_2[0]).length_sqr() > 0.0 {
+        (cp_2[1] - cp_2[0]) * 3.0
+    } else {
+        cp[3] - cp[0]
+    };
+
+    (lerp_point(u, &cp_2[0], &cp_2[1]), deriv)
+}
+
+// Control points of both halves, sharing the middle one
+fn subdivide_bezier(cp: &[Point3f; 4]) -> [Point3f; 7] {
+    let a = lerp_point(0.5, &cp[0], &cp[1]);
+    let b = lerp_point(0.5, &cp[1], &cp[2]);
+    let c = lerp_point(0.5, &cp[2], &cp[3]);
+    let ab = lerp_point(0.5, &a, &b);
+    let bc = lerp_point(0.5, &b, &c);
+
+    [cp[0], a, ab, lerp_point(0.5, &ab, &bc), bc, c, cp[3]]
+}
+
+fn blossom_bezier(cp: &[Point3f; 4], u_0: f32, u_1: f32, u_2: f32) -> Point3f {
+    let a = [lerp_point(u_0, &cp[0], &cp[1]), lerp_point(u_0, &cp[1], &cp[2]), lerp_point(u_0, &cp[2], &cp[3])];
+    let b = [lerp_point(u_1, &a[0], &a[1]), lerp_point(u_1, &a[1], &a[2])];
+
+    lerp_point(u_2, &b[0], &b[1])
+}
+
+struct CurveHit {
+    t: f32,
+    u: f32,
+    v: f32,
+    hit_width: f32,
+    n_hit: Vector3f,
+}
+
+// Part [u_min, u_max] of a cubic Bézier curve
+pub struct Curve {
+    common: Arc<CurveCommon>,
+    u_min: f32,
+    u_max: f32,
+    area_light: Option<Arc<dyn AreaLight>>,
+    material: Option<Arc<dyn Material>>,
+}
+
+impl Shape for Curve {
+    fn object_to_world(&self) -> Transform { self.common.object_to_world.clone() }
+
+    fn world_to_object(&self) -> Transform { self.common.world_to_object.clone() }
+
+    fn reverse_orientation(&self) -> bool { self.common.reverse_orientation }
+
+    fn transform_swaps_handedness(&self) -> bool { self.common.object_to_world.swaps_handedness() }
+
+    fn get_object_bounds(&self) -> Bounds3f {
+        let cp = self.control_points();
+        let mut bounds = Bounds3f::init_one(&cp[0]);
+        for p in &cp[1..] {
+            bounds = Bounds3f::union_pt(&bounds, p);
+        }
+
+        let width = self.common.width_at(self.u_min).max(self.common.width_at(self.u_max));
+        Bounds3f::expand(&bounds, 0.5 * width)
+    }
+
+    fn get_world_bounds(&self) -> Bounds3f { &self.common.object_to_world * self.get_object_bounds() }
+
+    // Length of the control polygon times the average width
+    fn area(&self) -> f32 {
+        let cp = self.control_points();
+        let width = 0.5 * (self.common.width_at(self.u_min) + self.common.width_at(self.u_max));
+        let length: f32 = (0..3).map(|i| (cp[i + 1] - cp[i]).length()).sum();
+
+        length * width
+    }
+
+    fn area_light(&mut self) -> &mut Option<Arc<dyn AreaLight>> { &mut self.area_light }
+    fn material(&mut self) -> &mut Option<Arc<dyn Material>> { &mut self.material }
+
+    fn sample_u(&self, _u: &Point2f, _pdf: &mut f32) -> Box<dyn Interaction> {
+        panic!("Curves can not be sampled, so they can not be area lights!");
+    }
+
+    fn intersect(&self, ray: &Ray, t_hit: &mut f32, its: &mut SurfaceInteraction) -> bool {
+        let ray_obj = &self.common.world_to_object * ray;
+        let (object_to_ray, cp) = match self.to_ray_space(&ray_obj) {
+            Some(ray_space) => ray_space,
+            None => return false
+        };
+        let ray_to_object = object_to_ray.inverse();
+
+        let mut hit: Option<CurveHit> = None;
+        self.recursive_intersect(&ray_obj, &cp, self.u_min, self.u_max, self.max_depth(&cp), false, &mut hit);
+
+        let hit = match hit {
+            Some(hit) => hit,
+            None => return false
+        };
+        *t_hit = hit.t;
+
+        let (_, dpdu) = eval_bezier(&self.common.cp_obj, hit.u);
+        let dpdv = if self.common.curve_type == CurveType::Ribbon {
+            Vector3f::normalize(&Vector3f::cross(&hit.n_hit, &dpdu)) * hit.hit_width
+        } else {
+            // in ray space the width runs across the curve, perpendicular to the ray
+            let dpdu_plane: Vector3f = &object_to_ray * dpdu;
+            let mut dpdv_plane = Vector3f::normalize(&Vector3f::init([-dpdu_plane.y(), dpdu_plane.x(), 0.0])) * hit.hit_width;
+            if self.common.curve_type == CurveType::Cylinder {
+                // tilt the width towards the ray at the edges so the normal wraps around like a tube
+                let theta = -90.0 + 180.0 * hit.v;
+                dpdv_plane = &Transform::rotate(-theta, &dpdu_plane) * dpdv_plane;
+            }
+            &ray_to_object * dpdv_plane
+        };
+
+        let u = self.common.strand_u[0] + (self.common.strand_u[1] - self.common.strand_u[0]) * hit.u;
+        let uv = Point2f::init([u, hit.v]);
+        let obj_its = SurfaceInteraction::init(ray_obj.at(hit.t), uv, -ray_obj.d, dpdu, dpdv, Normal3f::new(), Normal3f::new(), hit.t);
+
+        *its = &self.common.object_to_world * &obj_its;
+        if self.common.reverse_orientation {
+            its.n = -its.n;
+            its.shading.n = -its.shading.n;
+        }
+
+        true
+    }
+
+    fn intersect_p(&self, ray: &Ray) -> bool {
+        let ray_obj = &self.common.world_to_object * ray;
+        let (_, cp) = match self.to_ray_space(&ray_obj) {
+            Some(ray_space) => ray_space,
+            None => return false
+        };
+
+        let mut hit: Option<CurveHit> = None;
+        self.recursive_intersect(&ray_obj, &cp, self.u_min, self.u_max, self.max_depth(&cp), true, &mut hit);
+
+        hit.is_some()
+    }
+}
+
+impl LeadObjectTrait for Curve {
+    fn init(&mut self, _prop_list: PropertyList) { }
+
+    fn add_child(&mut self, child: &mut LeadObject) {
+        match child {
+            LeadObject::AreaLight(area_light) => self.area_light = Some(area_light.clone()),
+            LeadObject::Material(material) => self.material = Some(material.clone()),
+            _ => println!("Struct Curve does not take a child of class {}", child.to_string())
+        }
+    }
+
+    fn activate(&mut self) { }
+
+    fn to_string(&self) -> String {
+        format!("curve: [\n  u_min: {}\n  u_max: {}\n  bounding_box: \n{}\n]", self.u_min, self.u_max, indent(&self.get_world_bounds().to_string(), 4))
+    }
+}
+
+impl Curve {
+    // Control points of just this segment
+    fn control_points(&self) -> [Point3f; 4] {
+        let cp = &self.common.cp_obj;
+        [
+            blossom_bezier(cp, self.u_min, self.u_min, self.u_min),
+            blossom_bezier(cp, self.u_min, self.u_min, self.u_max),
+            blossom_bezier(cp, self.u_min, self.u_max, self.u_max),
+            blossom_bezier(cp, self.u_max, self.u_max, self.u_max),
+        ]
+    }
+
+    // Frame with the ray along +z from the origin, so hits are points of the curve near the z axis.
+    // Returns it with the segment's control points in that frame, or None if the ray misses their bounds
+    fn to_ray_space(&self, ray_obj: &Ray) -> Option<(Transform, [Point3f; 4])> {
+        let cp = self.control_points();
+
+        let mut dx = Vector3f::cross(&ray_obj.d, &(cp[3] - cp[0]));
+        if dx.length_sqr() == 0.0 {
+            let mut dy = Vector3f::new();
+            coordinate_system(&ray_obj.d, &mut dx, &mut dy);
+        }
+        let object_to_ray = Transform::look_at(&ray_obj.o, &(ray_obj.o + ray_obj.d), &dx).inverse();
+
+        let cp = cp.map(|p| &object_to_ray * p);
+        let mut curve_bounds = Bounds3f::init_one(&cp[0]);
+        for p in &cp[1..] {
+            curve_bounds = Bounds3f::union_pt(&curve_bounds, p);
+        }
+        let width = self.common.width_at(self.u_min).max(self.common.width_at(self.u_max));
+        let curve_bounds = Bounds3f::expand(&curve_bounds, 0.5 * width);
+
+        let z_max = ray_obj.d.length() * ray_obj.t_max;
+        let ray_bounds = Bounds3f::init(&Point3f::new(), &Point3f::init([0.0, 0.0, z_max]));
+        if !Bounds3f::overlaps(&curve_bounds, &ray_bounds) {
+            return None;
+        }
+
+        Some((object_to_ray, cp))
+    }
+
+    // Subdivide until the segments are flat enough to be treated as lines, relative to the width
+    fn max_depth(&self, cp: &[Point3f; 4]) -> i32 {
+        let mut l_0 = 0f32;
+        for i in 0..2 {
+            let d = (cp[i] - cp[i + 1]) - (cp[i + 1] - cp[i + 2]);
+            l_0 = l_0.max(d.x().abs()).max(d.y().abs()).max(d.z().abs());
+        }
+
+        let eps = self.common.width[0].max(self.common.width[1]) * 0.05;
+        let r = std::f32::consts::SQRT_2 * 6.0 * l_0 / (8.0 * eps);
+        // log base 4 of r
+        let depth = if r < 1.0 { 0 } else { r.log2().round() as i32 / 2 };
+        depth.clamp(0, 10)
+    }
+
+    // cp are in ray space, keeps the closest hit in `hit`
+    #[allow(clippy::too_many_arguments)]
+    fn recursive_intersect(&self, ray: &Ray, cp: &[Point3f; 4], u_0: f32, u_1: f32, depth: i32, any: bool, hit: &mut Option<CurveHit>) {
+        let ray_length = ray.d.length();
+        let z_max = ray_length * hit.as_ref().map_or(ray.t_max, |hit| hit.t);
+
+        if depth > 0 {
+            let cp_split = subdivide_bezier(cp);
+            let u = [u_0, 0.5 * (u_0 + u_1), u_1];
+
+            for seg in 0..2 {
+                let cps = [cp_split[3 * seg], cp_split[3 * seg + 1], cp_split[3 * seg + 2], cp_split[3 * seg + 3]];
+                let half_width = 0.5 * self.common.width_at(u[seg]).max(self.common.width_at(u[seg + 1]));
+
+                // the curve lies within its control points, so skip halves that can't reach the ray
+                let outside = (0..3).any(|axis| {
+                    let lo = cps.iter().map(|p| p[axis]).fold(INFINITY, f32::min) - half_width;
+                    let hi = cps.iter().map(|p| p[axis]).fold(-INFINITY, f32::max) + half_width;
+                    hi < 0.0 || lo > if axis == 2 { z_max } else { 0.0 }
+                });
+                if outside {
+                    continue;
+                }
+
+                self.recursive_intersect(ray, &cps, u[seg], u[seg + 1], depth - 1, any, hit);
+                if any && hit.is_some() {
+                    return;
+                }
+            }
+
+            return;
+        }
+
+        // the ray has to pass between the perpendiculars at both ends of the segment
+        let edge = (cp[1].y() - cp[0].y()) * -cp[0].y() + cp[0].x() * (cp[0].x() - cp[1].x());
+        if edge < 0.0 {
+            return;
+        }
+        let edge = (cp[2].y() - cp[3].y()) * -cp[3].y() + cp[3].x() * (cp[3].x() - cp[2].x());
+        if edge < 0.0 {
+            return;
+        }
+
+        // closest point to the ray on the line through the segment's ends
+        let segment_x = cp[3].x() - cp[0].x();
+        let segment_y = cp[3].y() - cp[0].y();
+        let denom = segment_x * segment_x + segment_y * segment_y;
+        if denom == 0.0 {
+            return;
+        }
+        let w = (-cp[0].x() * segment_x - cp[0].y() * segment_y) / denom;
+
+        let u = (u_0 + (u_1 - u_0) * w).clamp(u_0, u_1);
+        let mut hit_width = self.common.width_at(u);
+        let mut n_hit = Vector3f::new();
+        if self.common.curve_type == CurveType::Ribbon {
+            // ribbons look thinner when seen from the side
+            n_hit = self.common.normal_at(u);
+            hit_width *= Vector3f::dot(&n_hit, &ray.d).abs() / ray_length;
+        }
+
+        let (pc, dpcdw) = eval_bezier(cp, w.clamp(0.0, 1.0));
+        let pt_curve_dist_2 = pc.x() * pc.x() + pc.y() * pc.y();
+        if pt_curve_dist_2 > hit_width * hit_width * 0.25 {
+            return;
+        }
+        if pc.z() < ray.t_min * ray_length || pc.z() > z_max {
+            return;
+        }
+
+        // v runs across the width, which side depends on where the ray passes the curve
+        let pt_curve_dist = pt_curve_dist_2.sqrt();
+        let edge_func = dpcdw.x() * -pc.y() + pc.x() * dpcdw.y();
+        let v = if edge_func > 0.0 {
+            0.5 + pt_curve_dist / hit_width
+        } else {
+            0.5 - pt_curve_dist / hit_width
+        };
+
+        *hit = Some(CurveHit {
+            t: pc.z() / ray_length,
+            u,
+            v,
+            hit_width,
+            n_hit,
+        });
+    }
+}
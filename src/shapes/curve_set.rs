@@ -0,0 +1,186 @@
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+
+use crate::common::*;
+
+// Control points, widths at both ends and for ribbons the normals at both ends
+type Strand = (Vec<Point3f>, [f32; 2], Option<[Vector3f; 2]>);
+
+// Curves declared inline or read from a curve file, split into segments when the scene is built.
+// A curve file has one strand per line: its 3n + 1 control points, the widths at both ends and,
+// for ribbons, the normals at both ends. Empty lines and lines starting with # are skipped
+pub struct CurveSet {
+    filename: String,
+    curve_type: CurveType,
+    split_depth: u32,
+    curves: Vec<Arc<CurveCommon>>,
+    area_light: Option<Arc<dyn AreaLight>>,
+    material: Option<Arc<dyn Material>>,
+}
+
+fn create_curve_set(prop_list: PropertyList) -> Result<LeadObject, String> {
+    let curves = CurveSet::new(prop_list)?;
+    Ok(LeadObject::Shape(Arc::new(curves)))
+}
+
+impl Shape for CurveSet {
+    fn object_to_world(&self) -> Transform { self.curves[0].object_to_world.clone() }
+
+    fn world_to_object(&self) -> Transform { self.curves[0].world_to_object.clone() }
+
+    fn reverse_orientation(&self) -> bool { self.curves[0].reverse_orientation }
+
+    fn transform_swaps_handedness(&self) -> bool { self.curves[0].object_to_world.swaps_handedness() }
+
+    fn get_object_bounds(&self) -> Bounds3f { &self.world_to_object() * self.get_world_bounds() }
+
+    fn get_world_bounds(&self) -> Bounds3f {
+        self.refine().iter()
+            .map(|segment| segment.get_world_bounds())
+            .reduce(|b_1, b_2| Bounds3f::union(&b_1, &b_2))
+            .unwrap()
+    }
+
+    fn area(&self) -> f32 {
+        self.refine().iter().map(|segment| segment.area()).sum()
+    }
+
+    fn area_light(&mut self) -> &mut Option<Arc<dyn AreaLight>> { &mut self.area_light }
+    fn material(&mut self) -> &mut Option<Arc<dyn Material>> { &mut self.material }
+
+    fn can_intersect(&self) -> bool { false }
+
+    fn refine(&self) -> Vec<Arc<dyn Shape>> {
+        self.curves.iter()
+            .flat_map(|curve| create_curve_segments(curve.clone(), self.split_depth))
+            .collect()
+    }
+
+    fn sample_u(&self, _u: &Point2f, _pdf: &mut f32) -> Box<dyn Interaction> {
+        panic!("Curves can not be sampled, so they can not be area lights!");
+    }
+
+    fn intersect(&self, _ray: &Ray, _t_hit: &mut f32, _its: &mut SurfaceInteraction) -> bool {
+        panic!("CurveSet has to be refined into curve segments before it can be intersected!");
+    }
+}
+
+impl LeadObjectTrait for CurveSet {
+    fn init(&mut self, _prop_list: PropertyList) { }
+
+    fn add_child(&mut self, child: &mut LeadObject) {
+        match child {
+            // curves can not be sampled, which emitters need
+            LeadObject::AreaLight(_) => println!("Curves can not be area lights, the emitter is ignored"),
+            LeadObject::Material(material) => self.material = Some(material.clone()),
+            _ => println!("Struct CurveSet does not take a child of class {}", child.to_string())
+        }
+    }
+
+    fn activate(&mut self) { }
+
+    fn to_string(&self) -> String {
+        format!("curve: [\n  filename: {}\n  type: {:?}\n  curves: {}\n  split_depth: {}\n  bounding_box: \n{}\n]", self.filename, self.curve_type, self.curves.len(), self.split_depth, indent(&self.get_world_bounds().to_string(), 4))
+    }
+}
+
+impl CurveSet {
+    pub fn new(prop_list: PropertyList) -> Result<Self, String> {
+        let curve_type = match prop_list.get_string("type", "flat").as_str() {
+            "flat" => CurveType::Flat,
+            "cylinder" => CurveType::Cylinder,
+            "ribbon" => CurveType::Ribbon,
+            other => return Err(format!("Unknown curve type {other}, expected \"flat\", \"cylinder\" or \"ribbon\""))
+        };
+        let split_depth = prop_list.get_int("split_depth", 3).clamp(0, 10) as u32;
+        let object_to_world = prop_list.get_transform();
+        let reverse_orientation = prop_list.get_bool("reverse_orientation", false);
+
+        let filename = prop_list.get_string("filename", "");
+        let strands = if filename.is_empty() {
+            // a single Bézier given by its properties
+            let cp = ["p0", "p1", "p2", "p3"].iter().map(|name| prop_list.get_point3(name, Point3f::new())).collect();
+            let width = prop_list.get_float("width", 1f32);
+            let width = [prop_list.get_float("width0", width), prop_list.get_float("width1", width)];
+            let n = if curve_type == CurveType::Ribbon {
+                Some([prop_list.get_vector3("n0", Vector3f::init([0.0, 0.0, 1.0])), prop_list.get_vector3("n1", Vector3f::init([0.0, 0.0, 1.0]))])
+            } else {
+                None
+            };
+            vec![(cp, width, n)]
+        } else {
+            Self::load(&filename, curve_type)?
+        };
+
+        // strands longer than one Bézier are split up, with the widths and normals interpolated
+        // along them so that neighbouring pieces meet
+        let mut curves = Vec::new();
+        for (cp, width, n) in strands {
+            let n_beziers = (cp.len() - 1) / 3;
+            for i in 0..n_beziers {
+                let u = [i as f32 / n_beziers as f32, (i + 1) as f32 / n_beziers as f32];
+                let width_i = u.map(|u| width[0] + (width[1] - width[0]) * u);
+                let n_i = n.map(|n| u.map(|u| n[0] * (1.0 - u) + n[1] * u));
+
+                curves.push(Arc::new(CurveCommon::new(
+                    curve_type,
+                    [cp[3 * i], cp[3 * i + 1], cp[3 * i + 2], cp[3 * i + 3]],
+                    width_i, n_i, u,
+                    object_to_world.clone(), reverse_orientation
+                )));
+            }
+        }
+
+        if curves.is_empty() {
+            return Err(format!("Curve file {} does not contain any curves", filename));
+        }
+
+        Ok(Self {
+            filename,
+            curve_type,
+            split_depth,
+            curves,
+            area_light: None,
+            material: None,
+        })
+    }
+
+    fn load(filename: &str, curve_type: CurveType) -> Result<Vec<Strand>, String> {
+        let file = File::open(filename).map_err(|e| format!("{}: {}", filename, e))?;
+
+        let mut strands = Vec::new();
+        for (line_number, line) in BufReader::new(file).lines().enumerate() {
+            let line = line.map_err(|e| format!("{}: {}", filename, e))?;
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let v: Vec<f32> = line.split_whitespace()
+                .map(|t| t.parse::<f32>().map_err(|_| format!("{}:{}: invalid number {}", filename, line_number + 1, t)))
+                .collect::<Result<_, _>>()?;
+
+            // what is left after the widths and normals has to be 3n + 1 points
+            let extra = if curve_type == CurveType::Ribbon { 8 } else { 2 };
+            let n_cp = v.len().saturating_sub(extra) / 3;
+            if v.len() < extra + 12 || !(v.len() - extra).is_multiple_of(3) || !(n_cp - 1).is_multiple_of(3) {
+                return Err(format!("{}:{}: expected 3n + 1 control points followed by {} numbers, got {} numbers", filename, line_number + 1, extra, v.len()));
+            }
+
+            let cp = (0..n_cp).map(|i| Point3f::init([v[3 * i], v[3 * i + 1], v[3 * i + 2]])).collect();
+            let rest = &v[3 * n_cp..];
+            let width = [rest[0], rest[1]];
+            let n = if curve_type == CurveType::Ribbon {
+                Some([Vector3f::init([rest[2], rest[3], rest[4]]), Vector3f::init([rest[5], rest[6], rest[7]])])
+            } else {
+                None
+            };
+
+            strands.push((cp, width, n));
+        }
+
+        Ok(strands)
+    }
+}
+
+register_fallible_struct!("curve", create_curve_set);
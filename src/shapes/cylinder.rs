@@ -0,0 +1,180 @@
+use crate::common::*;
+
+// Cylinder around the z axis
+pub struct Cylinder {
+    radius: f32,
+    z_min: f32, z_max: f32,
+    phi_max: f32,
+
+    object_to_world: Transform, world_to_object: Transform,
+    bounding_box: Bounds3f,
+    reverse_orientation: bool,
+    area_light: Option<Arc<dyn AreaLight>>,
+    material: Option<Arc<dyn Material>>,
+}
+
+fn create_cylinder(prop_list: PropertyList) -> LeadObject {
+    let cylinder = Cylinder::new(prop_list);
+    LeadObject::Shape(Arc::new(cylinder))
+}
+
+impl Shape for Cylinder {
+    fn object_to_world(&self) -> Transform { self.object_to_world.clone() }
+
+    fn world_to_object(&self) -> Transform { self.world_to_object.clone() }
+
+    fn reverse_orientation(&self) -> bool { self.reverse_orientation }
+
+    fn transform_swaps_handedness(&self) -> bool { self.object_to_world.swaps_handedness() }
+
+    fn get_object_bounds(&self) -> Bounds3f { self.bounding_box }
+
+    fn get_world_bounds(&self) -> Bounds3f { &self.object_to_world * self.bounding_box }
+
+    fn area(&self) -> f32 {
+        (self.z_max - self.z_min) * self.radius * self.phi_max
+    }
+
+    fn area_light(&mut self) -> &mut Option<Arc<dyn AreaLight>> { &mut self.area_light }
+    fn material(&mut self) -> &mut Option<Arc<dyn Material>> { &mut self.material }
+
+    fn sample_u(&self, u: &Point2f, pdf: &mut f32) -> Box<dyn Interaction> {
+        let z = self.z_min + u.x() * (self.z_max - self.z_min);
+        let phi = u.y() * self.phi_max;
+        let p_obj = Point3f::init([self.radius * phi.cos(), self.radius * phi.sin(), z]);
+
+        let mut n = Normal3f::normalize(&(&self.object_to_world * Normal3f::init([p_obj.x(), p_obj.y(), 0.0])));
+        if self.reverse_orientation {
+            n = n * -1.0;
+        }
+
+        let mut its = SurfaceInteraction::new();
+        its.p = &self.object_to_world * p_obj;
+        its.n = n;
+        its.shading.n = n;
+
+        *pdf = 1.0 / self.area();
+        Box::new(its)
+    }
+
+    fn intersect(&self, ray: &Ray, t_hit: &mut f32, its: &mut SurfaceInteraction) -> bool {
+        let (p, phi, t) = match self.hit(ray) {
+            Some(hit) => hit,
+            None => return false
+        };
+        *t_hit = t;
+
+        let u = phi / self.phi_max;
+        let v = (p.z() - self.z_min) / (self.z_max - self.z_min);
+        let uv = Point2f::init([u, v]);
+
+        let dpdu = Vector3f::init([-self.phi_max * p.y(), self.phi_max * p.x(), 0.0]);
+        let dpdv = Vector3f::init([0.0, 0.0, self.z_max - self.z_min]);
+
+        let d2_pduu = Vector3f::init([p.x(), p.y(), 0.0]) * -self.phi_max * self.phi_max;
+        let d2_pduv = Vector3f::new();
+        let d2_pdvv = Vector3f::new();
+        let (dndu, dndv) = SurfaceInteraction::normal_derivatives(&dpdu, &dpdv, &d2_pduu, &d2_pduv, &d2_pdvv);
+
+        let d_obj: Vector3f = &self.world_to_object * ray.d;
+        let obj_its = SurfaceInteraction::init(p, uv, -d_obj, dpdu, dpdv, dndu, dndv, t);
+
+        *its = &self.object_to_world * &obj_its;
+        if self.reverse_orientation {
+            its.n = -its.n;
+            its.shading.n = -its.shading.n;
+        }
+
+        true
+    }
+
+    fn intersect_p(&self, ray: &Ray) -> bool {
+        self.hit(ray).is_some()
+    }
+}
+
+impl LeadObjectTrait for Cylinder {
+    fn init(&mut self, _prop_list: PropertyList) { }
+
+    fn add_child(&mut self, child: &mut LeadObject) {
+        match child {
+            LeadObject::AreaLight(area_light) => self.area_light = Some(area_light.clone()),
+            LeadObject::Material(material) => self.material = Some(material.clone()),
+            _ => println!("Struct Cylinder does not take a child of class {}", child.to_string())
+        }
+    }
+
+    fn activate(&mut self) { }
+
+    fn to_string(&self) -> String {
+        format!("cylinder: [\n  radius: {}\n  z_min: {}\n  z_max: {}\n  phi_max: {}\n  bounding_box: \n{}\n  object_to_world: \n{}\n]", self.radius, self.z_min, self.z_max, self.phi_max, indent(&(self.get_world_bounds()).to_string(), 4), indent(&self.object_to_world().to_string(), 4))
+    }
+}
+
+impl Cylinder {
+    pub fn new(prop_list: PropertyList) -> Self {
+        let radius = prop_list.get_float("radius", 1f32);
+        let z_0 = prop_list.get_float("z_min", -1f32);
+        let z_1 = prop_list.get_float("z_max", 1f32);
+        let phi_max = prop_list.get_float("phi_max", 360f32).clamp(0f32, 360f32).to_radians();
+
+        let z_min = z_0.min(z_1);
+        let z_max = z_0.max(z_1);
+        let bounding_box = Bounds3f::init(
+            &Point3f::init([-radius, -radius, z_min]),
+            &Point3f::init([radius, radius, z_max])
+        );
+
+        let object_to_world = prop_list.get_transform();
+
+        Cylinder {
+            radius,
+            z_min, z_max,
+            phi_max,
+            world_to_object: object_to_world.inverse(),
+            object_to_world,
+            bounding_box,
+            reverse_orientation: prop_list.get_bool("reverse_orientation", false),
+            area_light: None,
+            material: None,
+        }
+    }
+
+    // Closest hit inside the ray's range and the clipped part of the surface, in object space
+    fn hit(&self, ray: &Ray) -> Option<(Point3f, f32, f32)> {
+        let o_obj: Point3f = &self.world_to_object * ray.o;
+        let d_obj: Vector3f = &self.world_to_object * ray.d;
+
+        let a = d_obj.x() * d_obj.x() + d_obj.y() * d_obj.y();
+        let b = 2.0 * (d_obj.x() * o_obj.x() + d_obj.y() * o_obj.y());
+        let c = o_obj.x() * o_obj.x() + o_obj.y() * o_obj.y() - self.radius * self.radius;
+
+        let mut r_1: Option<f32> = None;
+        let mut r_2: Option<f32> = None;
+        if !Solver::quadratic(a, b, c, &mut r_1, &mut r_2) {
+            return None;
+        }
+
+        for t in [r_1?, r_2?] {
+            if t <= ray.t_min || t > ray.t_max {
+                continue;
+            }
+
+            let p = o_obj + d_obj * t;
+            let mut phi = p.y().atan2(p.x());
+            if phi < 0.0 {
+                phi += 2.0 * M_PI;
+            }
+
+            if p.z() < self.z_min || p.z() > self.z_max || phi > self.phi_max {
+                continue;
+            }
+
+            return Some((p, phi, t));
+        }
+
+        None
+    }
+}
+
+register_struct!("cylinder", create_cylinder);
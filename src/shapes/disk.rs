@@ -0,0 +1,182 @@
+use crate::common::*;
+
+// Disk or annulus in the plane z = height, facing +z
+pub struct Disk {
+    height: f32,
+    radius: f32, inner_radius: f32,
+    phi_max: f32,
+
+    object_to_world: Transform, world_to_object: Transform,
+    bounding_box: Bounds3f,
+    reverse_orientation: bool,
+    area_light: Option<Arc<dyn AreaLight>>,
+    material: Option<Arc<dyn Material>>,
+}
+
+fn create_disk(prop_list: PropertyList) -> LeadObject {
+    let disk = Disk::new(prop_list);
+    LeadObject::Shape(Arc::new(disk))
+}
+
+impl Shape for Disk {
+    fn object_to_world(&self) -> Transform { self.object_to_world.clone() }
+
+    fn world_to_object(&self) -> Transform { self.world_to_object.clone() }
+
+    fn reverse_orientation(&self) -> bool { self.reverse_orientation }
+
+    fn transform_swaps_handedness(&self) -> bool { self.object_to_world.swaps_handedness() }
+
+    fn get_object_bounds(&self) -> Bounds3f { self.bounding_box }
+
+    fn get_world_bounds(&self) -> Bounds3f { &self.object_to_world * self.bounding_box }
+
+    fn area(&self) -> f32 {
+        0.5 * self.phi_max * (self.radius * self.radius - self.inner_radius * self.inner_radius)
+    }
+
+    fn area_light(&mut self) -> &mut Option<Arc<dyn AreaLight>> { &mut self.area_light }
+    fn material(&mut self) -> &mut Option<Arc<dyn Material>> { &mut self.material }
+
+    fn sample_u(&self, u: &Point2f, pdf: &mut f32) -> Box<dyn Interaction> {
+        // the radius is warped so the samples stay uniform in area
+        let r_inner_2 = self.inner_radius * self.inner_radius;
+        let r = (u.x() * (self.radius * self.radius - r_inner_2) + r_inner_2).sqrt();
+        let phi = u.y() * self.phi_max;
+        let p_obj = Point3f::init([r * phi.cos(), r * phi.sin(), self.height]);
+
+        let mut n = Normal3f::normalize(&(&self.object_to_world * Normal3f::init([0.0, 0.0, 1.0])));
+        if self.reverse_orientation {
+            n = n * -1.0;
+        }
+
+        let mut its = SurfaceInteraction::new();
+        its.p = &self.object_to_world * p_obj;
+        its.n = n;
+        its.shading.n = n;
+
+        *pdf = 1.0 / self.area();
+        Box::new(its)
+    }
+
+    fn intersect(&self, ray: &Ray, t_hit: &mut f32, its: &mut SurfaceInteraction) -> bool {
+        let (p, phi, t) = match self.hit(ray) {
+            Some(hit) => hit,
+            None => return false
+        };
+        *t_hit = t;
+
+        let r_hit = (p.x() * p.x() + p.y() * p.y()).sqrt();
+        let u = phi / self.phi_max;
+        let v = (self.radius - r_hit) / (self.radius - self.inner_radius);
+        let uv = Point2f::init([u, v]);
+
+        let dpdu = Vector3f::init([-self.phi_max * p.y(), self.phi_max * p.x(), 0.0]);
+        // the radial direction is undefined at the center, any direction in the plane will do
+        let dpdv = if r_hit > 0.0 {
+            Vector3f::init([p.x(), p.y(), 0.0]) * ((self.inner_radius - self.radius) / r_hit)
+        } else {
+            Vector3f::init([self.inner_radius - self.radius, 0.0, 0.0])
+        };
+
+        // the disk is flat, so the normal never changes
+        let dndu = Normal3f::new();
+        let dndv = Normal3f::new();
+
+        let d_obj: Vector3f = &self.world_to_object * ray.d;
+        let obj_its = SurfaceInteraction::init(p, uv, -d_obj, dpdu, dpdv, dndu, dndv, t);
+
+        *its = &self.object_to_world * &obj_its;
+        if self.reverse_orientation {
+            its.n = -its.n;
+            its.shading.n = -its.shading.n;
+        }
+
+        true
+    }
+
+    fn intersect_p(&self, ray: &Ray) -> bool {
+        self.hit(ray).is_some()
+    }
+}
+
+impl LeadObjectTrait for Disk {
+    fn init(&mut self, _prop_list: PropertyList) { }
+
+    fn add_child(&mut self, child: &mut LeadObject) {
+        match child {
+            LeadObject::AreaLight(area_light) => self.area_light = Some(area_light.clone()),
+            LeadObject::Material(material) => self.material = Some(material.clone()),
+            _ => println!("Struct Disk does not take a child of class {}", child.to_string())
+        }
+    }
+
+    fn activate(&mut self) { }
+
+    fn to_string(&self) -> String {
+        format!("disk: [\n  height: {}\n  radius: {}\n  inner_radius: {}\n  phi_max: {}\n  bounding_box: \n{}\n  object_to_world: \n{}\n]", self.height, self.radius, self.inner_radius, self.phi_max, indent(&(self.get_world_bounds()).to_string(), 4), indent(&self.object_to_world().to_string(), 4))
+    }
+}
+
+impl Disk {
+    pub fn new(prop_list: PropertyList) -> Self {
+        let height = prop_list.get_float("height", 0f32);
+        let radius = prop_list.get_float("radius", 1f32);
+        let inner_radius = prop_list.get_float("inner_radius", 0f32).clamp(0f32, radius);
+        let phi_max = prop_list.get_float("phi_max", 360f32).clamp(0f32, 360f32).to_radians();
+
+        let bounding_box = Bounds3f::init(
+            &Point3f::init([-radius, -radius, height]),
+            &Point3f::init([radius, radius, height])
+        );
+
+        let object_to_world = prop_list.get_transform();
+
+        Disk {
+            height,
+            radius, inner_radius,
+            phi_max,
+            world_to_object: object_to_world.inverse(),
+            object_to_world,
+            bounding_box,
+            reverse_orientation: prop_list.get_bool("reverse_orientation", false),
+            area_light: None,
+            material: None,
+        }
+    }
+
+    // Hit inside the ray's range and the annulus, in object space
+    fn hit(&self, ray: &Ray) -> Option<(Point3f, f32, f32)> {
+        let o_obj: Point3f = &self.world_to_object * ray.o;
+        let d_obj: Vector3f = &self.world_to_object * ray.d;
+
+        // rays parallel to the disk never hit it
+        if d_obj.z() == 0.0 {
+            return None;
+        }
+
+        let t = (self.height - o_obj.z()) / d_obj.z();
+        if t <= ray.t_min || t > ray.t_max {
+            return None;
+        }
+
+        let p = o_obj + d_obj * t;
+        let dist_2 = p.x() * p.x() + p.y() * p.y();
+        if dist_2 > self.radius * self.radius || dist_2 < self.inner_radius * self.inner_radius {
+            return None;
+        }
+
+        let mut phi = p.y().atan2(p.x());
+        if phi < 0.0 {
+            phi += 2.0 * M_PI;
+        }
+
+        if phi > self.phi_max {
+            return None;
+        }
+
+        Some((p, phi, t))
+    }
+}
+
+register_struct!("disk", create_disk);
@@ -0,0 +1,267 @@
+use crate::common::*;
+
+// Hyperboloid swept by rotating the line from p1 to p2 around the z axis
+pub struct Hyperboloid {
+    p_1: Point3f, p_2: Point3f,
+    z_min: f32, z_max: f32,
+    phi_max: f32,
+    // implicit form a_h * (x^2 + y^2) - c_h * z^2 = 1
+    a_h: f32, c_h: f32,
+    // area along the line, used for both the area and for sampling
+    profile: Distribution1D,
+
+    object_to_world: Transform, world_to_object: Transform,
+    bounding_box: Bounds3f,
+    reverse_orientation: bool,
+    area_light: Option<Arc<dyn AreaLight>>,
+    material: Option<Arc<dyn Material>>,
+}
+
+fn create_hyperboloid(prop_list: PropertyList) -> LeadObject {
+    let hyperboloid = Hyperboloid::new(prop_list);
+    LeadObject::Shape(Arc::new(hyperboloid))
+}
+
+impl Shape for Hyperboloid {
+    fn object_to_world(&self) -> Transform { self.object_to_world.clone() }
+
+    fn world_to_object(&self) -> Transform { self.world_to_object.clone() }
+
+    fn reverse_orientation(&self) -> bool { self.reverse_orientation }
+
+    fn transform_swaps_handedness(&self) -> bool { self.object_to_world.swaps_handedness() }
+
+    fn get_object_bounds(&self) -> Bounds3f { self.bounding_box }
+
+    fn get_world_bounds(&self) -> Bounds3f { &self.object_to_world * self.bounding_box }
+
+    fn area(&self) -> f32 {
+        self.phi_max * self.profile.func_int
+    }
+
+    fn area_light(&mut self) -> &mut Option<Arc<dyn AreaLight>> { &mut self.area_light }
+    fn material(&mut self) -> &mut Option<Arc<dyn Material>> { &mut self.material }
+
+    fn sample_u(&self, u: &Point2f, pdf: &mut f32) -> Box<dyn Interaction> {
+        let mut v_pdf = 0f32;
+        let mut offset = 0usize;
+        let v = self.profile.sample_continuous(u.x(), &mut v_pdf, &mut offset);
+        let phi = u.y() * self.phi_max;
+
+        let p_obj = self.point(v, phi);
+        let (dpdu, dpdv) = self.partials(&p_obj, phi);
+        let n_obj = Vector3f::cross(&dpdu, &dpdv);
+
+        let mut n = Normal3f::normalize(&(&self.object_to_world * Normal3f::init([n_obj.x(), n_obj.y(), n_obj.z()])));
+        if self.reverse_orientation {
+            n = n * -1.0;
+        }
+
+        let mut its = SurfaceInteraction::new();
+        its.p = &self.object_to_world * p_obj;
+        its.n = n;
+        its.shading.n = n;
+
+        *pdf = 1.0 / self.area();
+        Box::new(its)
+    }
+
+    fn intersect(&self, ray: &Ray, t_hit: &mut f32, its: &mut SurfaceInteraction) -> bool {
+        let (p, phi, t) = match self.hit(ray) {
+            Some(hit) => hit,
+            None => return false
+        };
+        *t_hit = t;
+
+        let u = phi / self.phi_max;
+        let v = (p.z() - self.p_1.z()) / (self.p_2.z() - self.p_1.z());
+        let uv = Point2f::init([u, v]);
+
+        let (dpdu, dpdv) = self.partials(&p, phi);
+
+        let d2_pduu = Vector3f::init([p.x(), p.y(), 0.0]) * -self.phi_max * self.phi_max;
+        let d2_pduv = Vector3f::init([-dpdv.y(), dpdv.x(), 0.0]) * self.phi_max;
+        let d2_pdvv = Vector3f::new();
+        let (dndu, dndv) = SurfaceInteraction::normal_derivatives(&dpdu, &dpdv, &d2_pduu, &d2_pduv, &d2_pdvv);
+
+        let d_obj: Vector3f = &self.world_to_object * ray.d;
+        let obj_its = SurfaceInteraction::init(p, uv, -d_obj, dpdu, dpdv, dndu, dndv, t);
+
+        *its = &self.object_to_world * &obj_its;
+        if self.reverse_orientation {
+            its.n = -its.n;
+            its.shading.n = -its.shading.n;
+        }
+
+        true
+    }
+
+    fn intersect_p(&self, ray: &Ray) -> bool {
+        self.hit(ray).is_some()
+    }
+}
+
+impl LeadObjectTrait for Hyperboloid {
+    fn init(&mut self, _prop_list: PropertyList) { }
+
+    fn add_child(&mut self, child: &mut LeadObject) {
+        match child {
+            LeadObject::AreaLight(area_light) => self.area_light = Some(area_light.clone()),
+            LeadObject::Material(material) => self.material = Some(material.clone()),
+            _ => println!("Struct Hyperboloid does not take a child of class {}", child.to_string())
+        }
+    }
+
+    fn activate(&mut self) { }
+
+    fn to_string(&self) -> String {
+        format!("hyperboloid: [\n  p1: {}\n  p2: {}\n  phi_max: {}\n  bounding_box: \n{}\n  object_to_world: \n{}\n]", self.p_1.to_string(), self.p_2.to_string(), self.phi_max, indent(&(self.get_world_bounds()).to_string(), 4), indent(&self.object_to_world().to_string(), 4))
+    }
+}
+
+impl Hyperboloid {
+    pub fn new(prop_list: PropertyList) -> Self {
+        let p_1 = prop_list.get_point3("p1", Point3f::init([0.0, 0.0, 0.0]));
+        let p_2 = prop_list.get_point3("p2", Point3f::init([1.0, 1.0, 1.0]));
+        let phi_max = prop_list.get_float("phi_max", 360f32).clamp(0f32, 360f32).to_radians();
+
+        if p_1.z() == p_2.z() {
+            panic!("A hyperboloid needs p1 and p2 at different heights!");
+        }
+
+        let z_min = p_1.z().min(p_2.z());
+        let z_max = p_1.z().max(p_2.z());
+        let r_max = (p_1.x() * p_1.x() + p_1.y() * p_1.y()).sqrt().max((p_2.x() * p_2.x() + p_2.y() * p_2.y()).sqrt());
+        let bounding_box = Bounds3f::init(
+            &Point3f::init([-r_max, -r_max, z_min]),
+            &Point3f::init([r_max, r_max, z_max])
+        );
+
+        let (a_h, c_h) = Self::implicit_coefficients(&p_1, &p_2);
+        let profile = Self::profile(&p_1, &p_2);
+
+        let object_to_world = prop_list.get_transform();
+
+        Hyperboloid {
+            p_1, p_2,
+            z_min, z_max,
+            phi_max,
+            a_h, c_h,
+            profile,
+            world_to_object: object_to_world.inverse(),
+            object_to_world,
+            bounding_box,
+            reverse_orientation: prop_list.get_bool("reverse_orientation", false),
+            area_light: None,
+            material: None,
+        }
+    }
+
+    // Solves a_h and c_h from p2 and a third point further along the line, stepping further out
+    // while the system is degenerate
+    fn implicit_coefficients(p_1: &Point3f, p_2: &Point3f) -> (f32, f32) {
+        // the solve divides by the z of the second point, so use the one off the xy plane
+        let (p_1, p_2) = if p_2.z() == 0.0 { (p_2, p_1) } else { (p_1, p_2) };
+        let (p_1, p_2) = ([p_1.x() as f64, p_1.y() as f64, p_1.z() as f64], [p_2.x() as f64, p_2.y() as f64, p_2.z() as f64]);
+
+        let xy_2 = p_2[0] * p_2[0] + p_2[1] * p_2[1];
+        let z_2 = p_2[2] * p_2[2];
+        for i in 1..64 {
+            let s = 2.0 * i as f64;
+            let pp = [p_1[0] + s * (p_2[0] - p_1[0]), p_1[1] + s * (p_2[1] - p_1[1]), p_1[2] + s * (p_2[2] - p_1[2])];
+            let xy_1 = pp[0] * pp[0] + pp[1] * pp[1];
+            let z_1 = pp[2] * pp[2];
+
+            let a_h = (1.0 / xy_1 - z_1 / (xy_1 * z_2)) / (1.0 - (xy_2 * z_1) / (xy_1 * z_2));
+            let c_h = (a_h * xy_2 - 1.0) / z_2;
+            if a_h.is_finite() && c_h.is_finite() {
+                return (a_h as f32, c_h as f32);
+            }
+        }
+
+        panic!("Could not find the implicit form of the hyperboloid, is the line going through the z axis?");
+    }
+
+    // Area of the full revolution per unit of phi at points along the line, |dp/du x dp/dv| / phi_max
+    fn profile(p_1: &Point3f, p_2: &Point3f) -> Distribution1D {
+        let d = *p_2 - *p_1;
+        let (dx, dy, dz) = (d.x() as f64, d.y() as f64, d.z() as f64);
+        let (x_1, y_1) = (p_1.x() as f64, p_1.y() as f64);
+
+        // r(v)^2 = a v^2 + b v + c, so the squared length is a quadratic in v too
+        let a = dx * dx + dy * dy;
+        let b = 2.0 * (x_1 * dx + y_1 * dy);
+        let c = x_1 * x_1 + y_1 * y_1;
+        let q_a = a * a + dz * dz * a;
+        let q_b = b * (a + dz * dz);
+        let q_c = b * b / 4.0 + dz * dz * c;
+
+        let n = 256;
+        let func: Vec<f32> = (0..n).map(|i| {
+            let v = (i as f64 + 0.5) / n as f64;
+            (q_a * v * v + q_b * v + q_c).max(0.0).sqrt() as f32
+        }).collect();
+
+        Distribution1D::new(&func)
+    }
+
+    fn point(&self, v: f32, phi: f32) -> Point3f {
+        let p = self.p_1 + (self.p_2 - self.p_1) * v;
+        let (sin_phi, cos_phi) = phi.sin_cos();
+        Point3f::init([p.x() * cos_phi - p.y() * sin_phi, p.x() * sin_phi + p.y() * cos_phi, p.z()])
+    }
+
+    fn partials(&self, p: &Point3f, phi: f32) -> (Vector3f, Vector3f) {
+        let (sin_phi, cos_phi) = phi.sin_cos();
+        let d = self.p_2 - self.p_1;
+
+        let dpdu = Vector3f::init([-self.phi_max * p.y(), self.phi_max * p.x(), 0.0]);
+        let dpdv = Vector3f::init([d.x() * cos_phi - d.y() * sin_phi, d.x() * sin_phi + d.y() * cos_phi, d.z()]);
+        (dpdu, dpdv)
+    }
+
+    // Closest hit inside the ray's range and the clipped part of the surface, in object space.
+    // phi is measured from the point on the line at the hit's height
+    fn hit(&self, ray: &Ray) -> Option<(Point3f, f32, f32)> {
+        let o_obj: Point3f = &self.world_to_object * ray.o;
+        let d_obj: Vector3f = &self.world_to_object * ray.d;
+
+        let a = self.a_h * (d_obj.x() * d_obj.x() + d_obj.y() * d_obj.y()) - self.c_h * d_obj.z() * d_obj.z();
+        let b = 2.0 * (self.a_h * (d_obj.x() * o_obj.x() + d_obj.y() * o_obj.y()) - self.c_h * d_obj.z() * o_obj.z());
+        let c = self.a_h * (o_obj.x() * o_obj.x() + o_obj.y() * o_obj.y()) - self.c_h * o_obj.z() * o_obj.z() - 1.0;
+
+        let mut r_1: Option<f32> = None;
+        let mut r_2: Option<f32> = None;
+        if !Solver::quadratic(a, b, c, &mut r_1, &mut r_2) {
+            return None;
+        }
+
+        for t in [r_1?, r_2?] {
+            if t <= ray.t_min || t > ray.t_max {
+                continue;
+            }
+
+            let p = o_obj + d_obj * t;
+            if p.z() < self.z_min || p.z() > self.z_max {
+                continue;
+            }
+
+            let v = (p.z() - self.p_1.z()) / (self.p_2.z() - self.p_1.z());
+            let p_r = self.p_1 + (self.p_2 - self.p_1) * v;
+            let mut phi = (p_r.x() * p.y() - p.x() * p_r.y()).atan2(p.x() * p_r.x() + p.y() * p_r.y());
+            if phi < 0.0 {
+                phi += 2.0 * M_PI;
+            }
+
+            if phi > self.phi_max {
+                continue;
+            }
+
+            return Some((p, phi, t));
+        }
+
+        None
+    }
+}
+
+register_struct!("hyperboloid", create_hyperboloid);
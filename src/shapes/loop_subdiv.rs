@@ -0,0 +1,302 @@
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::BufReader;
+
+use crate::common::*;
+use crate::shapes::obj_mesh::ObjMesh;
+use crate::shapes::ply_mesh::read_ply;
+
+// Vertices opposite to each edge, one per face on it
+type EdgeFaces = HashMap<(usize, usize), Vec<usize>>;
+// One ring of every vertex and whether it is on a boundary
+type Rings = Vec<(Vec<usize>, bool)>;
+
+// Loop subdivision surface of a triangle cage read from an obj or ply file. The cage is
+// subdivided `levels` times, pushed to the limit surface and split into triangles like a mesh
+pub struct LoopSubdiv {
+    filename: String,
+    levels: u32,
+    mesh: Arc<TriangleMesh>,
+    area_light: Option<Arc<dyn AreaLight>>,
+    material: Option<Arc<dyn Material>>,
+}
+
+fn create_loop_subdiv(prop_list: PropertyList) -> Result<LeadObject, String> {
+    let surface = LoopSubdiv::new(prop_list)?;
+    Ok(LeadObject::Shape(Arc::new(surface)))
+}
+
+impl Shape for LoopSubdiv {
+    fn object_to_world(&self) -> Transform { self.mesh.object_to_world.clone() }
+
+    fn world_to_object(&self) -> Transform { self.mesh.world_to_object.clone() }
+
+    fn reverse_orientation(&self) -> bool { self.mesh.reverse_orientation }
+
+    fn transform_swaps_handedness(&self) -> bool { self.mesh.object_to_world.swaps_handedness() }
+
+    fn get_object_bounds(&self) -> Bounds3f { &self.mesh.world_to_object * self.mesh.world_bounds() }
+
+    fn get_world_bounds(&self) -> Bounds3f { self.mesh.world_bounds() }
+
+    fn area(&self) -> f32 { self.mesh.area() }
+
+    fn area_light(&mut self) -> &mut Option<Arc<dyn AreaLight>> { &mut self.area_light }
+    fn material(&mut self) -> &mut Option<Arc<dyn Material>> { &mut self.material }
+
+    fn can_intersect(&self) -> bool { false }
+
+    fn refine(&self) -> Vec<Arc<dyn Shape>> {
+        create_triangles(self.mesh.clone())
+    }
+
+    fn sample_u(&self, _u: &Point2f, _pdf: &mut f32) -> Box<dyn Interaction> {
+        panic!("LoopSubdiv has to be refined into triangles before it can be sampled!");
+    }
+
+    fn intersect(&self, _ray: &Ray, _t_hit: &mut f32, _its: &mut SurfaceInteraction) -> bool {
+        panic!("LoopSubdiv has to be refined into triangles before it can be intersected!");
+    }
+}
+
+impl LeadObjectTrait for LoopSubdiv {
+    fn init(&mut self, _prop_list: PropertyList) { }
+
+    fn add_child(&mut self, child: &mut LeadObject) {
+        match child {
+            LeadObject::AreaLight(area_light) => self.area_light = Some(area_light.clone()),
+            LeadObject::Material(material) => self.material = Some(material.clone()),
+            _ => println!("Struct LoopSubdiv does not take a child of class {}", child.to_string())
+        }
+    }
+
+    fn activate(&mut self) { }
+
+    fn to_string(&self) -> String {
+        format!("loopsubdiv: [\n  filename: {}\n  levels: {}\n  mesh: {}\n  bounding_box: \n{}\n]", self.filename, self.levels, self.mesh.to_string(), indent(&self.get_world_bounds().to_string(), 4))
+    }
+}
+
+impl LoopSubdiv {
+    pub fn new(prop_list: PropertyList) -> Result<Self, String> {
+        let filename = prop_list.get_string("filename", "");
+        let levels = prop_list.get_int("levels", 3).max(0) as u32;
+
+        let (vertex_indices, p) = if filename.ends_with(".obj") {
+            let (vertex_indices, p, _, _) = ObjMesh::load(&filename)?;
+            (vertex_indices, p)
+        } else if filename.ends_with(".ply") {
+            let file = File::open(&filename).map_err(|e| format!("{}: {}", filename, e))?;
+            let (vertex_indices, p, _, _) = read_ply(BufReader::new(file)).map_err(|e| format!("{}: {}", filename, e))?;
+            (vertex_indices, p)
+        } else {
+            return Err(format!("A loopsubdiv shape needs an obj or ply control mesh, got \"{}\"", filename));
+        };
+
+        // the loaders split vertices at uv and normal seams, which would tear the surface apart
+        let (mut vertex_indices, mut p) = Self::weld(&vertex_indices, &p);
+        for _ in 0..levels {
+            (vertex_indices, p) = Self::subdivide(&vertex_indices, &p).map_err(|e| format!("{}: {}", filename, e))?;
+        }
+        let (p, n) = Self::limit(&vertex_indices, &p).map_err(|e| format!("{}: {}", filename, e))?;
+
+        let mesh = TriangleMesh::new(
+            prop_list.get_transform(), vertex_indices, p, n, Vec::new(), Vec::new(),
+            prop_list.get_bool("reverse_orientation", false)
+        );
+
+        Ok(Self {
+            filename,
+            levels,
+            mesh: Arc::new(mesh),
+            area_light: None,
+            material: None,
+        })
+    }
+
+    // Merges vertices at exactly the same position
+    fn weld(vertex_indices: &[usize], p: &[Point3f]) -> (Vec<usize>, Vec<Point3f>) {
+        let mut welded: HashMap<[u32; 3], usize> = HashMap::new();
+        let mut new_p = Vec::new();
+        let remap: Vec<usize> = p.iter().map(|p| {
+            *welded.entry([p.x().to_bits(), p.y().to_bits(), p.z().to_bits()]).or_insert_with(|| {
+                new_p.push(*p);
+                new_p.len() - 1
+            })
+        }).collect();
+
+        (vertex_indices.iter().map(|&i| remap[i]).collect(), new_p)
+    }
+
+    // Faces on every edge, given by the vertex opposite to it, and the one ring of every vertex.
+    // Rings are ordered counter clockwise, boundary rings start and end on the boundary
+    fn topology(vertex_indices: &[usize], n_vertices: usize) -> Result<(EdgeFaces, Rings), String> {
+        let mut edges: EdgeFaces = HashMap::new();
+        // next and previous vertex of every face around a vertex
+        let mut fans: Vec<Vec<(usize, usize)>> = vec![Vec::new(); n_vertices];
+
+        for face in vertex_indices.chunks(3) {
+            for i in 0..3 {
+                let (v, next, prev) = (face[i], face[(i + 1) % 3], face[(i + 2) % 3]);
+                let opposite = edges.entry((v.min(next), v.max(next))).or_default();
+                opposite.push(prev);
+                if opposite.len() > 2 {
+                    return Err(format!("the edge between vertices {} and {} is shared by more than two faces", v, next));
+                }
+                fans[v].push((next, prev));
+            }
+        }
+
+        let rings = fans.iter().enumerate().map(|(v, fan)| {
+            if fan.is_empty() {
+                return Ok((Vec::new(), false));
+            }
+
+            // on a boundary exactly one face has no neighbour before it
+            let start = fan.iter().find(|(next, _)| !fan.iter().any(|(_, prev)| prev == next));
+            let boundary = start.is_some();
+            let mut current = *start.unwrap_or(&fan[0]);
+
+            let mut ring = vec![current.0];
+            for _ in 0..fan.len() {
+                ring.push(current.1);
+                match fan.iter().find(|(next, _)| *next == current.1) {
+                    Some(&face) if ring.len() <= fan.len() => current = face,
+                    _ => break
+                }
+            }
+            // going all the way around comes back to the first vertex
+            if !boundary {
+                ring.pop();
+            }
+
+            let expected = if boundary { fan.len() + 1 } else { fan.len() };
+            if ring.len() != expected || (!boundary && current.1 != ring[0]) {
+                return Err(format!("the faces around vertex {} do not form a single fan", v));
+            }
+
+            Ok((ring, boundary))
+        }).collect::<Result<Rings, String>>()?;
+
+        Ok((edges, rings))
+    }
+
+    fn subdivide(vertex_indices: &[usize], p: &[Point3f]) -> Result<(Vec<usize>, Vec<Point3f>), String> {
+        let (edges, rings) = Self::topology(vertex_indices, p.len())?;
+
+        // existing vertices move towards their neighbours
+        let mut new_p: Vec<Point3f> = rings.iter().enumerate().map(|(v, (ring, boundary))| {
+            if ring.is_empty() {
+                p[v]
+            } else if *boundary {
+                Self::weight_boundary(p, v, ring, 1.0 / 8.0)
+            } else if ring.len() == 6 {
+                Self::weight_one_ring(p, v, ring, 1.0 / 16.0)
+            } else {
+                Self::weight_one_ring(p, v, ring, Self::beta(ring.len()))
+            }
+        }).collect();
+
+        // and every edge gets a new vertex, placed in face order so the result doesn't depend on hashing
+        let mut edge_vertices: HashMap<(usize, usize), usize> = HashMap::new();
+        let mut new_indices = Vec::with_capacity(4 * vertex_indices.len());
+        for face in vertex_indices.chunks(3) {
+            let e: Vec<usize> = (0..3).map(|i| {
+                let (a, b) = (face[i], face[(i + 1) % 3]);
+                let key = (a.min(b), a.max(b));
+                *edge_vertices.entry(key).or_insert_with(|| {
+                    let opposite = &edges[&key];
+                    let pos = if opposite.len() == 2 {
+                        (p[a] + p[b]) * (3.0 / 8.0) + (p[opposite[0]] + p[opposite[1]]) * (1.0 / 8.0)
+                    } else {
+                        (p[a] + p[b]) * 0.5
+                    };
+                    new_p.push(pos);
+                    new_p.len() - 1
+                })
+            }).collect();
+
+            // one triangle at each corner and one in the middle
+            new_indices.extend_from_slice(&[face[0], e[0], e[2]]);
+            new_indices.extend_from_slice(&[face[1], e[1], e[0]]);
+            new_indices.extend_from_slice(&[face[2], e[2], e[1]]);
+            new_indices.extend_from_slice(&[e[0], e[1], e[2]]);
+        }
+
+        Ok((new_indices, new_p))
+    }
+
+    // Positions on the limit surface and the normals there
+    fn limit(vertex_indices: &[usize], p: &[Point3f]) -> Result<(Vec<Point3f>, Vec<Normal3f>), String> {
+        let (_, rings) = Self::topology(vertex_indices, p.len())?;
+
+        let p_limit = rings.iter().enumerate().map(|(v, (ring, boundary))| {
+            if ring.is_empty() {
+                p[v]
+            } else if *boundary {
+                Self::weight_boundary(p, v, ring, 1.0 / 5.0)
+            } else {
+                Self::weight_one_ring(p, v, ring, Self::gamma(ring.len()))
+            }
+        }).collect();
+
+        let n = rings.iter().enumerate().map(|(v, (ring, boundary))| {
+            let ring: Vec<Vector3f> = ring.iter().map(|&i| p[i] - p[v]).collect();
+            let valence = ring.len();
+            if valence < 2 {
+                return Normal3f::new();
+            }
+
+            let (s, t) = if !boundary {
+                let mut s = Vector3f::new();
+                let mut t = Vector3f::new();
+                for (j, r) in ring.iter().enumerate() {
+                    let angle = 2.0 * M_PI * j as f32 / valence as f32;
+                    s += *r * angle.cos();
+                    t += *r * angle.sin();
+                }
+                (s, t)
+            } else {
+                // along the boundary, and a weighted stencil into the surface
+                let s = ring[0] - ring[valence - 1];
+                let t = match valence {
+                    2 => ring[0] + ring[1],
+                    3 => ring[1],
+                    4 => ring[0] * -1.0 + ring[1] * 2.0 + ring[2] * 2.0 - ring[3],
+                    _ => {
+                        let theta = M_PI / (valence - 1) as f32;
+                        let mut t = (ring[0] + ring[valence - 1]) * theta.sin();
+                        for (k, r) in ring.iter().enumerate().take(valence - 1).skip(1) {
+                            t += *r * ((2.0 * theta.cos() - 2.0) * (k as f32 * theta).sin());
+                        }
+                        t * -1.0
+                    }
+                };
+                (s, t)
+            };
+
+            let n = Vector3f::cross(&s, &t);
+            Normal3f::init([n.x(), n.y(), n.z()])
+        }).collect();
+
+        Ok((p_limit, n))
+    }
+
+    fn beta(valence: usize) -> f32 {
+        if valence == 3 { 3.0 / 16.0 } else { 3.0 / (8.0 * valence as f32) }
+    }
+
+    fn gamma(valence: usize) -> f32 {
+        1.0 / (valence as f32 + 3.0 / (8.0 * Self::beta(valence)))
+    }
+
+    fn weight_one_ring(p: &[Point3f], v: usize, ring: &[usize], beta: f32) -> Point3f {
+        ring.iter().fold(p[v] * (1.0 - ring.len() as f32 * beta), |pos, &i| pos + p[i] * beta)
+    }
+
+    fn weight_boundary(p: &[Point3f], v: usize, ring: &[usize], beta: f32) -> Point3f {
+        p[v] * (1.0 - 2.0 * beta) + (p[ring[0]] + p[ring[ring.len() - 1]]) * beta
+    }
+}
+
+register_fallible_struct!("loopsubdiv", create_loop_subdiv);
@@ -1,4 +1,31 @@
 pub mod shape;
-pub use shape::Shape;
+pub use shape::{Shape, sample_area_from, pdf_area_from};
 
 pub mod sphere;
+
+pub mod triangle;
+pub use triangle::{TriangleMesh, Triangle, create_triangles};
+
+pub mod obj_mesh;
+
+pub mod ply_mesh;
+
+pub mod cylinder;
+
+pub mod disk;
+
+pub mod cone;
+
+pub mod paraboloid;
+
+pub mod hyperboloid;
+
+pub mod curve;
+pub use curve::{CurveType, CurveCommon, Curve, create_curve_segments};
+
+pub mod curve_set;
+
+pub mod loop_subdiv;
+
+pub mod animated_shape;
+pub use animated_shape::AnimatedShape;
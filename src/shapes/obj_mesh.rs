@@ -0,0 +1,198 @@
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+
+use crate::common::*;
+
+// Vertex indices, positions, normals and uvs
+pub type ObjMeshData = (Vec<usize>, Vec<Point3f>, Vec<Normal3f>, Vec<Point2f>);
+
+// Wavefront OBJ file, split into one triangle shape per face when the scene is built
+pub struct ObjMesh {
+    filename: String,
+    mesh: Arc<TriangleMesh>,
+    area_light: Option<Arc<dyn AreaLight>>,
+    material: Option<Arc<dyn Material>>,
+}
+
+fn create_obj_mesh(prop_list: PropertyList) -> Result<LeadObject, String> {
+    let mesh = ObjMesh::new(prop_list)?;
+    Ok(LeadObject::Shape(Arc::new(mesh)))
+}
+
+impl Shape for ObjMesh {
+    fn object_to_world(&self) -> Transform { self.mesh.object_to_world.clone() }
+
+    fn world_to_object(&self) -> Transform { self.mesh.world_to_object.clone() }
+
+    fn reverse_orientation(&self) -> bool { self.mesh.reverse_orientation }
+
+    fn transform_swaps_handedness(&self) -> bool { self.mesh.object_to_world.swaps_handedness() }
+
+    fn get_object_bounds(&self) -> Bounds3f { &self.mesh.world_to_object * self.mesh.world_bounds() }
+
+    fn get_world_bounds(&self) -> Bounds3f { self.mesh.world_bounds() }
+
+    fn area(&self) -> f32 { self.mesh.area() }
+
+    fn area_light(&mut self) -> &mut Option<Arc<dyn AreaLight>> { &mut self.area_light }
+    fn material(&mut self) -> &mut Option<Arc<dyn Material>> { &mut self.material }
+
+    fn can_intersect(&self) -> bool { false }
+
+    fn refine(&self) -> Vec<Arc<dyn Shape>> {
+        create_triangles(self.mesh.clone())
+    }
+
+    fn sample_u(&self, _u: &Point2f, _pdf: &mut f32) -> Box<dyn Interaction> {
+        panic!("ObjMesh has to be refined into triangles before it can be sampled!");
+    }
+
+    fn intersect(&self, _ray: &Ray, _t_hit: &mut f32, _its: &mut SurfaceInteraction) -> bool {
+        panic!("ObjMesh has to be refined into triangles before it can be intersected!");
+    }
+}
+
+impl LeadObjectTrait for ObjMesh {
+    fn init(&mut self, _prop_list: PropertyList) { }
+
+    fn add_child(&mut self, child: &mut LeadObject) {
+        match child {
+            LeadObject::AreaLight(area_light) => self.area_light = Some(area_light.clone()),
+            LeadObject::Material(material) => self.material = Some(material.clone()),
+            _ => println!("Struct ObjMesh does not take a child of class {}", child.to_string())
+        }
+    }
+
+    fn activate(&mut self) { }
+
+    fn to_string(&self) -> String {
+        format!("obj: [\n  filename: {}\n  mesh: {}\n  bounding_box: \n{}\n]", self.filename, self.mesh.to_string(), indent(&self.get_world_bounds().to_string(), 4))
+    }
+}
+
+impl ObjMesh {
+    pub fn new(prop_list: PropertyList) -> Result<Self, String> {
+        let filename = prop_list.get_string("filename", "");
+        if filename.is_empty() {
+            return Err("An obj shape needs a filename".to_string());
+        }
+
+        let (vertex_indices, p, n, uv) = Self::load(&filename)?;
+        let mesh = TriangleMesh::new(
+            prop_list.get_transform(), vertex_indices, p, n, Vec::new(), uv,
+            prop_list.get_bool("reverse_orientation", false)
+        );
+
+        Ok(Self {
+            filename,
+            mesh: Arc::new(mesh),
+            area_light: None,
+            material: None,
+        })
+    }
+
+    // Reads positions, normals and uvs, faces with more than three corners are fanned into triangles.
+    // OBJ indexes every attribute separately, so each distinct corner becomes its own vertex
+    pub fn load(filename: &str) -> Result<ObjMeshData, String> {
+        let file = File::open(filename).map_err(|e| format!("{}: {}", filename, e))?;
+
+        let mut positions: Vec<Point3f> = Vec::new();
+        let mut normals: Vec<Normal3f> = Vec::new();
+        let mut uvs: Vec<Point2f> = Vec::new();
+
+        let mut corners: HashMap<(usize, Option<usize>, Option<usize>), usize> = HashMap::new();
+        let mut corner_list: Vec<(usize, Option<usize>, Option<usize>)> = Vec::new();
+        let mut vertex_indices: Vec<usize> = Vec::new();
+
+        for (line_number, line) in BufReader::new(file).lines().enumerate() {
+            let line = line.map_err(|e| format!("{}: {}", filename, e))?;
+            let error = |message: String| format!("{}:{}: {}", filename, line_number + 1, message);
+            let mut tokens = line.split_whitespace();
+            let floats = |tokens: std::str::SplitWhitespace| -> Result<Vec<f32>, String> {
+                tokens.map(|t| t.parse::<f32>().map_err(|_| error(format!("invalid number {}", t)))).collect()
+            };
+
+            match tokens.next() {
+                Some("v") => {
+                    let v = floats(tokens)?;
+                    if v.len() < 3 {
+                        return Err(error("vertex needs three coordinates".to_string()));
+                    }
+                    positions.push(Point3f::init([v[0], v[1], v[2]]));
+                },
+                Some("vn") => {
+                    let v = floats(tokens)?;
+                    if v.len() < 3 {
+                        return Err(error("normal needs three coordinates".to_string()));
+                    }
+                    normals.push(Normal3f::init([v[0], v[1], v[2]]));
+                },
+                Some("vt") => {
+                    let v = floats(tokens)?;
+                    if v.len() < 2 {
+                        return Err(error("uv needs two coordinates".to_string()));
+                    }
+                    uvs.push(Point2f::init([v[0], v[1]]));
+                },
+                Some("f") => {
+                    let mut face: Vec<usize> = Vec::new();
+                    for corner in tokens {
+                        let mut parts = corner.split('/');
+                        let p = Self::index(parts.next(), positions.len()).map_err(error)?
+                            .ok_or_else(|| error("face corner without a position".to_string()))?;
+                        let uv = Self::index(parts.next(), uvs.len()).map_err(error)?;
+                        let n = Self::index(parts.next(), normals.len()).map_err(error)?;
+
+                        face.push(*corners.entry((p, uv, n)).or_insert_with(|| {
+                            corner_list.push((p, uv, n));
+                            corner_list.len() - 1
+                        }));
+                    }
+                    if face.len() < 3 {
+                        return Err(error("face needs at least three corners".to_string()));
+                    }
+
+                    for i in 1..face.len() - 1 {
+                        vertex_indices.extend_from_slice(&[face[0], face[i], face[i + 1]]);
+                    }
+                },
+                // groups, materials and smoothing groups are not used
+                _ => ()
+            }
+        }
+
+        // attributes are only kept if every corner has one
+        let p = corner_list.iter().map(|&(p, _, _)| positions[p]).collect();
+        let uv = if corner_list.iter().all(|&(_, uv, _)| uv.is_some()) {
+            corner_list.iter().map(|&(_, uv, _)| uvs[uv.unwrap()]).collect()
+        } else {
+            Vec::new()
+        };
+        let n = if corner_list.iter().all(|&(_, _, n)| n.is_some()) {
+            corner_list.iter().map(|&(_, _, n)| normals[n.unwrap()]).collect()
+        } else {
+            Vec::new()
+        };
+
+        Ok((vertex_indices, p, n, uv))
+    }
+
+    // OBJ indices start at one, negative ones count back from the last element read so far
+    fn index(token: Option<&str>, count: usize) -> Result<Option<usize>, String> {
+        let token = match token {
+            Some(token) if !token.is_empty() => token,
+            _ => return Ok(None)
+        };
+
+        let i: i64 = token.parse().map_err(|_| format!("invalid index {}", token))?;
+        let i = if i < 0 { count as i64 + i } else { i - 1 };
+        if i < 0 || i >= count as i64 {
+            return Err(format!("index {} out of range", token));
+        }
+
+        Ok(Some(i as usize))
+    }
+}
+
+register_fallible_struct!("obj", create_obj_mesh);
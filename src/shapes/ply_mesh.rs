@@ -0,0 +1,482 @@
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+
+use crate::common::*;
+
+// Stanford PLY file, split into one triangle shape per face when the scene is built
+pub struct PlyMesh {
+    filename: String,
+    mesh: Arc<TriangleMesh>,
+    area_light: Option<Arc<dyn AreaLight>>,
+    material: Option<Arc<dyn Material>>,
+}
+
+fn create_ply_mesh(prop_list: PropertyList) -> Result<LeadObject, String> {
+    let mesh = PlyMesh::new(prop_list)?;
+    Ok(LeadObject::Shape(Arc::new(mesh)))
+}
+
+impl Shape for PlyMesh {
+    fn object_to_world(&self) -> Transform { self.mesh.object_to_world.clone() }
+
+    fn world_to_object(&self) -> Transform { self.mesh.world_to_object.clone() }
+
+    fn reverse_orientation(&self) -> bool { self.mesh.reverse_orientation }
+
+    fn transform_swaps_handedness(&self) -> bool { self.mesh.object_to_world.swaps_handedness() }
+
+    fn get_object_bounds(&self) -> Bounds3f { &self.mesh.world_to_object * self.mesh.world_bounds() }
+
+    fn get_world_bounds(&self) -> Bounds3f { self.mesh.world_bounds() }
+
+    fn area(&self) -> f32 { self.mesh.area() }
+
+    fn area_light(&mut self) -> &mut Option<Arc<dyn AreaLight>> { &mut self.area_light }
+    fn material(&mut self) -> &mut Option<Arc<dyn Material>> { &mut self.material }
+
+    fn can_intersect(&self) -> bool { false }
+
+    fn refine(&self) -> Vec<Arc<dyn Shape>> {
+        create_triangles(self.mesh.clone())
+    }
+
+    fn sample_u(&self, _u: &Point2f, _pdf: &mut f32) -> Box<dyn Interaction> {
+        panic!("PlyMesh has to be refined into triangles before it can be sampled!");
+    }
+
+    fn intersect(&self, _ray: &Ray, _t_hit: &mut f32, _its: &mut SurfaceInteraction) -> bool {
+        panic!("PlyMesh has to be refined into triangles before it can be intersected!");
+    }
+}
+
+impl LeadObjectTrait for PlyMesh {
+    fn init(&mut self, _prop_list: PropertyList) { }
+
+    fn add_child(&mut self, child: &mut LeadObject) {
+        match child {
+            LeadObject::AreaLight(area_light) => self.area_light = Some(area_light.clone()),
+            LeadObject::Material(material) => self.material = Some(material.clone()),
+            _ => println!("Struct PlyMesh does not take a child of class {}", child.to_string())
+        }
+    }
+
+    fn activate(&mut self) { }
+
+    fn to_string(&self) -> String {
+        format!("ply: [\n  filename: {}\n  mesh: {}\n  bounding_box: \n{}\n]", self.filename, self.mesh.to_string(), indent(&self.get_world_bounds().to_string(), 4))
+    }
+}
+
+impl PlyMesh {
+    pub fn new(prop_list: PropertyList) -> Result<Self, String> {
+        let filename = prop_list.get_string("filename", "");
+        if filename.is_empty() {
+            return Err("A ply shape needs a filename".to_string());
+        }
+
+        let file = File::open(&filename).map_err(|e| format!("{}: {}", filename, e))?;
+        let (vertex_indices, p, n, uv) = read_ply(BufReader::new(file)).map_err(|e| format!("{}: {}", filename, e))?;
+
+        let mesh = TriangleMesh::new(
+            prop_list.get_transform(), vertex_indices, p, n, Vec::new(), uv,
+            prop_list.get_bool("reverse_orientation", false)
+        );
+
+        Ok(Self {
+            filename,
+            mesh: Arc::new(mesh),
+            area_light: None,
+            material: None,
+        })
+    }
+}
+
+// Vertex indices, positions, normals and uvs
+pub type PlyMeshData = (Vec<usize>, Vec<Point3f>, Vec<Normal3f>, Vec<Point2f>);
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum PlyFormat {
+    Ascii,
+    BinaryLittleEndian,
+    BinaryBigEndian
+}
+
+#[derive(Debug, Clone, Copy)]
+enum PlyScalar {
+    Int8, UInt8, Int16, UInt16, Int32, UInt32, Float32, Float64
+}
+
+impl PlyScalar {
+    fn parse(name: &str) -> Result<Self, String> {
+        match name {
+            "char" | "int8" => Ok(Self::Int8),
+            "uchar" | "uint8" => Ok(Self::UInt8),
+            "short" | "int16" => Ok(Self::Int16),
+            "ushort" | "uint16" => Ok(Self::UInt16),
+            "int" | "int32" => Ok(Self::Int32),
+            "uint" | "uint32" => Ok(Self::UInt32),
+            "float" | "float32" => Ok(Self::Float32),
+            "double" | "float64" => Ok(Self::Float64),
+            _ => Err(format!("unknown property type {}", name))
+        }
+    }
+
+    fn size(&self) -> usize {
+        match self {
+            Self::Int8 | Self::UInt8 => 1,
+            Self::Int16 | Self::UInt16 => 2,
+            Self::Int32 | Self::UInt32 | Self::Float32 => 4,
+            Self::Float64 => 8
+        }
+    }
+
+    fn decode(&self, b: &[u8], format: PlyFormat) -> f64 {
+        macro_rules! convert {
+            ($t:ty, $n:expr) => {{
+                let bytes: [u8; $n] = b[..$n].try_into().unwrap();
+                (if format == PlyFormat::BinaryBigEndian { <$t>::from_be_bytes(bytes) } else { <$t>::from_le_bytes(bytes) }) as f64
+            }};
+        }
+
+        match self {
+            Self::Int8 => convert!(i8, 1),
+            Self::UInt8 => convert!(u8, 1),
+            Self::Int16 => convert!(i16, 2),
+            Self::UInt16 => convert!(u16, 2),
+            Self::Int32 => convert!(i32, 4),
+            Self::UInt32 => convert!(u32, 4),
+            Self::Float32 => convert!(f32, 4),
+            Self::Float64 => convert!(f64, 8)
+        }
+    }
+}
+
+enum PlyProperty {
+    Scalar(String, PlyScalar),
+    // name, type of the count and type of the items
+    List(String, PlyScalar, PlyScalar)
+}
+
+impl PlyProperty {
+    fn name(&self) -> &str {
+        match self {
+            Self::Scalar(name, _) | Self::List(name, _, _) => name
+        }
+    }
+}
+
+struct PlyElement {
+    name: String,
+    count: usize,
+    properties: Vec<PlyProperty>
+}
+
+impl PlyElement {
+    fn property_index(&self, names: &[&str]) -> Option<usize> {
+        self.properties.iter().position(|p| names.contains(&p.name()))
+    }
+}
+
+// Reads the values of one element after the other, in either encoding
+struct PlyValues<R: BufRead> {
+    reader: R,
+    format: PlyFormat,
+    line: usize,
+    // tokens left on the current line of an ascii file
+    tokens: Vec<String>,
+}
+
+impl<R: BufRead> PlyValues<R> {
+    fn scalar(&mut self, ty: PlyScalar) -> Result<f64, String> {
+        if self.format != PlyFormat::Ascii {
+            let mut buf = [0u8; 8];
+            self.reader.read_exact(&mut buf[..ty.size()]).map_err(|_| "unexpected end of file".to_string())?;
+            return Ok(ty.decode(&buf, self.format));
+        }
+
+        while self.tokens.is_empty() {
+            let mut line = String::new();
+            if self.reader.read_line(&mut line).map_err(|e| e.to_string())? == 0 {
+                return Err("unexpected end of file".to_string());
+            }
+            self.line += 1;
+            self.tokens = line.split_whitespace().rev().map(String::from).collect();
+        }
+
+        let token = self.tokens.pop().unwrap();
+        token.parse::<f64>().map_err(|_| format!("line {}: invalid number {}", self.line, token))
+    }
+
+    // One value per scalar property, lists come back whole
+    fn element(&mut self, element: &PlyElement, index: usize) -> Result<Vec<Vec<f64>>, String> {
+        let mut values = Vec::with_capacity(element.properties.len());
+        for property in &element.properties {
+            let context = |e: String| format!("{} {} of {}, property {}: {}", element.name, index, element.count, property.name(), e);
+            match property {
+                PlyProperty::Scalar(_, ty) => values.push(vec![self.scalar(*ty).map_err(context)?]),
+                PlyProperty::List(_, count_ty, item_ty) => {
+                    let count = self.scalar(*count_ty).map_err(context)?;
+                    if count < 0.0 || count.fract() != 0.0 {
+                        return Err(context(format!("invalid list length {}", count)));
+                    }
+                    let mut list = Vec::with_capacity(count as usize);
+                    for _ in 0..count as usize {
+                        list.push(self.scalar(*item_ty).map_err(context)?);
+                    }
+                    values.push(list);
+                }
+            }
+        }
+
+        // every element of an ascii file sits on its own line
+        if self.format == PlyFormat::Ascii && !self.tokens.is_empty() {
+            return Err(format!("line {}: {} {} has more values than properties", self.line, element.name, index));
+        }
+
+        Ok(values)
+    }
+}
+
+fn read_header<R: BufRead>(reader: &mut R, line_number: &mut usize) -> Result<(PlyFormat, Vec<PlyElement>), String> {
+    let next_line = |reader: &mut R, line_number: &mut usize| -> Result<String, String> {
+        let mut line = String::new();
+        if reader.read_line(&mut line).map_err(|e| e.to_string())? == 0 {
+            return Err("unexpected end of file in the header".to_string());
+        }
+        *line_number += 1;
+        Ok(line.trim().to_string())
+    };
+
+    if next_line(reader, line_number)? != "ply" {
+        return Err("not a ply file, it has to start with \"ply\"".to_string());
+    }
+
+    let mut format = None;
+    let mut elements: Vec<PlyElement> = Vec::new();
+    loop {
+        let line = next_line(reader, line_number)?;
+        let tokens: Vec<&str> = line.split_whitespace().collect();
+        let error = |message: &str| format!("line {}: {}", line_number, message);
+
+        match tokens.as_slice() {
+            ["format", kind, _version] => {
+                format = Some(match *kind {
+                    "ascii" => PlyFormat::Ascii,
+                    "binary_little_endian" => PlyFormat::BinaryLittleEndian,
+                    "binary_big_endian" => PlyFormat::BinaryBigEndian,
+                    _ => return Err(error(&format!("unknown format {}", kind)))
+                });
+            },
+            ["element", name, count] => {
+                let count = count.parse::<usize>().map_err(|_| error(&format!("invalid element count {}", count)))?;
+                elements.push(PlyElement { name: name.to_string(), count, properties: Vec::new() });
+            },
+            ["property", "list", count_ty, item_ty, name] => {
+                let element = elements.last_mut().ok_or_else(|| error("property declared before any element"))?;
+                let count_ty = PlyScalar::parse(count_ty).map_err(|e| error(&e))?;
+                let item_ty = PlyScalar::parse(item_ty).map_err(|e| error(&e))?;
+                element.properties.push(PlyProperty::List(name.to_string(), count_ty, item_ty));
+            },
+            ["property", ty, name] => {
+                let element = elements.last_mut().ok_or_else(|| error("property declared before any element"))?;
+                let ty = PlyScalar::parse(ty).map_err(|e| error(&e))?;
+                element.properties.push(PlyProperty::Scalar(name.to_string(), ty));
+            },
+            ["end_header"] => break,
+            ["comment", ..] | ["obj_info", ..] | [] => (),
+            _ => return Err(error(&format!("could not parse header line \"{}\"", line)))
+        }
+    }
+
+    match format {
+        Some(format) => Ok((format, elements)),
+        None => Err("the header does not declare a format".to_string())
+    }
+}
+
+// Positions, normals and uvs per vertex, faces with more than three corners are fanned into triangles
+pub fn read_ply<R: BufRead>(mut reader: R) -> Result<PlyMeshData, String> {
+    let mut line = 0;
+    let (format, elements) = read_header(&mut reader, &mut line)?;
+    let mut values = PlyValues { reader, format, line, tokens: Vec::new() };
+
+    let mut p: Vec<Point3f> = Vec::new();
+    let mut n: Vec<Normal3f> = Vec::new();
+    let mut uv: Vec<Point2f> = Vec::new();
+    let mut vertex_indices: Vec<usize> = Vec::new();
+    let mut has_faces = false;
+
+    for element in &elements {
+        match element.name.as_str() {
+            "vertex" => {
+                let position = ["x", "y", "z"].map(|name| element.property_index(&[name]));
+                let normal = ["nx", "ny", "nz"].map(|name| element.property_index(&[name]));
+                let texcoord = [
+                    element.property_index(&["u", "s", "texture_u", "texture_s"]),
+                    element.property_index(&["v", "t", "texture_v", "texture_t"])
+                ];
+                let [Some(x), Some(y), Some(z)] = position else {
+                    return Err("vertices need x, y and z properties".to_string());
+                };
+                let normal = match normal { [Some(nx), Some(ny), Some(nz)] => Some([nx, ny, nz]), _ => None };
+                let texcoord = match texcoord { [Some(u), Some(v)] => Some([u, v]), _ => None };
+
+                for i in 0..element.count {
+                    let v = values.element(element, i)?;
+                    p.push(Point3f::init([v[x][0] as f32, v[y][0] as f32, v[z][0] as f32]));
+                    if let Some([nx, ny, nz]) = normal {
+                        n.push(Normal3f::init([v[nx][0] as f32, v[ny][0] as f32, v[nz][0] as f32]));
+                    }
+                    if let Some([u, v_idx]) = texcoord {
+                        uv.push(Point2f::init([v[u][0] as f32, v[v_idx][0] as f32]));
+                    }
+                }
+            },
+            "face" => {
+                has_faces = true;
+                let indices = match element.property_index(&["vertex_indices", "vertex_index"]) {
+                    Some(i) if matches!(element.properties[i], PlyProperty::List(..)) => i,
+                    _ => return Err("faces need a vertex_indices list property".to_string())
+                };
+
+                for i in 0..element.count {
+                    let v = values.element(element, i)?;
+                    let face = &v[indices];
+                    if face.len() < 3 {
+                        return Err(format!("face {} has only {} vertices", i, face.len()));
+                    }
+                    for &index in face {
+                        if index < 0.0 || index as usize >= p.len() {
+                            return Err(format!("face {} refers to vertex {}, but there are only {} vertices", i, index, p.len()));
+                        }
+                    }
+
+                    for k in 1..face.len() - 1 {
+                        vertex_indices.extend_from_slice(&[face[0] as usize, face[k] as usize, face[k + 1] as usize]);
+                    }
+                }
+            },
+            // other elements, like edges or materials, still have to be read past
+            _ => {
+                for i in 0..element.count {
+                    values.element(element, i)?;
+                }
+            }
+        }
+    }
+
+    if p.is_empty() {
+        return Err("the file has no vertices".to_string());
+    }
+    if !has_faces {
+        return Err("the file has no faces".to_string());
+    }
+
+    Ok((vertex_indices, p, n, uv))
+}
+
+register_fallible_struct!("ply", create_ply_mesh);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const QUAD_POSITIONS: [[f32; 3]; 4] = [[0.0, 0.0, 0.0], [1.0, 0.0, 0.0], [1.0, 1.0, 0.0], [0.0, 1.0, 0.0]];
+
+    // A unit quad with normals and uvs, as one face of four vertices
+    fn binary_quad(format: &str, big_endian: bool) -> Vec<u8> {
+        let mut data = format!(
+            "ply\nformat {} 1.0\nelement vertex 4\nproperty float x\nproperty float y\nproperty float z\n\
+             property float nx\nproperty float ny\nproperty float nz\nproperty float u\nproperty float v\n\
+             element face 1\nproperty list uchar int vertex_indices\nend_header\n",
+            format
+        ).into_bytes();
+
+        let float = |x: f32| if big_endian { x.to_be_bytes() } else { x.to_le_bytes() };
+        for p in QUAD_POSITIONS {
+            for x in [p[0], p[1], p[2], 0.0, 0.0, 1.0, p[0], p[1]] {
+                data.extend_from_slice(&float(x));
+            }
+        }
+        data.push(4);
+        for i in 0..4 {
+            data.extend_from_slice(&if big_endian { i32::to_be_bytes(i) } else { i32::to_le_bytes(i) });
+        }
+        data
+    }
+
+    fn ascii_quad() -> Vec<u8> {
+        b"ply\nformat ascii 1.0\ncomment a unit quad\nelement vertex 4\nproperty float x\nproperty float y\n\
+          property float z\nproperty float nx\nproperty float ny\nproperty float nz\nproperty float s\n\
+          property float t\nelement face 1\nproperty list uchar int vertex_indices\nend_header\n\
+          0 0 0 0 0 1 0 0\n1 0 0 0 0 1 1 0\n1 1 0 0 0 1 1 1\n0 1 0 0 0 1 0 1\n4 0 1 2 3\n".to_vec()
+    }
+
+    fn check_quad(data: PlyMeshData) {
+        let (vertex_indices, p, n, uv) = data;
+        assert_eq!(vertex_indices, vec![0, 1, 2, 0, 2, 3]);
+        assert_eq!(p.len(), 4);
+        assert_eq!(n.len(), 4);
+        assert_eq!(uv.len(), 4);
+        for (i, expected) in QUAD_POSITIONS.iter().enumerate() {
+            assert_eq!([p[i][0], p[i][1], p[i][2]], *expected);
+            assert_eq!([n[i][0], n[i][1], n[i][2]], [0.0, 0.0, 1.0]);
+            assert_eq!([uv[i][0], uv[i][1]], [expected[0], expected[1]]);
+        }
+    }
+
+    #[test]
+    fn reads_ascii() {
+        check_quad(read_ply(&ascii_quad()[..]).unwrap());
+    }
+
+    #[test]
+    fn reads_binary_little_endian() {
+        check_quad(read_ply(&binary_quad("binary_little_endian", false)[..]).unwrap());
+    }
+
+    #[test]
+    fn reads_binary_big_endian() {
+        check_quad(read_ply(&binary_quad("binary_big_endian", true)[..]).unwrap());
+    }
+
+    #[test]
+    fn reads_past_unknown_elements() {
+        let data = b"ply\nformat ascii 1.0\nelement vertex 3\nproperty float x\nproperty float y\nproperty float z\n\
+                     element edge 1\nproperty int vertex1\nproperty int vertex2\n\
+                     element face 1\nproperty list uchar int vertex_index\nend_header\n\
+                     0 0 0\n1 0 0\n0 1 0\n0 1\n3 0 1 2\n";
+        let (vertex_indices, p, n, uv) = read_ply(&data[..]).unwrap();
+        assert_eq!(vertex_indices, vec![0, 1, 2]);
+        assert_eq!(p.len(), 3);
+        assert!(n.is_empty() && uv.is_empty());
+    }
+
+    #[test]
+    fn truncated_ascii_fails() {
+        let data = ascii_quad();
+        let error = read_ply(&data[..data.len() - 4]).unwrap_err();
+        assert!(error.contains("face 0 of 1"), "{}", error);
+    }
+
+    #[test]
+    fn truncated_binary_fails() {
+        let data = binary_quad("binary_little_endian", false);
+        let error = read_ply(&data[..data.len() - 2]).unwrap_err();
+        assert!(error.contains("unexpected end of file"), "{}", error);
+    }
+
+    #[test]
+    fn truncated_header_fails() {
+        let error = read_ply(&b"ply\nformat ascii 1.0\nelement vertex 3\n"[..]).unwrap_err();
+        assert!(error.contains("unexpected end of file in the header"), "{}", error);
+    }
+
+    #[test]
+    fn out_of_range_index_fails() {
+        let data = b"ply\nformat ascii 1.0\nelement vertex 3\nproperty float x\nproperty float y\nproperty float z\n\
+                     element face 1\nproperty list uchar int vertex_indices\nend_header\n0 0 0\n1 0 0\n0 1 0\n3 0 1 3\n";
+        let error = read_ply(&data[..]).unwrap_err();
+        assert!(error.contains("refers to vertex 3"), "{}", error);
+    }
+}
@@ -10,6 +10,11 @@ pub trait Shape: LeadObjectTrait{
 
     fn get_object_bounds(&self) -> Bounds3f;
     fn get_world_bounds(&self) -> Bounds3f;
+    // Bounds of the part of the shape inside b on either side of the plane at position along axis, used by
+    // spatial splits. Shapes that can be cut give tighter ones
+    fn get_split_world_bounds(&self, b: &Bounds3f, axis: usize, position: f32) -> (Bounds3f, Bounds3f) {
+        Bounds3f::split(&Bounds3f::intersect(&self.get_world_bounds(), b), axis, position)
+    }
     //After calling intersect make sure to set which shape it is in the its
     fn intersect(&self, ray: &Ray, t_hit: &mut f32, its:  &mut SurfaceInteraction) -> bool;
     fn intersect_p(&self, ray: &Ray) -> bool {
@@ -19,10 +24,60 @@ pub trait Shape: LeadObjectTrait{
         self.intersect(ray, &mut t_hit, &mut its)
     }
     fn area(&self) -> f32;
-    fn sample_u(&self, u: &Point2f) -> Box<dyn Interaction>;
-    fn pdf(&self, _its: &Box<dyn Interaction>) -> f32 {
+    // Emitter declared on the shape, handed out mutably so the scene can take it
+    fn area_light(&mut self) -> &mut Option<Arc<dyn AreaLight>>;
+    fn material(&mut self) -> &mut Option<Arc<dyn Material>>;
+
+    // Shapes like meshes are split into ones that can be intersected when the scene is built
+    fn can_intersect(&self) -> bool { true }
+    fn refine(&self) -> Vec<Arc<dyn Shape>> { Vec::new() }
+    // Shapes that move over the shutter, their parts are given where they are at the start
+    fn motion(&self) -> Option<AnimatedTransform> { None }
+
+    // Samples a point uniformly by area, pdf is with respect to area
+    fn sample_u(&self, u: &Point2f, pdf: &mut f32) -> Box<dyn Interaction>;
+    fn pdf(&self, _its: &dyn Interaction) -> f32 {
         1.0 / self.area()
     }
-    fn sample(&self, _reference: &Box<dyn Interaction>, u: &Point2f) -> Box<dyn Interaction>;
-    fn pdf_wi(&self, reference: &Box<dyn Interaction>, wi: &Vector3f) -> f32;
-}
\ No newline at end of file
+
+    // Samples a point as seen from the reference, pdf is with respect to solid angle
+    fn sample(&self, reference: &dyn Interaction, u: &Point2f, pdf: &mut f32) -> Box<dyn Interaction> {
+        sample_area_from(self, reference, u, pdf)
+    }
+
+    fn pdf_wi(&self, reference: &dyn Interaction, wi: &Vector3f) -> f32 {
+        pdf_area_from(self, reference, wi)
+    }
+}
+
+// Area sampling converted to solid angle, what the defaults above use. Public so shapes with a
+// better strategy can still fall back to it
+pub fn sample_area_from<S: Shape + ?Sized>(shape: &S, reference: &dyn Interaction, u: &Point2f, pdf: &mut f32) -> Box<dyn Interaction> {
+    let its = shape.sample_u(u, pdf);
+    let wi = its.p() - reference.p();
+
+    if wi.length_sqr() == 0.0 {
+        *pdf = 0.0;
+    } else {
+        let wi = Vector3f::normalize(&wi);
+        *pdf *= (reference.p() - its.p()).length_sqr() / Normal3f::abs_dot(&its.n(), &-wi);
+        if pdf.is_infinite() {
+            *pdf = 0.0;
+        }
+    }
+
+    its
+}
+
+pub fn pdf_area_from<S: Shape + ?Sized>(shape: &S, reference: &dyn Interaction, wi: &Vector3f) -> f32 {
+    let ray = reference.spawn_ray(wi);
+    let mut t_hit = 0f32;
+    let mut its_light = SurfaceInteraction::new();
+    if !shape.intersect(&ray, &mut t_hit, &mut its_light) {
+        return 0.0;
+    }
+
+    // convert the area density to solid angle
+    let pdf = (reference.p() - its_light.p).length_sqr() / (Normal3f::abs_dot(&its_light.n, &-*wi) * shape.area());
+    if pdf.is_infinite() { 0.0 } else { pdf }
+}
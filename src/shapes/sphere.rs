@@ -1,13 +1,20 @@
 use crate::common::*;
 
+stat_ratio!(SPHERE_OCCLUSIONS, "Spheres/Occlusion tests that hit");
+
 pub struct Sphere {
     radius: f32,
     z_min: f32, z_max: f32,
     theta_min: f32, theta_max: f32, phi_max: f32,
+    // world space center and radius, only set when the whole sphere is there and the
+    // transform keeps it round, which is what cone sampling needs
+    world_sphere: Option<(Point3f, f32)>,
 
     object_to_world: Transform, world_to_object: Transform,
     bounding_box: Bounds3f,
     reverse_orientation: bool, 
+    area_light: Option<Arc<dyn AreaLight>>,
+    material: Option<Arc<dyn Material>>,
 }
 
 fn create_sphere(prop_list: PropertyList) -> LeadObject {
@@ -32,45 +39,101 @@ impl Shape for Sphere {
         self.phi_max * self.radius * (self.z_max - self.z_min)
     }
 
-    fn pdf(&self, its: &Box<dyn Interaction>) -> f32 {
-        let x = its.p().x();
-        let y = its.p().y();
-        let z = its.p().z();
+    fn area_light(&mut self) -> &mut Option<Arc<dyn AreaLight>> { &mut self.area_light }
+    fn material(&mut self) -> &mut Option<Arc<dyn Material>> { &mut self.material }
 
-        let theta = (z / self.radius).acos();
-        if theta > self.theta_max || theta < self.theta_min {
-            return 0f32;
-        }
+    fn sample_u(&self, u: &Point2f, pdf: &mut f32) -> Box<dyn Interaction> {
+        // on a sphere, uniform in z and phi is uniform in area
+        let z = self.z_min + u.x() * (self.z_max - self.z_min);
+        let phi = u.y() * self.phi_max;
+        let r = 0f32.max(self.radius * self.radius - z * z).sqrt();
+        let p_obj = Point3f::init([r * phi.cos(), r * phi.sin(), z]);
 
-        let phi = y.signum() * (x / (x*x + y*y).sqrt()).acos();
-        if phi > self.phi_max {
-            return 0f32;
+        let mut n = Normal3f::normalize(&(&self.object_to_world * Normal3f::init([p_obj.x(), p_obj.y(), p_obj.z()])));
+        if self.reverse_orientation {
+            n = n * -1.0;
         }
 
-        1f32 / self.area()
+        let mut its = SurfaceInteraction::new();
+        its.p = &self.object_to_world * p_obj;
+        its.n = n;
+        its.shading.n = n;
+
+        *pdf = 1.0 / self.area();
+        Box::new(its)
     }
 
-    fn pdf_wi(&self, reference: &Box<dyn Interaction>, _wi: &Vector3f) -> f32 {
-        if reference.p().z() > self.z_max || reference.p().z() < self.z_min {
-            return 0f32;
+    fn sample(&self, reference: &dyn Interaction, u: &Point2f, pdf: &mut f32) -> Box<dyn Interaction> {
+        let (center, radius) = match self.world_sphere {
+            Some(sphere) => sphere,
+            None => return sample_area_from(self, reference, u, pdf)
+        };
+
+        // from inside every point can be seen, so area sampling is as good as it gets
+        let dc_2 = (center - reference.p()).length_sqr();
+        if dc_2 <= radius * radius {
+            return sample_area_from(self, reference, u, pdf);
         }
 
-        let x = reference.p().x();
-        let y = reference.p().y();
-        let phi = y.signum() * (x / (x*x + y*y).sqrt()).acos();
-        if phi > self.phi_max {
-            return 0f32;
+        // sample a direction in the cone around the center that the sphere covers,
+        // 1 - cos is kept explicitly so far away spheres don't round it to zero
+        let dc = dc_2.sqrt();
+        let sin_theta_max_2 = radius * radius / dc_2;
+        let cos_theta_max = (1.0 - sin_theta_max_2).max(0.0).sqrt();
+        let one_minus_cos_max = sin_theta_max_2 / (1.0 + cos_theta_max);
+
+        let one_minus_cos = u.x() * one_minus_cos_max;
+        let cos_theta = 1.0 - one_minus_cos;
+        let sin_theta_2 = one_minus_cos * (2.0 - one_minus_cos);
+        let phi = u.y() * 2.0 * M_PI;
+
+        // distance to the first point hit along that direction, then its angle seen from the center
+        let ds = dc * cos_theta - (radius * radius - dc_2 * sin_theta_2).max(0.0).sqrt();
+        let cos_alpha = ((dc_2 + radius * radius - ds * ds) / (2.0 * dc * radius)).clamp(-1.0, 1.0);
+        let sin_alpha = (1.0 - cos_alpha * cos_alpha).max(0.0).sqrt();
+
+        let wc = Vector3f::normalize(&(center - reference.p()));
+        let mut wc_x = Vector3f::new();
+        let mut wc_y = Vector3f::new();
+        coordinate_system(&wc, &mut wc_x, &mut wc_y);
+        let n_world = (wc_x * (sin_alpha * phi.cos()) + wc_y * (sin_alpha * phi.sin()) + wc * cos_alpha) * -1.0;
+
+        let mut n = Normal3f::init([n_world.x(), n_world.y(), n_world.z()]);
+        if self.reverse_orientation {
+            n = n * -1.0;
         }
 
-        1f32 / self.area()
-    }
+        let mut its = SurfaceInteraction::new();
+        its.p = center + n_world * radius;
+        its.n = n;
+        its.shading.n = n;
 
-    fn sample(&self, _reference: &Box<dyn Interaction>, u: &Point2f) -> Box<dyn Interaction> {
-        self.sample_u(u)
+        *pdf = 1.0 / (2.0 * M_PI * one_minus_cos_max);
+        Box::new(its)
     }
 
-    fn sample_u(&self, _u: &Point2f) -> Box<dyn Interaction> {
-        Box::new(SurfaceInteraction::new())
+    fn pdf_wi(&self, reference: &dyn Interaction, wi: &Vector3f) -> f32 {
+        let (center, radius) = match self.world_sphere {
+            Some(sphere) => sphere,
+            None => return pdf_area_from(self, reference, wi)
+        };
+
+        let dc_2 = (center - reference.p()).length_sqr();
+        if dc_2 <= radius * radius {
+            return pdf_area_from(self, reference, wi);
+        }
+
+        let sin_theta_max_2 = radius * radius / dc_2;
+        let cos_theta_max = (1.0 - sin_theta_max_2).max(0.0).sqrt();
+        let one_minus_cos_max = sin_theta_max_2 / (1.0 + cos_theta_max);
+
+        // directions outside the cone miss the sphere
+        let wc = Vector3f::normalize(&(center - reference.p()));
+        if Vector3f::dot(&Vector3f::normalize(wi), &wc) < cos_theta_max {
+            return 0.0;
+        }
+
+        1.0 / (2.0 * M_PI * one_minus_cos_max)
     }
 
     fn intersect(&self, ray: &Ray, t_hit: &mut f32, its:  &mut SurfaceInteraction) -> bool {
@@ -113,11 +176,14 @@ impl Shape for Sphere {
 
         let p = o_obj + d_obj * (*t_hit);
 
-        let theta = (p.z() / self.radius).acos();
-        let phi = p.y().signum() * (p.x() / (p.x()*p.x() + p.y()*p.y()).sqrt()).acos();
+        let theta = (p.z() / self.radius).clamp(-1.0, 1.0).acos();
+        let mut phi = p.y().atan2(p.x());
+        if phi < 0.0 {
+            phi += 2.0 * M_PI;
+        }
 
         // Check if within bounds
-        if phi > self.phi_max || theta > self.theta_max || theta < self.theta_min {
+        if (self.z_min > -self.radius && p.z() < self.z_min) || (self.z_max < self.radius && p.z() > self.z_max) || phi > self.phi_max {
             return false;
         }
 
@@ -132,19 +198,9 @@ impl Shape for Sphere {
         let d2_pduv = Vector3f::init([-phi.sin(), phi.cos(), 0.0]) * -(self.theta_max - self.theta_min) * p.z() * self.phi_max;
         let d2_pdvv = Vector3f::init([p.x(), p.y(), p.z()]) * -(self.theta_max - self.theta_min)*(self.theta_max - self.theta_min);
         
-        let big_e = Vector3f::dot(&dpdu, &dpdu);
-        let big_f = Vector3f::dot(&dpdu, &dpdv);
-        let big_g = Vector3f::dot(&dpdv, &dpdv);
-        let big_n = Vector3f::normalize(&Vector3f::cross(&dpdu, &dpdv));
-        let e = Vector3f::dot(&big_n, &d2_pduu);
-        let f = Vector3f::dot(&big_n, &d2_pduv);
-        let g = Vector3f::dot(&big_n, &d2_pdvv);
-
-        let inv_egf2 = 1.0 / (big_e*big_g - big_f*big_f);
-        let dndu = Normal3f::init_vector(&(dpdu * (f*big_f - e*big_g)*inv_egf2 + dpdv*(e*big_f - f*big_e)*inv_egf2));
-        let dndv = Normal3f::init_vector(&(dpdu * (g*big_f - f*big_g)*inv_egf2 + dpdv*(f*big_f - f*big_e)*inv_egf2));
+        let (dndu, dndv) = SurfaceInteraction::normal_derivatives(&dpdu, &dpdv, &d2_pduu, &d2_pduv, &d2_pdvv);
 
-        let obj_its = SurfaceInteraction::init(p, uv, -ray.d, dpdu, dpdv, dndu, dndv, *t_hit);
+        let obj_its = SurfaceInteraction::init(p, uv, -d_obj, dpdu, dpdv, dndu, dndv, *t_hit);
 
         *its = &self.object_to_world * &obj_its;
 
@@ -152,6 +208,8 @@ impl Shape for Sphere {
     }
 
     fn intersect_p(&self, ray: &Ray) -> bool {
+        // every test counts, the hits only once they made it through all the checks below
+        SPHERE_OCCLUSIONS.with(|occlusions| occlusions.add(0, 1));
         let mut t_hit: f32 = 0f32;
         let o_obj: Point3f = &self.world_to_object() * ray.o;
         let d_obj: Vector3f = &self.world_to_object() * ray.d;
@@ -195,13 +253,16 @@ impl Shape for Sphere {
         let p = o_obj + d_obj * t_hit;
 
 
-        let theta = (p.z() / self.radius).acos();
-        let phi = p.y().signum() * (p.x() / (p.x()*p.x() + p.y()*p.y()).sqrt()).acos();
+        let mut phi = p.y().atan2(p.x());
+        if phi < 0.0 {
+            phi += 2.0 * M_PI;
+        }
 
         // Check if within bounds
-        if phi > self.phi_max || theta > self.theta_max || theta < self.theta_min {
+        if (self.z_min > -self.radius && p.z() < self.z_min) || (self.z_max < self.radius && p.z() > self.z_max) || phi > self.phi_max {
             return false;
         }
+        SPHERE_OCCLUSIONS.with(|occlusions| occlusions.add(1, 0));
         true
     }
 }
@@ -210,7 +271,11 @@ impl LeadObjectTrait for Sphere {
     fn init(&mut self, _prop_list: PropertyList) { }
 
     fn add_child(&mut self, child: &mut LeadObject) {
-        println!("Struct Sphere does not take a child of class {}", child.to_string());
+        match child {
+            LeadObject::AreaLight(area_light) => self.area_light = Some(area_light.clone()),
+            LeadObject::Material(material) => self.material = Some(material.clone()),
+            _ => println!("Struct Sphere does not take a child of class {}", child.to_string())
+        }
     }
 
     fn activate(&mut self) { }
@@ -233,20 +298,44 @@ impl Sphere{
         );
 
         let object_to_world = prop_list.get_transform();
+        let full = z_min <= -radius && z_max >= radius && phi_max >= 2.0 * M_PI - EPSILON;
+        let world_sphere = match Self::uniform_scale(&object_to_world) {
+            Some(scale) if full => Some((&object_to_world * Point3f::new(), radius * scale)),
+            _ => None
+        };
 
         Sphere{
             radius,
             z_min, z_max,
-            theta_min: M_PI - (z_min / radius).clamp(-1f32, 1f32).acos(),
-            theta_max: M_PI - (z_max / radius).clamp(-1f32, 1f32).acos(),
+            theta_min: (z_min / radius).clamp(-1f32, 1f32).acos(),
+            theta_max: (z_max / radius).clamp(-1f32, 1f32).acos(),
             phi_max,
+            world_sphere,
             world_to_object: object_to_world.inverse(),
             object_to_world: object_to_world,
             bounding_box,
             reverse_orientation: prop_list.get_bool("reverse_orientation", false),
+            area_light: None,
+            material: None,
         }
     }
 
+    // Scale of a transform that only rotates, translates and scales the same along every axis
+    fn uniform_scale(transform: &Transform) -> Option<f32> {
+        let x: Vector3f = transform * Vector3f::init([1.0, 0.0, 0.0]);
+        let y: Vector3f = transform * Vector3f::init([0.0, 1.0, 0.0]);
+        let z: Vector3f = transform * Vector3f::init([0.0, 0.0, 1.0]);
+
+        let scale = x.length();
+        let tolerance = 1e-4 * scale;
+        let same_length = (y.length() - scale).abs() <= tolerance && (z.length() - scale).abs() <= tolerance;
+        let orthogonal = Vector3f::dot(&x, &y).abs() <= tolerance * scale
+            && Vector3f::dot(&y, &z).abs() <= tolerance * scale
+            && Vector3f::dot(&z, &x).abs() <= tolerance * scale;
+
+        if same_length && orthogonal { Some(scale) } else { None }
+    }
+
     pub fn calculate_uv(u: &mut f32, v: &mut f32, p: Point3f) {
         let theta = p.x().atan2(p.y()) + M_PI;
         let length = Point3f::dot(&p, &p);
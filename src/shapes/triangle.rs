@@ -0,0 +1,360 @@
+use crate::common::*;
+
+// Vertex data shared by all triangles of a mesh, already transformed to world space
+pub struct TriangleMesh {
+    pub n_triangles: usize,
+    pub vertex_indices: Vec<usize>,
+    pub p: Vec<Point3f>,
+    pub n: Vec<Normal3f>,
+    pub s: Vec<Vector3f>,
+    pub uv: Vec<Point2f>,
+
+    pub object_to_world: Transform, pub world_to_object: Transform,
+    pub reverse_orientation: bool,
+}
+
+impl TriangleMesh {
+    // n, s and uv are either empty or hold one entry per vertex
+    pub fn new(object_to_world: Transform, vertex_indices: Vec<usize>, p: Vec<Point3f>, n: Vec<Normal3f>, s: Vec<Vector3f>, uv: Vec<Point2f>, reverse_orientation: bool) -> Self {
+        assert!(vertex_indices.len().is_multiple_of(3), "Triangle mesh indices have to come in threes!");
+        assert!(vertex_indices.iter().all(|&i| i < p.len()), "Triangle mesh index out of range!");
+        assert!(n.is_empty() || n.len() == p.len(), "Triangle mesh needs a normal for every vertex!");
+        assert!(s.is_empty() || s.len() == p.len(), "Triangle mesh needs a tangent for every vertex!");
+        assert!(uv.is_empty() || uv.len() == p.len(), "Triangle mesh needs a uv for every vertex!");
+
+        let p = p.iter().map(|&p| &object_to_world * p).collect();
+        let n = n.iter().map(|&n| Normal3f::normalize(&(&object_to_world * n))).collect();
+        let s = s.iter().map(|&s| &object_to_world * s).collect();
+
+        Self {
+            n_triangles: vertex_indices.len() / 3,
+            vertex_indices,
+            p, n, s, uv,
+            world_to_object: object_to_world.inverse(),
+            object_to_world,
+            reverse_orientation,
+        }
+    }
+
+    pub fn world_bounds(&self) -> Bounds3f {
+        match self.p.first() {
+            Some(first) => self.p.iter().fold(Bounds3f::init_one(first), |b, p| Bounds3f::union_pt(&b, p)),
+            None => Bounds3f::new()
+        }
+    }
+
+    pub fn area(&self) -> f32 {
+        (0..self.n_triangles).map(|i| self.triangle_area(i)).sum()
+    }
+
+    fn triangle_area(&self, i: usize) -> f32 {
+        let p0 = self.p[self.vertex_indices[3 * i]];
+        let p1 = self.p[self.vertex_indices[3 * i + 1]];
+        let p2 = self.p[self.vertex_indices[3 * i + 2]];
+
+        0.5 * Vector3f::cross(&(p1 - p0), &(p2 - p0)).length()
+    }
+
+    pub fn to_string(&self) -> String {
+        format!(
+            "TriangleMesh[triangles: {}, vertices: {}, normals: {}, tangents: {}, uvs: {}]",
+            self.n_triangles, self.p.len(), !self.n.is_empty(), !self.s.is_empty(), !self.uv.is_empty()
+        )
+    }
+}
+
+pub fn create_triangles(mesh: Arc<TriangleMesh>) -> Vec<Arc<dyn Shape>> {
+    (0..mesh.n_triangles).map(|i| Arc::new(Triangle::new(mesh.clone(), i)) as Arc<dyn Shape>).collect()
+}
+
+pub struct Triangle {
+    mesh: Arc<TriangleMesh>,
+    // offset of the first of the three indices in the mesh
+    v: usize,
+    area_light: Option<Arc<dyn AreaLight>>,
+    material: Option<Arc<dyn Material>>,
+}
+
+impl Shape for Triangle {
+    fn object_to_world(&self) -> Transform { self.mesh.object_to_world.clone() }
+
+    fn world_to_object(&self) -> Transform { self.mesh.world_to_object.clone() }
+
+    fn reverse_orientation(&self) -> bool { self.mesh.reverse_orientation }
+
+    fn transform_swaps_handedness(&self) -> bool { self.mesh.object_to_world.swaps_handedness() }
+
+    fn get_object_bounds(&self) -> Bounds3f {
+        let [p0, p1, p2] = self.positions();
+        let world_to_object = &self.mesh.world_to_object;
+
+        Bounds3f::union_pt(&Bounds3f::union_pt(&Bounds3f::init_one(&(world_to_object * p0)), &(world_to_object * p1)), &(world_to_object * p2))
+    }
+
+    fn get_world_bounds(&self) -> Bounds3f {
+        let [p0, p1, p2] = self.positions();
+        Bounds3f::union_pt(&Bounds3f::union_pt(&Bounds3f::init_one(&p0), &p1), &p2)
+    }
+
+    // Each side is bound by the corners on it and the points where the edges cross the plane. These bound the
+    // whole triangle, so they are cut down to b afterwards
+    fn get_split_world_bounds(&self, b: &Bounds3f, axis: usize, position: f32) -> (Bounds3f, Bounds3f) {
+        let p = self.positions();
+        let (mut below, mut above) = (Bounds3f::new(), Bounds3f::new());
+        for i in 0..3 {
+            let (p0, p1) = (p[i], p[(i + 1) % 3]);
+            if p0[axis] <= position {
+                below = Bounds3f::union_pt(&below, &p0);
+            }
+            if p0[axis] >= position {
+                above = Bounds3f::union_pt(&above, &p0);
+            }
+            if (p0[axis] < position && position < p1[axis]) || (p1[axis] < position && position < p0[axis]) {
+                let t = (position - p0[axis]) / (p1[axis] - p0[axis]);
+                let mut crossing = p0 + (p1 - p0) * t;
+                // exactly on the plane, whatever the rounding did
+                crossing[axis] = position;
+                below = Bounds3f::union_pt(&below, &crossing);
+                above = Bounds3f::union_pt(&above, &crossing);
+            }
+        }
+        (Bounds3f::intersect(&below, b), Bounds3f::intersect(&above, b))
+    }
+
+    fn area(&self) -> f32 {
+        let [p0, p1, p2] = self.positions();
+        0.5 * Vector3f::cross(&(p1 - p0), &(p2 - p0)).length()
+    }
+
+    fn area_light(&mut self) -> &mut Option<Arc<dyn AreaLight>> { &mut self.area_light }
+    fn material(&mut self) -> &mut Option<Arc<dyn Material>> { &mut self.material }
+
+    fn sample_u(&self, u: &Point2f, pdf: &mut f32) -> Box<dyn Interaction> {
+        let [p0, p1, p2] = self.positions();
+        let b = Warp::uniform_sample_triangle(*u);
+
+        let mut its = SurfaceInteraction::new();
+        its.p = p0 * b.x() + p1 * b.y() + p2 * (1.0 - b.x() - b.y());
+
+        let ng = Vector3f::cross(&(p1 - p0), &(p2 - p0));
+        let mut n = Normal3f::normalize(&Normal3f::init_vector(&ng));
+        if self.mesh.n.is_empty() {
+            if self.reverse_orientation() ^ self.transform_swaps_handedness() {
+                n = -n;
+            }
+        } else {
+            let [n0, n1, n2] = self.normals();
+            let ns = n0 * b.x() + n1 * b.y() + n2 * (1.0 - b.x() - b.y());
+            n = Normal3f::faceforward(&n, &Vector3f::init([ns.x(), ns.y(), ns.z()]));
+        }
+        its.n = n;
+        its.shading.n = n;
+
+        *pdf = 1.0 / self.area();
+        Box::new(its)
+    }
+
+    fn intersect(&self, ray: &Ray, t_hit: &mut f32, its: &mut SurfaceInteraction) -> bool {
+        let (b, t) = match self.hit(ray) {
+            Some(hit) => hit,
+            None => return false
+        };
+
+        let [p0, p1, p2] = self.positions();
+        let [uv0, uv1, uv2] = self.uvs();
+
+        // partial derivatives come from the linear system of the two edges in position and uv
+        let duv02 = uv0 - uv2;
+        let duv12 = uv1 - uv2;
+        let dp02 = p0 - p2;
+        let dp12 = p1 - p2;
+        let determinant = duv02.x() * duv12.y() - duv02.y() * duv12.x();
+
+        let mut dpdu = Vector3f::new();
+        let mut dpdv = Vector3f::new();
+        let degenerate_uv = determinant.abs() < 1e-8;
+        if !degenerate_uv {
+            let inv_det = 1.0 / determinant;
+            dpdu = (dp02 * duv12.y() - dp12 * duv02.y()) * inv_det;
+            dpdv = (dp12 * duv02.x() - dp02 * duv12.x()) * inv_det;
+        }
+        if degenerate_uv || Vector3f::cross(&dpdu, &dpdv).length_sqr() == 0.0 {
+            let ng = Vector3f::cross(&(p2 - p0), &(p1 - p0));
+            if ng.length_sqr() == 0.0 {
+                return false;
+            }
+            coordinate_system(&Vector3f::normalize(&ng), &mut dpdu, &mut dpdv);
+        }
+
+        let p_hit = p0 * b[0] + p1 * b[1] + p2 * b[2];
+        let uv_hit = uv0 * b[0] + uv1 * b[1] + uv2 * b[2];
+
+        *t_hit = t;
+        *its = SurfaceInteraction::init(p_hit, uv_hit, -ray.d, dpdu, dpdv, Normal3f::new(), Normal3f::new(), t);
+
+        // the geometric normal follows the winding order, not the uv parametrization
+        let n = Normal3f::normalize(&Normal3f::init_vector(&Vector3f::cross(&dp02, &dp12)));
+        its.n = n;
+        its.shading.n = n;
+        if self.mesh.n.is_empty() && self.reverse_orientation() ^ self.transform_swaps_handedness() {
+            its.n = -n;
+            its.shading.n = -n;
+        }
+
+        if !self.mesh.n.is_empty() || !self.mesh.s.is_empty() {
+            self.set_shading(its, &b, degenerate_uv, &duv02, &duv12);
+        }
+
+        true
+    }
+
+    fn intersect_p(&self, ray: &Ray) -> bool {
+        self.hit(ray).is_some()
+    }
+}
+
+impl LeadObjectTrait for Triangle {
+    fn init(&mut self, _prop_list: PropertyList) { }
+
+    fn add_child(&mut self, child: &mut LeadObject) {
+        match child {
+            LeadObject::AreaLight(area_light) => self.area_light = Some(area_light.clone()),
+            LeadObject::Material(material) => self.material = Some(material.clone()),
+            _ => println!("Struct Triangle does not take a child of class {}", child.to_string())
+        }
+    }
+
+    fn activate(&mut self) { }
+
+    fn to_string(&self) -> String {
+        let [p0, p1, p2] = self.positions();
+        format!("triangle: [{}, {}, {}]", p0.to_string(), p1.to_string(), p2.to_string())
+    }
+}
+
+impl Triangle {
+    pub fn new(mesh: Arc<TriangleMesh>, triangle_number: usize) -> Self {
+        Self {
+            mesh,
+            v: 3 * triangle_number,
+            area_light: None,
+            material: None,
+        }
+    }
+
+    fn positions(&self) -> [Point3f; 3] {
+        let idx = &self.mesh.vertex_indices[self.v..self.v + 3];
+        [self.mesh.p[idx[0]], self.mesh.p[idx[1]], self.mesh.p[idx[2]]]
+    }
+
+    fn normals(&self) -> [Normal3f; 3] {
+        let idx = &self.mesh.vertex_indices[self.v..self.v + 3];
+        [self.mesh.n[idx[0]], self.mesh.n[idx[1]], self.mesh.n[idx[2]]]
+    }
+
+    fn uvs(&self) -> [Point2f; 3] {
+        if self.mesh.uv.is_empty() {
+            return [Point2f::init([0.0, 0.0]), Point2f::init([1.0, 0.0]), Point2f::init([1.0, 1.0])];
+        }
+
+        let idx = &self.mesh.vertex_indices[self.v..self.v + 3];
+        [self.mesh.uv[idx[0]], self.mesh.uv[idx[1]], self.mesh.uv[idx[2]]]
+    }
+
+    // Watertight ray-triangle test, returns the barycentrics and the distance of the hit
+    fn hit(&self, ray: &Ray) -> Option<([f32; 3], f32)> {
+        let [p0, p1, p2] = self.positions();
+
+        // move to a space where the ray starts at the origin and points down +z
+        let d_abs = ray.d.abs();
+        let kz = if d_abs.x() > d_abs.y() && d_abs.x() > d_abs.z() { 0 } else if d_abs.y() > d_abs.z() { 1 } else { 2 };
+        let kx = (kz + 1) % 3;
+        let ky = (kx + 1) % 3;
+
+        let d = Vector3f::permute(&ray.d, [kx, ky, kz]);
+        let mut p0t = Vector3f::permute(&(p0 - ray.o), [kx, ky, kz]);
+        let mut p1t = Vector3f::permute(&(p1 - ray.o), [kx, ky, kz]);
+        let mut p2t = Vector3f::permute(&(p2 - ray.o), [kx, ky, kz]);
+
+        let sx = -d.x() / d.z();
+        let sy = -d.y() / d.z();
+        let sz = 1.0 / d.z();
+        p0t[0] += sx * p0t.z(); p0t[1] += sy * p0t.z();
+        p1t[0] += sx * p1t.z(); p1t[1] += sy * p1t.z();
+        p2t[0] += sx * p2t.z(); p2t[1] += sy * p2t.z();
+
+        let mut e0 = p1t.x() * p2t.y() - p1t.y() * p2t.x();
+        let mut e1 = p2t.x() * p0t.y() - p2t.y() * p0t.x();
+        let mut e2 = p0t.x() * p1t.y() - p0t.y() * p1t.x();
+
+        // edges that come out exactly zero are redone in double precision
+        if e0 == 0.0 || e1 == 0.0 || e2 == 0.0 {
+            e0 = (p1t.x() as f64 * p2t.y() as f64 - p1t.y() as f64 * p2t.x() as f64) as f32;
+            e1 = (p2t.x() as f64 * p0t.y() as f64 - p2t.y() as f64 * p0t.x() as f64) as f32;
+            e2 = (p0t.x() as f64 * p1t.y() as f64 - p0t.y() as f64 * p1t.x() as f64) as f32;
+        }
+
+        if (e0 < 0.0 || e1 < 0.0 || e2 < 0.0) && (e0 > 0.0 || e1 > 0.0 || e2 > 0.0) {
+            return None;
+        }
+        let det = e0 + e1 + e2;
+        if det == 0.0 {
+            return None;
+        }
+
+        // distance test before the division
+        let t_scaled = e0 * p0t.z() * sz + e1 * p1t.z() * sz + e2 * p2t.z() * sz;
+        if det < 0.0 && (t_scaled >= ray.t_min * det || t_scaled < ray.t_max * det) {
+            return None;
+        }
+        if det > 0.0 && (t_scaled <= ray.t_min * det || t_scaled > ray.t_max * det) {
+            return None;
+        }
+
+        let inv_det = 1.0 / det;
+        Some(([e0 * inv_det, e1 * inv_det, e2 * inv_det], t_scaled * inv_det))
+    }
+
+    // Shading frame from the interpolated normals and tangents
+    fn set_shading(&self, its: &mut SurfaceInteraction, b: &[f32; 3], degenerate_uv: bool, duv02: &Vector2f, duv12: &Vector2f) {
+        let ns = if self.mesh.n.is_empty() {
+            its.n
+        } else {
+            let [n0, n1, n2] = self.normals();
+            let ns = n0 * b[0] + n1 * b[1] + n2 * b[2];
+            if ns.length_sqr() > 0.0 { Normal3f::normalize(&ns) } else { its.n }
+        };
+        let ns_v = Vector3f::init([ns.x(), ns.y(), ns.z()]);
+
+        let mut ss = its.dpdu;
+        if !self.mesh.s.is_empty() {
+            let idx = &self.mesh.vertex_indices[self.v..self.v + 3];
+            let s = self.mesh.s[idx[0]] * b[0] + self.mesh.s[idx[1]] * b[1] + self.mesh.s[idx[2]] * b[2];
+            if s.length_sqr() > 0.0 {
+                ss = s;
+            }
+        }
+        ss = Vector3f::normalize(&ss);
+
+        let mut ts = Vector3f::cross(&ns_v, &ss);
+        if ts.length_sqr() > 0.0 {
+            ts = Vector3f::normalize(&ts);
+            ss = Vector3f::cross(&ts, &ns_v);
+        } else {
+            coordinate_system(&ns_v, &mut ss, &mut ts);
+        }
+
+        let mut dndu = Normal3f::new();
+        let mut dndv = Normal3f::new();
+        if !self.mesh.n.is_empty() && !degenerate_uv {
+            let [n0, n1, n2] = self.normals();
+            let dn1 = n0 - n2;
+            let dn2 = n1 - n2;
+            let inv_det = 1.0 / (duv02.x() * duv12.y() - duv02.y() * duv12.x());
+            dndu = (dn1 * duv12.y() - dn2 * duv02.y()) * inv_det;
+            dndv = (dn2 * duv02.x() - dn1 * duv12.x()) * inv_det;
+        }
+
+        its.set_shading_geometry(ss, ts, dndu, dndv, true);
+    }
+}
@@ -192,6 +192,20 @@ impl CoefficientSpectrum<3> for RGBSpectrum {
         false
     }
 
+    fn is_black(&self) -> bool {
+        for i in self.c {
+            if i != 0.0 {
+                return false;
+            }
+        }
+
+        true
+    }
+
+    fn max_component_value(&self) -> f64 {
+        self.c[0].max(self.c[1]).max(self.c[2])
+    }
+
     fn lerp(t: f64, a: &Self, b: &Self) -> Self {
         let mut c = [0f64; 3];
         for i in 0..3 {
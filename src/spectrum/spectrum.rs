@@ -28,6 +28,8 @@ pub trait CoefficientSpectrum<const N: usize>: Index<usize> + IndexMut<usize> {
     fn exp(&self) -> Self;
     fn clamp(&self, low: f64, high: f64) -> Self;
     fn has_nan(&self) -> bool;
+    fn is_black(&self) -> bool;
+    fn max_component_value(&self) -> f64;
     fn lerp(t: f64, a: &Self, b: &Self) -> Self;
 
     fn interpolate_spectrum_samples(lambda: &Vec<f64>, vals: &Vec<f64>, l: f64) -> f64 {
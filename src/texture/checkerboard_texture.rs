@@ -0,0 +1,104 @@
+use crate::common::*;
+
+// Alternates between two textures on the unit squares of the mapped (s, t) plane
+pub struct CheckerboardTexture<T: TextureValue> {
+    tex1: Arc<dyn Texture<T>>,
+    tex2: Arc<dyn Texture<T>>,
+    mapping: UVMapping2D,
+    // box filters the checks over the pixel footprint, instead of point sampling them
+    antialiased: bool
+}
+
+// Constructor
+fn create_checkerboard_texture(prop_list: PropertyList) -> LeadObject {
+    if is_float_texture(&prop_list) {
+        create::<f32>(prop_list)
+    } else {
+        create::<Spectrum>(prop_list)
+    }
+}
+
+fn create<T: TextureValue>(prop_list: PropertyList) -> LeadObject {
+    let mut texture = CheckerboardTexture::<T>::new();
+    texture.init(prop_list);
+    T::lead_object(Arc::new(texture))
+}
+
+impl<T: TextureValue> Texture<T> for CheckerboardTexture<T> {
+    fn evaluate(&self, its: &SurfaceInteraction) -> T {
+        let mut dstdx = Vector2f::new();
+        let mut dstdy = Vector2f::new();
+        let st = self.mapping.map(its, &mut dstdx, &mut dstdy);
+
+        let ds = dstdx.x().abs().max(dstdy.x().abs());
+        let dt = dstdx.y().abs().max(dstdy.y().abs());
+        let (s0, s1) = (st.x() - ds, st.x() + ds);
+        let (t0, t1) = (st.y() - dt, st.y() + dt);
+
+        // point sample when the filter footprint lies inside a single check
+        if !self.antialiased || (s0.floor() == s1.floor() && t0.floor() == t1.floor()) {
+            if (st.x().floor() as i64 + st.y().floor() as i64) % 2 == 0 {
+                return self.tex1.evaluate(its);
+            }
+            return self.tex2.evaluate(its);
+        }
+
+        // integral of the 1d check pattern, used to get the fraction of tex2 under the box
+        let bump_int = |x: f32| (x / 2.0).floor() + 2.0 * (x / 2.0 - (x / 2.0).floor() - 0.5).max(0.0);
+        let s_int = (bump_int(s1) - bump_int(s0)) / (2.0 * ds);
+        let t_int = (bump_int(t1) - bump_int(t0)) / (2.0 * dt);
+
+        let mut area2 = s_int + t_int - 2.0 * s_int * t_int;
+        if ds > 1.0 || dt > 1.0 {
+            area2 = 0.5;
+        }
+
+        self.tex1.evaluate(its) * (1.0 - area2) + self.tex2.evaluate(its) * area2
+    }
+}
+
+impl<T: TextureValue> LeadObjectTrait for CheckerboardTexture<T> {
+    fn init(&mut self, prop_list: PropertyList) {
+        let tex1 = T::get_property(&prop_list, "tex1", T::from_spectrum(&Spectrum::init_one(1.0)));
+        let tex2 = T::get_property(&prop_list, "tex2", T::from_spectrum(&Spectrum::init_one(0.0)));
+        self.tex1 = Arc::new(ConstantTexture::new(tex1));
+        self.tex2 = Arc::new(ConstantTexture::new(tex2));
+        self.mapping = UVMapping2D::init(&prop_list);
+        self.antialiased = prop_list.get_bool("antialiased", true);
+    }
+
+    fn activate(&mut self) { }
+
+    fn add_child(&mut self, child: &mut LeadObject) {
+        if let Some((name, texture)) = T::texture_child(child) {
+            match name.as_str() {
+                "tex1" => { self.tex1 = texture; return; },
+                "tex2" => { self.tex2 = texture; return; },
+                _ => {}
+            }
+        }
+
+        println!("Struct CheckerboardTexture does not take a child of class {}", child.to_string());
+    }
+
+    fn to_string(&self) -> String {
+        format!(
+            "CheckerboardTexture[\n  tex1: {},\n  tex2: {},\n  mapping: {},\n  antialiased: {}\n]",
+            indent(&self.tex1.to_string(), 2).trim_start(), indent(&self.tex2.to_string(), 2).trim_start(),
+            self.mapping.to_string(), self.antialiased
+        )
+    }
+}
+
+impl<T: TextureValue> CheckerboardTexture<T> {
+    pub fn new() -> Self {
+        Self {
+            tex1: Arc::new(ConstantTexture::new(T::from_spectrum(&Spectrum::init_one(1.0)))),
+            tex2: Arc::new(ConstantTexture::new(T::from_spectrum(&Spectrum::init_one(0.0)))),
+            mapping: UVMapping2D::new(),
+            antialiased: true
+        }
+    }
+}
+
+register_struct!("checkerboard", create_checkerboard_texture);
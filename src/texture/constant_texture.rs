@@ -0,0 +1,52 @@
+use crate::common::*;
+
+pub struct ConstantTexture<T: TextureValue> {
+    value: T
+}
+
+// Constructor
+fn create_constant_texture(prop_list: PropertyList) -> LeadObject {
+    if is_float_texture(&prop_list) {
+        create::<f32>(prop_list)
+    } else {
+        create::<Spectrum>(prop_list)
+    }
+}
+
+fn create<T: TextureValue>(prop_list: PropertyList) -> LeadObject {
+    let mut texture = ConstantTexture::new(T::from_spectrum(&Spectrum::init_one(1.0)));
+    texture.init(prop_list);
+    T::lead_object(Arc::new(texture))
+}
+
+impl<T: TextureValue> Texture<T> for ConstantTexture<T> {
+    fn evaluate(&self, _its: &SurfaceInteraction) -> T {
+        self.value
+    }
+}
+
+impl<T: TextureValue> LeadObjectTrait for ConstantTexture<T> {
+    fn init(&mut self, prop_list: PropertyList) {
+        self.value = T::get_property(&prop_list, "value", self.value);
+    }
+
+    fn activate(&mut self) { }
+
+    fn add_child(&mut self, child: &mut LeadObject) {
+        println!("Struct ConstantTexture does not take a child of class {}", child.to_string());
+    }
+
+    fn to_string(&self) -> String {
+        format!("ConstantTexture[value: {}]", self.value.describe())
+    }
+}
+
+impl<T: TextureValue> ConstantTexture<T> {
+    pub fn new(value: T) -> Self {
+        Self {
+            value
+        }
+    }
+}
+
+register_struct!("constant", create_constant_texture);
@@ -0,0 +1,105 @@
+use crate::common::*;
+
+// EXR image looked up through a MIP map, with (0, 0) at the lower left of the image
+pub struct ImageTexture<T: TextureValue> {
+    filename: String,
+    mipmap: Option<MIPMap>,
+    mapping: UVMapping2D,
+    scale: f32,
+    _value: std::marker::PhantomData<T>
+}
+
+// Constructor
+fn create_image_texture(prop_list: PropertyList) -> LeadObject {
+    if is_float_texture(&prop_list) {
+        create::<f32>(prop_list)
+    } else {
+        create::<Spectrum>(prop_list)
+    }
+}
+
+fn create<T: TextureValue>(prop_list: PropertyList) -> LeadObject {
+    let mut texture = ImageTexture::<T>::new();
+    texture.init(prop_list);
+    T::lead_object(Arc::new(texture))
+}
+
+impl<T: TextureValue> Texture<T> for ImageTexture<T> {
+    fn evaluate(&self, its: &SurfaceInteraction) -> T {
+        let mipmap = self.mipmap.as_ref().expect("ImageTexture has no image loaded!");
+
+        let mut dstdx = Vector2f::new();
+        let mut dstdy = Vector2f::new();
+        let st = self.mapping.map(its, &mut dstdx, &mut dstdy);
+
+        T::from_spectrum(&(mipmap.lookup(&st, &dstdx, &dstdy) * self.scale))
+    }
+}
+
+impl<T: TextureValue> LeadObjectTrait for ImageTexture<T> {
+    fn init(&mut self, prop_list: PropertyList) {
+        self.filename = prop_list.get_string("filename", "");
+        if self.filename.is_empty() {
+            panic!("ImageTexture needs a filename!");
+        }
+
+        let wrap = match prop_list.get_string("wrap", "repeat").as_str() {
+            "repeat" => ImageWrap::Repeat,
+            "black" => ImageWrap::Black,
+            "clamp" => ImageWrap::Clamp,
+            wrap => panic!("Unknown wrap mode {} for ImageTexture!", wrap)
+        };
+        let filter = match prop_list.get_string("filter", "ewa").as_str() {
+            "bilinear" => MIPFilter::Bilinear,
+            "trilinear" => MIPFilter::Trilinear,
+            "ewa" => MIPFilter::EWA,
+            filter => panic!("Unknown filter {} for ImageTexture!", filter)
+        };
+        let max_anisotropy = prop_list.get_float("max_anisotropy", 8.0);
+
+        // image rows are stored top to bottom, texture space has t going up
+        let mut image = ImageMap::read(&self.filename);
+        let (width, height) = (image.width(), image.height());
+        for y in 0..height / 2 {
+            for x in 0..width {
+                image.texels.swap(y * width + x, (height - 1 - y) * width + x);
+            }
+        }
+
+        self.mipmap = Some(MIPMap::new(image, wrap, filter, max_anisotropy));
+        self.mapping = UVMapping2D::init(&prop_list);
+        self.scale = prop_list.get_float("scale", 1.0);
+    }
+
+    fn activate(&mut self) { }
+
+    fn add_child(&mut self, child: &mut LeadObject) {
+        println!("Struct ImageTexture does not take a child of class {}", child.to_string());
+    }
+
+    fn to_string(&self) -> String {
+        let mipmap = match &self.mipmap {
+            Some(mipmap) => mipmap.to_string(),
+            None => String::from("None")
+        };
+
+        format!(
+            "ImageTexture[\n  filename: {},\n  mipmap: {},\n  mapping: {},\n  scale: {}\n]",
+            self.filename, mipmap, self.mapping.to_string(), self.scale
+        )
+    }
+}
+
+impl<T: TextureValue> ImageTexture<T> {
+    pub fn new() -> Self {
+        Self {
+            filename: String::new(),
+            mipmap: None,
+            mapping: UVMapping2D::new(),
+            scale: 1.0,
+            _value: std::marker::PhantomData
+        }
+    }
+}
+
+register_struct!("image", create_image_texture);
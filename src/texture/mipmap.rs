@@ -0,0 +1,251 @@
+use crate::common::*;
+
+#[derive(Debug, Clone, Copy)]
+pub enum ImageWrap {
+    Repeat,
+    Black,
+    Clamp
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum MIPFilter {
+    Bilinear,
+    Trilinear,
+    EWA
+}
+
+const WEIGHT_LUT_SIZE: usize = 128;
+
+// Image pyramid, level 0 is the full resolution image resampled to a power of two
+pub struct MIPMap {
+    pyramid: Vec<ImageMap>,
+    wrap: ImageWrap,
+    filter: MIPFilter,
+    max_anisotropy: f32,
+    // gaussian falloff of the ewa filter, indexed by the squared radius
+    weight_lut: Vec<f32>
+}
+
+impl MIPMap {
+    pub fn new(image: ImageMap, wrap: ImageWrap, filter: MIPFilter, max_anisotropy: f32) -> Self {
+        assert!(!image.is_empty(), "Cannot build a MIPMap from an empty image!");
+
+        let mut ret = Self {
+            pyramid: Vec::new(),
+            wrap,
+            filter,
+            max_anisotropy,
+            weight_lut: Vec::new()
+        };
+
+        let width = image.width().next_power_of_two();
+        let height = image.height().next_power_of_two();
+        let mut level = if width != image.width() || height != image.height() {
+            ret.resample(&image, width, height)
+        } else {
+            image
+        };
+
+        // every level box filters the one above it
+        loop {
+            let (w, h) = (level.width(), level.height());
+            if w == 1 && h == 1 {
+                ret.pyramid.push(level);
+                break;
+            }
+
+            let (nw, nh) = ((w / 2).max(1), (h / 2).max(1));
+            let mut texels = Vec::with_capacity(nw * nh);
+            for t in 0..nh {
+                for s in 0..nw {
+                    let (s2, t2) = (2 * s as i64, 2 * t as i64);
+                    let sum = ret.wrapped_texel(&level, s2, t2) + ret.wrapped_texel(&level, s2 + 1, t2) +
+                        ret.wrapped_texel(&level, s2, t2 + 1) + ret.wrapped_texel(&level, s2 + 1, t2 + 1);
+                    texels.push(sum * 0.25f32);
+                }
+            }
+
+            ret.pyramid.push(level);
+            level = ImageMap::init(texels, Point2f::init([nw as f32, nh as f32]));
+        }
+
+        let alpha = 2f32;
+        ret.weight_lut = (0..WEIGHT_LUT_SIZE).map(|i| {
+            let r2 = i as f32 / (WEIGHT_LUT_SIZE - 1) as f32;
+            (-alpha * r2).exp() - (-alpha).exp()
+        }).collect();
+
+        ret
+    }
+
+    pub fn levels(&self) -> usize {
+        self.pyramid.len()
+    }
+
+    pub fn width(&self) -> usize {
+        self.pyramid[0].width()
+    }
+
+    pub fn height(&self) -> usize {
+        self.pyramid[0].height()
+    }
+
+    pub fn texel(&self, level: usize, s: i64, t: i64) -> Spectrum {
+        self.wrapped_texel(&self.pyramid[level], s, t)
+    }
+
+    // Filtered lookup, dst0 and dst1 span the footprint of the pixel in texture space
+    pub fn lookup(&self, st: &Point2f, dst0: &Vector2f, dst1: &Vector2f) -> Spectrum {
+        match self.filter {
+            MIPFilter::Bilinear => self.bilerp(0, st),
+            MIPFilter::Trilinear => {
+                let width = 2.0 * dst0.x().abs().max(dst0.y().abs()).max(dst1.x().abs()).max(dst1.y().abs());
+                self.lookup_width(st, width)
+            },
+            MIPFilter::EWA => self.lookup_ewa(st, dst0, dst1)
+        }
+    }
+
+    // Isotropic lookup, blends the two levels whose texel spacing brackets width
+    pub fn lookup_width(&self, st: &Point2f, width: f32) -> Spectrum {
+        let level = self.levels() as f32 - 1.0 + width.max(1e-8).log2();
+
+        if level < 0.0 {
+            return self.bilerp(0, st);
+        }
+        if level >= (self.levels() - 1) as f32 {
+            return self.texel(self.levels() - 1, 0, 0);
+        }
+
+        let level_floor = level.floor() as usize;
+        let delta = level - level_floor as f32;
+
+        self.bilerp(level_floor, st) * (1.0 - delta) + self.bilerp(level_floor + 1, st) * delta
+    }
+
+    pub fn bilerp(&self, level: usize, st: &Point2f) -> Spectrum {
+        self.bilerp_image(&self.pyramid[level.min(self.levels() - 1)], st)
+    }
+
+    fn lookup_ewa(&self, st: &Point2f, dst0: &Vector2f, dst1: &Vector2f) -> Spectrum {
+        // dst0 is the major axis of the ellipse
+        let (mut dst0, mut dst1) = (*dst0, *dst1);
+        if dst0.length_sqr() < dst1.length_sqr() {
+            std::mem::swap(&mut dst0, &mut dst1);
+        }
+        let major_length = dst0.length();
+        let mut minor_length = dst1.length();
+
+        // clamp the eccentricity, so very thin ellipses do not visit too many texels
+        if minor_length * self.max_anisotropy < major_length && minor_length > 0.0 {
+            let scale = major_length / (minor_length * self.max_anisotropy);
+            dst1 = dst1 * scale;
+            minor_length *= scale;
+        }
+        if minor_length == 0.0 {
+            return self.bilerp(0, st);
+        }
+
+        let lod = 0f32.max(self.levels() as f32 - 1.0 + minor_length.log2());
+        let lod_floor = lod.floor() as usize;
+        let delta = lod - lod_floor as f32;
+
+        self.ewa(lod_floor, st, &dst0, &dst1) * (1.0 - delta) + self.ewa(lod_floor + 1, st, &dst0, &dst1) * delta
+    }
+
+    fn ewa(&self, level: usize, st: &Point2f, dst0: &Vector2f, dst1: &Vector2f) -> Spectrum {
+        if level >= self.levels() {
+            return self.texel(self.levels() - 1, 0, 0);
+        }
+        let image = &self.pyramid[level];
+        let (w, h) = (image.width() as f32, image.height() as f32);
+
+        let s = st.x() * w - 0.5;
+        let t = st.y() * h - 0.5;
+        let (dst0, dst1) = (Vector2f::init([dst0.x() * w, dst0.y() * h]), Vector2f::init([dst1.x() * w, dst1.y() * h]));
+
+        // implicit ellipse a s^2 + b s t + c t^2 = 1, slightly widened to cover at least a texel
+        let mut a = dst0.y() * dst0.y() + dst1.y() * dst1.y() + 1.0;
+        let mut b = -2.0 * (dst0.x() * dst0.y() + dst1.x() * dst1.y());
+        let mut c = dst0.x() * dst0.x() + dst1.x() * dst1.x() + 1.0;
+        let inv_f = 1.0 / (a * c - b * b * 0.25);
+        a *= inv_f;
+        b *= inv_f;
+        c *= inv_f;
+
+        // bounding box of the ellipse in texel space
+        let det = -b * b + 4.0 * a * c;
+        let inv_det = 1.0 / det;
+        let u_sqrt = (det * c).sqrt();
+        let v_sqrt = (a * det).sqrt();
+        let s0 = (s - 2.0 * inv_det * u_sqrt).ceil() as i64;
+        let s1 = (s + 2.0 * inv_det * u_sqrt).floor() as i64;
+        let t0 = (t - 2.0 * inv_det * v_sqrt).ceil() as i64;
+        let t1 = (t + 2.0 * inv_det * v_sqrt).floor() as i64;
+
+        let mut sum = Spectrum::init_one(0.0);
+        let mut sum_weights = 0f32;
+        for it in t0..=t1 {
+            let tt = it as f32 - t;
+            for is in s0..=s1 {
+                let ss = is as f32 - s;
+                let r2 = a * ss * ss + b * ss * tt + c * tt * tt;
+                if r2 < 1.0 {
+                    let index = ((r2 * WEIGHT_LUT_SIZE as f32) as usize).min(WEIGHT_LUT_SIZE - 1);
+                    let weight = self.weight_lut[index];
+                    sum = sum + self.wrapped_texel(image, is, it) * weight;
+                    sum_weights += weight;
+                }
+            }
+        }
+
+        if sum_weights > 0.0 { sum / sum_weights } else { self.bilerp(level, st) }
+    }
+
+    fn wrapped_texel(&self, image: &ImageMap, s: i64, t: i64) -> Spectrum {
+        let (w, h) = (image.width() as i64, image.height() as i64);
+
+        match self.wrap {
+            ImageWrap::Repeat => image.texel(s.rem_euclid(w), t.rem_euclid(h)),
+            ImageWrap::Clamp => image.texel(s, t),
+            ImageWrap::Black => {
+                if s < 0 || s >= w || t < 0 || t >= h {
+                    Spectrum::init_one(0.0)
+                } else {
+                    image.texel(s, t)
+                }
+            }
+        }
+    }
+
+    fn bilerp_image(&self, image: &ImageMap, st: &Point2f) -> Spectrum {
+        let s = st.x() * image.width() as f32 - 0.5;
+        let t = st.y() * image.height() as f32 - 0.5;
+        let (s0, t0) = (s.floor(), t.floor());
+        let (ds, dt) = (s - s0, t - t0);
+        let (s0, t0) = (s0 as i64, t0 as i64);
+
+        self.wrapped_texel(image, s0, t0) * ((1.0 - ds) * (1.0 - dt)) + self.wrapped_texel(image, s0 + 1, t0) * (ds * (1.0 - dt)) +
+        self.wrapped_texel(image, s0, t0 + 1) * ((1.0 - ds) * dt) + self.wrapped_texel(image, s0 + 1, t0 + 1) * (ds * dt)
+    }
+
+    // Bilinear resampling, used to bring the base image up to a power of two resolution
+    fn resample(&self, image: &ImageMap, width: usize, height: usize) -> ImageMap {
+        let mut texels = Vec::with_capacity(width * height);
+        for t in 0..height {
+            for s in 0..width {
+                let st = Point2f::init([(s as f32 + 0.5) / width as f32, (t as f32 + 0.5) / height as f32]);
+                texels.push(self.bilerp_image(image, &st));
+            }
+        }
+
+        ImageMap::init(texels, Point2f::init([width as f32, height as f32]))
+    }
+
+    pub fn to_string(&self) -> String {
+        format!(
+            "MIPMap[resolution: {}x{}, levels: {}, wrap: {:?}, filter: {:?}, max_anisotropy: {}]",
+            self.width(), self.height(), self.levels(), self.wrap, self.filter, self.max_anisotropy
+        )
+    }
+}
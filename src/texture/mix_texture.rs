@@ -0,0 +1,85 @@
+use crate::common::*;
+
+// Linear blend of two textures, driven by a scalar texture
+pub struct MixTexture<T: TextureValue> {
+    tex1: Arc<dyn Texture<T>>,
+    tex2: Arc<dyn Texture<T>>,
+    amount: Arc<dyn Texture<f32>>
+}
+
+// Constructor
+fn create_mix_texture(prop_list: PropertyList) -> LeadObject {
+    if is_float_texture(&prop_list) {
+        create::<f32>(prop_list)
+    } else {
+        create::<Spectrum>(prop_list)
+    }
+}
+
+fn create<T: TextureValue>(prop_list: PropertyList) -> LeadObject {
+    let mut texture = MixTexture::<T>::new();
+    texture.init(prop_list);
+    T::lead_object(Arc::new(texture))
+}
+
+impl<T: TextureValue> Texture<T> for MixTexture<T> {
+    fn evaluate(&self, its: &SurfaceInteraction) -> T {
+        let amount = self.amount.evaluate(its);
+
+        // skip the lookups that end up with no weight
+        let t1 = if amount != 1.0 { self.tex1.evaluate(its) * (1.0 - amount) } else { T::from_spectrum(&Spectrum::init_one(0.0)) };
+        let t2 = if amount != 0.0 { self.tex2.evaluate(its) * amount } else { T::from_spectrum(&Spectrum::init_one(0.0)) };
+
+        t1 + t2
+    }
+}
+
+impl<T: TextureValue> LeadObjectTrait for MixTexture<T> {
+    fn init(&mut self, prop_list: PropertyList) {
+        let tex1 = T::get_property(&prop_list, "tex1", T::from_spectrum(&Spectrum::init_one(0.0)));
+        let tex2 = T::get_property(&prop_list, "tex2", T::from_spectrum(&Spectrum::init_one(1.0)));
+        self.tex1 = Arc::new(ConstantTexture::new(tex1));
+        self.tex2 = Arc::new(ConstantTexture::new(tex2));
+        self.amount = Arc::new(ConstantTexture::new(prop_list.get_float("amount", 0.5)));
+    }
+
+    fn activate(&mut self) { }
+
+    fn add_child(&mut self, child: &mut LeadObject) {
+        if let Some((name, texture)) = T::texture_child(child) {
+            match name.as_str() {
+                "tex1" => { self.tex1 = texture; return; },
+                "tex2" => { self.tex2 = texture; return; },
+                _ => {}
+            }
+        }
+        if let Some((name, texture)) = f32::texture_child(child) {
+            if name == "amount" {
+                self.amount = texture;
+                return;
+            }
+        }
+
+        println!("Struct MixTexture does not take a child of class {}", child.to_string());
+    }
+
+    fn to_string(&self) -> String {
+        format!(
+            "MixTexture[\n  tex1: {},\n  tex2: {},\n  amount: {}\n]",
+            indent(&self.tex1.to_string(), 2).trim_start(), indent(&self.tex2.to_string(), 2).trim_start(),
+            indent(&self.amount.to_string(), 2).trim_start()
+        )
+    }
+}
+
+impl<T: TextureValue> MixTexture<T> {
+    pub fn new() -> Self {
+        Self {
+            tex1: Arc::new(ConstantTexture::new(T::from_spectrum(&Spectrum::init_one(0.0)))),
+            tex2: Arc::new(ConstantTexture::new(T::from_spectrum(&Spectrum::init_one(1.0)))),
+            amount: Arc::new(ConstantTexture::new(0.5))
+        }
+    }
+}
+
+register_struct!("mix", create_mix_texture);
@@ -0,0 +1,15 @@
+pub mod texture;
+pub mod mipmap;
+pub mod constant_texture;
+pub mod image_texture;
+pub mod checkerboard_texture;
+pub mod scale_texture;
+pub mod mix_texture;
+
+pub use texture::{Texture, TextureValue, TextureMapping2D, UVMapping2D, is_float_texture};
+pub use mipmap::{MIPMap, ImageWrap, MIPFilter};
+pub use constant_texture::ConstantTexture;
+pub use image_texture::ImageTexture;
+pub use checkerboard_texture::CheckerboardTexture;
+pub use scale_texture::ScaleTexture;
+pub use mix_texture::MixTexture;
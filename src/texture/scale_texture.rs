@@ -0,0 +1,73 @@
+use crate::common::*;
+
+// Product of a texture and a scalar texture
+pub struct ScaleTexture<T: TextureValue> {
+    tex: Arc<dyn Texture<T>>,
+    scale: Arc<dyn Texture<f32>>
+}
+
+// Constructor
+fn create_scale_texture(prop_list: PropertyList) -> LeadObject {
+    if is_float_texture(&prop_list) {
+        create::<f32>(prop_list)
+    } else {
+        create::<Spectrum>(prop_list)
+    }
+}
+
+fn create<T: TextureValue>(prop_list: PropertyList) -> LeadObject {
+    let mut texture = ScaleTexture::<T>::new();
+    texture.init(prop_list);
+    T::lead_object(Arc::new(texture))
+}
+
+impl<T: TextureValue> Texture<T> for ScaleTexture<T> {
+    fn evaluate(&self, its: &SurfaceInteraction) -> T {
+        self.tex.evaluate(its) * self.scale.evaluate(its)
+    }
+}
+
+impl<T: TextureValue> LeadObjectTrait for ScaleTexture<T> {
+    fn init(&mut self, prop_list: PropertyList) {
+        let tex = T::get_property(&prop_list, "tex", T::from_spectrum(&Spectrum::init_one(1.0)));
+        self.tex = Arc::new(ConstantTexture::new(tex));
+        self.scale = Arc::new(ConstantTexture::new(prop_list.get_float("scale", 1.0)));
+    }
+
+    fn activate(&mut self) { }
+
+    fn add_child(&mut self, child: &mut LeadObject) {
+        if let Some((name, texture)) = T::texture_child(child) {
+            if name == "tex" {
+                self.tex = texture;
+                return;
+            }
+        }
+        if let Some((name, texture)) = f32::texture_child(child) {
+            if name == "scale" {
+                self.scale = texture;
+                return;
+            }
+        }
+
+        println!("Struct ScaleTexture does not take a child of class {}", child.to_string());
+    }
+
+    fn to_string(&self) -> String {
+        format!(
+            "ScaleTexture[\n  tex: {},\n  scale: {}\n]",
+            indent(&self.tex.to_string(), 2).trim_start(), indent(&self.scale.to_string(), 2).trim_start()
+        )
+    }
+}
+
+impl<T: TextureValue> ScaleTexture<T> {
+    pub fn new() -> Self {
+        Self {
+            tex: Arc::new(ConstantTexture::new(T::from_spectrum(&Spectrum::init_one(1.0)))),
+            scale: Arc::new(ConstantTexture::new(1.0))
+        }
+    }
+}
+
+register_struct!("scale", create_scale_texture);
@@ -0,0 +1,110 @@
+use std::ops::{Add, Mul};
+
+use crate::common::*;
+
+// A quantity varying over surfaces, evaluated at a hit point
+pub trait Texture<T>: LeadObjectTrait {
+    fn evaluate(&self, its: &SurfaceInteraction) -> T;
+}
+
+// The values a texture can produce, f32 for scalar and Spectrum for colour parameters
+pub trait TextureValue: Copy + Add<Output = Self> + Mul<f32, Output = Self> + 'static {
+    fn from_spectrum(s: &Spectrum) -> Self;
+    fn get_property(prop_list: &PropertyList, k: &str, default: Self) -> Self;
+    // A texture child of this value type, along with the parameter it was declared for
+    fn texture_child(child: &LeadObject) -> Option<(String, Arc<dyn Texture<Self>>)>;
+    fn lead_object(texture: Arc<dyn Texture<Self>>) -> LeadObject;
+    fn describe(&self) -> String;
+}
+
+impl TextureValue for f32 {
+    fn from_spectrum(s: &Spectrum) -> Self { s.y() as f32 }
+
+    fn get_property(prop_list: &PropertyList, k: &str, default: Self) -> Self {
+        prop_list.get_float(k, default)
+    }
+
+    fn texture_child(child: &LeadObject) -> Option<(String, Arc<dyn Texture<Self>>)> {
+        match child {
+            LeadObject::FloatTexture(name, texture) => Some((name.clone(), texture.clone())),
+            _ => None
+        }
+    }
+
+    fn lead_object(texture: Arc<dyn Texture<Self>>) -> LeadObject {
+        LeadObject::FloatTexture(String::new(), texture)
+    }
+
+    fn describe(&self) -> String { format!("{}", self) }
+}
+
+impl TextureValue for Spectrum {
+    fn from_spectrum(s: &Spectrum) -> Self { *s }
+
+    fn get_property(prop_list: &PropertyList, k: &str, default: Self) -> Self {
+        prop_list.get_rgb(k, default)
+    }
+
+    fn texture_child(child: &LeadObject) -> Option<(String, Arc<dyn Texture<Self>>)> {
+        match child {
+            LeadObject::SpectrumTexture(name, texture) => Some((name.clone(), texture.clone())),
+            _ => None
+        }
+    }
+
+    fn lead_object(texture: Arc<dyn Texture<Self>>) -> LeadObject {
+        LeadObject::SpectrumTexture(String::new(), texture)
+    }
+
+    fn describe(&self) -> String { self.to_string() }
+}
+
+// Textures are spectral unless declared with <string name="format" value="float"/>
+pub fn is_float_texture(prop_list: &PropertyList) -> bool {
+    match prop_list.get_string("format", "spectrum").as_str() {
+        "float" => true,
+        "spectrum" => false,
+        format => panic!("Unknown texture format {}!", format)
+    }
+}
+
+pub trait TextureMapping2D {
+    // Texture coordinates at the hit, along with their screen space derivatives
+    fn map(&self, its: &SurfaceInteraction, dstdx: &mut Vector2f, dstdy: &mut Vector2f) -> Point2f;
+}
+
+pub struct UVMapping2D {
+    su: f32, sv: f32,
+    du: f32, dv: f32
+}
+
+impl TextureMapping2D for UVMapping2D {
+    fn map(&self, its: &SurfaceInteraction, dstdx: &mut Vector2f, dstdy: &mut Vector2f) -> Point2f {
+        *dstdx = Vector2f::init([self.su * its.dudx, self.sv * its.dvdx]);
+        *dstdy = Vector2f::init([self.su * its.dudy, self.sv * its.dvdy]);
+
+        Point2f::init([self.su * its.uv.x() + self.du, self.sv * its.uv.y() + self.dv])
+    }
+}
+
+impl UVMapping2D {
+    pub fn new() -> Self {
+        Self {
+            su: 1.0, sv: 1.0,
+            du: 0.0, dv: 0.0
+        }
+    }
+
+    pub fn init(prop_list: &PropertyList) -> Self {
+        Self {
+            su: prop_list.get_float("u_scale", 1.0),
+            sv: prop_list.get_float("v_scale", 1.0),
+            du: prop_list.get_float("u_offset", 0.0),
+            dv: prop_list.get_float("v_offset", 0.0)
+        }
+    }
+
+    pub fn to_string(&self) -> String {
+        format!("UVMapping2D[scale: ({}, {}), offset: ({}, {})]", self.su, self.sv, self.du, self.dv)
+    }
+}
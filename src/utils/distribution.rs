@@ -0,0 +1,120 @@
+use crate::common::*;
+
+// Piecewise-constant 1D function, sampled in proportion to its value
+pub struct Distribution1D {
+    pub func: Vec<f32>,
+    pub cdf: Vec<f32>,
+    pub func_int: f32,
+}
+
+impl Distribution1D {
+    pub fn new(f: &[f32]) -> Self {
+        let n = f.len();
+        let func = f.to_vec();
+        let mut cdf = vec![0f32; n + 1];
+
+        for i in 1..n + 1 {
+            cdf[i] = cdf[i - 1] + func[i - 1].abs() / n as f32;
+        }
+
+        let func_int = cdf[n];
+        if func_int == 0.0 {
+            // nothing to importance sample, fall back to uniform
+            for (i, c) in cdf.iter_mut().enumerate().skip(1) {
+                *c = i as f32 / n as f32;
+            }
+        } else {
+            for c in cdf.iter_mut().skip(1) {
+                *c /= func_int;
+            }
+        }
+
+        Self {
+            func,
+            cdf,
+            func_int
+        }
+    }
+
+    pub fn count(&self) -> usize {
+        self.func.len()
+    }
+
+    // Last cdf entry that is <= u
+    fn find_interval(&self, u: f32) -> usize {
+        let idx = self.cdf.partition_point(|c| *c <= u);
+        idx.saturating_sub(1).min(self.cdf.len() - 2)
+    }
+
+    pub fn sample_continuous(&self, u: f32, pdf: &mut f32, offset: &mut usize) -> f32 {
+        let idx = self.find_interval(u);
+        *offset = idx;
+
+        let mut du = u - self.cdf[idx];
+        if self.cdf[idx + 1] - self.cdf[idx] > 0.0 {
+            du /= self.cdf[idx + 1] - self.cdf[idx];
+        }
+
+        *pdf = if self.func_int > 0.0 { self.func[idx] / self.func_int } else { 0.0 };
+
+        (idx as f32 + du) / self.count() as f32
+    }
+
+    pub fn sample_discrete(&self, u: f32, pdf: &mut f32) -> usize {
+        let idx = self.find_interval(u);
+        *pdf = self.discrete_pdf(idx);
+
+        idx
+    }
+
+    pub fn discrete_pdf(&self, index: usize) -> f32 {
+        if self.func_int == 0.0 {
+            return 0.0;
+        }
+
+        self.func[index] / (self.func_int * self.count() as f32)
+    }
+}
+
+// Piecewise-constant 2D function, sampled as a marginal over v and conditionals over u
+pub struct Distribution2D {
+    p_conditional_v: Vec<Distribution1D>,
+    p_marginal: Distribution1D,
+}
+
+impl Distribution2D {
+    // func holds nv rows of nu values
+    pub fn new(func: &[f32], nu: usize, nv: usize) -> Self {
+        let p_conditional_v: Vec<Distribution1D> = (0..nv).map(|v| Distribution1D::new(&func[v * nu..(v + 1) * nu])).collect();
+        let marginal_func: Vec<f32> = p_conditional_v.iter().map(|conditional| conditional.func_int).collect();
+
+        Self {
+            p_conditional_v,
+            p_marginal: Distribution1D::new(&marginal_func)
+        }
+    }
+
+    pub fn sample_continuous(&self, u: &Point2f, pdf: &mut f32) -> Point2f {
+        let mut pdfs = [0f32; 2];
+        let mut v = 0usize;
+
+        let d1 = self.p_marginal.sample_continuous(u.y(), &mut pdfs[1], &mut v);
+        let d0 = self.p_conditional_v[v].sample_continuous(u.x(), &mut pdfs[0], &mut v);
+        *pdf = pdfs[0] * pdfs[1];
+
+        Point2f::init([d0, d1])
+    }
+
+    pub fn pdf(&self, p: &Point2f) -> f32 {
+        let nu = self.p_conditional_v[0].count();
+        let nv = self.p_marginal.count();
+        let iu = ((p.x() * nu as f32) as usize).min(nu - 1);
+        let iv = ((p.y() * nv as f32) as usize).min(nv - 1);
+
+        if self.p_marginal.func_int == 0.0 {
+            return 0.0;
+        }
+
+        self.p_conditional_v[iv].func[iu] / self.p_marginal.func_int
+    }
+}
@@ -0,0 +1,73 @@
+use crate::common::*;
+
+// An rgb image kept in memory for lookups, rows stored top to bottom
+pub struct ImageMap {
+    pub texels: Vec<Spectrum>,
+    pub resolution: Point2f,
+}
+
+impl ImageMap {
+    pub fn new() -> Self {
+        Self {
+            texels: Vec::new(),
+            resolution: Point2f::new()
+        }
+    }
+
+    pub fn init(texels: Vec<Spectrum>, resolution: Point2f) -> Self {
+        assert!(texels.len() == (resolution.x() * resolution.y()) as usize, "Image does not match its resolution!");
+
+        Self {
+            texels,
+            resolution
+        }
+    }
+
+    pub fn read(filename: &str) -> Self {
+        let (texels, resolution) = read_image_from_file(filename);
+        Self::init(texels, resolution)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.texels.is_empty()
+    }
+
+    pub fn width(&self) -> usize {
+        self.resolution.x() as usize
+    }
+
+    pub fn height(&self) -> usize {
+        self.resolution.y() as usize
+    }
+
+    // Out of range coordinates are clamped to the border
+    pub fn texel(&self, x: i64, y: i64) -> Spectrum {
+        let x = x.clamp(0, self.width() as i64 - 1) as usize;
+        let y = y.clamp(0, self.height() as i64 - 1) as usize;
+
+        self.texels[y * self.width() + x]
+    }
+
+    // st in [0, 1]^2, bilinearly interpolated between texel centers
+    pub fn bilerp(&self, st: &Point2f) -> Spectrum {
+        let x = st.x() * self.resolution.x() - 0.5;
+        let y = st.y() * self.resolution.y() - 0.5;
+        let x0 = x.floor();
+        let y0 = y.floor();
+        let dx = (x - x0) as f64;
+        let dy = (y - y0) as f64;
+        let (x0, y0) = (x0 as i64, y0 as i64);
+
+        self.texel(x0, y0) * ((1.0 - dx) * (1.0 - dy)) + self.texel(x0 + 1, y0) * (dx * (1.0 - dy)) +
+        self.texel(x0, y0 + 1) * ((1.0 - dx) * dy) + self.texel(x0 + 1, y0 + 1) * (dx * dy)
+    }
+
+    pub fn average(&self) -> Spectrum {
+        let mut sum = Spectrum::init_one(0.0);
+        for texel in &self.texels {
+            sum = sum + *texel;
+        }
+
+        if self.texels.is_empty() { sum } else { sum / self.texels.len() as f32 }
+    }
+}
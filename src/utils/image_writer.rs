@@ -18,4 +18,19 @@ pub fn write_image_to_file(rgb: Vec<f32>, filename: String, resolution: &Point2f
             (r, g, b, a)
         }
     ).unwrap();
-}
\ No newline at end of file
+}
+// Reads the first rgba layer of an exr into rows of spectra, top row first
+pub fn read_image_from_file(filename: &str) -> (Vec<Spectrum>, Point2f) {
+    let image = read_first_rgba_layer_from_file(
+        filename,
+        |resolution, _| {
+            (vec![Spectrum::init_one(0.0); resolution.width() * resolution.height()], resolution.width(), resolution.height())
+        },
+        |(pixels, width, _), position, (r, g, b, _a): (f32, f32, f32, f32)| {
+            pixels[position.y() * *width + position.x()] = Spectrum::from_rgb([r as f64, g as f64, b as f64]);
+        }
+    ).unwrap_or_else(|e| panic!("Could not read image {filename}: {e}"));
+
+    let (pixels, width, height) = image.layer_data.channel_data.pixels;
+    (pixels, Point2f::init([width as f32, height as f32]))
+}
@@ -21,6 +21,7 @@ impl Shading {
 pub trait Interaction {
     fn p(&self) -> Point3f;
     fn t(&self) -> f32;
+    fn time(&self) -> f32;
     fn wo(&self) -> Vector3f;
     fn n(&self) -> Normal3f;
     fn medium_interface(&self) -> &MediumInterface;
@@ -29,8 +30,11 @@ pub trait Interaction {
         Normal3f::equal(&self.n(), &Normal3f::new())
     }
 
+    // Spawned rays leave at the same moment the interaction happened
     fn spawn_ray(&self, d: &Vector3f) -> Ray {
-        Ray::init(&self.p(), d, EPSILON, INFINITY)
+        let mut ray = Ray::init(&self.p(), d, EPSILON, INFINITY);
+        ray.time = self.time();
+        ray
     }
 
     fn spawn_ray_to(&self, p: &Point3f) -> Ray {
@@ -42,7 +46,9 @@ pub trait Interaction {
         let o = self.p();
         let d = it.p() - o;
 
-        Ray::init(&o, &d, EPSILON, INFINITY)
+        let mut ray = Ray::init(&o, &d, EPSILON, INFINITY);
+        ray.time = self.time();
+        ray
     }
 
     fn is_medium_interaction(&self) -> bool {
@@ -70,6 +76,7 @@ pub trait Interaction {
 pub struct SurfaceInteraction {
     pub p: Point3f,
     pub t: f32,
+    pub time: f32,
     pub wo: Vector3f,
     pub n: Normal3f,
     pub medium_interface: MediumInterface,
@@ -81,13 +88,15 @@ pub struct SurfaceInteraction {
     pub dpdx: Vector3f, pub dpdy: Vector3f,
     pub dudx: f32, pub dvdx: f32, pub dudy: f32, pub dvdy: f32,
 
-    // TODO add BSDF, BSSRDF
+    pub bsdf: Option<Arc<BSDF>>,
+    // TODO add BSSRDF
     pub primitive: Option<Arc<dyn Primitive>>,
 }
 
 impl Interaction for SurfaceInteraction {
     fn p(&self) -> Point3f { self.p }
     fn t(&self) -> f32 { self.t }
+    fn time(&self) -> f32 { self.time }
     fn n(&self) -> Normal3f { self.n }
     fn wo(&self) -> Vector3f { self.wo }
     fn medium_interface(&self) -> &MediumInterface { &self.medium_interface }
@@ -98,6 +107,7 @@ impl SurfaceInteraction {
         Self {
             p: Point3f::new(),
             t: 0f32,
+            time: 0f32,
             wo: Vector3f::new(),
             n: Normal3f::new(),
             medium_interface: MediumInterface::new(),
@@ -108,6 +118,7 @@ impl SurfaceInteraction {
             shading: Shading::new(),
             dpdx: Vector3f::new(), dpdy: Vector3f::new(),
             dudx: 0f32, dudy: 0f32, dvdx: 0f32, dvdy: 0f32,
+            bsdf: None,
             primitive: None
         }
     }
@@ -146,6 +157,27 @@ impl SurfaceInteraction {
         ret
     }
 
+    // Weingarten equations, the change of the normal along u and v from the second derivatives of p
+    pub fn normal_derivatives(dpdu: &Vector3f, dpdv: &Vector3f, d2pduu: &Vector3f, d2pduv: &Vector3f, d2pdvv: &Vector3f) -> (Normal3f, Normal3f) {
+        let big_e = Vector3f::dot(dpdu, dpdu);
+        let big_f = Vector3f::dot(dpdu, dpdv);
+        let big_g = Vector3f::dot(dpdv, dpdv);
+        let n = Vector3f::normalize(&Vector3f::cross(dpdu, dpdv));
+        let e = Vector3f::dot(&n, d2pduu);
+        let f = Vector3f::dot(&n, d2pduv);
+        let g = Vector3f::dot(&n, d2pdvv);
+
+        let inv_egf2 = 1.0 / (big_e * big_g - big_f * big_f);
+        if !inv_egf2.is_finite() {
+            return (Normal3f::new(), Normal3f::new());
+        }
+
+        let dndu = Normal3f::init_vector(&(*dpdu * ((f * big_f - e * big_g) * inv_egf2) + *dpdv * ((e * big_f - f * big_e) * inv_egf2)));
+        let dndv = Normal3f::init_vector(&(*dpdu * ((g * big_f - f * big_g) * inv_egf2) + *dpdv * ((f * big_f - g * big_e) * inv_egf2)));
+
+        (dndu, dndv)
+    }
+
     pub fn set_shape(&mut self, shape: Arc<dyn Shape>) {
         self.shape = Some(shape);
     }
@@ -154,6 +186,76 @@ impl SurfaceInteraction {
         self.primitive = Some(primitive);
     }
 
+    // Asks the primitive that was hit to fill in the bsdf
+    pub fn compute_scattering_functions(&mut self, ray: &RayDifferential, mode: TransportMode, allow_multiple_lobes: bool) {
+        self.compute_differentials(ray);
+
+        if let Some(primitive) = self.primitive.clone() {
+            primitive.compute_scattering_functions(self, mode, allow_multiple_lobes);
+        }
+    }
+
+    // Screen space derivatives of p and uv, from where the offset rays meet the tangent plane
+    pub fn compute_differentials(&mut self, ray: &RayDifferential) {
+        self.dpdx = Vector3f::new(); self.dpdy = Vector3f::new();
+        self.dudx = 0.0; self.dvdx = 0.0; self.dudy = 0.0; self.dvdy = 0.0;
+        if !ray.has_differential {
+            return;
+        }
+
+        let n = Vector3f::init([self.n.x(), self.n.y(), self.n.z()]);
+        let p = Vector3f::init([self.p.x(), self.p.y(), self.p.z()]);
+        let d = Vector3f::dot(&n, &p);
+
+        let tx = -(Vector3f::dot(&n, &Vector3f::init([ray.rx_o.x(), ray.rx_o.y(), ray.rx_o.z()])) - d) / Vector3f::dot(&n, &ray.rx_d);
+        let ty = -(Vector3f::dot(&n, &Vector3f::init([ray.ry_o.x(), ray.ry_o.y(), ray.ry_o.z()])) - d) / Vector3f::dot(&n, &ray.ry_d);
+        if !tx.is_finite() || !ty.is_finite() {
+            return;
+        }
+
+        let px = ray.rx_o + ray.rx_d * tx;
+        let py = ray.ry_o + ray.ry_d * ty;
+        let dpdx = px - self.p;
+        let dpdy = py - self.p;
+
+        // solve the overdetermined system on the two axes the normal is least aligned with
+        let dim = if self.n.x().abs() > self.n.y().abs() && self.n.x().abs() > self.n.z().abs() {
+            [1, 2]
+        } else if self.n.y().abs() > self.n.z().abs() {
+            [0, 2]
+        } else {
+            [0, 1]
+        };
+
+        let a = [[self.dpdu[dim[0]], self.dpdv[dim[0]]], [self.dpdu[dim[1]], self.dpdv[dim[1]]]];
+        let bx = [dpdx[dim[0]], dpdx[dim[1]]];
+        let by = [dpdy[dim[0]], dpdy[dim[1]]];
+
+        let (mut dudx, mut dvdx) = (0f32, 0f32);
+        let (mut dudy, mut dvdy) = (0f32, 0f32);
+        if !Solver::linear_system_2x2(a, bx, &mut dudx, &mut dvdx) || !Solver::linear_system_2x2(a, by, &mut dudy, &mut dvdy) {
+            return;
+        }
+
+        self.dpdx = dpdx;
+        self.dpdy = dpdy;
+        self.dudx = dudx; self.dvdx = dvdx;
+        self.dudy = dudy; self.dvdy = dvdy;
+    }
+
+    // Radiance emitted from the hit point, if it lies on an area light
+    pub fn le(&self, w: &Vector3f) -> Spectrum {
+        let area_light = match &self.primitive {
+            Some(primitive) => primitive.get_area_light(),
+            None => None
+        };
+
+        match area_light {
+            Some(area_light) => area_light.l(self, w),
+            None => Spectrum::init_one(0.0)
+        }
+    }
+
     pub fn set_shading_geometry(&mut self, dpdus: Vector3f, dpdvs: Vector3f, dndus: Normal3f, dndvs: Normal3f, orientation_is_authority: bool) {
         let c_p = Vector3f::cross(&dpdus, &dpdvs);
         let n_temp = Normal3f::normalize(&Normal3f::init([c_p.x(), c_p.y(), c_p.z()]));
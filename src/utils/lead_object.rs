@@ -8,11 +8,25 @@ pub trait LeadObjectTrait {
     fn add_child(&mut self, child: &mut LeadObject);
 }
 
+#[derive(Clone)]
 pub enum LeadObject {
     Scene(Arc<dyn SceneTrait>),
     Shape(Arc<dyn Shape>),
     Camera(Arc<dyn Camera>),
     Sampler(Arc<dyn Sampler>),
+    Film(Arc<Film>),
+    // Declared as <rfilter> inside a film
+    Filter(Arc<dyn Filter>),
+    Integrator(Arc<dyn Integrator>),
+    Light(Arc<dyn Light>),
+    AreaLight(Arc<dyn AreaLight>),
+    Material(Arc<dyn Material>),
+    // Textures carry the name of the parameter they were declared for
+    FloatTexture(String, Arc<dyn Texture<f32>>),
+    SpectrumTexture(String, Arc<dyn Texture<Spectrum>>),
+    // Built by the parser, e.g. an instance of a named object
+    Primitive(Arc<dyn Primitive>),
+    Aggregate(Arc<dyn Aggregate>),
     Unknown(())
 }
 
@@ -23,6 +37,16 @@ impl LeadObject {
             LeadObject::Shape(_) => "shape",
             LeadObject::Camera(_) => "camera",
             LeadObject::Sampler(_) => "sampler",
+            LeadObject::Film(_) => "film",
+            LeadObject::Filter(_) => "filter",
+            LeadObject::Integrator(_) => "integrator",
+            LeadObject::Light(_) => "light",
+            LeadObject::AreaLight(_) => "area_light",
+            LeadObject::Material(_) => "material",
+            LeadObject::FloatTexture(_, _) => "float_texture",
+            LeadObject::SpectrumTexture(_, _) => "spectrum_texture",
+            LeadObject::Primitive(_) => "primitive",
+            LeadObject::Aggregate(_) => "aggregate",
             LeadObject::Unknown(_) => "Unknown",
         }
     }
@@ -33,6 +57,16 @@ impl LeadObject {
             LeadObject::Shape(s) => Arc::get_mut(s).unwrap().add_child(child),
             LeadObject::Camera(s) => Arc::get_mut(s).unwrap().add_child(child),
             LeadObject::Sampler(s) => Arc::get_mut(s).unwrap().add_child(child),
+            LeadObject::Film(s) => Arc::get_mut(s).unwrap().add_child(child),
+            LeadObject::Filter(_) => panic!("Cannot add child to a filter!"),
+            LeadObject::Integrator(s) => Arc::get_mut(s).unwrap().add_child(child),
+            LeadObject::Light(s) => Arc::get_mut(s).unwrap().add_child(child),
+            LeadObject::AreaLight(s) => Arc::get_mut(s).unwrap().add_child(child),
+            LeadObject::Material(s) => Arc::get_mut(s).unwrap().add_child(child),
+            LeadObject::FloatTexture(_, s) => Arc::get_mut(s).unwrap().add_child(child),
+            LeadObject::SpectrumTexture(_, s) => Arc::get_mut(s).unwrap().add_child(child),
+            LeadObject::Primitive(_) => panic!("Cannot add child to a primitive!"),
+            LeadObject::Aggregate(s) => Arc::get_mut(s).unwrap().add_child(child),
             LeadObject::Unknown(_) => panic!("Cannot add child to unknown object!")
         };
     }
@@ -43,7 +77,25 @@ impl LeadObject {
             LeadObject::Shape(s) => Arc::get_mut(s).unwrap().activate(),
             LeadObject::Camera(s) => Arc::get_mut(s).unwrap().activate(),
             LeadObject::Sampler(s) => Arc::get_mut(s).unwrap().activate(),
+            LeadObject::Film(s) => Arc::get_mut(s).unwrap().activate(),
+            LeadObject::Filter(_) => {},
+            LeadObject::Integrator(s) => Arc::get_mut(s).unwrap().activate(),
+            LeadObject::Light(s) => Arc::get_mut(s).unwrap().activate(),
+            LeadObject::AreaLight(s) => Arc::get_mut(s).unwrap().activate(),
+            LeadObject::Material(s) => Arc::get_mut(s).unwrap().activate(),
+            LeadObject::FloatTexture(_, s) => Arc::get_mut(s).unwrap().activate(),
+            LeadObject::SpectrumTexture(_, s) => Arc::get_mut(s).unwrap().activate(),
+            LeadObject::Primitive(_) => {},
+            LeadObject::Aggregate(s) => Arc::get_mut(s).unwrap().activate(),
             LeadObject::Unknown(_) => panic!("Cannot activate unknown object")
         }
     }
-}
\ No newline at end of file
+
+    // Binds a texture to the parameter name it is given in the XML
+    pub fn set_name(&mut self, name: &str) {
+        match self {
+            LeadObject::FloatTexture(n, _) | LeadObject::SpectrumTexture(n, _) => *n = name.to_string(),
+            _ => {}
+        }
+    }
+}
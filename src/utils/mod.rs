@@ -6,6 +6,9 @@ pub mod solver;
 pub mod vis_test;
 pub mod warp_samples;
 pub mod image_writer;
+pub mod image_map;
+pub mod distribution;
+pub mod stats;
 
 pub use interaction::{Shading, Interaction, SurfaceInteraction};
 pub use lead_object::{LeadObject, LeadObjectTrait};
@@ -14,4 +17,7 @@ pub use rng::RNG;
 pub use solver::Solver;
 pub use vis_test::VisibilityTester;
 pub use warp_samples::Warp;
-pub use image_writer::write_image_to_file;
\ No newline at end of file
+pub use image_writer::{write_image_to_file, read_image_from_file};
+pub use image_map::ImageMap;
+pub use distribution::{Distribution1D, Distribution2D};
+pub use stats::{report_thread_stats, clear_stats, stats_to_string, category_stats_to_string, stats_to_json};
\ No newline at end of file
@@ -11,8 +11,11 @@ pub struct PropertyList{
     point_3s: HashMap<String, Point3f>,
     vector_2s: HashMap<String, Vector2f>,
     vector_3s: HashMap<String, Vector3f>,
+    rgbs: HashMap<String, Spectrum>,
 
     transform_matrix: Matrix4x4,
+    // Transforms given in <transform time="..."> blocks, at their time
+    keyframes: Vec<(f32, Matrix4x4)>,
 }
 
 impl PropertyList{
@@ -28,7 +31,9 @@ impl PropertyList{
             point_3s: HashMap::new(),
             vector_2s: HashMap::new(),
             vector_3s: HashMap::new(),
-            transform_matrix: Matrix4x4::identity()
+            rgbs: HashMap::new(),
+            transform_matrix: Matrix4x4::identity(),
+            keyframes: Vec::new()
         }
     }
 
@@ -40,6 +45,7 @@ impl PropertyList{
             "bool" => true,
             "point2" | "point3" => true,
             "vector2" | "vector3" => true,
+            "rgb" => true,
 
             // For transform
             "scale" => true,
@@ -52,7 +58,7 @@ impl PropertyList{
     pub fn is_property_valid(name: &str, attrs: &HashMap<String, String>) -> bool {
         match name {
             "string" | "float" | "int" | "bool" => attrs.contains_key("name") && attrs.contains_key("value"),
-            "point2" | "point3" | "vector2" | "vector3" => attrs.contains_key("name") && attrs.contains_key("value"),
+            "point2" | "point3" | "vector2" | "vector3" | "rgb" => attrs.contains_key("name") && attrs.contains_key("value"),
             "scale" | "translate" => attrs.contains_key("value"),
             "rotate" => attrs.contains_key("axis") && attrs.contains_key("angle"),
             _ => false
@@ -109,6 +115,9 @@ impl PropertyList{
             "vector3" => {
                 self.set_vector3(key, value);
             }
+            "rgb" => {
+                self.set_rgb(key, value);
+            }
             _ => {}
         }
     }
@@ -149,6 +158,11 @@ impl PropertyList{
         self.vector_3s.insert(k, Vector3f::init_string(v));
     }
 
+    pub fn set_rgb(&mut self, k: String, v: String) {
+        let rgb = Vector3f::init_string(v);
+        self.rgbs.insert(k, Spectrum::from_rgb([rgb.x() as f64, rgb.y() as f64, rgb.z() as f64]));
+    }
+
     pub fn add_scale(&mut self, v: String) {
         let scaling_vector = Vector3f::init_string(v);
         let scaling_transform = Transform::scale(&scaling_vector);
@@ -203,7 +217,35 @@ impl PropertyList{
         self.vector_3s.get(k).cloned().unwrap_or(default)
     }
 
+    pub fn get_rgb(&self, k: &str, default: Spectrum) -> Spectrum {
+        self.rgbs.get(k).cloned().unwrap_or(default)
+    }
+
+    // The transforms in the block are applied after the ones given outside of any block
+    pub fn add_keyframe(&mut self, time: f32, keyframe: &PropertyList) {
+        if self.keyframes.iter().any(|(t, _)| *t == time) {
+            panic!("Transform keyframe at time {time} is given twice!");
+        }
+        self.keyframes.push((time, keyframe.transform_matrix.clone()));
+        self.keyframes.sort_by(|k_1, k_2| k_1.0.total_cmp(&k_2.0));
+    }
+
+    // Where the object is at the first keyframe, for objects that do not move
     pub fn get_transform(&self) -> Transform {
-        Transform::init_mat(&self.transform_matrix)
+        match self.keyframes.first() {
+            Some((_, keyframe)) => Transform::init_mat(&Matrix4x4::mul(keyframe, &self.transform_matrix)),
+            None => Transform::init_mat(&self.transform_matrix)
+        }
+    }
+
+    pub fn get_animated_transform(&self) -> AnimatedTransform {
+        match self.keyframes.as_slice() {
+            [] | [_] => AnimatedTransform::init_static(self.get_transform()),
+            [(start_time, start), (end_time, end)] => AnimatedTransform::new(
+                Transform::init_mat(&Matrix4x4::mul(start, &self.transform_matrix)), *start_time,
+                Transform::init_mat(&Matrix4x4::mul(end, &self.transform_matrix)), *end_time
+            ),
+            _ => panic!("Only two transform keyframes are supported, got {}!", self.keyframes.len())
+        }
     }
 }
\ No newline at end of file
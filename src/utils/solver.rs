@@ -1,20 +1,47 @@
 pub struct Solver { }
 
 impl Solver {
+    // Roots come back in increasing order, a zero a falls back to the linear equation
     pub fn quadratic(a: f32, b: f32, c: f32, r_1: &mut Option<f32>, r_2: &mut Option<f32>) -> bool {
-        
+        if a == 0.0 {
+            if b == 0.0 {
+                return false;
+            }
+            *r_1 = Some(-c / b);
+            *r_2 = Some(-c / b);
+            return true;
+        }
+
+        let (a, b, c) = (a as f64, b as f64, c as f64);
         let det = b*b - 4.0*a*c;
         if det < 0.0{
             return false;
         }
 
+        // avoids the cancellation of -b + sqrt(det) when b is large
         let sub = det.sqrt();
-        let r1 = (-b - sub) / (2.0 * a);
-        let r2 = (-b + sub) / (2.0 * a);
+        let q = if b < 0.0 { -0.5 * (b - sub) } else { -0.5 * (b + sub) };
+        let mut r1 = (q / a) as f32;
+        let mut r2 = if q != 0.0 { (c / q) as f32 } else { r1 };
+        if r1 > r2 {
+            std::mem::swap(&mut r1, &mut r2);
+        }
 
         *r_1 = Some(r1);
         *r_2 = Some(r2);
 
         true
     }
+
+    pub fn linear_system_2x2(a: [[f32; 2]; 2], b: [f32; 2], x0: &mut f32, x1: &mut f32) -> bool {
+        let det = a[0][0] * a[1][1] - a[0][1] * a[1][0];
+        if det.abs() < 1e-10 {
+            return false;
+        }
+
+        *x0 = (a[1][1] * b[0] - a[0][1] * b[1]) / det;
+        *x1 = (a[0][0] * b[1] - a[1][0] * b[0]) / det;
+
+        x0.is_finite() && x1.is_finite()
+    }
 }
\ No newline at end of file
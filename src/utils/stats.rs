@@ -0,0 +1,316 @@
+use std::cell::Cell;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use lazy_static::lazy_static;
+
+// Statistics are thread locals declared with the stat_* macros, so recording one is a plain add on
+// the thread's own copy. A thread folds its copies into the global accumulator with
+// report_thread_stats once it is done. Names are "Category/Description", and statistics declared
+// with the same name in different places are added together
+
+type StatReporter = fn(&mut StatsAccumulator);
+
+lazy_static! {
+    static ref REPORTERS: Mutex<Vec<StatReporter>> = Mutex::new(Vec::new());
+    static ref ACCUMULATOR: Mutex<StatsAccumulator> = Mutex::new(StatsAccumulator::new());
+}
+
+// Called by the stat_* macros before main
+pub fn register_stat(reporter: StatReporter) {
+    REPORTERS.lock().unwrap().push(reporter);
+}
+
+// Adds everything this thread recorded to the totals and zeroes it
+pub fn report_thread_stats() {
+    let reporters = REPORTERS.lock().unwrap();
+    let mut accumulator = ACCUMULATOR.lock().unwrap();
+    for reporter in reporters.iter() {
+        reporter(&mut accumulator);
+    }
+}
+
+pub fn clear_stats() {
+    ACCUMULATOR.lock().unwrap().stats.clear();
+}
+
+pub fn stats_to_string() -> String {
+    ACCUMULATOR.lock().unwrap().to_string()
+}
+
+// Only the statistics of one category, e.g. "Accelerator"
+pub fn category_stats_to_string(category: &str) -> String {
+    let accumulator = ACCUMULATOR.lock().unwrap();
+    let stats = accumulator.stats.iter()
+        .filter(|(name, _)| name.split_once('/').is_some_and(|(name_category, _)| name_category == category))
+        .map(|(&name, &value)| (name, value))
+        .collect();
+    StatsAccumulator { stats }.to_string()
+}
+
+pub fn stats_to_json() -> String {
+    ACCUMULATOR.lock().unwrap().to_json()
+}
+
+pub trait Stat {
+    // Hands the thread's value to the accumulator and starts over from zero
+    fn report(&self, name: &'static str, accumulator: &mut StatsAccumulator);
+}
+
+pub struct StatCounter {
+    count: Cell<u64>,
+}
+
+impl StatCounter {
+    pub const fn new() -> Self {
+        Self { count: Cell::new(0) }
+    }
+
+    pub fn add(&self, n: u64) {
+        self.count.set(self.count.get() + n);
+    }
+}
+
+impl Default for StatCounter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Stat for StatCounter {
+    fn report(&self, name: &'static str, accumulator: &mut StatsAccumulator) {
+        accumulator.add(name, StatValue::Counter(self.count.replace(0)));
+    }
+}
+
+// How often something happened out of how many tries, printed as a percentage
+pub struct StatRatio {
+    numerator: Cell<u64>,
+    denominator: Cell<u64>,
+}
+
+impl StatRatio {
+    pub const fn new() -> Self {
+        Self { numerator: Cell::new(0), denominator: Cell::new(0) }
+    }
+
+    pub fn add(&self, numerator: u64, denominator: u64) {
+        self.numerator.set(self.numerator.get() + numerator);
+        self.denominator.set(self.denominator.get() + denominator);
+    }
+}
+
+impl Default for StatRatio {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Stat for StatRatio {
+    fn report(&self, name: &'static str, accumulator: &mut StatsAccumulator) {
+        accumulator.add(name, StatValue::Ratio(self.numerator.replace(0), self.denominator.replace(0)));
+    }
+}
+
+// Mean, minimum and maximum of a recorded value
+pub struct StatDistribution {
+    count: Cell<u64>,
+    sum: Cell<f64>,
+    min: Cell<f64>,
+    max: Cell<f64>,
+}
+
+impl StatDistribution {
+    pub const fn new() -> Self {
+        Self { count: Cell::new(0), sum: Cell::new(0.0), min: Cell::new(f64::INFINITY), max: Cell::new(f64::NEG_INFINITY) }
+    }
+
+    pub fn record(&self, value: f64) {
+        self.count.set(self.count.get() + 1);
+        self.sum.set(self.sum.get() + value);
+        self.min.set(self.min.get().min(value));
+        self.max.set(self.max.get().max(value));
+    }
+}
+
+impl Default for StatDistribution {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Stat for StatDistribution {
+    fn report(&self, name: &'static str, accumulator: &mut StatsAccumulator) {
+        accumulator.add(name, StatValue::Distribution {
+            count: self.count.replace(0),
+            sum: self.sum.replace(0.0),
+            min: self.min.replace(f64::INFINITY),
+            max: self.max.replace(f64::NEG_INFINITY),
+        });
+    }
+}
+
+// Time spent in a piece of code and how often it ran
+pub struct StatTimer {
+    count: Cell<u64>,
+    elapsed: Cell<Duration>,
+}
+
+impl StatTimer {
+    pub const fn new() -> Self {
+        Self { count: Cell::new(0), elapsed: Cell::new(Duration::ZERO) }
+    }
+
+    pub fn add(&self, elapsed: Duration) {
+        self.count.set(self.count.get() + 1);
+        self.elapsed.set(self.elapsed.get() + elapsed);
+    }
+
+    pub fn time<T>(&self, f: impl FnOnce() -> T) -> T {
+        let start = Instant::now();
+        let result = f();
+        self.add(start.elapsed());
+        result
+    }
+}
+
+impl Default for StatTimer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Stat for StatTimer {
+    fn report(&self, name: &'static str, accumulator: &mut StatsAccumulator) {
+        accumulator.add(name, StatValue::Timer { count: self.count.replace(0), elapsed: self.elapsed.replace(Duration::ZERO) });
+    }
+}
+
+#[derive(Clone, Copy)]
+pub enum StatValue {
+    Counter(u64),
+    Ratio(u64, u64),
+    Distribution { count: u64, sum: f64, min: f64, max: f64 },
+    Timer { count: u64, elapsed: Duration },
+}
+
+impl StatValue {
+    fn merge(&mut self, name: &str, other: StatValue) {
+        match (self, other) {
+            (Self::Counter(a), Self::Counter(b)) => *a += b,
+            (Self::Ratio(a_num, a_den), Self::Ratio(b_num, b_den)) => {
+                *a_num += b_num;
+                *a_den += b_den;
+            },
+            (Self::Distribution { count, sum, min, max }, Self::Distribution { count: b_count, sum: b_sum, min: b_min, max: b_max }) => {
+                *count += b_count;
+                *sum += b_sum;
+                *min = min.min(b_min);
+                *max = max.max(b_max);
+            },
+            (Self::Timer { count, elapsed }, Self::Timer { count: b_count, elapsed: b_elapsed }) => {
+                *count += b_count;
+                *elapsed += b_elapsed;
+            },
+            _ => panic!("Statistic {} is declared as two different kinds!", name)
+        }
+    }
+
+    fn to_json(self) -> String {
+        match self {
+            Self::Counter(count) => format!("{}", count),
+            Self::Ratio(numerator, denominator) => {
+                format!("{{ \"numerator\": {}, \"denominator\": {} }}", numerator, denominator)
+            },
+            Self::Distribution { count: 0, .. } => String::from("{ \"count\": 0 }"),
+            Self::Distribution { count, sum, min, max } => {
+                format!("{{ \"count\": {}, \"mean\": {}, \"min\": {}, \"max\": {} }}", count, sum / count as f64, min, max)
+            },
+            Self::Timer { count, elapsed } => {
+                format!("{{ \"count\": {}, \"seconds\": {} }}", count, elapsed.as_secs_f64())
+            },
+        }
+    }
+}
+
+impl fmt::Display for StatValue {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Self::Counter(count) => write!(f, "{}", count),
+            Self::Ratio(numerator, denominator) => {
+                let percent = if denominator > 0 { 100.0 * numerator as f64 / denominator as f64 } else { 0.0 };
+                write!(f, "{} / {} ({:.2}%)", numerator, denominator, percent)
+            },
+            Self::Distribution { count: 0, .. } => write!(f, "no samples"),
+            Self::Distribution { count, sum, min, max } => {
+                write!(f, "{:.3} avg [{:.3} - {:.3}] over {} samples", sum / count as f64, min, max, count)
+            },
+            Self::Timer { count, elapsed } => {
+                let average = if count > 0 { 1000.0 * elapsed.as_secs_f64() / count as f64 } else { 0.0 };
+                write!(f, "{:.3}s over {} calls ({:.3}ms avg)", elapsed.as_secs_f64(), count, average)
+            },
+        }
+    }
+}
+
+// Totals over every thread that reported, sorted by name so categories stay together
+#[derive(Default)]
+pub struct StatsAccumulator {
+    stats: BTreeMap<&'static str, StatValue>,
+}
+
+impl StatsAccumulator {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn add(&mut self, name: &'static str, value: StatValue) {
+        match self.stats.get_mut(name) {
+            Some(total) => total.merge(name, value),
+            None => { self.stats.insert(name, value); }
+        }
+    }
+
+    // (category, [(description, value)]) in name order
+    fn categories(&self) -> Vec<(&'static str, Vec<(&'static str, StatValue)>)> {
+        let mut categories: Vec<(&'static str, Vec<(&'static str, StatValue)>)> = Vec::new();
+        for (&name, &value) in &self.stats {
+            let (category, description) = name.split_once('/').unwrap_or(("Other", name));
+            match categories.last_mut() {
+                Some((last, entries)) if *last == category => entries.push((description, value)),
+                _ => categories.push((category, vec![(description, value)]))
+            }
+        }
+        categories
+    }
+
+    pub fn to_json(&self) -> String {
+        let categories: Vec<String> = self.categories().into_iter()
+            .map(|(category, entries)| {
+                let entries: Vec<String> = entries.iter()
+                    .map(|(description, value)| format!("    \"{}\": {}", description, value.to_json()))
+                    .collect();
+                format!("  \"{}\": {{\n{}\n  }}", category, entries.join(",\n"))
+            })
+            .collect();
+
+        format!("{{\n{}\n}}\n", categories.join(",\n"))
+    }
+}
+
+impl fmt::Display for StatsAccumulator {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let categories: Vec<String> = self.categories().into_iter()
+            .map(|(category, entries)| {
+                let entries: Vec<String> = entries.iter()
+                    .map(|(description, value)| format!("    {}: {}", description, value))
+                    .collect();
+                format!("  {}[\n{}\n  ]", category, entries.join(",\n"))
+            })
+            .collect();
+
+        write!(f, "Statistics[\n{}\n]", categories.join(",\n"))
+    }
+}
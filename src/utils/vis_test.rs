@@ -1,3 +1,38 @@
-pub struct VisibilityTester{
-    
-}
\ No newline at end of file
+use crate::common::*;
+
+pub struct VisibilityTester {
+    pub p0: Point3f,
+    pub p1: Point3f,
+    pub time: f32,
+}
+
+impl VisibilityTester {
+    pub fn new() -> Self {
+        Self {
+            p0: Point3f::new(),
+            p1: Point3f::new(),
+            time: 0.0
+        }
+    }
+
+    pub fn init(p0: &Point3f, p1: &Point3f, time: f32) -> Self {
+        Self {
+            p0: *p0,
+            p1: *p1,
+            time
+        }
+    }
+
+    pub fn unoccluded(&self, scene: &Scene) -> bool {
+        let d = self.p1 - self.p0;
+        let dist = d.length();
+        if dist == 0.0 {
+            return true;
+        }
+
+        // stop just short of p1 so the surface it lies on does not count as an occluder
+        let mut ray = Ray::init(&self.p0, &d, EPSILON, dist - EPSILON);
+        ray.time = self.time;
+        !scene.intersect_p(&ray)
+    }
+}
@@ -28,5 +28,30 @@ impl Warp {
 
         Point2f::init([theta.cos(), theta.sin()]) * r
     }
-}
 
+    pub fn cosine_sample_hemisphere(u: Point2f) -> Vector3f {
+        let d = Self::sample_concentric_disk(u);
+        let z = 0f32.max(1.0 - d.x()*d.x() - d.y()*d.y()).sqrt();
+
+        Vector3f::init([d.x(), d.y(), z])
+    }
+
+    pub fn uniform_sample_hemisphere(u: Point2f) -> Vector3f {
+        let z = u.x();
+        let r = 0f32.max(1.0 - z * z).sqrt();
+        let phi = 2.0 * M_PI * u.y();
+
+        Vector3f::init([r * phi.cos(), r * phi.sin(), z])
+    }
+
+    pub fn uniform_hemisphere_pdf() -> f32 {
+        0.5 * M_INV_PI
+    }
+
+    // Barycentrics of a point uniformly distributed over a triangle
+    pub fn uniform_sample_triangle(u: Point2f) -> Point2f {
+        let su0 = u.x().sqrt();
+
+        Point2f::init([1.0 - su0, u.y() * su0])
+    }
+}